@@ -0,0 +1,20 @@
+use dices::*;
+
+// cargo bench --bench memory
+// compares the heap size of distributions with one fraction per value with their compact form
+fn main() {
+    println!("formula | values | fractions | compact | ratio");
+    for formula in ["d100000", "100d100", "50d1000", "d20^2*d20", "40d6!"] {
+        let dice = Dice::build_from_string(formula).unwrap();
+        let compact = dice.to_compact().unwrap();
+        let fractions = dice.distribution_heap_size();
+        let compact_size = compact.heap_size();
+        println!(
+            "{formula} | {} | {} KiB | {} KiB | {:.2}",
+            dice.distribution.len(),
+            fractions / 1024,
+            compact_size / 1024,
+            compact_size as f64 / fractions as f64
+        );
+    }
+}
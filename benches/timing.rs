@@ -8,7 +8,47 @@ pub fn criterion_benchmark(c: &mut Criterion) {
             let _ = Dice::build_from_string(black_box("2d200")).unwrap();
         })
     });
+
+    c.bench_function("d100*d100", |b| {
+        b.iter(|| {
+            let _ = Dice::build_from_string(black_box("d100*d100")).unwrap();
+        })
+    });
+
+    let d10000 = Dice::build_from_string("d10000").unwrap();
+    c.bench_function("prob queries d10000", |b| {
+        b.iter(|| {
+            for v in (0..10000).step_by(97) {
+                let _ = d10000.prob(black_box(v));
+                let _ = d10000.prob_lte(black_box(v));
+                let _ = d10000.prob_gt(black_box(v));
+            }
+        })
+    });
+    c.bench_function("quantile d10000", |b| {
+        b.iter(|| {
+            for i in 0..100 {
+                let _ = d10000.quantile(black_box(i as f64 / 100.0));
+            }
+        })
+    });
+}
+
+// compares the backends of the intermediate distributions:
+// cargo bench --bench timing -- --save-baseline hash
+// cargo bench --bench timing --features btree_distributions -- --baseline hash
+pub fn backend_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("backend");
+    group.sample_size(10);
+    for formula in ["d20000", "d20xd20", "40d6!", "mix(1:d300,1:d500)*d20"] {
+        group.bench_function(formula, |b| {
+            b.iter(|| {
+                let _ = Dice::build_from_string(black_box(formula)).unwrap();
+            })
+        });
+    }
+    group.finish();
 }
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(benches, criterion_benchmark, backend_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,50 @@
+use crate::dice_builder::{DiceBuilder, Value};
+
+impl DiceBuilder {
+    /// the damage of one attack roll with a d20, like in Dungeons & Dragons
+    ///
+    /// The attack hits if the d20 plus `to_hit` is at least `armor_class` and deals `damage`.
+    /// A natural 1 always misses and deals no damage, a natural 20 always hits.
+    /// Natural rolls of at least `crit_min` (20 for most characters, 19 for an improved critical) are critical hits and deal `crit_damage` instead,
+    /// like `2d8+3` for a hit of `d8+3` that rolls its damage dice twice.
+    ///
+    /// The result is a [`DiceBuilder::Mixture`] of the three outcomes, so it can be combined with other formulas like any [`DiceBuilder`].
+    ///
+    /// # Examples
+    /// a longsword with +5 to hit against AC 15:
+    /// ```
+    /// use dices::DiceBuilder;
+    /// use fraction::BigFraction;
+    /// let damage = DiceBuilder::from_string("d8+3").unwrap();
+    /// let crit_damage = DiceBuilder::from_string("2d8+3").unwrap();
+    /// let attack = DiceBuilder::attack(5, 15, damage, 20, crit_damage);
+    /// assert_eq!(attack.to_string(), "mix(9:0,10:d8+3,1:2xd8+3)");
+    /// let dice = attack.build();
+    /// assert_eq!(dice.prob(0), BigFraction::new(9u64, 20u64));
+    /// assert_eq!(dice.max, 19);
+    /// ```
+    pub fn attack(
+        to_hit: Value,
+        armor_class: Value,
+        damage: DiceBuilder,
+        crit_min: Value,
+        crit_damage: DiceBuilder,
+    ) -> DiceBuilder {
+        let (mut misses, mut hits, mut crits) = (0u32, 0u32, 0u32);
+        for natural in 1..=20 {
+            match natural {
+                1 => misses += 1,
+                n if n >= crit_min => crits += 1,
+                20 => hits += 1,
+                n if n.saturating_add(to_hit) >= armor_class => hits += 1,
+                _ => misses += 1,
+            }
+        }
+        let components = [
+            (misses, DiceBuilder::Constant(0)),
+            (hits, damage),
+            (crits, crit_damage),
+        ];
+        DiceBuilder::Mixture(components.into_iter().filter(|(w, _)| *w > 0).collect())
+    }
+}
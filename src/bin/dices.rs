@@ -0,0 +1,193 @@
+use std::{process::ExitCode, str::FromStr, time::Duration};
+
+use dices::{BuildOptions, Dice, DiceBuildError, DiceBuilder, HistogramOptions};
+
+// cargo run --features cli -- "2d6+3" --stats --histogram --roll 10
+const USAGE: &str = "\
+usage: dices <formula> [options]
+
+calculates the probability distribution of a dice formula like \"2d6+3\"
+
+options:
+  --stats           print a summary of the distribution (the default if no other output is selected)
+  --histogram       print a bar chart of the distribution
+  --cumulative      show the cumulative distribution in the histogram
+  --width <n>       width of the histogram bars in characters (default 50)
+  --json            print the distribution and its statistics as JSON
+  --csv             print the distribution as CSV
+  --roll <n>        roll the dice n times and print the results
+  --timeout <ms>    abort the calculation after ms milliseconds
+  --max-outcomes <n>
+                    abort the calculation if a distribution has more than n values
+  --max-work <n>    abort the calculation after n elementary operations
+  -h, --help        print this help";
+
+#[derive(Debug, Default)]
+struct Args {
+    formula: String,
+    stats: bool,
+    histogram: bool,
+    cumulative: bool,
+    width: Option<usize>,
+    json: bool,
+    csv: bool,
+    roll: Option<usize>,
+    options: BuildOptions,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut formula: Option<String> = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stats" => parsed.stats = true,
+            "--histogram" => parsed.histogram = true,
+            "--cumulative" => parsed.cumulative = true,
+            "--json" => parsed.json = true,
+            "--csv" => parsed.csv = true,
+            "--width" => parsed.width = Some(parse_number(&arg, args.next())?),
+            "--roll" => parsed.roll = Some(parse_number(&arg, args.next())?),
+            "--timeout" => {
+                let millis = parse_number(&arg, args.next())?;
+                parsed.options.timeout = Some(Duration::from_millis(millis));
+            }
+            "--max-outcomes" => {
+                parsed.options.max_outcomes = Some(parse_number(&arg, args.next())?)
+            }
+            "--max-work" => parsed.options.max_total_work = Some(parse_number(&arg, args.next())?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+            _ => match formula {
+                None => formula = Some(arg),
+                Some(_) => return Err(format!("unexpected argument '{arg}'")),
+            },
+        }
+    }
+    parsed.formula = formula.ok_or("missing formula")?;
+    if !(parsed.histogram || parsed.json || parsed.csv || parsed.roll.is_some()) {
+        parsed.stats = true;
+    }
+    Ok(parsed)
+}
+
+/// builds the formula of `args` within the limits of its options
+fn build(args: &Args) -> Result<Dice, DiceBuildError> {
+    DiceBuilder::from_string(&args.formula)?.build_with_options(&args.options)
+}
+
+fn parse_number<T: FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{option} needs a number"))?;
+    value
+        .parse()
+        .map_err(|_| format!("{option} needs a number, got '{value}'"))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        // an empty message means that help was requested
+        Err(message) if message.is_empty() => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let dice = match build(&args) {
+        Ok(dice) => dice,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.stats {
+        println!("{dice}");
+    }
+    if args.histogram {
+        let options = HistogramOptions {
+            width: args.width.unwrap_or(HistogramOptions::default().width),
+            cumulative: args.cumulative,
+            ..Default::default()
+        };
+        print!("{}", dice.to_histogram(&options));
+    }
+    if args.json {
+        println!("{}", dice.to_json(None));
+    }
+    if args.csv {
+        if let Err(err) = dice.to_csv(std::io::stdout().lock(), &Default::default()) {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(n) = args.roll {
+        let rolls: Vec<String> = dice.roll_many(n).map(|v| v.to_string()).collect();
+        println!("{}", rolls.join(" "));
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let parsed = args(&[
+            "2d6",
+            "--histogram",
+            "--timeout",
+            "200",
+            "--max-outcomes",
+            "10",
+        ])
+        .unwrap();
+        assert_eq!(parsed.formula, "2d6");
+        assert!(parsed.histogram);
+        assert!(!parsed.stats);
+        assert_eq!(parsed.options.timeout, Some(Duration::from_millis(200)));
+        assert_eq!(parsed.options.max_outcomes, Some(10));
+        assert!(args(&["2d6"]).unwrap().stats);
+
+        assert_eq!(args(&["--stats"]).unwrap_err(), "missing formula");
+        assert_eq!(args(&["d6", "d8"]).unwrap_err(), "unexpected argument 'd8'");
+        assert_eq!(
+            args(&["d6", "--max-work", "many"]).unwrap_err(),
+            "--max-work needs a number, got 'many'"
+        );
+        assert_eq!(
+            args(&["d6", "--roll"]).unwrap_err(),
+            "--roll needs a number"
+        );
+        assert_eq!(args(&["-h"]).unwrap_err(), "");
+    }
+
+    #[test]
+    fn build_errors_test() {
+        let build_args = |a: &[&str]| build(&args(a).unwrap()).err();
+        assert_eq!(build_args(&["d0"]), Some(DiceBuildError::ZeroSidedDie));
+        assert_eq!(
+            build_args(&["d6/(d2-1)"]),
+            Some(DiceBuildError::DivisionByZero)
+        );
+        assert!(matches!(
+            build_args(&["2^-1"]),
+            Some(DiceBuildError::NegativeExponent(_))
+        ));
+        assert!(matches!(
+            build_args(&["2d6+"]),
+            Some(DiceBuildError::Parse(_))
+        ));
+        assert_eq!(
+            build_args(&["d100000000", "--max-outcomes", "1000"]),
+            Some(DiceBuildError::TooManyOutcomes { limit: 1000 })
+        );
+        assert_eq!(build_args(&["2d6", "--max-outcomes", "11"]), None);
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{
+    dice_builder::Value,
+    distribution_map::DistributionMap,
+    wasm_safe::{elapsed_millis, WasmSafeInstant},
+    DiceBuildError, DiceBuilder,
+};
+
+/// Limits for the calculation of a [`Dice`](crate::Dice) in [`DiceBuilder::build_with_options()`](crate::DiceBuilder::build_with_options).
+///
+/// Formulas like `"d100xd100xd100"` can take minutes to calculate. With limits set, the calculation is aborted with a [`DiceBuildError`] instead.
+/// All limits are `None` (unlimited) by default and the formula is not simplified.
+///
+/// # Examples
+/// ```
+/// use dices::{BuildOptions, DiceBuildError, DiceBuilder};
+/// let options = BuildOptions {
+///     max_outcomes: Some(1000),
+///     ..Default::default()
+/// };
+/// let builder = DiceBuilder::from_string("d100xd100xd100").unwrap();
+/// assert!(matches!(
+///     builder.build_with_options(&options),
+///     Err(DiceBuildError::TooManyOutcomes { .. })
+/// ));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildOptions {
+    /// maximum number of distinct values of any intermediate distribution
+    pub max_outcomes: Option<usize>,
+    /// maximum number of elementary operations, that is pairs of values combined while convoluting distributions
+    pub max_total_work: Option<u64>,
+    /// maximum time the calculation may take
+    pub timeout: Option<Duration>,
+    /// aborts the calculation with [`DiceBuildError::Cancelled`] once the token is cancelled, for example from another thread
+    pub cancellation: Option<CancellationToken>,
+    /// calculates the distribution of [`DiceBuilder::simplify()`] instead of the formula itself, which is faster for big generated formulas
+    pub simplify: bool,
+    /// sample sums of at least this many rolls of the same die, like `"1000d6"`, are approximated by a discretized normal distribution instead of being convoluted exactly, see [`BuildReport::approximate`]
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{BuildOptions, DiceBuilder};
+    /// let options = BuildOptions {
+    ///     normal_approximation_min_count: Some(100),
+    ///     ..Default::default()
+    /// };
+    /// let dice = DiceBuilder::from_string("1000d6")
+    ///     .unwrap()
+    ///     .build_with_options(&options)
+    ///     .unwrap();
+    /// assert!(dice.build_report.approximate);
+    /// assert_eq!(dice.median, 3500);
+    /// ```
+    pub normal_approximation_min_count: Option<usize>,
+}
+
+/// A flag shared between clones that cancels all builds using it in their [`BuildOptions`].
+///
+/// The token is checked between chunks of the calculation, so a build stops shortly after `cancel()` is called.
+///
+/// # Examples
+/// ```
+/// use dices::{BuildOptions, CancellationToken, DiceBuildError, DiceBuilder};
+/// let token = CancellationToken::new();
+/// let options = BuildOptions {
+///     cancellation: Some(token.clone()),
+///     ..Default::default()
+/// };
+/// token.cancel();
+/// let builder = DiceBuilder::from_string("d100xd100xd100").unwrap();
+/// assert_eq!(
+///     builder.build_with_options(&options),
+///     Err(DiceBuildError::Cancelled)
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// a token that is not cancelled yet
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// cancels all builds using this token or one of its clones
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// true if `cancel()` was called on this token or one of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// tokens are equal if they are clones of each other
+impl PartialEq for CancellationToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.cancelled, &other.cancelled)
+    }
+}
+
+impl Eq for CancellationToken {}
+
+/// The state of a running calculation, passed to the callback of [`DiceBuilder::build_with_progress()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildProgress {
+    /// number of elementary operations done so far
+    pub work_done: u64,
+    /// milliseconds since the calculation started
+    pub elapsed_millis: u64,
+}
+
+/// Statistics about a finished calculation, stored in the `build_report` of a [`Dice`](crate::Dice).
+///
+/// Dice that were not calculated from a [`DiceBuilder`], like the results of transformations, have an empty report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildReport {
+    /// time the calculation took, including the distribution parameters
+    pub elapsed: Duration,
+    /// number of nodes of the [`DiceBuilder`] tree whose distribution was calculated, identical closed subtrees are only calculated once
+    pub nodes: usize,
+    /// largest number of distinct values of any intermediate distribution
+    pub max_intermediate_support: usize,
+    /// true if the distribution is only an approximation, because a sample sum was approximated by a normal distribution, see [`BuildOptions::normal_approximation_min_count`]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub approximate: bool,
+}
+
+/// number of elementary operations between two calls of the progress callback
+pub(crate) const PROGRESS_INTERVAL: u64 = 100_000;
+
+/// called with the current [`BuildProgress`] during a build, returning `false` cancels the build
+pub(crate) type ProgressCallback<'a> = &'a mut dyn FnMut(&BuildProgress) -> bool;
+
+/// keeps track of the work done during one build and checks it against the [`BuildOptions`]
+pub(crate) struct BuildContext<'a, 't, P> {
+    options: &'a BuildOptions,
+    start_instant: WasmSafeInstant,
+    /// elementary operations registered with `add_work()`, including those not done yet
+    total_work: u64,
+    /// elementary operations reported as done with `complete_work()`
+    work_done: u64,
+    progress: Option<ProgressCallback<'a>>,
+    next_progress_report: u64,
+    nodes: usize,
+    max_intermediate_support: usize,
+    approximate: bool,
+    /// distributions of the subtrees calculated so far, so identical subtrees like the 4d6 in `max(4d6,4d6)` are only calculated once
+    memo: HashMap<&'t DiceBuilder, DistributionMap<P>>,
+    /// values of the variables bound by the enclosing [`DiceBuilder::Let`]s, innermost last
+    bindings: Vec<(&'t str, Value)>,
+}
+
+impl<'a, 't, P> BuildContext<'a, 't, P> {
+    pub fn new(options: &'a BuildOptions) -> BuildContext<'a, 't, P> {
+        BuildContext::with_progress(options, None)
+    }
+
+    pub fn with_progress(
+        options: &'a BuildOptions,
+        progress: Option<ProgressCallback<'a>>,
+    ) -> BuildContext<'a, 't, P> {
+        BuildContext {
+            options,
+            start_instant: WasmSafeInstant::now(),
+            total_work: 0,
+            work_done: 0,
+            progress,
+            next_progress_report: PROGRESS_INTERVAL,
+            nodes: 0,
+            max_intermediate_support: 0,
+            approximate: false,
+            memo: HashMap::new(),
+            bindings: vec![],
+        }
+    }
+
+    pub fn memoized(&self, dice_builder: &DiceBuilder) -> Option<&DistributionMap<P>> {
+        self.memo.get(dice_builder)
+    }
+
+    pub fn memoize(&mut self, dice_builder: &'t DiceBuilder, hashmap: DistributionMap<P>) {
+        self.memo.insert(dice_builder, hashmap);
+    }
+
+    pub fn bind(&mut self, name: &'t str, value: Value) {
+        self.bindings.push((name, value));
+    }
+
+    pub fn unbind(&mut self) {
+        self.bindings.pop();
+    }
+
+    /// the value of the innermost binding of `name`
+    pub fn binding(&self, name: &str) -> Option<Value> {
+        self.bindings
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    /// registers `work` elementary operations that are about to be done
+    pub fn add_work(&mut self, work: u64) -> Result<(), DiceBuildError> {
+        self.total_work = self.total_work.saturating_add(work);
+        if let Some(limit) = self.options.max_total_work {
+            if self.total_work > limit {
+                return Err(DiceBuildError::TooMuchWork { limit });
+            }
+        }
+        self.check_timeout()?;
+        self.check_cancellation()
+    }
+
+    /// registers `work` elementary operations as done, checks the timeout and the cancellation token and calls the progress callback every [`PROGRESS_INTERVAL`] operations
+    pub fn complete_work(&mut self, work: u64) -> Result<(), DiceBuildError> {
+        self.work_done = self.work_done.saturating_add(work);
+        self.check_timeout()?;
+        self.check_cancellation()?;
+        if self.work_done < self.next_progress_report {
+            return Ok(());
+        }
+        self.next_progress_report = self.work_done.saturating_add(PROGRESS_INTERVAL);
+        if let Some(progress) = self.progress.as_mut() {
+            let state = BuildProgress {
+                work_done: self.work_done,
+                elapsed_millis: elapsed_millis(&self.start_instant),
+            };
+            if !progress(&state) {
+                return Err(DiceBuildError::Cancelled);
+            }
+        }
+        Ok(())
+    }
+
+    /// registers a node of the tree whose distribution was calculated
+    pub fn count_node(&mut self) {
+        self.nodes += 1;
+    }
+
+    /// true if a sample sum of `count` rolls should be approximated by a normal distribution
+    pub fn approximates_sample_sum(&self, count: usize) -> bool {
+        matches!(self.options.normal_approximation_min_count, Some(min_count) if count >= min_count)
+    }
+
+    /// marks the result of the calculation as approximate
+    pub fn mark_approximate(&mut self) {
+        self.approximate = true;
+    }
+
+    /// the [`BuildReport`] of the calculation so far
+    pub fn report(&self) -> BuildReport {
+        BuildReport {
+            elapsed: Duration::from_millis(elapsed_millis(&self.start_instant)),
+            nodes: self.nodes,
+            max_intermediate_support: self.max_intermediate_support,
+            approximate: self.approximate,
+        }
+    }
+
+    /// checks the number of distinct values of an intermediate distribution
+    pub fn check_outcomes(&mut self, outcomes: usize) -> Result<(), DiceBuildError> {
+        self.max_intermediate_support = self.max_intermediate_support.max(outcomes);
+        match self.options.max_outcomes {
+            Some(limit) if outcomes > limit => Err(DiceBuildError::TooManyOutcomes { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_timeout(&self) -> Result<(), DiceBuildError> {
+        if let Some(timeout) = self.options.timeout {
+            if elapsed_millis(&self.start_instant) as u128 > timeout.as_millis() {
+                return Err(DiceBuildError::Timeout { limit: timeout });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn check_cancellation(&self) -> Result<(), DiceBuildError> {
+        match &self.options.cancellation {
+            Some(token) if token.is_cancelled() => Err(DiceBuildError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+}
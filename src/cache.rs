@@ -0,0 +1,140 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use crate::{
+    dice::Dice,
+    dice_builder::{DiceBuildError, DiceBuilder},
+};
+
+/// A store of built [`Dice`], consulted by [`Dice::build_from_string_cached()`] and, once set with [`Dice::set_global_cache()`], by [`Dice::build_from_string()`].
+///
+/// The keys are the canonical forms of the formulas, see [`DiceBuilder::canonicalize()`], so `"d4+2*d6"` and `"d6*2+d4"` share an entry.
+/// Implementations need interior mutability, because the cache is shared between threads.
+pub trait DiceCache: Send + Sync {
+    /// the cached [`Dice`] of the canonical formula `key`
+    fn get(&self, key: &str) -> Option<Dice>;
+    /// stores the [`Dice`] of the canonical formula `key`
+    fn insert(&self, key: String, dice: Dice);
+}
+
+/// A [`DiceCache`] holding at most `capacity` [`Dice`], evicting the least recently used one when it is full.
+///
+/// # Examples
+/// ```
+/// use dices::{Dice, LruDiceCache};
+/// let cache = LruDiceCache::new(100);
+/// let first = Dice::build_from_string_cached("d4+2*d6", &cache).unwrap();
+/// // served from the cache, with the formula that was asked for
+/// let second = Dice::build_from_string_cached("d6*2+d4", &cache).unwrap();
+/// assert_eq!(cache.len(), 1);
+/// assert_eq!(second.builder_string, "d6*2+d4");
+/// assert_eq!(first.distribution, second.distribution);
+/// ```
+#[derive(Debug)]
+pub struct LruDiceCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    /// every cached dice with the tick of its last use
+    entries: HashMap<String, (Dice, u64)>,
+    /// the keys of `entries` ordered by the tick of their last use
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LruDiceCache {
+    /// an empty cache for at most `capacity` [`Dice`]
+    pub fn new(capacity: usize) -> LruDiceCache {
+        LruDiceCache {
+            capacity,
+            state: Mutex::new(LruState::default()),
+        }
+    }
+
+    /// number of cached [`Dice`]
+    pub fn len(&self) -> usize {
+        self.state().entries.len()
+    }
+
+    /// true if no [`Dice`] is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, LruState> {
+        // a panic while holding the lock cannot leave the maps inconsistent in a harmful way
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl LruState {
+    /// marks `key` as used most recently
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some((_, last_use)) = self.entries.get_mut(key) {
+            self.recency.remove(last_use);
+            *last_use = self.tick;
+            self.recency.insert(self.tick, key.to_string());
+        }
+    }
+}
+
+impl DiceCache for LruDiceCache {
+    fn get(&self, key: &str) -> Option<Dice> {
+        let mut state = self.state();
+        state.touch(key);
+        state.entries.get(key).map(|(dice, _)| dice.clone())
+    }
+
+    fn insert(&self, key: String, dice: Dice) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.state();
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((_, last_use)) = state.entries.insert(key.clone(), (dice, tick)) {
+            state.recency.remove(&last_use);
+        }
+        state.recency.insert(tick, key);
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+    }
+}
+
+static GLOBAL_CACHE: RwLock<Option<Arc<dyn DiceCache>>> = RwLock::new(None);
+
+pub(crate) fn global_cache() -> Option<Arc<dyn DiceCache>> {
+    GLOBAL_CACHE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+pub(crate) fn set_global_cache(cache: Option<Arc<dyn DiceCache>>) {
+    *GLOBAL_CACHE.write().unwrap_or_else(|e| e.into_inner()) = cache;
+}
+
+/// the [`Dice`] of `dice_builder` from `cache`, built and stored in the cache if it is not cached yet
+pub(crate) fn try_build_cached(
+    dice_builder: DiceBuilder,
+    cache: &dyn DiceCache,
+) -> Result<Dice, DiceBuildError> {
+    let key = dice_builder.clone().canonicalize().to_string();
+    if let Some(mut dice) = cache.get(&key) {
+        dice.builder_string = dice_builder.to_string();
+        return Ok(dice);
+    }
+    let dice = dice_builder.try_build()?;
+    cache.insert(key, dice.clone());
+    Ok(dice)
+}
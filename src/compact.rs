@@ -0,0 +1,176 @@
+use std::mem::size_of;
+
+use fraction::BigUint;
+
+use crate::{
+    convolution::common_denominator,
+    dice::Dice,
+    dice_builder::{DiceBuildError, Prob, Value},
+};
+
+/// The distribution of a [`Dice`] with exact probabilities, stored with one common denominator and a [`BigUint`] numerator per value, created with [`Dice::to_compact()`].
+///
+/// A [`Prob`](fraction::BigFraction) keeps a numerator and a denominator for every value, so for supports with hundreds of thousands of values, like big sums of dice,
+/// the compact form needs about half of the memory. Contiguous supports also do not store their values.
+/// Probabilities are converted back to fractions on access, `Dice::from_compact()` restores the [`Dice`].
+///
+/// # Examples
+/// ```
+/// use dices::Dice;
+/// use fraction::BigFraction;
+/// let dice = Dice::build_from_string("3d6").unwrap();
+/// let compact = dice.to_compact().unwrap();
+/// assert_eq!(compact.denominator(), &216u32.into());
+/// assert_eq!(compact.prob(10), BigFraction::new(27u64, 216u64));
+/// assert_eq!(compact.prob(2), BigFraction::from(0));
+/// let restored = Dice::from_compact(&compact, dice.builder_string.clone()).unwrap();
+/// assert_eq!(restored.distribution, dice.distribution);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactDistribution {
+    support: Support,
+    numerators: Vec<BigUint>,
+    denominator: BigUint,
+}
+
+/// the values of a [`CompactDistribution`], in ascending order
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Support {
+    /// `len` values without gaps, starting at `min`
+    Contiguous { min: Value },
+    /// every value, for supports with gaps
+    Sparse(Vec<Value>),
+}
+
+impl CompactDistribution {
+    /// the compact form of a `distribution` sorted by value, `None` if a probability is negative or not finite
+    pub fn from_distribution(distribution: &[(Value, Prob)]) -> Option<CompactDistribution> {
+        let probs: Vec<Prob> = distribution.iter().map(|(_, p)| p.clone()).collect();
+        let (numerators, denominator) = common_denominator(&probs)?;
+        let support = match (distribution.first(), distribution.last()) {
+            (Some((min, _)), Some((max, _)))
+                if max.abs_diff(*min) == distribution.len() as u64 - 1 =>
+            {
+                Support::Contiguous { min: *min }
+            }
+            _ => Support::Sparse(distribution.iter().map(|(v, _)| *v).collect()),
+        };
+        Some(CompactDistribution {
+            support,
+            numerators,
+            denominator,
+        })
+    }
+
+    /// number of values
+    pub fn len(&self) -> usize {
+        self.numerators.len()
+    }
+
+    /// true if the distribution has no values
+    pub fn is_empty(&self) -> bool {
+        self.numerators.is_empty()
+    }
+
+    /// the common denominator of all probabilities, the least common multiple of their denominators
+    pub fn denominator(&self) -> &BigUint {
+        &self.denominator
+    }
+
+    /// the numerator of the probability of `value` over `denominator()`, 0 if it is not a value of the distribution
+    pub fn numerator(&self, value: Value) -> BigUint {
+        match self.index_of(value) {
+            Some(i) => self.numerators[i].clone(),
+            None => BigUint::from(0u32),
+        }
+    }
+
+    /// the probability of `value`
+    pub fn prob(&self, value: Value) -> Prob {
+        match self.index_of(value) {
+            Some(i) => self.prob_at(i),
+            None => Prob::from(0),
+        }
+    }
+
+    /// every value with its probability in ascending order, converted to fractions while iterating
+    pub fn iter(&self) -> impl Iterator<Item = (Value, Prob)> + '_ {
+        (0..self.len()).map(|i| (self.value_at(i), self.prob_at(i)))
+    }
+
+    /// the distribution with one fraction per value, like [`Dice::distribution`]
+    pub fn to_distribution(&self) -> Vec<(Value, Prob)> {
+        self.iter().collect()
+    }
+
+    /// the approximate number of bytes on the heap, for comparisons with `Dice::distribution_heap_size()`
+    pub fn heap_size(&self) -> usize {
+        let values = match &self.support {
+            Support::Contiguous { .. } => 0,
+            Support::Sparse(values) => values.len() * size_of::<Value>(),
+        };
+        let numerators: usize = self.numerators.iter().map(biguint_heap_size).sum();
+        values
+            + self.numerators.len() * size_of::<BigUint>()
+            + numerators
+            + biguint_heap_size(&self.denominator)
+    }
+
+    fn index_of(&self, value: Value) -> Option<usize> {
+        match &self.support {
+            Support::Contiguous { min } => {
+                let i = usize::try_from(value.checked_sub(*min)?).ok()?;
+                (i < self.len()).then_some(i)
+            }
+            Support::Sparse(values) => values.binary_search(&value).ok(),
+        }
+    }
+
+    fn value_at(&self, index: usize) -> Value {
+        match &self.support {
+            Support::Contiguous { min } => min + index as Value,
+            Support::Sparse(values) => values[index],
+        }
+    }
+
+    fn prob_at(&self, index: usize) -> Prob {
+        Prob::new(self.numerators[index].clone(), self.denominator.clone())
+    }
+}
+
+impl Dice {
+    /// the distribution in the memory-compact form of a [`CompactDistribution`], `None` if a probability is negative
+    pub fn to_compact(&self) -> Option<CompactDistribution> {
+        CompactDistribution::from_distribution(&self.distribution)
+    }
+
+    /// the [`Dice`] of a [`CompactDistribution`], with all distribution paramters recalculated
+    pub fn from_compact(
+        compact: &CompactDistribution,
+        builder_string: String,
+    ) -> Result<Dice, DiceBuildError> {
+        Dice::from_sorted_distribution(compact.to_distribution(), builder_string)
+    }
+
+    /// the approximate number of bytes on the heap that the distribution takes, for comparisons with `CompactDistribution::heap_size()`
+    ///
+    /// the cumulative distribution, once calculated, takes about the same again.
+    pub fn distribution_heap_size(&self) -> usize {
+        let fractions: usize = self
+            .distribution
+            .iter()
+            .map(|(_, p)| match p {
+                Prob::Rational(_, ratio) => {
+                    biguint_heap_size(ratio.numer()) + biguint_heap_size(ratio.denom())
+                }
+                _ => 0,
+            })
+            .sum();
+        self.distribution.len() * size_of::<(Value, Prob)>() + fractions
+    }
+}
+
+/// bytes of the digits of `n`, without spare capacity
+fn biguint_heap_size(n: &BigUint) -> usize {
+    n.bits().div_ceil(u64::BITS as u64) as usize * size_of::<u64>()
+}
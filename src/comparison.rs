@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+
+use crate::{
+    dice::Dice,
+    dice_builder::{DiceBuildError, Prob, Value},
+    prob_like::ProbLike,
+    transform::DiceOperation,
+};
+
+/// The outcome of an opposed roll between two independent [`Dice`], created with [`Dice::contest()`]
+///
+/// # Examples
+/// ```
+/// use dices::Contest;
+/// use fraction::BigFraction;
+/// let contest = Contest::build_from_strings("d20+5", "d20+3").unwrap();
+/// assert_eq!(contest.win + contest.tie + contest.loss, BigFraction::from(1));
+/// assert_eq!(contest.expected_margin, BigFraction::from(2));
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct Contest<P = Prob> {
+    /// distribution of the margin of success, the first value minus the second value
+    pub margin: Dice<P>,
+    /// distribution of the sign of the margin: 1 for a win, 0 for a tie and -1 for a loss of the first dice
+    pub outcome: Dice<P>,
+    /// probability that the first value is greater than the second value
+    pub win: P,
+    /// probability that both values are equal
+    pub tie: P,
+    /// probability that the first value is less than the second value
+    pub loss: P,
+    /// mean of the margin of success
+    pub expected_margin: P,
+}
+
+impl Contest {
+    /// builds both formulas and contests them against each other
+    pub fn build_from_strings(first: &str, second: &str) -> Result<Contest, DiceBuildError> {
+        let first = Dice::try_build_from_string(first)?;
+        let second = Dice::try_build_from_string(second)?;
+        first.try_contest(&second)
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// true if `self` and `other` have exactly the same probability for every value, regardless of their formulas
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let a = Dice::build_from_string("2d6").unwrap();
+    /// let b = Dice::build_from_string("d6+d6").unwrap();
+    /// assert!(a.same_distribution(&b));
+    /// assert!(!a.same_distribution(&Dice::build_from_string("d11+1").unwrap()));
+    /// ```
+    pub fn same_distribution(&self, other: &Dice<P>) -> bool {
+        // values with probability zero are not part of a distribution
+        let zero = P::zero();
+        let support = self.distribution.iter().filter(|(_, p)| *p != zero);
+        support.eq(other.distribution.iter().filter(|(_, p)| *p != zero))
+    }
+
+    /// probability that a number sampled from `self` is greater than an independently sampled number from `other`
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// assert_eq!(d6.prob_beats(&d6), BigFraction::new(15u64, 36u64));
+    /// ```
+    pub fn prob_beats(&self, other: &Dice<P>) -> P {
+        let mut total = P::zero();
+        for (v, p) in self.distribution.iter() {
+            total += p.clone() * other.prob_lt(*v);
+        }
+        total
+    }
+
+    /// probability that a number sampled from `self` is equal to an independently sampled number from `other`
+    pub fn prob_ties(&self, other: &Dice<P>) -> P {
+        let mut total = P::zero();
+        for (v, p) in self.distribution.iter() {
+            total += p.clone() * other.prob(*v);
+        }
+        total
+    }
+
+    /// probability that a number sampled from `self` is less than an independently sampled number from `other`
+    pub fn prob_loses(&self, other: &Dice<P>) -> P {
+        other.prob_beats(self)
+    }
+
+    /// the opposed roll of `self` against an independent roll of `other`
+    ///
+    /// panics if a margin does not fit into a [`Value`], `try_contest()` returns an error instead.
+    pub fn contest(&self, other: &Dice<P>) -> Contest<P> {
+        self.try_contest(other)
+            .expect("the margins do not fit into 64 bits")
+    }
+
+    /// the opposed roll of `self` against an independent roll of `other` like `contest()`, [`DiceBuildError::ValueOverflow`] if a margin does not fit into a [`Value`]
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Contest, DiceBuildError};
+    /// assert_eq!(
+    ///     Contest::build_from_strings("9223372036854775807", "-1").err(),
+    ///     Some(DiceBuildError::ValueOverflow)
+    /// );
+    /// ```
+    pub fn try_contest(&self, other: &Dice<P>) -> Result<Contest<P>, DiceBuildError> {
+        let margin = self.try_combined_with(DiceOperation::Subtract, other)?;
+        let outcome = margin.map_values(|v| v.signum(), String::new());
+        Ok(Contest {
+            win: margin.prob_gt(0),
+            tie: margin.prob(0),
+            loss: margin.prob_lt(0),
+            expected_margin: margin.mean.clone(),
+            margin,
+            outcome,
+        })
+    }
+
+    /// total variation distance to `other`: the largest difference in probability that the two dice can assign to the same event
+    ///
+    /// 0 for dice with the same distribution, 1 for dice that have no value in common.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// let shifted = Dice::build_from_string("d6+1").unwrap();
+    /// assert_eq!(d6.total_variation(&shifted), BigFraction::new(1u64, 6u64));
+    /// assert_eq!(d6.earth_movers_distance(&shifted), BigFraction::from(1));
+    /// ```
+    pub fn total_variation(&self, other: &Dice<P>) -> P {
+        let mut total = P::zero();
+        for (_, p, q) in merged_pmfs(self, other) {
+            total += abs_diff(p, q);
+        }
+        total * P::from_ratio(1, 2)
+    }
+
+    /// Kullback-Leibler divergence of `self` from `other` in bits: the information lost when `other` is used to approximate `self`
+    ///
+    /// Not symmetric. Infinite if `self` has a value that `other` can never roll.
+    pub fn kl_divergence(&self, other: &Dice<P>) -> f64 {
+        merged_pmfs(self, other)
+            .into_iter()
+            .map(|(_, p, q)| (p.to_float(), q.to_float()))
+            .filter(|(p, _)| *p > 0.0)
+            .map(|(p, q)| match q > 0.0 {
+                true => p * (p / q).log2(),
+                false => f64::INFINITY,
+            })
+            .sum()
+    }
+
+    /// earth mover's distance (Wasserstein-1 distance) to `other`: the expected distance probability mass has to be moved to turn one distribution into the other
+    ///
+    /// For dice that only differ by a constant shift this is the size of the shift.
+    pub fn earth_movers_distance(&self, other: &Dice<P>) -> P {
+        let merged = merged_pmfs(self, other);
+        let mut cumulative_self = P::zero();
+        let mut cumulative_other = P::zero();
+        let mut total = P::zero();
+        for window in merged.windows(2) {
+            let (v, p, q) = &window[0];
+            cumulative_self += p;
+            cumulative_other += q;
+            let width = P::from_value(window[1].0 - v);
+            total += abs_diff(cumulative_self.clone(), cumulative_other.clone()) * width;
+        }
+        total
+    }
+}
+
+/// the union of the values of both distributions with the probability of each value in `first` and `second`
+fn merged_pmfs<P: ProbLike>(first: &Dice<P>, second: &Dice<P>) -> Vec<(Value, P, P)> {
+    let mut merged = Vec::with_capacity(first.distribution.len() + second.distribution.len());
+    let mut first_iter = first.distribution.iter().peekable();
+    let mut second_iter = second.distribution.iter().peekable();
+    loop {
+        let order = match (first_iter.peek(), second_iter.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((a, _)), Some((b, _))) => a.cmp(b),
+        };
+        let entry = match order {
+            Ordering::Less => {
+                let (v, p) = first_iter.next().unwrap();
+                (*v, p.clone(), P::zero())
+            }
+            Ordering::Greater => {
+                let (v, q) = second_iter.next().unwrap();
+                (*v, P::zero(), q.clone())
+            }
+            Ordering::Equal => {
+                let (v, p) = first_iter.next().unwrap();
+                let (_, q) = second_iter.next().unwrap();
+                (*v, p.clone(), q.clone())
+            }
+        };
+        merged.push(entry);
+    }
+    merged
+}
+
+fn abs_diff<P: ProbLike>(a: P, b: P) -> P {
+    match a > b {
+        true => a - b,
+        false => b - a,
+    }
+}
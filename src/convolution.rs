@@ -0,0 +1,235 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use fraction::{BigUint, GenericFraction, Integer, Ratio, Sign, Zero};
+
+use crate::{
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// sum convolutions with at least this many pairs of values use [`ProbLike::fast_sum_convolute()`]
+pub(crate) const FAST_CONVOLUTION_MIN_WORK: usize = 64;
+
+/// the distribution of `operation(a, b)` for two independent samples `a` and `b` of the sorted distributions `first` and `second`
+///
+/// the result is sorted by value again. Values that `operation` maps to the same result have their probabilities added up.
+/// `None` if `operation` returns `None` for a pair of values.
+pub(crate) fn convolute_sorted<P: ProbLike>(
+    first: &[(Value, P)],
+    second: &[(Value, P)],
+    operation: impl Fn(Value, Value) -> Option<Value>,
+) -> Option<Vec<(Value, P)>> {
+    let fast = P::fast_convolute(
+        first.iter().map(|(v, p)| (v, p)),
+        second.iter().map(|(v, p)| (v, p)),
+        &operation,
+    );
+    if fast.is_some() {
+        return fast;
+    }
+    let mut result: BTreeMap<Value, P> = BTreeMap::new();
+    for (v1, p1) in first.iter() {
+        for (v2, p2) in second.iter() {
+            let mut p = p1.clone();
+            p *= p2;
+            match result.entry(operation(*v1, *v2)?) {
+                std::collections::btree_map::Entry::Occupied(mut e) => {
+                    *e.get_mut() += p;
+                }
+                std::collections::btree_map::Entry::Vacant(e) => {
+                    e.insert(p);
+                }
+            }
+        }
+    }
+    Some(result.into_iter().collect())
+}
+
+/// the distribution of the sum of two independent samples of the sorted distributions `first` and `second`
+///
+/// if both supports are contiguous the probabilities are accumulated in a vector instead of a map.
+/// The sums of the smallest and of the largest values have to fit into a [`Value`].
+pub(crate) fn sum_convolute_sorted<P: ProbLike>(
+    first: &[(Value, P)],
+    second: &[(Value, P)],
+) -> Vec<(Value, P)> {
+    if !is_contiguous(first) || !is_contiguous(second) {
+        return convolute_sorted(first, second, Value::checked_add)
+            .expect("the sums of the extremes fit into a value");
+    }
+    let offset = first[0].0 + second[0].0;
+    let first_probs: Vec<P> = first.iter().map(|(_, p)| p.clone()).collect();
+    let second_probs: Vec<P> = second.iter().map(|(_, p)| p.clone()).collect();
+    let fast = match first.len() * second.len() >= FAST_CONVOLUTION_MIN_WORK {
+        true => P::fast_sum_convolute(&first_probs, &second_probs),
+        false => None,
+    };
+    let probs = fast.unwrap_or_else(|| {
+        let mut probs: Vec<P> = vec![P::zero(); first.len() + second.len() - 1];
+        for (i, p1) in first_probs.iter().enumerate() {
+            for (j, p2) in second_probs.iter().enumerate() {
+                let mut p = p1.clone();
+                p *= p2;
+                probs[i + j] += p;
+            }
+        }
+        probs
+    });
+    probs
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| (offset + i as Value, p))
+        .collect()
+}
+
+/// the distribution of the sum of `count` independent samples of the sorted `distribution`, by binary exponentiation
+///
+/// The distributions of 1, 2, 4, ... samples are calculated by convoluting the previous one with itself,
+/// and the ones of the set bits of `count` are convoluted into the result, so about 2 log2(`count`) convolutions are needed instead of `count` - 1.
+///
+/// `None` if `distribution` is empty or a sum does not fit into a [`Value`].
+pub(crate) fn sample_sum_sorted<P: ProbLike>(
+    distribution: &[(Value, P)],
+    count: usize,
+) -> Option<Vec<(Value, P)>> {
+    let (min, max) = (distribution.first()?.0, distribution.last()?.0);
+    // every partial sum lies between the sums of `count` minima and maxima
+    let count_value = Value::try_from(count).ok()?;
+    min.checked_mul(count_value)?;
+    max.checked_mul(count_value)?;
+    let mut result: Vec<(Value, P)> = vec![(0, P::one())];
+    let mut power: Cow<[(Value, P)]> = Cow::Borrowed(distribution);
+    let mut remaining = count;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = sum_convolute_sorted(&result, &power);
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            power = Cow::Owned(sum_convolute_sorted(&power, &power));
+        }
+    }
+    Some(result)
+}
+
+/// true if the sorted distribution is non-empty and has no gaps in its support
+fn is_contiguous<P>(distribution: &[(Value, P)]) -> bool {
+    match (distribution.first(), distribution.last()) {
+        (Some((min, _)), Some((max, _))) => max.abs_diff(*min) == distribution.len() as u64 - 1,
+        _ => false,
+    }
+}
+
+/// exact sum convolution of non-negative fractions by Kronecker substitution
+///
+/// The probabilities of each side are brought to a common denominator and their numerators are packed into the digits of a single big integer,
+/// with enough space per value that no sum of products overflows into the next one.
+/// The product of the two big integers then holds the numerators of the convoluted probabilities, over the product of the denominators.
+/// Multiplying big integers is subquadratic and avoids reducing a fraction for every pair of probabilities.
+///
+/// `None` if one of the probabilities is negative, infinite or NaN.
+pub(crate) fn kronecker_sum_convolute(first: &[Prob], second: &[Prob]) -> Option<Vec<Prob>> {
+    let (first_numers, first_denom) = common_denominator(first)?;
+    let (second_numers, second_denom) = common_denominator(second)?;
+    let max_bits = |numers: &[BigUint]| numers.iter().map(|n| n.bits()).max().unwrap_or(0);
+    // every value of the result is a sum of at most min(len) products
+    let shorter_len = first.len().min(second.len()) as u64;
+    let slot_bits = max_bits(&first_numers)
+        + max_bits(&second_numers)
+        + (u64::BITS - shorter_len.leading_zeros()) as u64;
+    let slot_digits = slot_bits.div_ceil(u32::BITS as u64) as usize;
+    let product = pack(&first_numers, slot_digits) * pack(&second_numers, slot_digits);
+    let mut digits = product.to_u32_digits();
+    let len = first.len() + second.len() - 1;
+    digits.resize(len * slot_digits, 0);
+    let denom = first_denom * second_denom;
+    Some(
+        digits
+            .chunks(slot_digits)
+            .map(|numer| Prob::new(BigUint::new(numer.to_vec()), denom.clone()))
+            .collect(),
+    )
+}
+
+/// exact convolution of fractions that share a denominator, by adding up products of their numerators as integers
+///
+/// The probabilities of a build are mostly fractions over products of die sizes, so the denominators of one distribution all divide the largest one.
+/// The numerators over it are multiplied and added up without any fractions,
+/// and only the probability of each result is reduced once over the product of both denominators, instead of a fraction for every pair of probabilities.
+///
+/// `None` if the denominators of one side do not all divide the largest one, if a probability is negative, infinite or NaN, or if `operation` overflows.
+pub(crate) fn shared_denominator_convolute<'a>(
+    first: impl Iterator<Item = (&'a Value, &'a Prob)>,
+    second: impl Iterator<Item = (&'a Value, &'a Prob)> + Clone,
+    operation: impl Fn(Value, Value) -> Option<Value>,
+) -> Option<Vec<(Value, Prob)>> {
+    let (first_values, first_probs): (Vec<Value>, Vec<&Prob>) = first.unzip();
+    let (second_values, second_probs): (Vec<Value>, Vec<&Prob>) = second.unzip();
+    let (first_numers, first_denom) = shared_denominator(&first_probs)?;
+    let (second_numers, second_denom) = shared_denominator(&second_probs)?;
+    let mut numers: BTreeMap<Value, BigUint> = BTreeMap::new();
+    for (v1, n1) in first_values.iter().zip(&first_numers) {
+        for (v2, n2) in second_values.iter().zip(&second_numers) {
+            *numers.entry(operation(*v1, *v2)?).or_default() += n1 * n2;
+        }
+    }
+    let denom = first_denom * second_denom;
+    Some(
+        numers
+            .into_iter()
+            .map(|(v, numer)| (v, Prob::new(numer, denom.clone())))
+            .collect(),
+    )
+}
+
+/// the numerators of `probs` over the largest of their denominators, `None` if it is not a multiple of all others
+fn shared_denominator(probs: &[&Prob]) -> Option<(Vec<BigUint>, BigUint)> {
+    let ratios = non_negative_ratios(probs.iter().copied())?;
+    let denom = ratios.iter().map(|r| r.denom()).max()?.clone();
+    let mut numers = Vec::with_capacity(ratios.len());
+    for r in ratios {
+        let (factor, remainder) = denom.div_rem(r.denom());
+        if !remainder.is_zero() {
+            return None;
+        }
+        numers.push(r.numer() * factor);
+    }
+    Some((numers, denom))
+}
+
+/// the numerators of `probs` over their least common denominator
+pub(crate) fn common_denominator(probs: &[Prob]) -> Option<(Vec<BigUint>, BigUint)> {
+    let ratios = non_negative_ratios(probs.iter())?;
+    let denom = ratios
+        .iter()
+        .fold(BigUint::from(1u32), |d, r| d.lcm(r.denom()));
+    let numers = ratios
+        .iter()
+        .map(|r| r.numer() * (&denom / r.denom()))
+        .collect();
+    Some((numers, denom))
+}
+
+/// the ratios of `probs`, `None` if one of them is negative, infinite or NaN
+fn non_negative_ratios<'a>(
+    probs: impl Iterator<Item = &'a Prob>,
+) -> Option<Vec<&'a Ratio<BigUint>>> {
+    probs
+        .map(|p| match p {
+            GenericFraction::Rational(Sign::Plus, ratio) => Some(ratio),
+            GenericFraction::Rational(Sign::Minus, ratio) if ratio.numer().is_zero() => Some(ratio),
+            _ => None,
+        })
+        .collect()
+}
+
+/// a big integer with `numers` as its digits in base 2^(32 * `slot_digits`), the first one lowest
+fn pack(numers: &[BigUint], slot_digits: usize) -> BigUint {
+    let mut digits = vec![0u32; numers.len() * slot_digits];
+    for (slot, numer) in digits.chunks_mut(slot_digits).zip(numers) {
+        for (digit, numer_digit) in slot.iter_mut().zip(numer.to_u32_digits()) {
+            *digit = numer_digit;
+        }
+    }
+    BigUint::new(digits)
+}
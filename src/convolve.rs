@@ -0,0 +1,108 @@
+//! Convolutions of distributions, for custom mechanics that are easier to write as code than as a [`DiceBuilder`](crate::DiceBuilder) tree.
+//!
+//! All functions take distributions as slices of values with their probabilities, like [`Dice::distribution`](crate::Dice::distribution),
+//! and return them sorted by value. They use the same engine as a build, including its fast paths for big sums and exact fractions.
+//!
+//! # Examples
+//! ```
+//! use dices::{convolve, Dice};
+//! use fraction::BigFraction;
+//! let d6 = Dice::build_from_string("d6").unwrap();
+//! // a 6 counts double
+//! let doubled_six = convolve::convolve_with(&d6.distribution, &d6.distribution, |a, b| {
+//!     let count = |v: i64| if v == 6 { 12 } else { v };
+//!     count(a).checked_add(count(b))
+//! })
+//! .unwrap();
+//! assert_eq!(doubled_six.last(), Some(&(24, BigFraction::new(1u64, 36u64))));
+//! let three_d6 = convolve::sample_sum(&d6.distribution, 3).unwrap();
+//! assert_eq!(three_d6, Dice::build_from_string("3d6").unwrap().distribution);
+//! ```
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use crate::{
+    build_options::{BuildContext, BuildOptions},
+    convolution::{convolute_sorted, sum_convolute_sorted},
+    dice_builder::{sample_sum_hashmap, DiceBuildError, Value},
+    distribution_map::{DistributionBackend, DistributionMap},
+    prob_like::ProbLike,
+};
+
+/// the distribution of the sum of two independent samples of `first` and `second`
+///
+/// Errors with [`DiceBuildError::EmptyDistribution`] if one of them is empty and with [`DiceBuildError::ValueOverflow`] if a sum does not fit into a [`Value`].
+///
+/// # Examples
+/// ```
+/// use dices::{convolve, Dice};
+/// let d4 = Dice::build_from_string("d4").unwrap();
+/// let d8 = Dice::build_from_string("d8").unwrap();
+/// assert_eq!(
+///     convolve::convolve_sum(&d4.distribution, &d8.distribution).unwrap(),
+///     Dice::build_from_string("d4+d8").unwrap().distribution
+/// );
+/// ```
+pub fn convolve_sum<P: ProbLike>(
+    first: &[(Value, P)],
+    second: &[(Value, P)],
+) -> Result<Vec<(Value, P)>, DiceBuildError> {
+    let (first, second) = (sorted(first)?, sorted(second)?);
+    // only the sums of the extremes can overflow
+    for (a, b) in [
+        (first[0].0, second[0].0),
+        (first[first.len() - 1].0, second[second.len() - 1].0),
+    ] {
+        a.checked_add(b).ok_or(DiceBuildError::ValueOverflow)?;
+    }
+    Ok(sum_convolute_sorted(&first, &second))
+}
+
+/// the distribution of `operation(a, b)` for two independent samples `a` of `first` and `b` of `second`
+///
+/// The probabilities of values that `operation` maps to the same result are added up.
+/// Errors with [`DiceBuildError::EmptyDistribution`] if one of the distributions is empty and with [`DiceBuildError::ValueOverflow`] if `operation` returns `None`.
+/// [`DiceOperation::checked_apply()`](crate::DiceOperation::checked_apply) can be used as `operation` for the operations of formulas.
+pub fn convolve_with<P: ProbLike>(
+    first: &[(Value, P)],
+    second: &[(Value, P)],
+    operation: impl Fn(Value, Value) -> Option<Value>,
+) -> Result<Vec<(Value, P)>, DiceBuildError> {
+    let (first, second) = (sorted(first)?, sorted(second)?);
+    convolute_sorted(&first, &second, operation).ok_or(DiceBuildError::ValueOverflow)
+}
+
+/// the distribution of the sum of `count` independent samples of `distribution`, like `count`d`distribution` in a formula
+///
+/// A `count` of 0 always sums up to 0. The sum is calculated with the limits of the default [`BuildOptions`],
+/// so it errors like a build does, for example with [`DiceBuildError::ValueOverflow`] or [`DiceBuildError::TooManyOutcomes`].
+pub fn sample_sum<P: ProbLike>(
+    distribution: &[(Value, P)],
+    count: usize,
+) -> Result<Vec<(Value, P)>, DiceBuildError> {
+    let distribution = sorted(distribution)?;
+    if count == 0 {
+        return Ok(vec![(0, P::one())]);
+    }
+    let hashmap: DistributionMap<P> = distribution.iter().cloned().collect();
+    let options = BuildOptions::default();
+    let mut ctx = BuildContext::new(&options);
+    Ok(sample_sum_hashmap(&hashmap, count, &mut ctx)?.into_sorted_vec())
+}
+
+/// `distribution` sorted by value with the probabilities of duplicate values added up, only copied if it is not sorted yet
+fn sorted<P: ProbLike>(
+    distribution: &[(Value, P)],
+) -> Result<Cow<'_, [(Value, P)]>, DiceBuildError> {
+    if distribution.is_empty() {
+        return Err(DiceBuildError::EmptyDistribution);
+    }
+    if distribution.windows(2).all(|w| w[0].0 < w[1].0) {
+        return Ok(Cow::Borrowed(distribution));
+    }
+    let mut merged: BTreeMap<Value, P> = BTreeMap::new();
+    for (v, p) in distribution {
+        merged.add_prob(*v, p.clone());
+    }
+    Ok(Cow::Owned(merged.into_iter().collect()))
+}
@@ -0,0 +1,356 @@
+use crate::dice_builder::DiceBuilder;
+
+/// An estimate of the size of a calculation, returned by [`DiceBuilder::estimate_cost()`].
+///
+/// The estimate only looks at the ranges of values in the tree and assumes that supports have no gaps,
+/// so it is meant to tell quick formulas apart from ones that take seconds or longer, not to predict exact numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// estimated number of distinct values of the resulting distribution
+    pub support_size: u64,
+    /// estimated number of elementary operations, comparable to [`BuildOptions::max_total_work`](crate::BuildOptions::max_total_work)
+    pub operations: u64,
+}
+
+/// more distinct counts than this make a sample sum too expensive to estimate in detail
+const MAX_ESTIMATED_COUNTS: i128 = 100_000;
+
+impl DiceBuilder {
+    /// estimates the support size and the number of operations of `build()` without calculating anything
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let cheap = DiceBuilder::from_string("2d6+3").unwrap().estimate_cost();
+    /// assert_eq!(cheap.support_size, 11);
+    /// let expensive = DiceBuilder::from_string("d100xd100xd100").unwrap().estimate_cost();
+    /// assert_eq!(expensive.support_size, 1000000);
+    /// assert!(expensive.operations > 1000 * cheap.operations);
+    /// ```
+    pub fn estimate_cost(&self) -> CostEstimate {
+        let estimate = self.estimate(&mut vec![]);
+        CostEstimate {
+            support_size: estimate.support,
+            operations: estimate.operations,
+        }
+    }
+
+    /// `bindings` holds the value ranges of the variables bound by the enclosing [`DiceBuilder::Let`]s
+    fn estimate<'t>(&'t self, bindings: &mut Vec<(&'t str, (i128, i128))>) -> NodeEstimate {
+        match self {
+            DiceBuilder::Constant(v) => NodeEstimate::constant(*v as i128),
+            DiceBuilder::FairDie { min, max } => {
+                let (min, max) = (*min as i128, *max as i128);
+                NodeEstimate {
+                    min,
+                    max: max.max(min),
+                    support: range_size(min, max).max(1),
+                    operations: 0,
+                }
+            }
+            DiceBuilder::SumCompound(vec) => fold_estimates(vec, bindings, |a, b| {
+                (a.min.saturating_add(b.min), a.max.saturating_add(b.max))
+            }),
+            DiceBuilder::ProductCompound(vec) => fold_estimates(vec, bindings, |a, b| {
+                corner_bounds(a, b, |x, y| x.saturating_mul(y))
+            }),
+            DiceBuilder::DivisionCompound(vec) => fold_estimates(vec, bindings, |a, _| {
+                let abs_max = a.min.saturating_abs().max(a.max.saturating_abs());
+                (-abs_max, abs_max)
+            }),
+            DiceBuilder::MaxCompound(vec) => {
+                fold_estimates(vec, bindings, |a, b| (a.min.max(b.min), a.max.max(b.max)))
+            }
+            DiceBuilder::MinCompound(vec) => {
+                fold_estimates(vec, bindings, |a, b| (a.min.min(b.min), a.max.min(b.max)))
+            }
+            DiceBuilder::PowerCompound(vec) => {
+                let estimates: Vec<NodeEstimate> =
+                    vec.iter().map(|d| d.estimate(bindings)).collect();
+                let mut iter = estimates.into_iter().rev();
+                let Some(mut exponent) = iter.next() else {
+                    return NodeEstimate::constant(0);
+                };
+                for base in iter {
+                    let e = |x: i128| u32::try_from(x.max(0)).unwrap_or(u32::MAX);
+                    let abs_max = base.min.saturating_abs().max(base.max.saturating_abs());
+                    let max = abs_max.saturating_pow(e(exponent.max));
+                    let min = match base.min >= 0 {
+                        true => base.min.saturating_pow(e(exponent.min)).min(base.min),
+                        false => -max,
+                    };
+                    exponent = combine(&base, &exponent, min, max);
+                }
+                exponent
+            }
+            DiceBuilder::SampleSumCompound(vec) => {
+                let mut iter = vec.iter();
+                let Some(first) = iter.next() else {
+                    return NodeEstimate::constant(0);
+                };
+                let mut count = first.estimate(bindings);
+                for d in iter {
+                    let sample = d.estimate(bindings);
+                    count = sample_sum(&count, &sample);
+                }
+                count
+            }
+            DiceBuilder::Absolute(d) => {
+                let d = d.estimate(bindings);
+                let abs_max = d.min.saturating_abs().max(d.max.saturating_abs());
+                let abs_min = match d.min <= 0 && d.max >= 0 {
+                    true => 0,
+                    false => d.min.saturating_abs().min(d.max.saturating_abs()),
+                };
+                NodeEstimate {
+                    min: abs_min,
+                    max: abs_max,
+                    ..d
+                }
+            }
+            DiceBuilder::Negation(d) => {
+                let d = d.estimate(bindings);
+                NodeEstimate {
+                    min: d.max.saturating_neg(),
+                    max: d.min.saturating_neg(),
+                    ..d
+                }
+            }
+            DiceBuilder::Let { name, value, body } => {
+                let value = value.estimate(bindings);
+                bindings.push((name, (value.min, value.max)));
+                let body = body.estimate(bindings);
+                bindings.pop();
+                // the body is calculated once for every value of the bound variable
+                NodeEstimate {
+                    operations: value
+                        .operations
+                        .saturating_add(value.support.saturating_mul(body.operations)),
+                    ..body
+                }
+            }
+            DiceBuilder::Variable(name) => {
+                let (min, max) = bindings
+                    .iter()
+                    .rev()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, bounds)| *bounds)
+                    .unwrap_or((0, 0));
+                NodeEstimate {
+                    min,
+                    max,
+                    support: 1,
+                    operations: 0,
+                }
+            }
+            DiceBuilder::NthHighest {
+                k, dice_builder, ..
+            } => {
+                let d = dice_builder.estimate(bindings);
+                NodeEstimate {
+                    operations: d
+                        .operations
+                        .saturating_add(d.support.saturating_mul(*k as u64)),
+                    ..d
+                }
+            }
+            DiceBuilder::Explode {
+                dice_builder,
+                max_iterations,
+                ..
+            } => {
+                // at worst every explosion adds another roll
+                let rolls = (*max_iterations as i128).saturating_add(1);
+                let d = dice_builder.estimate(bindings);
+                sample_sum(&NodeEstimate::constant(rolls), &d)
+            }
+            DiceBuilder::Reroll { dice_builder, .. } => {
+                // the rerolls of every low value are merged into the distribution
+                let d = dice_builder.estimate(bindings);
+                NodeEstimate {
+                    operations: d
+                        .operations
+                        .saturating_add(d.support.saturating_mul(d.support)),
+                    ..d
+                }
+            }
+            DiceBuilder::Mixture(components) => {
+                let mut estimates = components.iter().map(|(_, d)| d.estimate(bindings));
+                let Some(first) = estimates.next() else {
+                    return NodeEstimate::constant(0);
+                };
+                // every value of every component is weighted and merged once
+                estimates.fold(
+                    NodeEstimate {
+                        operations: first.operations.saturating_add(first.support),
+                        ..first
+                    },
+                    |acc, d| {
+                        let (min, max) = (acc.min.min(d.min), acc.max.max(d.max));
+                        NodeEstimate {
+                            min,
+                            max,
+                            support: acc
+                                .support
+                                .saturating_add(d.support)
+                                .min(range_size(min, max)),
+                            operations: acc
+                                .operations
+                                .saturating_add(d.operations)
+                                .saturating_add(d.support),
+                        }
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// value range, support size and operations of the calculation of a subtree
+#[derive(Debug, Clone, Copy)]
+struct NodeEstimate {
+    min: i128,
+    max: i128,
+    support: u64,
+    operations: u64,
+}
+
+impl NodeEstimate {
+    fn constant(v: i128) -> NodeEstimate {
+        NodeEstimate {
+            min: v,
+            max: v,
+            support: 1,
+            operations: 0,
+        }
+    }
+}
+
+/// number of integers in `[min, max]`
+fn range_size(min: i128, max: i128) -> u64 {
+    match max < min {
+        true => 0,
+        false => u64::try_from(max.saturating_sub(min).saturating_add(1)).unwrap_or(u64::MAX),
+    }
+}
+
+/// the estimate of a convolution of `a` and `b`, whose result lies in `[min, max]`
+fn combine(a: &NodeEstimate, b: &NodeEstimate, min: i128, max: i128) -> NodeEstimate {
+    let pairs = a.support.saturating_mul(b.support);
+    NodeEstimate {
+        min,
+        max,
+        support: pairs.min(range_size(min, max)).max(1),
+        operations: a
+            .operations
+            .saturating_add(b.operations)
+            .saturating_add(pairs),
+    }
+}
+
+/// estimates the elements of a compound and combines them from left to right, `bounds` gives the value range of each combination
+fn fold_estimates<'t>(
+    vec: &'t [DiceBuilder],
+    bindings: &mut Vec<(&'t str, (i128, i128))>,
+    bounds: fn(&NodeEstimate, &NodeEstimate) -> (i128, i128),
+) -> NodeEstimate {
+    let mut iter = vec.iter();
+    let Some(first) = iter.next() else {
+        return NodeEstimate::constant(0);
+    };
+    let mut acc = first.estimate(bindings);
+    for d in iter {
+        let next = d.estimate(bindings);
+        let (min, max) = bounds(&acc, &next);
+        acc = combine(&acc, &next, min, max);
+    }
+    acc
+}
+
+/// the smallest and the largest of the four products of the bounds of `a` and `b`
+fn corner_bounds(
+    a: &NodeEstimate,
+    b: &NodeEstimate,
+    operation: fn(i128, i128) -> i128,
+) -> (i128, i128) {
+    let corners = [
+        operation(a.min, b.min),
+        operation(a.min, b.max),
+        operation(a.max, b.min),
+        operation(a.max, b.max),
+    ];
+    (
+        *corners.iter().min().unwrap(),
+        *corners.iter().max().unwrap(),
+    )
+}
+
+/// the estimate of rolling `sample` as many times as `count` rolls, every count is calculated as a separate repeated convolution
+fn sample_sum(count: &NodeEstimate, sample: &NodeEstimate) -> NodeEstimate {
+    let mut operations = count.operations.saturating_add(sample.operations);
+    let mut support: u64 = 0;
+    let (mut min, mut max) = (i128::MAX, i128::MIN);
+    if count.max.saturating_sub(count.min) > MAX_ESTIMATED_COUNTS {
+        operations = u64::MAX;
+    }
+    let counts = count.min
+        ..=count
+            .max
+            .min(count.min.saturating_add(MAX_ESTIMATED_COUNTS));
+    for c in counts {
+        let n = c.unsigned_abs().min(u64::MAX as u128) as u64;
+        let (sum_min, sum_max) = match c < 0 {
+            true => (sample.max.saturating_mul(c), sample.min.saturating_mul(c)),
+            false => (sample.min.saturating_mul(c), sample.max.saturating_mul(c)),
+        };
+        min = min.min(sum_min);
+        max = max.max(sum_max);
+        let (sum_support, sum_operations) = repeated_sum(sample, n);
+        support = support.saturating_add(sum_support);
+        operations = operations.saturating_add(sum_operations);
+    }
+    NodeEstimate {
+        min,
+        max,
+        support: support.min(range_size(min, max)).max(1),
+        operations,
+    }
+}
+
+/// support size and operations of the sum of `n` samples of `sample`, convoluted one after the other
+fn repeated_sum(sample: &NodeEstimate, n: u64) -> (u64, u64) {
+    if n == 0 {
+        return (1, 0);
+    }
+    let width = range_size(sample.min, sample.max).saturating_sub(1) as u128;
+    let s = sample.support as u128;
+    // a sample with a single value, like a bound variable, never grows the support
+    if s <= 1 {
+        return (1, n - 1);
+    }
+    let mut support = s;
+    let mut operations: u128 = 0;
+    let mut i: u128 = 1;
+    // until the support fills its range, it grows by a factor of s with each sample,
+    // so it fills the range after about log(width)/log(s) samples, the few more steps cover the growing range
+    let growth_steps = u128::from(width.max(1).ilog(s)) + 8;
+    while i < n as u128 && i <= growth_steps && support < i * width + 1 {
+        operations = operations.saturating_add(support.saturating_mul(s));
+        support = support.saturating_mul(s).min((i + 1) * width + 1);
+        i += 1;
+    }
+    // afterwards the support of i samples is i * width + 1
+    if i < n as u128 {
+        let steps = n as u128 - i;
+        let first = i;
+        let last = n as u128 - 1;
+        let index_sum = (first + last).saturating_mul(steps) / 2;
+        let supports = width.saturating_mul(index_sum).saturating_add(steps);
+        operations = operations.saturating_add(supports.saturating_mul(s));
+        support = width.saturating_mul(n as u128).saturating_add(1);
+    }
+    (
+        u64::try_from(support).unwrap_or(u64::MAX),
+        u64::try_from(operations).unwrap_or(u64::MAX),
+    )
+}
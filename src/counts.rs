@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    dice::Dice,
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+    wasm_safe::{elapsed_millis, WasmSafeInstant},
+};
+
+/// How many rolls it takes until a condition first holds, created with [`Dice::rolls_until()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollsUntil<P = Prob> {
+    /// probability that a single roll satisfies the condition
+    pub prob_success: P,
+    /// probability that the condition first holds on the k-th roll, for every k from 1 up to the cap, in ascending order
+    pub distribution: Vec<(Value, P)>,
+    /// probability that the condition does not hold in any of the rolls up to the cap
+    pub prob_beyond_cap: P,
+    /// expected number of rolls without a cap, `None` if the condition can never hold
+    pub expected_rolls: Option<P>,
+}
+
+/// How many turns it takes until the running total of the rolls reaches a threshold, created with [`Dice::turns_to_reach()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnsToReach<P = Prob> {
+    /// probability that the total first reaches the threshold after k rolls, for every possible k up to the cap, in ascending order
+    pub distribution: Vec<(Value, P)>,
+    /// probability that the total is still below the threshold after the last turn of the cap
+    pub prob_beyond_cap: P,
+    /// expected number of turns, `None` if the threshold may not be reached within the cap
+    pub expected_turns: Option<P>,
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// distribution of how many of `n` independent rolls of `self` are `value`, like the number of 6s in 10d6
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// let sixes = d6.count_of(6, 10);
+    /// assert_eq!((sixes.min, sixes.max), (0, 10));
+    /// assert_eq!(sixes.mean, BigFraction::new(10u64, 6u64));
+    /// assert_eq!(sixes.prob(10), BigFraction::new(1u64, 60466176u64));
+    /// ```
+    pub fn count_of(&self, value: Value, n: usize) -> Dice<P> {
+        self.count_where(n, |v| v == value)
+    }
+
+    /// distribution of how many of `n` independent rolls of `self` satisfy `predicate`, like the successes of a dice pool
+    ///
+    /// # Examples
+    /// the number of dice showing at least 5 in a pool of 6d6:
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// let successes = d6.count_where(6, |v| v >= 5);
+    /// assert_eq!(successes.mean, BigFraction::from(2));
+    /// ```
+    pub fn count_where(&self, n: usize, predicate: impl Fn(Value) -> bool) -> Dice<P> {
+        let start_instant = WasmSafeInstant::now();
+        let mut p = P::zero();
+        for (_, q) in self.distribution.iter().filter(|(v, _)| predicate(*v)) {
+            p += q;
+        }
+        let mut dice = Dice::from_sorted_distribution(binomial(n, p), String::new())
+            .expect("a binomial distribution is not empty");
+        dice.build_time = elapsed_millis(&start_instant);
+        dice
+    }
+
+    /// the geometric distribution of the number of independent rolls of `self` until `predicate` first holds, up to `max_rolls` rolls
+    ///
+    /// # Examples
+    /// how many turns until a natural 20:
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d20 = Dice::build_from_string("d20").unwrap();
+    /// let turns = d20.rolls_until(|v| v == 20, 3);
+    /// assert_eq!(turns.expected_rolls, Some(BigFraction::from(20)));
+    /// assert_eq!(turns.distribution[1], (2, BigFraction::new(19u64, 400u64)));
+    /// assert_eq!(turns.prob_beyond_cap, BigFraction::new(6859u64, 8000u64));
+    /// ```
+    pub fn rolls_until(
+        &self,
+        predicate: impl Fn(Value) -> bool,
+        max_rolls: usize,
+    ) -> RollsUntil<P> {
+        let mut prob_success = P::zero();
+        for (_, p) in self.distribution.iter().filter(|(v, _)| predicate(*v)) {
+            prob_success += p;
+        }
+        let prob_failure = P::one() - prob_success.clone();
+        let mut distribution = Vec::with_capacity(max_rolls);
+        // probability that all rolls so far failed
+        let mut prob_beyond_cap = P::one();
+        for k in 1..=max_rolls {
+            distribution.push((k as Value, prob_beyond_cap.clone() * prob_success.clone()));
+            prob_beyond_cap *= &prob_failure;
+        }
+        let expected_rolls = match prob_success > P::zero() {
+            true => Some(P::one() / prob_success.clone()),
+            false => None,
+        };
+        RollsUntil {
+            prob_success,
+            distribution,
+            prob_beyond_cap,
+            expected_rolls,
+        }
+    }
+
+    /// the distribution of the number of independent rolls of `self` needed until their sum reaches `threshold`, up to `max_turns` rolls
+    ///
+    /// For a damage formula and the hit points of an enemy this is the number of turns it takes to defeat the enemy.
+    /// Rolls may be negative or zero, a threshold of 0 or less is reached after 0 turns.
+    ///
+    /// # Examples
+    /// turns to deal 10 damage with attacks that deal d6 damage:
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let damage = Dice::build_from_string("d6").unwrap();
+    /// let turns = damage.turns_to_reach(10, 10);
+    /// assert_eq!(turns.distribution[0], (2, BigFraction::new(6u64, 36u64)));
+    /// assert_eq!(turns.prob_beyond_cap, BigFraction::from(0));
+    /// assert!(turns.expected_turns.is_some());
+    /// ```
+    pub fn turns_to_reach(&self, threshold: Value, max_turns: usize) -> TurnsToReach<P> {
+        let zero = P::zero();
+        let mut distribution: Vec<(Value, P)> = vec![];
+        // probability of every total that has not reached the threshold yet, reached totals are absorbed.
+        // The totals are kept as i128, so sums of rolls near the bounds of a Value cannot overflow
+        let threshold = i128::from(threshold);
+        let mut running: BTreeMap<i128, P> = BTreeMap::new();
+        match threshold <= 0 {
+            true => distribution.push((0, P::one())),
+            false => {
+                running.insert(0, P::one());
+            }
+        }
+        for turn in 1..=max_turns {
+            if running.is_empty() {
+                break;
+            }
+            let mut next: BTreeMap<i128, P> = BTreeMap::new();
+            let mut reached = P::zero();
+            for (total, p) in running.iter() {
+                for (v, q) in self.distribution.iter() {
+                    let prob = p.clone() * q.clone();
+                    let sum = total + i128::from(*v);
+                    match sum >= threshold {
+                        true => reached += prob,
+                        false => *next.entry(sum).or_insert_with(P::zero) += prob,
+                    }
+                }
+            }
+            if reached != zero {
+                distribution.push((turn as Value, reached));
+            }
+            running = next;
+        }
+        let mut prob_beyond_cap = P::zero();
+        for p in running.values() {
+            prob_beyond_cap += p;
+        }
+        let expected_turns = match prob_beyond_cap == zero {
+            true => {
+                let mut expected = P::zero();
+                for (turn, p) in distribution.iter() {
+                    expected += P::from_value(*turn) * p.clone();
+                }
+                Some(expected)
+            }
+            false => None,
+        };
+        TurnsToReach {
+            distribution,
+            prob_beyond_cap,
+            expected_turns,
+        }
+    }
+
+    /// the distribution of the count of every value of `self` in `n` independent rolls, in ascending order of the values
+    ///
+    /// Every count is a marginal distribution, the counts of different values are not independent of each other.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d4 = Dice::build_from_string("d4").unwrap();
+    /// let counts = d4.value_counts(3);
+    /// assert_eq!(counts.len(), 4);
+    /// assert_eq!(counts[3].0, 4);
+    /// assert_eq!(counts[3].1.distribution, d4.count_of(4, 3).distribution);
+    /// ```
+    pub fn value_counts(&self, n: usize) -> Vec<(Value, Dice<P>)> {
+        self.distribution
+            .iter()
+            .map(|(v, _)| (*v, self.count_of(*v, n)))
+            .collect()
+    }
+}
+
+/// the distribution of the number of successes in `n` independent trials with success probability `p`
+fn binomial<P: ProbLike>(n: usize, p: P) -> Vec<(Value, P)> {
+    let q = P::one() - p.clone();
+    let powers = |base: P| {
+        let mut powers = Vec::with_capacity(n + 1);
+        powers.push(P::one());
+        for k in 0..n {
+            powers.push(powers[k].clone() * base.clone());
+        }
+        powers
+    };
+    let (p_powers, q_powers) = (powers(p), powers(q));
+    let zero = P::zero();
+    let mut coefficient = P::one();
+    let mut distribution = Vec::with_capacity(n + 1);
+    for k in 0..=n {
+        let prob = coefficient.clone() * p_powers[k].clone() * q_powers[n - k].clone();
+        // impossible counts, like any 7 in rolls of a d6, are not part of the distribution
+        if prob != zero {
+            distribution.push((k as Value, prob));
+        }
+        coefficient *= P::from_ratio((n - k) as u64, (k + 1) as u64);
+    }
+    distribution
+}
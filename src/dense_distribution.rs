@@ -0,0 +1,83 @@
+use crate::{
+    convolution::FAST_CONVOLUTION_MIN_WORK,
+    dice_builder::Value,
+    distribution_map::{DistributionBackend, DistributionMap},
+    prob_like::ProbLike,
+};
+
+/// A distribution with a contiguous support `offset..offset + probs.len()`, stored without hashing.
+///
+/// Most dice have contiguous supports, and sums of contiguous supports are contiguous again,
+/// so sum convolutions can index into vectors instead of hashing every value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DenseDistribution<P> {
+    offset: Value,
+    probs: Vec<P>,
+}
+
+impl<P: ProbLike> DenseDistribution<P> {
+    /// returns `None` if the support of `hashmap` has gaps
+    pub fn from_hashmap(hashmap: &DistributionMap<P>) -> Option<DenseDistribution<P>> {
+        let min = hashmap.min_value()?;
+        let max = hashmap.max_value()?;
+        if max.abs_diff(min) != hashmap.len() as u64 - 1 {
+            return None;
+        }
+        let mut probs: Vec<P> = vec![P::zero(); hashmap.len()];
+        for (value, p) in hashmap.iter() {
+            probs[(value - min) as usize] = p.clone();
+        }
+        Some(DenseDistribution { offset: min, probs })
+    }
+
+    pub fn into_hashmap(self) -> DistributionMap<P> {
+        let offset = self.offset;
+        self.probs
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (offset + i as Value, p))
+            .collect()
+    }
+
+    /// the smallest and the largest value
+    pub fn value_range(&self) -> (Value, Value) {
+        (self.offset, self.offset + (self.probs.len() - 1) as Value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.probs.len()
+    }
+
+    /// the distribution of the sum of two independent samples of `self` and `other`
+    ///
+    /// `checkpoint` is called before each of the `self.len()` rows of `other.len()` operations and can abort the convolution.
+    /// Big convolutions use [`ProbLike::fast_sum_convolute()`] if possible and call `checkpoint` once with all operations afterwards.
+    pub fn sum_convolute<E>(
+        &self,
+        other: &DenseDistribution<P>,
+        mut checkpoint: impl FnMut(u64) -> Result<(), E>,
+    ) -> Result<DenseDistribution<P>, E> {
+        if self.len() * other.len() >= FAST_CONVOLUTION_MIN_WORK {
+            if let Some(probs) = P::fast_sum_convolute(&self.probs, &other.probs) {
+                checkpoint((self.len() * other.len()) as u64)?;
+                return Ok(DenseDistribution {
+                    offset: self.offset + other.offset,
+                    probs,
+                });
+            }
+        }
+        let mut probs: Vec<P> = vec![P::zero(); self.len() + other.len() - 1];
+        for (i, p1) in self.probs.iter().enumerate() {
+            checkpoint(other.len() as u64)?;
+            for (j, p2) in other.probs.iter().enumerate() {
+                let mut p = p1.clone();
+                p *= p2;
+                probs[i + j] += p;
+            }
+        }
+        Ok(DenseDistribution {
+            offset: self.offset + other.offset,
+            probs,
+        })
+    }
+}
@@ -2,24 +2,34 @@
 use wasm_bindgen::prelude::*;
 
 #[cfg(feature = "wasm")]
-use serde::{Deserialize, Serialize};
-
-#[cfg(feature = "wasm")]
-use fraction::{BigFraction, BigUint, Sign};
-
-#[cfg(feature = "wasm")]
-use std::fmt::Display;
+use crate::{
+    build_options::BuildProgress,
+    js_types::{JsBucket, JsDiceError, JsDistribution, JsFraction, JsProbAll, JsStats},
+};
 
-use fraction::{One, ToPrimitive, Zero};
-use std::ops::Add;
+use fraction::ToPrimitive;
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use crate::{
-    dice_string_parser::DiceBuildingError,
+    build_options::{BuildContext, BuildOptions, BuildReport, ProgressCallback},
+    cache::{global_cache, set_global_cache, try_build_cached, DiceCache},
+    dice_string_parser::{DiceBuildingError, DiceBuildingErrorKind},
     wasm_safe::{elapsed_millis, random_number_between_0_and_1, WasmSafeInstant},
-    DiceBuilder,
+    DiceBuildError, DiceBuilder,
 };
 
-use super::dice_builder::{AggrValue, Prob, Value};
+use super::{
+    dice_builder::{Prob, Value},
+    dice_value::{value_distribution, DiceValue},
+    distribution_map::DistributionBackend,
+    prob_like::ProbLike,
+};
 
 /// A [`Dice`] represents a discrete probability distribution, providing paramters like mean, standard deviation and the `roll()` method to randomly sample from this distribution
 ///
@@ -35,44 +45,108 @@ use super::dice_builder::{AggrValue, Prob, Value};
 /// let dice = dice_builder.build();
 /// ```
 ///
-/// Values of the distribution are of type [`i64`]
-/// The probabilities are of type [`BigFraction`](fraction::BigFraction) from the [`fraction`](fraction) crate.
+/// By default the values of the distribution are of type [`i64`], see [`Value`].
+/// Formulas whose values do not fit into it can be built with a wider [`DiceValue`], see `DiceBuilder::build_values_as()`.
+/// By default the probabilities are of type [`BigFraction`](fraction::BigFraction) from the [`fraction`](fraction) crate.
 /// This allows for precise probabilites with infinite precision, at the cost of some slower operations compared to floats, but avoids pitfalls like floating point precision errors.
+/// Any other [`ProbLike`] type can be used instead, see [`DiceF64`] and `DiceBuilder::build_as()`.
+///
+/// A [`Dice`] can be sent to other threads. To share one between threads without cloning its distribution, wrap it in a [`SharedDice`](crate::SharedDice).
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct Dice {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dice<P = Prob, V = Value> {
     /// a string that can be used to recreate the [`DiceBuilder`] that the [`Dice`] was created from.
+    ///
+    /// empty if the [`Dice`] was derived from another [`Dice`] in a way that has no string notation, like `given()`.
     pub builder_string: String,
     /// mininum value of the probability distribution
-    pub min: Value,
+    pub min: V,
     /// maximum value of the probability distribution
-    pub max: Value,
-    /// median  of the probability distribution
-    pub median: Value,
+    pub max: V,
+    /// median  of the probability distribution, the smallest value with a cumulative probability of at least 1/2
+    ///
+    /// see `median_with()` for other conventions.
+    pub median: V,
     /// mode or modes of the probability distribution
-    pub mode: Vec<Value>,
+    ///
+    /// see `mode_with_prob()` for their probability.
+    pub mode: Vec<V>,
     /// mean of the probability distribution
-    pub mean: AggrValue,
+    pub mean: P,
     /// variance of the probability distribution
-    pub variance: AggrValue,
+    pub variance: P,
     /// the probability mass function (pmf) of the dice
     ///
     /// tuples of each value and its probability in ascending order (regarding value)
-    pub distribution: Vec<(Value, Prob)>,
-    /// the cumulative distribution function (cdf) of the dice
-    ///
-    /// tuples of each value and its cumulative probability in ascending order (regarding value)
-    pub cumulative_distribution: Vec<(Value, Prob)>,
+    pub distribution: Vec<(V, P)>,
+    /// the cumulative distribution function (cdf) of the dice, calculated on first access with `cumulative_distribution()`
+    pub(crate) cumulative_distribution: LazyCdf<V, P>,
 
-    /// time it took to build the dice in microseconds
+    /// time it took to build the dice in milliseconds
     pub build_time: u64,
+    /// statistics about the calculation of the dice
+    pub build_report: BuildReport,
 }
 
+/// A [`Dice`] with [`f64`] probabilities, created with `DiceBuilder::build_approx()`.
+///
+/// Calculating it is a lot faster than calculating a [`Dice`] with exact fractions, at the cost of small rounding errors.
+/// ```
+/// use dices::DiceBuilder;
+/// let dice = DiceBuilder::from_string("d10xd100").unwrap().build_approx();
+/// assert_eq!(dice.max, 1000);
+/// ```
+pub type DiceF64 = Dice<f64>;
+
 impl Dice {
     /// uses the `input` to create a [`DiceBuilder`] and calls `build()` on it
+    ///
+    /// If a global cache is set with `set_global_cache()`, the [`Dice`] is taken from it or stored in it.
+    ///
+    /// If the distribution cannot be calculated, like for `d0`, a [`DiceBuildingErrorKind::Build`] error spanning the whole input is returned.
+    /// Use `try_build_from_string()` to get the [`DiceBuildError`] directly.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Dice, DiceBuildError, DiceBuildingErrorKind};
+    /// let err = Dice::build_from_string("d0 + 3").unwrap_err();
+    /// assert_eq!(err.kind, DiceBuildingErrorKind::Build(Box::new(DiceBuildError::ZeroSidedDie)));
+    /// assert_eq!(err.span, 0..6);
+    /// ```
     pub fn build_from_string(input: &str) -> Result<Dice, DiceBuildingError> {
-        let builder = DiceBuilder::from_string(input)?;
-        Ok(builder.build())
+        Dice::try_build_from_string(input).map_err(|err| match err {
+            DiceBuildError::Parse(err) => err,
+            err => DiceBuildingError {
+                kind: DiceBuildingErrorKind::Build(Box::new(err)),
+                span: 0..input.len(),
+                snippet: input.to_string(),
+            },
+        })
+    }
+
+    /// like `try_build_from_string()`, but takes the [`Dice`] from `cache` if an equivalent formula was built before, and stores it in `cache` otherwise
+    pub fn build_from_string_cached(
+        input: &str,
+        cache: &dyn DiceCache,
+    ) -> Result<Dice, DiceBuildError> {
+        try_build_cached(DiceBuilder::from_string(input)?, cache)
+    }
+
+    /// sets the cache used by `build_from_string()` and `try_build_from_string()` for the whole program, `None` disables caching again
+    ///
+    /// # Examples
+    /// ```
+    /// use std::sync::Arc;
+    /// use dices::{Dice, LruDiceCache};
+    /// let cache = Arc::new(LruDiceCache::new(1000));
+    /// Dice::set_global_cache(Some(cache.clone()));
+    /// Dice::build_from_string("d20+5").unwrap();
+    /// Dice::build_from_string("5+d20").unwrap();
+    /// assert_eq!(cache.len(), 1);
+    /// Dice::set_global_cache(None);
+    /// ```
+    pub fn set_global_cache(cache: Option<Arc<dyn DiceCache>>) {
+        set_global_cache(cache);
     }
 
     /// uses the `input` to create a [`DiceBuilder`]. Same as [`DiceBuilder::from_string(input)`]
@@ -80,65 +154,111 @@ impl Dice {
         DiceBuilder::from_string(input)
     }
 
+    /// uses the `input` to create a [`DiceBuilder`] and calls `try_build()` on it, using the global cache like `build_from_string()`
+    pub fn try_build_from_string(input: &str) -> Result<Dice, DiceBuildError> {
+        match global_cache() {
+            Some(cache) => try_build_cached(DiceBuilder::from_string(input)?, cache.as_ref()),
+            None => DiceBuilder::try_build_from_string(input),
+        }
+    }
+
     /// builds a [`Dice`] from a given [`DiceBuilder`]
     ///
     /// this method calculates the distribution and all distribution paramters on the fly, to create the [`Dice`].
     /// Depending on the complexity of the `dice_builder` heavy lifting like convoluting probability distributions may take place here.
+    ///
+    /// panics if the [`DiceBuilder`] is degenerate, use `try_from_builder()` to get a [`DiceBuildError`] instead.
     pub fn from_builder(dice_builder: DiceBuilder) -> Dice {
-        let start_instant = WasmSafeInstant::now();
-        let distribution: Vec<(Value, Prob)> = dice_builder.distribution_iter().collect();
-        let max: Value = distribution.last().map(|e| e.0).unwrap();
-        let min: Value = distribution.first().map(|e| e.0).unwrap();
-        let mut mean: AggrValue = AggrValue::from(0);
+        match Dice::try_from_builder(dice_builder) {
+            Ok(dice) => dice,
+            Err(err) => panic!("could not build dice: {err:?}"),
+        }
+    }
 
-        let mut total_probability: Prob = Prob::new(0u64, 1u64);
-        let median_prob: Prob = Prob::new(1u64, 2u64);
-        // todo median
-        let mut median: Option<Value> = None;
-        let mut mode: Option<(Vec<Value>, Prob)> = None;
+    /// builds a [`Dice`] from a given [`DiceBuilder`] like `from_builder()`, but returns a [`DiceBuildError`] for degenerate trees
+    pub fn try_from_builder(dice_builder: DiceBuilder) -> Result<Dice, DiceBuildError> {
+        Dice::from_builder_with_options(dice_builder, &BuildOptions::default())
+    }
+}
 
-        for (val, prob) in distribution.iter().cloned() {
-            mean += prob.clone() * Prob::from(val);
-            total_probability += prob.clone();
-            match median {
-                Some(_) => {}
-                None => {
-                    if total_probability >= median_prob {
-                        median = Some(val);
-                    }
-                }
-            }
-            match &mode {
-                Some((old_vec, p)) => {
-                    if prob > *p {
-                        mode = Some((vec![val], prob));
-                    } else if prob == *p {
-                        let newvec: Vec<Value> = [val].iter().chain(old_vec).copied().collect();
-                        mode = Some((newvec, prob));
-                    }
-                }
-                None => {
-                    mode = Some((vec![val], prob));
-                }
-            }
-        }
+/// parses and builds a [`Dice`], equivalent to [`Dice::try_build_from_string`]
+///
+/// # Examples
+/// ```
+/// use dices::Dice;
+/// let dice: Dice = "2d6".parse().unwrap();
+/// assert_eq!(dice.max, 12);
+/// ```
+impl FromStr for Dice {
+    type Err = DiceBuildError;
 
-        let mut variance: AggrValue = AggrValue::from(0);
-        for (val, prob) in distribution.iter().cloned() {
-            let val = AggrValue::from(val);
-            let val_minus_mean = &val - &mean;
-            let square = (&val_minus_mean) * (&val_minus_mean);
-            variance += square * prob
-        }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Dice::try_build_from_string(s)
+    }
+}
 
-        let median = median.unwrap();
-        let mode = mode.unwrap().0;
+impl TryFrom<&str> for Dice {
+    type Error = DiceBuildError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Dice::try_build_from_string(value)
+    }
+}
+
+impl<P: ProbLike, V: DiceValue> Dice<P, V> {
+    /// builds a [`Dice`] with values of type `V` from a given [`DiceBuilder`], see `DiceBuilder::build_values_as()`
+    pub fn from_builder_with_values(
+        dice_builder: DiceBuilder,
+        options: &BuildOptions,
+    ) -> Result<Dice<P, V>, DiceBuildError> {
+        let start_instant = WasmSafeInstant::now();
+        let builder_string = dice_builder.to_string();
+        let dice_builder = match options.simplify {
+            true => dice_builder.simplify(),
+            false => dice_builder,
+        };
+        // the i64 calculation has faster paths, values that do not fit into it are calculated again in `V`
+        let (distribution, build_report) = match dice_builder.try_distribution_vec(options, None) {
+            Err(DiceBuildError::ValueOverflow) if V::WIDER_THAN_I64 => {
+                let mut ctx = BuildContext::new(options);
+                let distribution = value_distribution(&dice_builder, &mut vec![], &mut ctx)?;
+                (distribution.into_iter().collect(), ctx.report())
+            }
+            result => {
+                let (distribution, build_report) = result?;
+                let distribution = distribution
+                    .into_iter()
+                    .map(|(v, p)| Ok((V::from_value(v).ok_or(DiceBuildError::ValueOverflow)?, p)))
+                    .collect::<Result<Vec<(V, P)>, DiceBuildError>>()?;
+                (distribution, build_report)
+            }
+        };
+        let mut dice = Dice::from_sorted_distribution(distribution, builder_string)?;
+        dice.build_time = elapsed_millis(&start_instant);
+        dice.build_report = BuildReport {
+            elapsed: Duration::from_millis(dice.build_time),
+            ..build_report
+        };
+        Ok(dice)
+    }
 
-        // TODO: MAYBE: make cumulative_distribution lazy?
-        let cumulative_distribution = cumulative_distribution_from_distribution(&distribution);
+    /// creates a [`Dice`] from a distribution that is sorted by value, calculating all distribution paramters
+    pub(crate) fn from_sorted_distribution(
+        distribution: Vec<(V, P)>,
+        builder_string: String,
+    ) -> Result<Dice<P, V>, DiceBuildError> {
+        let start_instant = WasmSafeInstant::now();
+        let DistributionStats {
+            min,
+            max,
+            median,
+            mode,
+            mean,
+            variance,
+        } = DistributionStats::from_distribution(&distribution)?;
 
         let build_time: u64 = elapsed_millis(&start_instant);
-        Dice {
+        Ok(Dice {
             mean,
             variance,
             mode,
@@ -146,10 +266,202 @@ impl Dice {
             max,
             median,
             distribution,
-            cumulative_distribution,
-            builder_string: dice_builder.to_string(),
+            cumulative_distribution: LazyCdf::default(),
+            builder_string,
             build_time,
+            build_report: BuildReport::default(),
+        })
+    }
+
+    /// the cumulative distribution function (cdf) of the dice
+    ///
+    /// tuples of each value and its cumulative probability in ascending order (regarding value).
+    /// It is calculated from `distribution` on the first call and kept for later calls, so dice whose cdf is never needed do not store it.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("d4").unwrap();
+    /// assert_eq!(d.cumulative_distribution()[1], (2, BigFraction::new(1u64, 2u64)));
+    /// ```
+    pub fn cumulative_distribution(&self) -> &[(V, P)] {
+        self.cumulative_distribution
+            .0
+            .get_or_init(|| cumulative_distribution_from_distribution(&self.distribution))
+    }
+
+    /// iterator over the cumulative distribution, like `cumulative_distribution()`, but calculated while iterating instead of stored
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// assert!(d.cdf_iter().eq(d.cumulative_distribution().iter().cloned()));
+    /// ```
+    pub fn cdf_iter(&self) -> impl Iterator<Item = (V, P)> + '_ {
+        self.distribution
+            .iter()
+            .scan(P::zero(), |cumulative, (value, p)| {
+                *cumulative += p;
+                Some((value.clone(), cumulative.clone()))
+            })
+    }
+}
+
+/// the cumulative distribution of a [`Dice`], calculated from its distribution on first access
+///
+/// It is determined by the distribution, so it is left out of comparisons of dice.
+#[derive(Debug, Clone)]
+pub(crate) struct LazyCdf<V, P>(pub(crate) OnceLock<Vec<(V, P)>>);
+
+impl<V, P> Default for LazyCdf<V, P> {
+    fn default() -> Self {
+        LazyCdf(OnceLock::new())
+    }
+}
+
+impl<V, P> PartialEq for LazyCdf<V, P> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<V, P> Eq for LazyCdf<V, P> {}
+
+impl<P: ProbLike> Dice<P> {
+    /// builds a [`Dice`] from a given [`DiceBuilder`] like `try_from_builder()`, but aborts with a [`DiceBuildError`] as soon as one of the limits in `options` is exceeded
+    pub fn from_builder_with_options(
+        dice_builder: DiceBuilder,
+        options: &BuildOptions,
+    ) -> Result<Dice<P>, DiceBuildError> {
+        Dice::from_builder_with_progress(dice_builder, options, None)
+    }
+
+    pub(crate) fn from_builder_with_progress<'a>(
+        dice_builder: DiceBuilder,
+        options: &'a BuildOptions,
+        progress: Option<ProgressCallback<'a>>,
+    ) -> Result<Dice<P>, DiceBuildError> {
+        let start_instant = WasmSafeInstant::now();
+        let builder_string = dice_builder.to_string();
+        let dice_builder = match options.simplify {
+            true => dice_builder.simplify(),
+            false => dice_builder,
+        };
+        let (distribution, build_report) = dice_builder.try_distribution_vec(options, progress)?;
+        let mut dice = Dice::from_sorted_distribution(distribution, builder_string)?;
+        dice.build_time = elapsed_millis(&start_instant);
+        dice.build_report = BuildReport {
+            elapsed: Duration::from_millis(dice.build_time),
+            ..build_report
+        };
+        Ok(dice)
+    }
+
+    /// creates a [`Dice`] from a probability mass function that was calculated elsewhere, so it gets the full API of a [`Dice`]
+    ///
+    /// The values can be in any order, probabilities of duplicate values are added up and values with probability 0 are left out.
+    /// If the probabilities do not sum up to 1 they are normalized by their sum.
+    /// Errors with [`DiceBuildError::EmptyDistribution`] if `pmf` is empty, with [`DiceBuildError::NegativeProbability`] if a probability is negative or NaN
+    /// and with [`DiceBuildError::ZeroTotalProbability`] if all probabilities are 0.
+    /// The [`Dice`] has no formula, so its `builder_string` is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let pmf = vec![(3, BigFraction::new(1u64, 2u64)), (1, BigFraction::new(1u64, 2u64))];
+    /// let coin: Dice = Dice::from_pmf(pmf).unwrap();
+    /// assert_eq!(coin.min, 1);
+    /// assert_eq!(coin.mean, BigFraction::from(2));
+    /// ```
+    pub fn from_pmf(pmf: Vec<(Value, P)>) -> Result<Dice<P>, DiceBuildError> {
+        if pmf.is_empty() {
+            return Err(DiceBuildError::EmptyDistribution);
         }
+        let mut merged: BTreeMap<Value, P> = BTreeMap::new();
+        let mut total = P::zero();
+        for (value, p) in pmf {
+            // NaN is not comparable to 0
+            if !matches!(
+                p.partial_cmp(&P::zero()),
+                Some(Ordering::Greater | Ordering::Equal)
+            ) {
+                return Err(DiceBuildError::NegativeProbability(value));
+            }
+            total += &p;
+            merged.add_prob(value, p);
+        }
+        if total <= P::zero() {
+            return Err(DiceBuildError::ZeroTotalProbability);
+        }
+        let one = P::one();
+        let distribution = merged
+            .into_iter()
+            .filter(|(_, p)| *p > P::zero())
+            .map(|(v, p)| match total == one {
+                true => (v, p),
+                false => (v, p / total.clone()),
+            })
+            .collect();
+        Dice::from_sorted_distribution(distribution, String::new())
+    }
+
+    /// creates a [`Dice`] from the number of times each value was observed, like the rolls of a physical die
+    ///
+    /// Each value gets its relative frequency as probability, the values can be in any order and counts of duplicate values are added up.
+    /// Errors like `from_pmf()` and with [`DiceBuildError::ValueOverflow`] if the total count does not fit into a [`u64`].
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let observed: Dice = Dice::from_counts(vec![(1, 30), (2, 10), (1, 10), (3, 0)]).unwrap();
+    /// assert_eq!(observed.prob(1), BigFraction::new(4u64, 5u64));
+    /// assert_eq!(observed.max, 2);
+    /// ```
+    pub fn from_counts(counts: Vec<(Value, u64)>) -> Result<Dice<P>, DiceBuildError> {
+        let total = counts
+            .iter()
+            .try_fold(0u64, |total, (_, count)| total.checked_add(*count))
+            .ok_or(DiceBuildError::ValueOverflow)?;
+        if total == 0 {
+            return match counts.is_empty() {
+                true => Err(DiceBuildError::EmptyDistribution),
+                false => Err(DiceBuildError::ZeroTotalProbability),
+            };
+        }
+        let pmf = counts
+            .into_iter()
+            .map(|(v, count)| (v, P::from_ratio(count, total)))
+            .collect();
+        Dice::from_pmf(pmf)
+    }
+
+    /// the empirical distribution of logged rolls, where each value has the probability of its relative frequency in `samples`
+    ///
+    /// The result can be compared with a theoretical [`Dice`], for example with `total_variation()`, or tested with `chi_square_test()`.
+    ///
+    /// panics if `samples` is empty.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let rolls = [6, 2, 6, 5, 6, 1, 3, 6];
+    /// let physical: Dice = Dice::fit_from_samples(&rolls);
+    /// assert_eq!(physical.prob(6), BigFraction::new(1u64, 2u64));
+    /// assert_eq!(physical.mode, vec![6]);
+    /// let fair = Dice::build_from_string("d6").unwrap();
+    /// assert_eq!(physical.total_variation(&fair), BigFraction::new(1u64, 3u64));
+    /// ```
+    pub fn fit_from_samples(samples: &[Value]) -> Dice<P> {
+        let mut counts: BTreeMap<Value, u64> = BTreeMap::new();
+        for v in samples {
+            *counts.entry(*v).or_insert(0) += 1;
+        }
+        Dice::from_counts(counts.into_iter().collect()).expect("samples must not be empty")
     }
 
     /// Rolls a random number for this [`Dice`].
@@ -166,88 +478,201 @@ impl Dice {
     /// //prints something like: "rolled: 9"
     /// ```
     pub fn roll(&self) -> Value {
-        let r = random_number_between_0_and_1();
-        for (val, prob) in self.cumulative_distribution.iter() {
-            if prob.to_f64().unwrap() >= r {
-                return *val;
-            }
-        }
-        panic! {"Something went wrong in rolling. random value: {r}"}
+        self.value_at_percentile_u(random_number_between_0_and_1())
     }
 
-    /// rolls the [`Dice`] `n` times and returns the results as a vector
-    pub fn roll_many(&self, n: usize) -> Vec<Value> {
-        (0..n).map(|_| self.roll()).collect()
+    /// Rolls a random number for this [`Dice`], using `rng` as the source of randomness.
+    ///
+    /// With a seeded `rng` the rolls are reproducible, see also `roller()`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// let mut rng = StdRng::seed_from_u64(42);
+    /// let mut same_seed = StdRng::seed_from_u64(42);
+    /// assert_eq!(d.roll_with_rng(&mut rng), d.roll_with_rng(&mut same_seed));
+    /// ```
+    pub fn roll_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        self.value_at_percentile_u(rng.gen())
+    }
+
+    /// an endless iterator of random rolls of this [`Dice`], that only rolls when the next value is requested
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d20").unwrap();
+    /// let first_crit = d.roll_iter().position(|v| v == 20).unwrap();
+    /// let rolls: Vec<i64> = d.roll_iter().take(5).collect();
+    /// assert_eq!(rolls.len(), 5);
+    /// ```
+    pub fn roll_iter(&self) -> impl Iterator<Item = Value> + '_ {
+        std::iter::repeat_with(|| self.roll())
+    }
+
+    /// an endless iterator of rolls of this [`Dice`] like `roll_iter()`, using `rng` as the source of randomness
+    ///
+    /// `rng` can be passed by value or as a mutable reference to keep using it afterwards.
+    pub fn roll_iter_with_rng<'a, R: Rng + 'a>(
+        &'a self,
+        mut rng: R,
+    ) -> impl Iterator<Item = Value> + 'a {
+        std::iter::repeat_with(move || self.roll_with_rng(&mut rng))
+    }
+
+    /// the roll of this [`Dice`] for `u`, a number that was uniformly sampled from [0,1) elsewhere
+    ///
+    /// This is the inverse transform sampling that `roll()` uses, the first value whose cumulative probability reaches `u` like `quantile(u)`,
+    /// found by a binary search over the cumulative distribution.
+    /// Systems that manage their own randomness, like replays, networked games or provably fair servers,
+    /// can store the sampled numbers and map them to the same rolls again.
+    /// Numbers below 0 result in [`Dice::min`] and numbers of at least 1 in [`Dice::max`].
+    ///
+    /// panics if `u` is NaN.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d4").unwrap();
+    /// let samples = [0.1, 0.3, 0.6, 0.99];
+    /// let rolls: Vec<i64> = samples.iter().map(|u| d.value_at_percentile_u(*u)).collect();
+    /// assert_eq!(rolls, vec![1, 2, 3, 4]);
+    /// ```
+    pub fn value_at_percentile_u(&self, u: f64) -> Value {
+        assert!(!u.is_nan(), "the uniform sample must not be NaN");
+        self.quantile(u)
+    }
+
+    /// rolls the [`Dice`] `n` times, lazily as the returned iterator is consumed
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// let nums: Vec<i64> = d.roll_many(10).collect();
+    /// let total: i64 = d.roll_many(10).sum();
+    /// ```
+    pub fn roll_many(&self, n: usize) -> impl ExactSizeIterator<Item = Value> + '_ {
+        (0..n).map(|_| self.roll())
+    }
+
+    /// rolls the [`Dice`] `n` times like `roll_many()`, using `rng` as the source of randomness
+    pub fn roll_many_with_rng<'a, R: Rng + 'a>(
+        &'a self,
+        n: usize,
+        mut rng: R,
+    ) -> impl ExactSizeIterator<Item = Value> + 'a {
+        (0..n).map(move |_| self.roll_with_rng(&mut rng))
+    }
+
+    /// rolls the [`Dice`] `n` times, the same as `roll_many()`
+    #[deprecated(since = "0.3.0", note = "use `roll_many()` instead")]
+    pub fn roll_multiple(&self, n: usize) -> impl ExactSizeIterator<Item = Value> + '_ {
+        self.roll_many(n)
     }
 
     /// probability that a number sampled from `self` is `value`
-    pub fn prob(&self, value: Value) -> Prob {
-        match self.distribution.iter().find(|(v, _)| *v == value) {
-            None => Prob::zero(),
-            Some((_, p)) => p.clone(),
+    pub fn prob(&self, value: Value) -> P {
+        match self.distribution.binary_search_by_key(&value, |(v, _)| *v) {
+            Err(_) => P::zero(),
+            Ok(i) => self.distribution[i].1.clone(),
         }
     }
 
     /// probability that a number sampled from `self` is less than or equal to `value`
-    pub fn prob_lte(&self, value: Value) -> Prob {
-        if let Some((v, _)) = self.distribution.last() {
-            if value >= *v {
-                return Prob::one();
-            }
-        }
-
-        let mut lastp: Option<&Prob> = None;
-        for (v, p) in self.cumulative_distribution.iter() {
-            if *v > value {
-                break;
-            }
-            lastp = Some(p);
-        }
-        match lastp {
-            None => Prob::zero(),
-            Some(p) => p.clone(),
-        }
+    ///
+    /// Computed with binary search on the distribution and a lookup in the cumulative distribution.
+    pub fn prob_lte(&self, value: Value) -> P {
+        let index = self.distribution.partition_point(|(v, _)| *v <= value);
+        self.cumulative_prob_before(index)
     }
 
     /// probability that a number sampled from `self` is less than `value`
-    pub fn prob_lt(&self, value: Value) -> Prob {
-        if let Some((v, _)) = self.distribution.last() {
-            if value > *v {
-                return Prob::one();
-            }
-        }
+    ///
+    /// Computed with binary search on the distribution and a lookup in the cumulative distribution.
+    pub fn prob_lt(&self, value: Value) -> P {
+        let index = self.distribution.partition_point(|(v, _)| *v < value);
+        self.cumulative_prob_before(index)
+    }
 
-        let mut lastp: Option<&Prob> = None;
-        for (v, p) in self.cumulative_distribution.iter() {
-            if *v >= value {
-                break;
-            }
-            lastp = Some(p);
-        }
-        match lastp {
-            None => Prob::zero(),
-            Some(p) => p.clone(),
+    /// cumulative probability of all values before `index` in the cumulative distribution
+    fn cumulative_prob_before(&self, index: usize) -> P {
+        match index {
+            0 => P::zero(),
+            // exactly 1, even if rounding errors of float probabilities left the last cumulative probability slightly off
+            i if i == self.distribution.len() => P::one(),
+            i => self.cumulative_distribution()[i - 1].1.clone(),
         }
     }
 
     /// probability that a number sampled from `self` is greater than or equal to `value`
-    pub fn prob_gte(&self, value: Value) -> Prob {
-        Prob::one() - self.prob_lt(value)
+    pub fn prob_gte(&self, value: Value) -> P {
+        P::one() - self.prob_lt(value)
     }
 
     /// probability that a number sampled from `self` is greater than `value`
-    pub fn prob_gt(&self, value: Value) -> Prob {
-        Prob::one() - self.prob_lte(value)
+    pub fn prob_gt(&self, value: Value) -> P {
+        P::one() - self.prob_lte(value)
+    }
+
+    /// probability that a number sampled from `self` lies in `range`
+    ///
+    /// Computed from the cumulative distribution with binary search.
+    ///
+    /// # Examples
+    /// chance of rolling between 10 and 12 with 2 six-sided dice:
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// assert_eq!(d.prob_range(10..=12), BigFraction::new(1u64, 6u64));
+    /// assert_eq!(d.prob_range(..), BigFraction::new(1u64, 1u64));
+    /// ```
+    pub fn prob_range<R: RangeBounds<Value>>(&self, range: R) -> P {
+        let upper: P = match range.end_bound() {
+            Bound::Included(v) => self.prob_lte(*v),
+            Bound::Excluded(v) => self.prob_lt(*v),
+            Bound::Unbounded => P::one(),
+        };
+        let lower: P = match range.start_bound() {
+            Bound::Included(v) => self.prob_lt(*v),
+            Bound::Excluded(v) => self.prob_lte(*v),
+            Bound::Unbounded => P::zero(),
+        };
+        if upper <= lower {
+            P::zero()
+        } else {
+            upper - lower
+        }
     }
 
     /// returns prob_lt, prob_lte, prob, prob_gte, prob_gt in the [ProbAll] struct.
-    /// Computes them more efficiently than if we use all the functions individually.
-    pub fn prob_all(&self, value: Value) -> ProbAll {
-        let lt = self.prob_lt(value);
-        let eq = self.prob(value);
-        let lte = &eq + &lt;
-        let gte = &Prob::one() - &lt;
-        let gt = &Prob::one() - &lte;
+    ///
+    /// All five are derived from a single binary search on the cumulative distribution, instead of one search per probability.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("d4").unwrap();
+    /// let all = d.prob_all(3);
+    /// assert_eq!(all.lt, BigFraction::new(1u64, 2u64));
+    /// assert_eq!(all.lte, BigFraction::new(3u64, 4u64));
+    /// assert_eq!(all.gt, d.prob_gt(3));
+    /// ```
+    pub fn prob_all(&self, value: Value) -> ProbAll<P> {
+        let index = self
+            .cumulative_distribution()
+            .partition_point(|(v, _)| *v < value);
+        let lt = self.cumulative_prob_before(index);
+        let (eq, lte) = match self.distribution.get(index) {
+            Some((v, p)) if *v == value => (p.clone(), self.cumulative_prob_before(index + 1)),
+            _ => (P::zero(), lt.clone()),
+        };
+        let gte = P::one() - lt.clone();
+        let gt = P::one() - lte.clone();
         ProbAll {
             lt,
             lte,
@@ -260,21 +685,49 @@ impl Dice {
     /// returns the smallest p-quantile of the distribution.
     /// The smallest p-quantile q is the smallest value in the distribution for which it holds, that P(x ≤ q) ≥ p
     /// currently the trait [ToFloat] is implementen for [BigFraction] and [f64]
+    ///
+    /// other conventions are available with `quantile_with()`, see [`QuantileMethod`](crate::QuantileMethod).
+    /// The cumulative probabilities are converted to [`f64`] for the comparison with `p`, `quantile_exact()` compares them exactly.
     pub fn quantile<T: ToFloat>(&self, p: T) -> Value {
         let p: f64 = p.to_float();
-        if p >= 1.0 {
-            return self.cumulative_distribution.last().unwrap().0;
+        let cdf = self.cumulative_distribution();
+        let index = cdf.partition_point(|(_, prob)| prob.to_float() < p);
+        match cdf.get(index) {
+            Some((value, _)) => *value,
+            // p >= 1.0, or rounding errors of float probabilities left the last cumulative probability below p
+            None => self.max,
         }
-        for (i, prob) in &self.cumulative_distribution {
-            if prob.to_float() >= p {
-                return *i;
-            }
+    }
+
+    /// the smallest p-quantile like `quantile()`, but comparing `p` with the cumulative probabilities directly instead of converting them to [`f64`]
+    ///
+    /// For exact probabilities this classifies values of `p` right at or next to a cumulative probability correctly,
+    /// which `quantile()` can get wrong once both round to the same [`f64`].
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// assert_eq!(d.quantile_exact(BigFraction::new(21u64, 36u64)), 7);
+    /// let just_above = BigFraction::new(21u64, 36u64) + BigFraction::new(1u64, 10u64.pow(18));
+    /// assert_eq!(d.quantile_exact(just_above.clone()), 8);
+    /// assert_eq!(d.quantile(just_above), 7);
+    /// ```
+    pub fn quantile_exact(&self, p: P) -> Value {
+        let cdf = self.cumulative_distribution();
+        let index = cdf.partition_point(|(_, prob)| *prob < p);
+        match cdf.get(index) {
+            Some((value, _)) => *value,
+            // p > 1, or rounding errors of float probabilities left the last cumulative probability below p
+            None => self.max,
         }
-        panic!("should never end up here if a proper cumulative distribution is present")
     }
 }
 
+/// conversion of probabilities to [`f64`], used for comparisons with float inputs like in `Dice::quantile()`
 pub trait ToFloat {
+    /// the value as [`f64`]
     fn to_float(&self) -> f64;
 }
 
@@ -290,19 +743,94 @@ impl ToFloat for Prob {
     }
 }
 
-fn cumulative_distribution_from_distribution(distribution: &[(Value, Prob)]) -> Vec<(Value, Prob)> {
-    let mut acc_distr: Vec<(Value, Prob)> = vec![];
-    let mut last_acc_prob: Option<Prob> = None;
+/// the parameters of a distribution that are calculated when building a [`Dice`]
+pub(crate) struct DistributionStats<P: ProbLike, V = Value> {
+    pub min: V,
+    pub max: V,
+    pub median: V,
+    pub mode: Vec<V>,
+    pub mean: P,
+    pub variance: P,
+}
+
+impl<P: ProbLike, V: DiceValue> DistributionStats<P, V> {
+    /// `distribution` needs to be sorted by value
+    pub fn from_distribution(
+        distribution: &[(V, P)],
+    ) -> Result<DistributionStats<P, V>, DiceBuildError> {
+        let (min, max): (V, V) = match (distribution.first(), distribution.last()) {
+            (Some(first), Some(last)) => (first.0.clone(), last.0.clone()),
+            _ => return Err(DiceBuildError::EmptyDistribution),
+        };
+        let mut mean: P = P::zero();
+
+        let mut total_probability: P = P::zero();
+        let median_prob: P = P::from_ratio(1, 2);
+        let mut median: Option<V> = None;
+        let mut mode: Option<(Vec<V>, P)> = None;
+
+        for (val, prob) in distribution.iter().cloned() {
+            mean += prob.clone() * val.to_prob::<P>();
+            total_probability += &prob;
+            match median {
+                Some(_) => {}
+                None => {
+                    if total_probability >= median_prob {
+                        median = Some(val.clone());
+                    }
+                }
+            }
+            match &mode {
+                Some((old_vec, p)) => {
+                    if prob > *p {
+                        mode = Some((vec![val], prob));
+                    } else if prob == *p {
+                        let newvec: Vec<V> = [val].iter().chain(old_vec).cloned().collect();
+                        mode = Some((newvec, prob));
+                    }
+                }
+                None => {
+                    mode = Some((vec![val], prob));
+                }
+            }
+        }
+
+        let mut variance: P = P::zero();
+        for (val, prob) in distribution.iter().cloned() {
+            let val_minus_mean = val.to_prob::<P>() - mean.clone();
+            let square = val_minus_mean.clone() * val_minus_mean;
+            variance += square * prob
+        }
+
+        // rounding errors of floats can leave the total probability slightly below 1/2
+        let median = median.unwrap_or_else(|| max.clone());
+        let mode = mode.unwrap().0;
+        Ok(DistributionStats {
+            min,
+            max,
+            median,
+            mode,
+            mean,
+            variance,
+        })
+    }
+}
+
+pub(crate) fn cumulative_distribution_from_distribution<P: ProbLike, V: Clone>(
+    distribution: &[(V, P)],
+) -> Vec<(V, P)> {
+    let mut acc_distr: Vec<(V, P)> = vec![];
+    let mut last_acc_prob: Option<P> = None;
     for (val, prob) in distribution {
         match last_acc_prob {
             None => {
-                acc_distr.push((*val, prob.clone()));
+                acc_distr.push((val.clone(), prob.clone()));
                 last_acc_prob = Some(prob.clone());
             }
             Some(acc_p) => {
                 let acc_p = acc_p.clone().add(prob.clone());
                 last_acc_prob = Some(acc_p.clone());
-                acc_distr.push((*val, acc_p));
+                acc_distr.push((val.clone(), acc_p));
             }
         }
     }
@@ -312,7 +840,7 @@ fn cumulative_distribution_from_distribution(distribution: &[(Value, Prob)]) ->
 #[cfg(feature = "wasm")]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct JsDice {
-    dice: Dice,
+    pub(crate) dice: Dice,
 }
 
 #[cfg(feature = "wasm")]
@@ -340,42 +868,107 @@ impl JsDice {
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
     pub fn mode(&self) -> Vec<Value> {
-        self.dice.mode.iter().cloned().collect()
+        self.dice.mode.to_vec()
     }
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsFraction")
+    )]
     pub fn mean(&self) -> wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&JsFraction::from_big_fraction(&self.dice.mean)).unwrap()
     }
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsFraction")
+    )]
     pub fn variance(&self) -> wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&JsFraction::from_big_fraction(&self.dice.variance)).unwrap()
     }
 
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsDistribution")
+    )]
     pub fn distribution(&self) -> wasm_bindgen::JsValue {
         let js_dist = JsDistribution::from_distribution(&self.dice.distribution);
         serde_wasm_bindgen::to_value(&js_dist).unwrap()
     }
 
+    /// the distribution as `[values, probs]`, an `Int32Array` of the values and a `Float64Array` of their probabilities, for charting libraries that take typed arrays.
+    /// Much faster than `distribution` for big supports, but the probabilities are rounded. Throws a [`JsDiceError`] if a value does not fit into 32 bits.
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "[Int32Array, Float64Array]")
+    )]
+    pub fn distribution_floats(&self) -> Result<js_sys::Array, JsValue> {
+        let (values, probs) = self
+            .distribution_columns()
+            .map_err(|err| JsDiceError::from_build_error(&err).to_js_value())?;
+        Ok(js_sys::Array::of2(
+            &js_sys::Int32Array::from(values.as_slice()),
+            &js_sys::Float64Array::from(probs.as_slice()),
+        ))
+    }
+
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsDistribution")
+    )]
     pub fn cumulative_distribution(&self) -> wasm_bindgen::JsValue {
-        let js_dist = JsDistribution::from_distribution(&self.dice.cumulative_distribution);
+        let js_dist = JsDistribution::from_distribution(self.dice.cumulative_distribution());
         serde_wasm_bindgen::to_value(&js_dist).unwrap()
     }
 
+    /// min, max, median, mode, mean and variance in one object
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsStats")
+    )]
+    pub fn stats(&self) -> wasm_bindgen::JsValue {
+        serde_wasm_bindgen::to_value(&JsStats::from(&self.dice)).unwrap()
+    }
+
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter))]
     pub fn build_time(&self) -> u64 {
         self.dice.build_time
     }
 
-    pub fn build_from_string(input: &str) -> Result<JsDice, String> {
-        match DiceBuilder::from_string(input) {
-            Ok(builder) => Ok(JsDice {
-                dice: builder.build(),
-            }),
-            Err(err) => Err(format!("{:?}", err)),
+    /// parses and builds a formula like `"2d6+3"`, throws a [`JsDiceError`] with the kind, message and span of the error if it fails
+    pub fn build_from_string(input: &str) -> Result<JsDice, JsValue> {
+        match DiceBuilder::try_build_from_string(input) {
+            Ok(dice) => Ok(JsDice { dice }),
+            Err(err) => Err(JsDiceError::from_build_error(&err).to_js_value()),
+        }
+    }
+
+    /// builds like `build_from_string`, but calls `callback(workDone, elapsedMillis)` about every 100000 elementary operations.
+    /// The build is cancelled if the callback returns `false` or throws.
+    ///
+    /// The callback runs synchronously, so to keep a page responsive call this from a web worker that posts the progress to the main thread.
+    pub fn build_from_string_with_progress(
+        input: &str,
+        callback: &js_sys::Function,
+    ) -> Result<JsDice, JsValue> {
+        let builder = DiceBuilder::from_string(input)
+            .map_err(|err| JsDiceError::from_parse_error(&err).to_js_value())?;
+        let on_progress = |progress: &BuildProgress| {
+            let work_done = JsValue::from(progress.work_done as f64);
+            let elapsed_millis = JsValue::from(progress.elapsed_millis as f64);
+            match callback.call2(&JsValue::NULL, &work_done, &elapsed_millis) {
+                Ok(result) => result.as_bool() != Some(false),
+                Err(_) => false,
+            }
+        };
+        match builder.build_with_progress(&BuildOptions::default(), on_progress) {
+            Ok(dice) => Ok(JsDice { dice }),
+            Err(err) => Err(JsDiceError::from_build_error(&err).to_js_value()),
         }
     }
 
@@ -384,49 +977,62 @@ impl JsDice {
     }
 
     pub fn roll_many(&self, n: usize) -> Vec<Value> {
-        self.dice.roll_many(n)
+        self.dice.roll_many(n).collect()
+    }
+
+    /// rolls `n` times with a random number generator seeded with `seed`, giving the same rolls as `Dice::roller(seed)` in Rust
+    pub fn roll_many_seeded(&self, n: usize, seed: u64) -> Vec<Value> {
+        self.dice.roller(seed).roll_many(n).collect()
     }
     /// probability that a number sampled from `self` is less than `value`
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsFraction")
+    )]
     pub fn prob_lt(&self, value: Value) -> wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&JsFraction::from_big_fraction(&self.dice.prob_lt(value)))
             .unwrap()
     }
 
     /// probability that a number sampled from `self` is less or equal than `value`
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsFraction")
+    )]
     pub fn prob_lte(&self, value: Value) -> wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&JsFraction::from_big_fraction(&self.dice.prob_lte(value)))
             .unwrap()
     }
 
     /// probability that a number sampled from `self` is greater than or equal to `value`
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsFraction")
+    )]
     pub fn prob_gte(&self, value: Value) -> wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&JsFraction::from_big_fraction(&self.dice.prob_gte(value)))
             .unwrap()
     }
 
     /// probability that a number sampled from `self` is greater than `value`
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsFraction")
+    )]
     pub fn prob_gt(&self, value: Value) -> wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&JsFraction::from_big_fraction(&self.dice.prob_gt(value)))
             .unwrap()
     }
 
-    /// returns \[prob_lt, prob_lte, prob, prob_gte, prob_gt\] as a vector.
+    /// returns prob_lt, prob_lte, prob, prob_gte and prob_gt as an object `{ lt, lte, eq, gte, gt }`.
     /// Computes them more efficiently than if we use all the functions individually.
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsProbAll")
+    )]
     pub fn prob_all(&self, value: Value) -> wasm_bindgen::JsValue {
-        let ProbAll {
-            lt,
-            lte,
-            eq,
-            gte,
-            gt,
-        } = self.dice.prob_all(value);
-
-        let js_fractions: Vec<JsFraction> = vec![lt, lte, eq, gte, gt]
-            .iter()
-            .map(|e| JsFraction::from_big_fraction(&e))
-            .collect();
-
-        serde_wasm_bindgen::to_value(&js_fractions).unwrap()
+        let js_prob_all = JsProbAll::from(&self.dice.prob_all(value));
+        serde_wasm_bindgen::to_value(&js_prob_all).unwrap()
     }
 
     /// returns the smallest p-quantile of the distribution.
@@ -435,59 +1041,92 @@ impl JsDice {
     pub fn quantile(&self, p: f64) -> Value {
         self.dice.quantile(p)
     }
-}
 
-#[cfg(feature = "wasm")]
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JsDistribution {
-    pub values: Vec<(Value, JsFraction)>,
-}
+    /// the smallest p-quantile for each p in `ps`, see `quantile`
+    pub fn quantiles(&self, ps: &[f64]) -> Vec<Value> {
+        self.dice.quantiles(ps)
+    }
 
-#[cfg(feature = "wasm")]
-impl JsDistribution {
-    pub fn from_distribution(dist: &Vec<(Value, Prob)>) -> JsDistribution {
-        JsDistribution {
-            values: dist
-                .iter()
-                .map(|e| (e.0, JsFraction::from_big_fraction(&e.1)))
-                .collect(),
-        }
+    /// every value with the probability of rolling at least this value, like `{ values: [[value, fraction], ...] }`
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsDistribution")
+    )]
+    pub fn at_least_table(&self) -> wasm_bindgen::JsValue {
+        let js_dist = JsDistribution::from_distribution(&self.dice.at_least_table());
+        serde_wasm_bindgen::to_value(&js_dist).unwrap()
     }
-}
 
-#[cfg(feature = "wasm")]
-#[derive(Debug, Serialize, Deserialize)]
-pub struct JsFraction {
-    // pub numer: Vec<u64>,
-    // pub denom: Vec<u64>,
-    // pub negative: bool,
-    pub string: String,
-    pub float: f32,
-}
+    /// the values grouped into consecutive ranges of `bucket_size` values, like `[{ min, max, prob }, ...]`, for histograms of wide distributions.
+    /// Throws a [`JsDiceError`] if `bucket_size` is 0.
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsBucket[]")
+    )]
+    pub fn bucketed(&self, bucket_size: u64) -> Result<wasm_bindgen::JsValue, JsValue> {
+        if bucket_size == 0 {
+            let err = JsDiceError {
+                kind: "EmptyBucket".to_string(),
+                message: "buckets must contain at least one value".to_string(),
+                span_start: None,
+                span_end: None,
+                snippet: None,
+            };
+            return Err(err.to_js_value());
+        }
+        let buckets: Vec<JsBucket> = self
+            .dice
+            .bucketed(bucket_size)
+            .iter()
+            .map(JsBucket::from)
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&buckets).unwrap())
+    }
 
-#[cfg(feature = "wasm")]
-impl Display for JsFraction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} ({})", self.string, self.float,)
+    /// the `k` most likely values with their probabilities, most likely first, like `{ values: [[value, fraction], ...] }`
+    #[cfg_attr(
+        feature = "typescript",
+        wasm_bindgen(unchecked_return_type = "JsDistribution")
+    )]
+    pub fn most_likely(&self, k: usize) -> wasm_bindgen::JsValue {
+        let js_dist = JsDistribution::from_distribution(&self.dice.most_likely(k));
+        serde_wasm_bindgen::to_value(&js_dist).unwrap()
     }
 }
 
 #[cfg(feature = "wasm")]
-impl JsFraction {
-    pub fn from_big_fraction(big_fraction: &BigFraction) -> JsFraction {
-        JsFraction {
-            string: big_fraction.to_string(),
-            float: big_fraction.to_f32().unwrap(),
-        }
+impl JsDice {
+    /// the values and the rounded probabilities of `distribution_floats`, [`DiceBuildError::ValueOverflow`] if a value does not fit into 32 bits
+    pub(crate) fn distribution_columns(&self) -> Result<(Vec<i32>, Vec<f64>), DiceBuildError> {
+        let values = self
+            .dice
+            .distribution
+            .iter()
+            .map(|(v, _)| i32::try_from(*v).map_err(|_| DiceBuildError::ValueOverflow))
+            .collect::<Result<Vec<i32>, DiceBuildError>>()?;
+        let probs: Vec<f64> = self
+            .dice
+            .distribution
+            .iter()
+            .map(|(_, p)| p.to_float())
+            .collect();
+        Ok((values, probs))
     }
 }
 
 // https://rustwasm.github.io/wasm-bindgen/reference/arbitrary-data-with-serde.html
 
-pub struct ProbAll {
-    pub lt: Prob,
-    pub lte: Prob,
-    pub eq: Prob,
-    pub gte: Prob,
-    pub gt: Prob,
+/// the probabilities of all comparisons of a roll with one value, returned by [`Dice::prob_all()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbAll<P = Prob> {
+    /// probability that the roll is less than the value
+    pub lt: P,
+    /// probability that the roll is less than or equal to the value
+    pub lte: P,
+    /// probability that the roll is the value
+    pub eq: P,
+    /// probability that the roll is greater than or equal to the value
+    pub gte: P,
+    /// probability that the roll is greater than the value
+    pub gt: P,
 }
@@ -1,20 +1,134 @@
-use fraction::One;
-
 use super::{
-    dice::Dice,
+    build_options::{BuildContext, BuildOptions, BuildProgress, BuildReport, ProgressCallback},
+    dense_distribution::DenseDistribution,
+    dice::{Dice, DiceF64},
     dice_string_parser::{self, DiceBuildingError},
+    dice_value::DiceValue,
+    distribution_map::{DistributionBackend, DistributionMap},
+    prob_like::ProbLike,
 };
 use core::panic;
 use std::{
-    collections::HashMap,
-    fmt::{format, Display},
-    ops::{Add, Mul},
+    collections::BTreeSet,
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+    str::FromStr,
 };
 pub type Value = i64;
 pub type Prob = fraction::BigFraction;
-pub type AggrValue = fraction::BigFraction;
 type Distribution = Box<dyn Iterator<Item = (Value, Prob)>>;
-pub type DistributionHashMap = HashMap<Value, Prob>;
+
+/// Errors that can occur while calculating the distribution of a [`DiceBuilder`] in `try_build()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiceBuildError {
+    /// the input string could not be parsed into a [`DiceBuilder`]
+    Parse(DiceBuildingError),
+    /// a compound like [`DiceBuilder::SumCompound`] has no elements
+    EmptyCompound,
+    /// a [`DiceBuilder::FairDie`] without sides, like: d0 or d[1..0]
+    ZeroSidedDie,
+    /// a [`DiceBuilder::FairDie`] with a maximum smaller than its minimum minus one
+    InvalidFairDie {
+        /// minimum value of the die
+        min: Value,
+        /// maximum value of the die
+        max: Value,
+    },
+    /// a [`DiceBuilder::NthHighest`] where k is not in `1..=n`
+    InvalidNthHighest {
+        /// number of samples
+        n: usize,
+        /// position counted from the highest sample
+        k: usize,
+    },
+    /// the calculated distribution has no values
+    EmptyDistribution,
+    /// a [`DiceBuilder::Variable`] outside of a [`DiceBuilder::Let`] binding its name
+    UnboundVariable(String),
+    /// an exponent of a [`DiceBuilder::PowerCompound`] can be negative, which has no integer result
+    NegativeExponent(Value),
+    /// the weights of a [`DiceBuilder::Mixture`] sum up to zero
+    ZeroMixtureWeight,
+    /// the build was cancelled by the progress callback of [`DiceBuilder::build_with_progress()`] or by the [`CancellationToken`](crate::CancellationToken) in its [`BuildOptions`]
+    Cancelled,
+    /// the [`DiceBuilder`] contains a variant that cannot be calculated yet
+    NotImplemented(&'static str),
+    /// an intermediate distribution has more distinct values than [`BuildOptions::max_outcomes`]
+    TooManyOutcomes {
+        /// the limit that was exceeded
+        limit: usize,
+    },
+    /// the calculation needs more operations than [`BuildOptions::max_total_work`]
+    TooMuchWork {
+        /// the limit that was exceeded
+        limit: u64,
+    },
+    /// the calculation took longer than [`BuildOptions::timeout`]
+    Timeout {
+        /// the limit that was exceeded
+        limit: std::time::Duration,
+    },
+    /// a value of the distribution does not fit into a [`Value`], like in `d20^d20^d20`
+    ValueOverflow,
+    /// a divisor of a [`DiceBuilder::DivisionCompound`] can be 0, like in `d6/d[-1..1]`
+    DivisionByZero,
+    /// the probability of this value in a distribution passed to [`Dice::from_pmf()`] is negative or not a number
+    NegativeProbability(Value),
+    /// the probabilities passed to [`Dice::from_pmf()`] or the counts passed to [`Dice::from_counts()`] sum up to zero
+    ZeroTotalProbability,
+}
+
+impl From<DiceBuildingError> for DiceBuildError {
+    fn from(err: DiceBuildingError) -> Self {
+        DiceBuildError::Parse(err)
+    }
+}
+
+impl Display for DiceBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceBuildError::Parse(err) => write!(f, "could not parse dice formula: {err}"),
+            DiceBuildError::EmptyCompound => write!(f, "compound without elements"),
+            DiceBuildError::ZeroSidedDie => write!(f, "die without sides"),
+            DiceBuildError::InvalidFairDie { min, max } => {
+                write!(f, "die with minimum {min} greater than maximum {max}")
+            }
+            DiceBuildError::InvalidNthHighest { n, k } => {
+                write!(f, "cannot take the {k}th highest of {n} samples")
+            }
+            DiceBuildError::EmptyDistribution => write!(f, "distribution without values"),
+            DiceBuildError::UnboundVariable(name) => write!(f, "unbound variable {name}"),
+            DiceBuildError::NegativeExponent(exponent) => {
+                write!(f, "negative exponent {exponent}")
+            }
+            DiceBuildError::ZeroMixtureWeight => write!(f, "mixture weights sum up to zero"),
+            DiceBuildError::NotImplemented(feature) => {
+                write!(f, "{feature} is not implemented yet")
+            }
+            DiceBuildError::Cancelled => write!(f, "the build was cancelled"),
+            DiceBuildError::TooManyOutcomes { limit } => {
+                write!(f, "more than {limit} distinct outcomes")
+            }
+            DiceBuildError::TooMuchWork { limit } => write!(f, "more than {limit} operations"),
+            DiceBuildError::Timeout { limit } => write!(f, "took longer than {limit:?}"),
+            DiceBuildError::ValueOverflow => write!(f, "a value does not fit into 64 bits"),
+            DiceBuildError::DivisionByZero => write!(f, "division by zero"),
+            DiceBuildError::NegativeProbability(value) => {
+                write!(f, "negative probability of value {value}")
+            }
+            DiceBuildError::ZeroTotalProbability => write!(f, "probabilities sum up to zero"),
+        }
+    }
+}
+
+impl std::error::Error for DiceBuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DiceBuildError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 /// A [`DiceBuilder`] tree-like data structure representing the components of a dice formula like `max(2d6+4,d20)`
 ///
@@ -29,11 +143,14 @@ pub type DistributionHashMap = HashMap<Value, Prob>;
 /// let mean = dice.mean.to_f64().unwrap();
 /// assert_eq!(mean, 11.0);
 /// ```
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DiceBuilder {
     /// A constant value (i64) that does not
     Constant(Value),
-    /// A discrete uniform distribution over the integer interval `[min, max]`
+    /// A discrete uniform distribution over the integer interval `[min, max]`, like: d6 or d[0..9]
+    ///
+    /// a one-sided die like d1 always rolls its only value, a zero-sided die like d0 fails to build with [`DiceBuildError::ZeroSidedDie`].
     FairDie {
         /// minimum value of the die, inclusive
         min: Value,
@@ -80,21 +197,164 @@ pub enum DiceBuilder {
     /// assert_eq!(b1.build().distribution, b2.build().distribution);
     ///
     /// ```
+    ///
+    /// a count of 0 always sums up to 0, a negative count sums up the negated samples.
     SampleSumCompound(Vec<DiceBuilder>),
+    /// Power of the values of [`DiceBuilder`]s, evaluated from the right like: d6^2 or 2^d4^2 = 2^(d4^2)
+    ///
+    /// the square of a six-sided die roll:
+    /// ```
+    /// use dices::DiceBuilder::*;
+    /// let square = PowerCompound(vec![FairDie { min: 1, max: 6 }, Constant(2)]);
+    /// assert_eq!(square.build().max, 36);
+    /// ```
+    PowerCompound(Vec<DiceBuilder>),
     /// All negative values of the distribution become postive.
     Absolute(Box<DiceBuilder>),
+    /// Binds one roll of `value` to `name` within `body`, like: X := d20; max(X, 20-X)
+    ///
+    /// every [`DiceBuilder::Variable`] with this name in `body` refers to the same roll, instead of an independent one:
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let doubled = DiceBuilder::from_string("X := d6; X + X").unwrap().build();
+    /// assert_eq!(doubled.distribution.len(), 6);
+    /// ```
+    Let {
+        /// name of the variable
+        name: String,
+        /// the [`DiceBuilder`] that is rolled once
+        value: Box<DiceBuilder>,
+        /// the [`DiceBuilder`] in which `name` refers to the roll of `value`
+        body: Box<DiceBuilder>,
+    },
+    /// The roll bound to this name by an enclosing [`DiceBuilder::Let`]
+    Variable(String),
+    /// All values of the distribution are negated, like: -d6
+    ///
+    /// subtraction like d20-d4 is a [`DiceBuilder::SumCompound`] with negated subtrahends.
+    Negation(Box<DiceBuilder>),
+    /// the k-th highest of n independent samples of a [`DiceBuilder`], like: nth_highest(3,2,d20) for the middle die of 3d20
+    ///
+    /// k = 1 is the same as the maximum and k = n the same as the minimum of the n samples.
+    /// If the [`DiceBuilder`] explodes, like in `nth_highest(4,3,d6!)`, the rolls of the explosions are added to the pool, see [`ExplodeKind`].
+    NthHighest {
+        /// number of independent samples of `dice_builder`
+        n: usize,
+        /// position of the sample counted from the highest, starting at 1
+        k: usize,
+        /// the [`DiceBuilder`] that is sampled `n` times
+        dice_builder: Box<DiceBuilder>,
+    },
+    /// One of several [`DiceBuilder`]s, picked with a probability proportional to its weight, like: mix(1: 2d6, 3: d12)
+    ///
+    /// models choices like a monster that uses attack A half of the time and attack B otherwise:
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let attack = DiceBuilder::from_string("mix(1: 2d6, 1: d12)").unwrap().build();
+    /// assert_eq!(attack.min, 1);
+    /// assert_eq!(attack.max, 12);
+    /// ```
+    ///
+    /// components with weight 0 are never picked, a mixture whose weights sum up to 0 fails to build with [`DiceBuildError::ZeroMixtureWeight`].
+    Mixture(Vec<(u32, DiceBuilder)>),
     /// Specifies Exploding Dice.
     /// For example an exploding d6 is when we roll a d6 and on a 6 roll it again and add it to the result.
     /// For practical reasons we need an upper limit to such iterations because we do not have infinite memory nor computation power.
     /// if no min_value is given, explosing happens on the maximum value of the distribution (e.g. 6 on a d6).
+    ///
+    /// In strings explosions are written behind the exploding formula, like: d6!, d6!! or d6!p, with an optional minimum value like d6!>=5.
+    /// They explode at most [`DiceBuilder::DEFAULT_MAX_EXPLOSIONS`] times.
+    /// ```
+    /// use dices::DiceBuilder;
+    /// use fraction::BigFraction;
+    /// let penetrating = DiceBuilder::from_string("d6!p").unwrap().build();
+    /// // a 6 followed by a 1, which adds nothing
+    /// assert_eq!(penetrating.prob(6), BigFraction::new(1u64, 36u64));
+    /// ```
     Explode {
+        /// the [`DiceBuilder`] that is rolled again on high values
         dice_builder: Box<DiceBuilder>,
+        /// minimum value that causes an explosion, defaults to the maximum of the distribution
         min_value: Option<Value>,
+        /// upper limit for the number of explosions
         max_iterations: usize,
+        /// how the rolls of the explosions are added
+        kind: ExplodeKind,
+    },
+    /// Rerolls a [`DiceBuilder`] once if it rolls at most `max_value`, like: d20r<=2 or d20rb<=2
+    ///
+    /// `d20r<=2` keeps the new roll even if it is lower, `d20rb<=2` keeps the better of the two rolls.
+    /// ```
+    /// use dices::DiceBuilder;
+    /// use fraction::BigFraction;
+    /// let keep_new = DiceBuilder::from_string("d20r<=2").unwrap().build();
+    /// assert_eq!(keep_new.prob(1), BigFraction::new(2u64, 400u64));
+    /// let keep_best = DiceBuilder::from_string("d20rb<=2").unwrap().build();
+    /// assert_eq!(keep_best.prob(1), BigFraction::new(1u64, 400u64));
+    /// ```
+    Reroll {
+        /// the [`DiceBuilder`] that is rerolled on low values
+        dice_builder: Box<DiceBuilder>,
+        /// maximum value that causes a reroll
+        max_value: Value,
+        /// whether the better of both rolls is kept instead of the new roll
+        keep_best: bool,
     },
 }
 
+/// How the rolls of a [`DiceBuilder::Explode`] are added up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExplodeKind {
+    /// every explosion adds its roll, like: d6!
+    ///
+    /// In pools like `nth_highest(4,3,d6!)` every roll of an explosion is a value of the pool on its own.
+    Standard,
+    /// every explosion adds its roll to the same die, like: d6!!
+    ///
+    /// Unlike [`ExplodeKind::Standard`], the rolls of one die count as a single value in pools like `nth_highest(4,3,d6!!)`.
+    /// ```
+    /// use dices::Dice;
+    /// let standard = Dice::build_from_string("nth_highest(2,1,d6!)").unwrap();
+    /// let compounding = Dice::build_from_string("nth_highest(2,1,d6!!)").unwrap();
+    /// assert_eq!(standard.max, 6);
+    /// assert_eq!(compounding.max, 66);
+    /// ```
+    Compounding,
+    /// every explosion adds its roll minus 1, like: d6!p
+    ///
+    /// The rolls still explode on their unreduced value. Like with [`ExplodeKind::Standard`], every reduced roll is a value of its own in pools.
+    Penetrating,
+}
+
+impl ExplodeKind {
+    /// the amount subtracted from the roll of every explosion
+    pub(crate) fn penalty(self) -> Value {
+        match self {
+            ExplodeKind::Penetrating => 1,
+            ExplodeKind::Standard | ExplodeKind::Compounding => 0,
+        }
+    }
+
+    /// true if every roll of an explosion is a value of its own in pools like `nth_highest(4,3,d6!)`, false if the rolls of one die count as a single value
+    pub(crate) fn separates_rolls_in_pools(self) -> bool {
+        self != ExplodeKind::Compounding
+    }
+
+    /// the symbol written behind an exploding formula
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            ExplodeKind::Standard => "!",
+            ExplodeKind::Compounding => "!!",
+            ExplodeKind::Penetrating => "!p",
+        }
+    }
+}
+
 impl DiceBuilder {
+    /// upper limit for the number of explosions of exploding dice in strings, like `d6!`
+    pub const DEFAULT_MAX_EXPLOSIONS: usize = 10;
+
     /// parses the string into a tree-like structure to create a [`DiceBuilder`]
     ///
     /// # Syntax Examples:
@@ -128,10 +388,107 @@ impl DiceBuilder {
     ///
     /// this method calculates the distribution and all distribution paramters on the fly, to create the [`Dice`].
     /// Depending on the complexity of the `dice_builder` heavy lifting like convoluting probability distributions may take place here.
+    ///
+    /// panics if the [`DiceBuilder`] is degenerate, use `try_build()` to get a [`DiceBuildError`] instead.
     pub fn build(self) -> Dice {
+        match self.try_build() {
+            Ok(dice) => dice,
+            Err(err) => panic!("could not build dice: {err:?}"),
+        }
+    }
+
+    /// builds a [`Dice`] from [`self`], like `build()`, but returns a [`DiceBuildError`] instead of panicking on degenerate trees
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{DiceBuildError, DiceBuilder};
+    /// let builder = DiceBuilder::FairDie { min: 6, max: 1 };
+    /// assert_eq!(
+    ///     builder.try_build(),
+    ///     Err(DiceBuildError::InvalidFairDie { min: 6, max: 1 })
+    /// );
+    /// ```
+    pub fn try_build(self) -> Result<Dice, DiceBuildError> {
+        self.build_with_options(&BuildOptions::default())
+    }
+
+    /// builds a [`Dice`] from [`self`] like `try_build()`, but aborts with a [`DiceBuildError`] as soon as one of the limits in `options` is exceeded
+    pub fn build_with_options(self, options: &BuildOptions) -> Result<Dice, DiceBuildError> {
+        self.build_as(options)
+    }
+
+    /// builds a [`Dice`] from [`self`] like `build_with_options()`, calling `on_progress` about every 100000 elementary operations
+    ///
+    /// The build is aborted with [`DiceBuildError::Cancelled`] as soon as `on_progress` returns `false`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{BuildOptions, DiceBuildError, DiceBuilder};
+    /// let builder = DiceBuilder::from_string("10d100").unwrap();
+    /// let mut reports = 0;
+    /// let dice = builder.clone().build_with_progress(&BuildOptions::default(), |_| {
+    ///     reports += 1;
+    ///     true
+    /// });
+    /// assert!(dice.is_ok());
+    /// assert!(reports > 0);
+    /// let cancelled = builder.build_with_progress(&BuildOptions::default(), |progress| {
+    ///     progress.work_done < 200000
+    /// });
+    /// assert_eq!(cancelled, Err(DiceBuildError::Cancelled));
+    /// ```
+    pub fn build_with_progress(
+        self,
+        options: &BuildOptions,
+        mut on_progress: impl FnMut(&BuildProgress) -> bool,
+    ) -> Result<Dice, DiceBuildError> {
+        Dice::from_builder_with_progress(self, options, Some(&mut on_progress))
+    }
+
+    /// builds a [`DiceF64`] from [`self`], using [`f64`] instead of exact fractions for all probabilities
+    ///
+    /// This is a lot faster than `build()` for big formulas, at the cost of small rounding errors.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let dice = DiceBuilder::from_string("2d6").unwrap().build_approx();
+    /// assert!((dice.mean - 7.0).abs() < 1e-9);
+    /// ```
+    pub fn build_approx(self) -> DiceF64 {
+        match self.build_as(&BuildOptions::default()) {
+            Ok(dice) => dice,
+            Err(err) => panic!("could not build dice: {err:?}"),
+        }
+    }
+
+    /// builds a [`Dice`] from [`self`] that uses `P` for all probabilities, like `build_with_options()` does for exact fractions
+    pub fn build_as<P: ProbLike>(self, options: &BuildOptions) -> Result<Dice<P>, DiceBuildError> {
         #[cfg(feature = "console_error_panic_hook")]
         console_error_panic_hook::set_once();
-        Dice::from_builder(self)
+        Dice::from_builder_with_options(self, options)
+    }
+
+    /// builds a [`Dice`] from [`self`] whose values are of type `V` instead of [`Value`], like `build_as()` does for the probabilities
+    ///
+    /// With [`i128`] or [`BigInt`](fraction::BigInt) formulas like `d100^10*d100^10` can be calculated, whose values do not fit into a [`Value`].
+    /// Such formulas are calculated without the faster paths for [`Value`], and exploding dice and rerolls are not supported yet.
+    /// With [`i32`] the build fails with [`DiceBuildError::ValueOverflow`] if a value does not fit.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{BuildOptions, Dice, DiceBuildError, DiceBuilder};
+    /// use fraction::BigFraction;
+    /// let builder = DiceBuilder::from_string("d10^20*2").unwrap();
+    /// assert_eq!(builder.clone().try_build(), Err(DiceBuildError::ValueOverflow));
+    /// let dice: Dice<BigFraction, i128> = builder.build_values_as(&BuildOptions::default()).unwrap();
+    /// assert_eq!(dice.max, 2 * 10i128.pow(20));
+    /// ```
+    pub fn build_values_as<P: ProbLike, V: DiceValue>(
+        self,
+        options: &BuildOptions,
+    ) -> Result<Dice<P, V>, DiceBuildError> {
+        Dice::from_builder_with_values(self, options)
     }
 
     /// shortcut for `DiceBuilder::from_string(input).build()`
@@ -140,37 +497,55 @@ impl DiceBuilder {
         Ok(builder.build())
     }
 
+    /// shortcut for `DiceBuilder::from_string(input)?.try_build()`, errors of both steps are returned as [`DiceBuildError`]
+    pub fn try_build_from_string(input: &str) -> Result<Dice, DiceBuildError> {
+        let builder = DiceBuilder::from_string(input)?;
+        builder.try_build()
+    }
+
     /// constructs a string from the DiceBuilder that can be used to reconstruct an equivalent DiceBuilder from it.
     ///
-    /// currently fails to construct a correct string in case dices with a non-1 minimum are present. This is because there is no string notation for dices with a non-1 minimum yet.
+    /// dice with a minimum other than 1 are written as intervals, like `d[0..9]`.
     pub fn reconstruct_string(&self) -> String {
         match self {
             DiceBuilder::Constant(i) => i.to_string(),
-            DiceBuilder::FairDie { min, max } => match *min == 1 {
+            DiceBuilder::FairDie { min, max } => match *min == 1 && *max >= 0 {
                 true => format!("d{max}"),
-                false => "".to_owned(), // this is currently a weak point where errors can occur
+                false => format!("d[{min}..{max}]"),
             },
-            // ugly code right now, too much repetition:
-            DiceBuilder::SumCompound(v) => v
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<String>>()
-                .join("+"),
-            DiceBuilder::ProductCompound(v) => v
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<String>>()
-                .join("*"),
-            DiceBuilder::DivisionCompound(v) => v
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<String>>()
-                .join("/"),
-            DiceBuilder::SampleSumCompound(v) => v
-                .iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<String>>()
-                .join("x"),
+            DiceBuilder::SumCompound(v) => {
+                let mut s = String::new();
+                for (i, f) in v.iter().enumerate() {
+                    match f.negated_operand() {
+                        Some(operand) => {
+                            s.push('-');
+                            s.push_str(&operand);
+                        }
+                        None => {
+                            if i > 0 {
+                                s.push('+');
+                            }
+                            s.push_str(&f.operand_string(self.precedence()));
+                        }
+                    }
+                }
+                s
+            }
+            DiceBuilder::ProductCompound(v)
+            | DiceBuilder::DivisionCompound(v)
+            | DiceBuilder::SampleSumCompound(v)
+            | DiceBuilder::PowerCompound(v) => {
+                let operator = match self {
+                    DiceBuilder::ProductCompound(_) => "*",
+                    DiceBuilder::DivisionCompound(_) => "/",
+                    DiceBuilder::SampleSumCompound(_) => "x",
+                    _ => "^",
+                };
+                v.iter()
+                    .map(|f| f.operand_string(self.precedence()))
+                    .collect::<Vec<String>>()
+                    .join(operator)
+            }
             DiceBuilder::MaxCompound(v) => format!(
                 "max({})",
                 v.iter()
@@ -189,83 +564,348 @@ impl DiceBuilder {
                 dice_builder,
                 min_value,
                 max_iterations,
+                kind,
+            } if *max_iterations == DiceBuilder::DEFAULT_MAX_EXPLOSIONS => format!(
+                "{}{}{}",
+                dice_builder.operand_string(5),
+                kind.symbol(),
+                match min_value {
+                    Some(i) => format!(">={i}"),
+                    None => String::new(),
+                }
+            ),
+            // the string syntax has no upper limit for the number of explosions
+            DiceBuilder::Explode {
+                dice_builder,
+                min_value,
+                max_iterations,
+                kind,
             } => format!(
-                "explode({},{},{})",
-                dice_builder.to_string(),
+                "explode({},{},{}{})",
+                dice_builder,
                 match min_value {
                     Some(i) => i.to_string(),
                     None => "None".to_string(),
                 },
-                max_iterations
+                max_iterations,
+                match kind {
+                    ExplodeKind::Standard => String::new(),
+                    kind => format!(",{kind:?}"),
+                }
             ),
-            DiceBuilder::Absolute(dice_builder) => format!("abs({})", dice_builder.to_string()),
+            DiceBuilder::Reroll {
+                dice_builder,
+                max_value,
+                keep_best,
+            } => format!(
+                "{}r{}<={max_value}",
+                dice_builder.operand_string(5),
+                match keep_best {
+                    true => "b",
+                    false => "",
+                }
+            ),
+            DiceBuilder::Absolute(dice_builder) => format!("abs({})", dice_builder),
+            DiceBuilder::Let { name, value, body } => match value.as_ref() {
+                DiceBuilder::Let { .. } => format!("{name}:=({value});{body}"),
+                _ => format!("{name}:={value};{body}"),
+            },
+            DiceBuilder::Variable(name) => name.clone(),
+            DiceBuilder::Negation(_) => format!("-{}", self.negated_operand().unwrap()),
+            DiceBuilder::NthHighest { n, k, dice_builder } => {
+                format!("nth_highest({n},{k},{dice_builder})")
+            }
+            DiceBuilder::Mixture(components) => format!(
+                "mix({})",
+                components
+                    .iter()
+                    .map(|(weight, f)| format!("{weight}:{f}"))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        }
+    }
+
+    /// binding strength of the operator at the root of the string of [`self`], matching the parser:
+    ///
+    /// definitions, `-x` and negative constants (0) -> `+` (1) -> `/` (2) -> `*` (3) -> `x` (4) -> `^` (5) -> dice, constants and functions (6)
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            DiceBuilder::Let { .. } | DiceBuilder::Negation(_) => 0,
+            DiceBuilder::Constant(i) if *i < 0 => 0,
+            DiceBuilder::SumCompound(_) => 1,
+            DiceBuilder::DivisionCompound(_) => 2,
+            DiceBuilder::ProductCompound(_) => 3,
+            DiceBuilder::SampleSumCompound(_) => 4,
+            DiceBuilder::PowerCompound(_) => 5,
+            _ => 6,
+        }
+    }
+
+    /// the string of [`self`] as an operand of an operator with `precedence`, in brackets if the operator would otherwise bind parts of it
+    pub(crate) fn operand_string(&self, precedence: u8) -> String {
+        match self.precedence() <= precedence {
+            true => format!("({self})"),
+            false => self.to_string(),
+        }
+    }
+
+    /// the string behind the `-` if [`self`] is written with a leading minus, like `-d6` or `-3`
+    fn negated_operand(&self) -> Option<String> {
+        match self {
+            DiceBuilder::Negation(d) => Some(d.operand_string(1)),
+            DiceBuilder::Constant(i) if *i < 0 => Some(i.unsigned_abs().to_string()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn distribution_hashmap<'t, P: ProbLike>(
+        &'t self,
+        ctx: &mut BuildContext<'_, 't, P>,
+    ) -> Result<DistributionMap<P>, DiceBuildError> {
+        // subtrees with free variables have a different distribution for every binding
+        let memoizable = !matches!(self, DiceBuilder::Constant(_) | DiceBuilder::FairDie { .. })
+            && self.is_closed(&mut vec![]);
+        if memoizable {
+            if let Some(hashmap) = ctx.memoized(self) {
+                return Ok(hashmap.clone());
+            }
         }
+        let hashmap = self.calculate_distribution_hashmap(ctx)?;
+        ctx.count_node();
+        ctx.check_outcomes(hashmap.len())?;
+        if memoizable {
+            ctx.memoize(self, hashmap.clone());
+        }
+        Ok(hashmap)
     }
 
-    fn distribution_hashmap(&self) -> DistributionHashMap {
+    /// true if every [`DiceBuilder::Variable`] in [`self`] is in `bound` or bound by a [`DiceBuilder::Let`] inside of [`self`]
+    fn is_closed<'s>(&'s self, bound: &mut Vec<&'s str>) -> bool {
         match self {
+            DiceBuilder::Constant(_) | DiceBuilder::FairDie { .. } => true,
+            DiceBuilder::Variable(name) => bound.contains(&name.as_str()),
+            DiceBuilder::Let { name, value, body } => {
+                if !value.is_closed(bound) {
+                    return false;
+                }
+                bound.push(name);
+                let closed = body.is_closed(bound);
+                bound.pop();
+                closed
+            }
+            DiceBuilder::SumCompound(v)
+            | DiceBuilder::ProductCompound(v)
+            | DiceBuilder::DivisionCompound(v)
+            | DiceBuilder::MaxCompound(v)
+            | DiceBuilder::MinCompound(v)
+            | DiceBuilder::SampleSumCompound(v)
+            | DiceBuilder::PowerCompound(v) => v.iter().all(|d| d.is_closed(bound)),
+            DiceBuilder::Absolute(d)
+            | DiceBuilder::Negation(d)
+            | DiceBuilder::NthHighest {
+                dice_builder: d, ..
+            }
+            | DiceBuilder::Explode {
+                dice_builder: d, ..
+            }
+            | DiceBuilder::Reroll {
+                dice_builder: d, ..
+            } => d.is_closed(bound),
+            DiceBuilder::Mixture(components) => components.iter().all(|(_, d)| d.is_closed(bound)),
+        }
+    }
+
+    fn calculate_distribution_hashmap<'t, P: ProbLike>(
+        &'t self,
+        ctx: &mut BuildContext<'_, 't, P>,
+    ) -> Result<DistributionMap<P>, DiceBuildError> {
+        let hashmap = match self {
             DiceBuilder::Constant(v) => {
-                let mut m = DistributionHashMap::new();
-                m.insert(*v, Prob::one());
+                let mut m = DistributionMap::new();
+                m.insert(*v, P::one());
                 m
             }
             DiceBuilder::FairDie { min, max } => {
-                assert!(max >= min);
-                let min: i64 = *min;
-                let max: i64 = *max;
-                let prob: Prob = Prob::new(1u64, (max - min + 1) as u64);
-                let mut m = DistributionHashMap::new();
-                for v in min..=max {
+                let sides = fair_die_sides(*min, *max)?;
+                let prob: P = P::from_ratio(1, sides);
+                let mut m = DistributionMap::new();
+                for_each_side(*min, *max, ctx, |v| {
                     m.insert(v, prob.clone());
-                }
+                    Ok(())
+                })?;
                 m
             }
             DiceBuilder::SampleSumCompound(vec) => {
                 let hashmaps = vec
                     .iter()
-                    .map(|e| e.distribution_hashmap())
-                    .collect::<Vec<DistributionHashMap>>();
-                sample_sum_convolute_hashmaps(&hashmaps)
+                    .map(|e| e.distribution_hashmap(ctx))
+                    .collect::<Result<Vec<DistributionMap<P>>, DiceBuildError>>()?;
+                sample_sum_convolute_hashmaps(&hashmaps, ctx)?
+            }
+            DiceBuilder::SumCompound(vec) => {
+                let hashmaps = vec
+                    .iter()
+                    .map(|e| e.distribution_hashmap(ctx))
+                    .collect::<Result<Vec<DistributionMap<P>>, DiceBuildError>>()?;
+                sum_convolute_hashmaps(&hashmaps, ctx)?
             }
-            DiceBuilder::SumCompound(vec)
-            | DiceBuilder::ProductCompound(vec)
+            DiceBuilder::ProductCompound(vec)
             | DiceBuilder::DivisionCompound(vec)
             | DiceBuilder::MaxCompound(vec)
             | DiceBuilder::MinCompound(vec) => {
-                let operation = match self {
-                    DiceBuilder::SumCompound(_) => |a, b| a + b,
-                    DiceBuilder::ProductCompound(_) => |a, b| a * b,
-                    DiceBuilder::MaxCompound(_) => std::cmp::max,
-                    DiceBuilder::MinCompound(_) => std::cmp::min,
-                    DiceBuilder::DivisionCompound(_) => rounded_div::i64,
+                let operation: Operation = match self {
+                    DiceBuilder::ProductCompound(_) => Value::checked_mul,
+                    DiceBuilder::MaxCompound(_) => |a, b| Some(a.max(b)),
+                    DiceBuilder::MinCompound(_) => |a, b| Some(a.min(b)),
+                    DiceBuilder::DivisionCompound(_) => checked_rounded_div,
                     _ => panic!("unreachable by match"),
                 };
                 let hashmaps = vec
                     .iter()
-                    .map(|e| e.distribution_hashmap())
-                    .collect::<Vec<DistributionHashMap>>();
-                convolute_hashmaps(&hashmaps, operation)
+                    .map(|e| e.distribution_hashmap(ctx))
+                    .collect::<Result<Vec<DistributionMap<P>>, DiceBuildError>>()?;
+                if matches!(self, DiceBuilder::DivisionCompound(_))
+                    && hashmaps.iter().skip(1).any(|h| h.contains_key(&0))
+                {
+                    return Err(DiceBuildError::DivisionByZero);
+                }
+                convolute_hashmaps(&hashmaps, operation, ctx)?
+            }
+            DiceBuilder::PowerCompound(vec) => {
+                let hashmaps = vec
+                    .iter()
+                    .map(|e| e.distribution_hashmap(ctx))
+                    .collect::<Result<Vec<DistributionMap<P>>, DiceBuildError>>()?;
+                power_hashmaps(hashmaps, ctx)?
+            }
+            DiceBuilder::Absolute(d) => absolute_hashmap(d.distribution_hashmap(ctx)?)?,
+            DiceBuilder::Let { name, value, body } => {
+                let value_hashmap = value.distribution_hashmap(ctx)?;
+                let mut m = DistributionMap::<P>::new();
+                for (v, p) in value_hashmap.into_iter() {
+                    ctx.bind(name, v);
+                    let body_hashmap = body.distribution_hashmap(ctx);
+                    ctx.unbind();
+                    let mut body_hashmap = body_hashmap?;
+                    body_hashmap.values_mut().for_each(|q| *q *= &p);
+                    merge_hashmaps(&mut m, &body_hashmap);
+                }
+                m
+            }
+            DiceBuilder::Variable(name) => match ctx.binding(name) {
+                Some(v) => {
+                    let mut m = DistributionMap::<P>::new();
+                    m.insert(v, P::one());
+                    m
+                }
+                None => return Err(DiceBuildError::UnboundVariable(name.clone())),
+            },
+            DiceBuilder::Negation(d) => negated_hashmap(d.distribution_hashmap(ctx)?)?,
+            DiceBuilder::NthHighest { n, k, dice_builder } => {
+                if *k < 1 || k > n {
+                    return Err(DiceBuildError::InvalidNthHighest { n: *n, k: *k });
+                }
+                match dice_builder.as_ref() {
+                    DiceBuilder::Explode {
+                        dice_builder,
+                        min_value,
+                        max_iterations,
+                        kind,
+                    } if kind.separates_rolls_in_pools() => {
+                        let hashmap = dice_builder.distribution_hashmap(ctx)?;
+                        let threshold = explosion_threshold(&hashmap, *min_value)?;
+                        let explosions = Explosions {
+                            threshold,
+                            max_iterations: *max_iterations,
+                            penalty: kind.penalty(),
+                        };
+                        nth_highest_pool_hashmap(&hashmap, explosions, *n, *k, ctx)?
+                    }
+                    dice_builder => {
+                        nth_highest_hashmap(dice_builder.distribution_hashmap(ctx)?, *n, *k, ctx)?
+                    }
+                }
+            }
+            DiceBuilder::Mixture(components) => {
+                if components.is_empty() {
+                    return Err(DiceBuildError::EmptyCompound);
+                }
+                let total_weight: u64 = components.iter().map(|(w, _)| u64::from(*w)).sum();
+                if total_weight == 0 {
+                    return Err(DiceBuildError::ZeroMixtureWeight);
+                }
+                let mut m = DistributionMap::<P>::new();
+                for (weight, d) in components.iter().filter(|(w, _)| *w > 0) {
+                    let weight = P::from_ratio(u64::from(*weight), total_weight);
+                    let mut component_hashmap = d.distribution_hashmap(ctx)?;
+                    component_hashmap.values_mut().for_each(|p| *p *= &weight);
+                    merge_hashmaps(&mut m, &component_hashmap);
+                }
+                m
             }
-            DiceBuilder::Absolute(d) => absolute_hashmap(d.distribution_hashmap()),
             DiceBuilder::Explode {
                 dice_builder,
                 min_value,
                 max_iterations,
-            } => todo!(),
-        }
+                kind,
+            } => {
+                let hashmap = dice_builder.distribution_hashmap(ctx)?;
+                let threshold = explosion_threshold(&hashmap, *min_value)?;
+                explode_hashmap(&hashmap, threshold, *max_iterations, kind.penalty(), ctx)?
+            }
+            DiceBuilder::Reroll {
+                dice_builder,
+                max_value,
+                keep_best,
+            } => reroll_hashmap(
+                &dice_builder.distribution_hashmap(ctx)?,
+                *max_value,
+                *keep_best,
+                ctx,
+            )?,
+        };
+        Ok(hashmap)
     }
 
     /// iterator for the probability mass function (pmf) of the [`DiceBuilder`], with tuples for each value with its probability in ascending order (regarding value)
     ///
     /// Calculates the distribution and all distribution paramters.
     /// Depending on the complexity of [`self`] heavy lifting like convoluting probability distributions may take place here.
+    ///
+    /// panics if the [`DiceBuilder`] is degenerate, see `try_build()`.
     pub fn distribution_iter(&self) -> Distribution {
-        let mut distribution_vec = self
-            .distribution_hashmap()
-            .into_iter()
-            .collect::<Vec<(Value, Prob)>>();
-        distribution_vec.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-        Box::new(distribution_vec.into_iter())
+        match self.try_distribution_vec(&BuildOptions::default(), None) {
+            Ok((distribution_vec, _)) => Box::new(distribution_vec.into_iter()),
+            Err(err) => panic!("could not calculate distribution: {err:?}"),
+        }
+    }
+
+    /// the probability mass function (pmf) of the [`DiceBuilder`] sorted by value, or a [`DiceBuildError`] for degenerate trees
+    pub(crate) fn try_distribution_vec<'a, P: ProbLike>(
+        &self,
+        options: &'a BuildOptions,
+        progress: Option<ProgressCallback<'a>>,
+    ) -> Result<(Vec<(Value, P)>, BuildReport), DiceBuildError> {
+        let mut ctx = BuildContext::with_progress(options, progress);
+        let distribution_vec = self.distribution_hashmap(&mut ctx)?.into_sorted_vec();
+        Ok((distribution_vec, ctx.report()))
+    }
+}
+
+impl FromStr for DiceBuilder {
+    type Err = DiceBuildingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DiceBuilder::from_string(s)
+    }
+}
+
+impl TryFrom<&str> for DiceBuilder {
+    type Error = DiceBuildingError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        DiceBuilder::from_string(value)
     }
 }
 
@@ -275,125 +915,762 @@ impl Display for DiceBuilder {
     }
 }
 
-fn convolute_hashmaps(
-    hashmaps: &Vec<DistributionHashMap>,
-    operation: fn(Value, Value) -> Value,
-) -> DistributionHashMap {
+/// an operation that combines two values, `None` if the result does not fit into a [`Value`]
+pub(crate) type Operation = fn(Value, Value) -> Option<Value>;
+
+/// `dividend / divisor` rounded like [`DiceBuilder::DivisionCompound`], `None` if the divisor is 0 or the result does not fit into a [`Value`]
+pub(crate) fn checked_rounded_div(dividend: Value, divisor: Value) -> Option<Value> {
+    match divisor {
+        0 => None,
+        -1 => dividend.checked_neg(),
+        divisor => Some(rounded_div::i64(dividend, divisor)),
+    }
+}
+
+/// `dividend / divisor` like `checked_rounded_div()`, with the error of a build if there is no result
+pub(crate) fn try_rounded_div(dividend: Value, divisor: Value) -> Result<Value, DiceBuildError> {
+    match divisor {
+        0 => Err(DiceBuildError::DivisionByZero),
+        divisor => checked_rounded_div(dividend, divisor).ok_or(DiceBuildError::ValueOverflow),
+    }
+}
+
+/// `base^exponent` for a non-negative exponent, `None` if the result does not fit into a [`Value`]
+pub(crate) fn checked_power(base: Value, exponent: Value) -> Option<Value> {
+    match base {
+        // 0, 1 and -1 stay small for any exponent, only the parity of the exponent matters
+        0 | 1 if exponent != 0 => Some(base),
+        -1 if exponent % 2 != 0 => Some(-1),
+        -1..=1 => Some(1),
+        base => base.checked_pow(u32::try_from(exponent).ok()?),
+    }
+}
+
+fn convolute_hashmaps<P: ProbLike>(
+    hashmaps: &[DistributionMap<P>],
+    operation: Operation,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
     if hashmaps.is_empty() {
-        panic!("cannot convolute hashmaps from a zero element vector");
+        return Err(DiceBuildError::EmptyCompound);
     }
     let mut convoluted_h = hashmaps[0].clone();
     for h in hashmaps.iter().skip(1) {
-        convoluted_h = convolute_two_hashmaps(&convoluted_h, h, operation);
+        convoluted_h = convolute_two_hashmaps(&convoluted_h, h, operation, ctx)?;
+        ctx.check_outcomes(convoluted_h.len())?;
     }
-    convoluted_h
+    Ok(convoluted_h)
 }
 
-fn convolute_two_hashmaps(
-    h1: &DistributionHashMap,
-    h2: &DistributionHashMap,
-    operation: fn(Value, Value) -> Value,
-) -> DistributionHashMap {
-    let mut m = DistributionHashMap::new();
-    for (v1, p1) in h1.iter() {
-        for (v2, p2) in h2.iter() {
-            let v = operation(*v1, *v2);
-            let p = p1 * p2;
-            match m.entry(v) {
-                std::collections::hash_map::Entry::Occupied(mut e) => {
-                    *e.get_mut() += p;
-                }
-                std::collections::hash_map::Entry::Vacant(e) => {
-                    e.insert(p);
+/// the distribution of `h_1^(h_2^(...))`, a constant exponent is applied pointwise without convolution
+fn power_hashmaps<P: ProbLike>(
+    mut hashmaps: Vec<DistributionMap<P>>,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    let mut exponent = hashmaps.pop().ok_or(DiceBuildError::EmptyCompound)?;
+    while let Some(base) = hashmaps.pop() {
+        if let Some(negative) = exponent.keys().copied().filter(|e| *e < 0).min() {
+            return Err(DiceBuildError::NegativeExponent(negative));
+        }
+        exponent = match exponent.len() {
+            1 => {
+                let e = *exponent.keys().next().unwrap();
+                let mut m = DistributionMap::<P>::new();
+                for (v, p) in base.into_iter() {
+                    m.add_prob(checked_power(v, e).ok_or(DiceBuildError::ValueOverflow)?, p);
                 }
+                m
             }
+            _ => convolute_two_hashmaps(&base, &exponent, checked_power, ctx)?,
+        };
+        ctx.check_outcomes(exponent.len())?;
+    }
+    Ok(exponent)
+}
+
+/// like `convolute_hashmaps` with addition, but uses [`DenseDistribution`]s if all supports are contiguous
+fn sum_convolute_hashmaps<P: ProbLike>(
+    hashmaps: &[DistributionMap<P>],
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    let dense_distributions = hashmaps
+        .iter()
+        .map(DenseDistribution::from_hashmap)
+        .collect::<Option<Vec<DenseDistribution<P>>>>();
+    let dense_distributions = match dense_distributions {
+        Some(d) if !d.is_empty() => d,
+        _ => return convolute_hashmaps(hashmaps, Value::checked_add, ctx),
+    };
+    let mut convoluted = dense_distributions[0].clone();
+    for d in dense_distributions.iter().skip(1) {
+        let (min, max) = convoluted.value_range();
+        let (other_min, other_max) = d.value_range();
+        if min.checked_add(other_min).is_none() || max.checked_add(other_max).is_none() {
+            return Err(DiceBuildError::ValueOverflow);
+        }
+        ctx.add_work((convoluted.len() * d.len()) as u64)?;
+        convoluted = convoluted.sum_convolute(d, |work| ctx.complete_work(work))?;
+        ctx.check_outcomes(convoluted.len())?;
+    }
+    Ok(convoluted.into_hashmap())
+}
+
+/// distribution of rolling `hashmap` and rolling it again and adding the roll whenever it is at least `threshold`, with at most `max_iterations` additional rolls
+///
+/// every roll after the first one adds its value minus `penalty`, but explodes on its unreduced value.
+/// the minimum value that causes an explosion, the maximum of `hashmap` if no `min_value` is given
+fn explosion_threshold<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    min_value: Option<Value>,
+) -> Result<Value, DiceBuildError> {
+    match min_value {
+        Some(v) => Ok(v),
+        None => hashmap
+            .keys()
+            .max()
+            .copied()
+            .ok_or(DiceBuildError::EmptyCompound),
+    }
+}
+
+fn explode_hashmap<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    threshold: Value,
+    max_iterations: usize,
+    penalty: Value,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    if max_iterations == 0 {
+        return Ok(hashmap.clone());
+    }
+    // the sum of the explosion rolls with no explosions left, every iteration allows one more from the front
+    let mut explosions: DistributionMap<P> = hashmap
+        .iter()
+        .map(|(v, p)| Ok((checked_sub(*v, penalty)?, p.clone())))
+        .collect::<Result<_, DiceBuildError>>()?;
+    for iteration in 1..=max_iterations {
+        // the last iteration adds the first roll, which is not reduced
+        let penalty = match iteration == max_iterations {
+            true => 0,
+            false => penalty,
+        };
+        let mut m = DistributionMap::<P>::new();
+        for (v1, p1) in hashmap.iter() {
+            if *v1 < threshold {
+                *m.entry(checked_sub(*v1, penalty)?).or_insert_with(P::zero) += p1;
+                continue;
+            }
+            ctx.add_work(explosions.len() as u64)?;
+            for (v2, p2) in explosions.iter() {
+                let mut p = p1.clone();
+                p *= p2;
+                let v = checked_sub(*v1, penalty)?
+                    .checked_add(*v2)
+                    .ok_or(DiceBuildError::ValueOverflow)?;
+                *m.entry(v).or_insert_with(P::zero) += p;
+            }
+            ctx.complete_work(explosions.len() as u64)?;
+        }
+        ctx.check_outcomes(m.len())?;
+        explosions = m;
+    }
+    Ok(explosions)
+}
+
+/// the number of sides of the fair die from `min` to `max`, an error if it has none or more than fit into a [`Value`]
+pub(crate) fn fair_die_sides(min: Value, max: Value) -> Result<u64, DiceBuildError> {
+    if min.checked_sub(1) == Some(max) {
+        return Err(DiceBuildError::ZeroSidedDie);
+    }
+    if max < min {
+        return Err(DiceBuildError::InvalidFairDie { min, max });
+    }
+    max.checked_sub(min)
+        .and_then(|d| d.checked_add(1))
+        .map(|sides| sides as u64)
+        .ok_or(DiceBuildError::ValueOverflow)
+}
+
+/// number of sides of a fair die that are inserted between two checks of the timeout and the cancellation token
+const FAIR_DIE_CHUNK: Value = 1 << 16;
+
+/// calls `f` with every side of the fair die from `min` to `max`, checking the limits of `ctx` before the first side,
+/// so huge dice like d100000000 are rejected before any side is allocated, and after every [`FAIR_DIE_CHUNK`] sides
+pub(crate) fn for_each_side<P>(
+    min: Value,
+    max: Value,
+    ctx: &mut BuildContext<P>,
+    mut f: impl FnMut(Value) -> Result<(), DiceBuildError>,
+) -> Result<(), DiceBuildError> {
+    let sides = (max as i128 - min as i128 + 1).max(0) as u64;
+    ctx.check_outcomes(usize::try_from(sides).unwrap_or(usize::MAX))?;
+    ctx.add_work(sides)?;
+    let mut chunk_min = min;
+    while chunk_min <= max {
+        let chunk_max = chunk_min.saturating_add(FAIR_DIE_CHUNK - 1).min(max);
+        for v in chunk_min..=chunk_max {
+            f(v)?;
         }
+        ctx.complete_work((chunk_max as i128 - chunk_min as i128 + 1) as u64)?;
+        match chunk_max.checked_add(1) {
+            Some(next) => chunk_min = next,
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+fn checked_sub(a: Value, b: Value) -> Result<Value, DiceBuildError> {
+    a.checked_sub(b).ok_or(DiceBuildError::ValueOverflow)
+}
+
+/// distribution of rolling `hashmap` and rolling it again once if the roll is at most `max_value`, keeping either the new roll or the better of both
+fn reroll_hashmap<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    max_value: Value,
+    keep_best: bool,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    let mut m = DistributionMap::<P>::new();
+    for (v1, p1) in hashmap.iter() {
+        if *v1 > max_value {
+            *m.entry(*v1).or_insert_with(P::zero) += p1;
+            continue;
+        }
+        ctx.add_work(hashmap.len() as u64)?;
+        for (v2, p2) in hashmap.iter() {
+            let v = match keep_best {
+                true => *v1.max(v2),
+                false => *v2,
+            };
+            let mut p = p1.clone();
+            p *= p2;
+            *m.entry(v).or_insert_with(P::zero) += p;
+        }
+        ctx.complete_work(hashmap.len() as u64)?;
+    }
+    Ok(m)
+}
+
+fn convolute_two_hashmaps<P: ProbLike>(
+    h1: &DistributionMap<P>,
+    h2: &DistributionMap<P>,
+    operation: Operation,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    ctx.add_work((h1.len() * h2.len()) as u64)?;
+    if let Some(distribution) = P::fast_convolute(h1.iter(), h2.iter(), operation) {
+        ctx.complete_work((h1.len() * h2.len()) as u64)?;
+        return Ok(distribution.into_iter().collect());
     }
-    m
+    #[cfg(feature = "parallel")]
+    if h1.len() * h2.len() >= PARALLEL_MIN_WORK {
+        let rows: Vec<(&Value, &P)> = h1.iter().collect();
+        let rows_per_chunk = (PARALLEL_CHUNK_WORK / h2.len()).max(1);
+        let mut m = DistributionMap::<P>::new();
+        for chunk in rows.chunks(rows_per_chunk) {
+            merge_hashmaps(&mut m, &par_convolute_rows(chunk, h2, operation)?);
+            ctx.complete_work((chunk.len() * h2.len()) as u64)?;
+        }
+        return Ok(m);
+    }
+    let mut m = DistributionMap::<P>::new();
+    for (v1, p1) in h1.iter() {
+        ctx.complete_work(h2.len() as u64)?;
+        for (v2, p2) in h2.iter() {
+            let v = operation(*v1, *v2).ok_or(DiceBuildError::ValueOverflow)?;
+            let mut p = p1.clone();
+            p *= p2;
+            m.add_prob(v, p);
+        }
+    }
+    Ok(m)
+}
+
+/// convolutions with fewer operations than this are not worth the overhead of distributing them over threads
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_WORK: usize = 4096;
+
+/// number of operations distributed over threads at once, the limits and the progress callback are checked between these chunks
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_WORK: usize = 1 << 16;
+
+/// convolutes the values `rows` of the first distribution with all values of `h2`, distributing the rows over threads with rayon.
+/// Each thread collects into its own hashmap and the hashmaps are merged afterwards.
+#[cfg(feature = "parallel")]
+fn par_convolute_rows<P: ProbLike>(
+    rows: &[(&Value, &P)],
+    h2: &DistributionMap<P>,
+    operation: Operation,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    use rayon::prelude::*;
+    rows.par_iter()
+        .try_fold(DistributionMap::<P>::new, |mut m, &(v1, p1)| {
+            for (v2, p2) in h2.iter() {
+                let v = operation(*v1, *v2).ok_or(DiceBuildError::ValueOverflow)?;
+                let mut p = p1.clone();
+                p *= p2;
+                m.add_prob(v, p);
+            }
+            Ok(m)
+        })
+        .try_reduce(DistributionMap::<P>::new, |mut first, second| {
+            merge_hashmaps(&mut first, &second);
+            Ok(first)
+        })
 }
 
-fn sample_sum_convolute_hashmaps(hashmaps: &Vec<DistributionHashMap>) -> DistributionHashMap {
+fn sample_sum_convolute_hashmaps<P: ProbLike>(
+    hashmaps: &[DistributionMap<P>],
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
     if hashmaps.is_empty() {
-        panic!("cannot convolute hashmaps from a zero element vector");
+        return Err(DiceBuildError::EmptyCompound);
     }
     let mut convoluted_h = hashmaps[0].clone();
     for h in hashmaps.iter().skip(1) {
-        convoluted_h = sample_sum_convolute_two_hashmaps(&convoluted_h, h);
+        convoluted_h = sample_sum_convolute_two_hashmaps(&convoluted_h, h, ctx)?;
+        ctx.check_outcomes(convoluted_h.len())?;
     }
-    convoluted_h
+    Ok(convoluted_h)
 }
 
-fn sample_sum_convolute_two_hashmaps(
-    count_factor: &DistributionHashMap,
-    sample_factor: &DistributionHashMap,
-) -> DistributionHashMap {
-    let mut total_hashmap = DistributionHashMap::new();
+fn sample_sum_convolute_two_hashmaps<P: ProbLike>(
+    count_factor: &DistributionMap<P>,
+    sample_factor: &DistributionMap<P>,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    let mut total_hashmap = DistributionMap::<P>::new();
     for (count, count_p) in count_factor.iter() {
-        let mut count_hashmap: DistributionHashMap = match count.cmp(&0) {
+        let mut count_hashmap: DistributionMap<P> = match count.cmp(&0) {
+            // a negative count subtracts the samples, so that the sample sum of constants is their product
             std::cmp::Ordering::Less => {
-                let count: usize = (-count) as usize;
-                let sample_vec: Vec<DistributionHashMap> = std::iter::repeat(sample_factor)
-                    .take(count)
-                    .cloned()
-                    .collect();
-                convolute_hashmaps(&sample_vec, |a, b| a + b)
+                let count: usize = count.unsigned_abs() as usize;
+                negated_hashmap(sample_sum_hashmap(sample_factor, count, ctx)?)?
             }
             std::cmp::Ordering::Equal => {
-                let mut h = DistributionHashMap::new();
-                h.insert(0, Prob::new(1u64, 1u64));
+                let mut h = DistributionMap::<P>::new();
+                h.insert(0, P::one());
                 h
             }
-            std::cmp::Ordering::Greater => {
-                let count: usize = *count as usize;
-                let sample_vec: Vec<DistributionHashMap> = std::iter::repeat(sample_factor)
-                    .take(count)
-                    .cloned()
-                    .collect();
-                convolute_hashmaps(&sample_vec, |a, b| a + b)
-            }
+            std::cmp::Ordering::Greater => sample_sum_hashmap(sample_factor, *count as usize, ctx)?,
         };
         count_hashmap.iter_mut().for_each(|e| {
-            *e.1 *= count_p.clone();
+            *e.1 *= count_p;
         });
         merge_hashmaps(&mut total_hashmap, &count_hashmap);
     }
-    total_hashmap
+    Ok(total_hashmap)
+}
+
+/// distribution of the sum of `count` rolls of `hashmap`, approximated by a normal distribution if the [`BuildOptions`] ask for it
+pub(crate) fn sample_sum_hashmap<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    count: usize,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    if ctx.approximates_sample_sum(count) {
+        if let Some(approximation) = normal_sum_hashmap(hashmap, count, ctx)? {
+            ctx.mark_approximate();
+            return Ok(approximation);
+        }
+    }
+    let sample_vec: Vec<DistributionMap<P>> =
+        std::iter::repeat_n(hashmap, count).cloned().collect();
+    sum_convolute_hashmaps(&sample_vec, ctx)
 }
 
-fn absolute_hashmap(hashmap: DistributionHashMap) -> DistributionHashMap {
-    let mut total_hashmap = DistributionHashMap::new();
+/// number of standard deviations around the mean that `normal_sum_hashmap` keeps, the probability of values further out is negligible
+const NORMAL_APPROXIMATION_SIGMAS: f64 = 8.0;
+/// denominator of the probabilities of `normal_sum_hashmap` before normalizing, exact fractions with a huge denominator would slow down everything after the approximation
+const NORMAL_APPROXIMATION_RESOLUTION: f64 = (1u64 << 40) as f64;
+/// number of sums whose density is calculated by `normal_sum_hashmap` between two checks of the timeout and the cancellation token
+const NORMAL_APPROXIMATION_CHUNK: usize = 1 << 16;
+
+/// discretized normal distribution with the mean and variance of the sum of `count` rolls of `hashmap`
+///
+/// Only the sums that are actually possible get a probability, so that a sum of even values stays even.
+/// The probabilities are proportional to the normal density at each possible sum. None if `hashmap` has a single value or the sums do not fit into a [`Value`].
+/// The number of kept sums is checked against the limits of `ctx` before they are allocated.
+fn normal_sum_hashmap<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    count: usize,
+    ctx: &mut BuildContext<P>,
+) -> Result<Option<DistributionMap<P>>, DiceBuildError> {
+    let (Some(&min), Some(&max)) = (hashmap.keys().min(), hashmap.keys().max()) else {
+        return Ok(None);
+    };
+    // the possible sums are `count * min` plus multiples of the greatest common divisor of the steps between the values
+    let Some(step) = hashmap
+        .keys()
+        .try_fold(0, |g, v| Some(gcd(g, v.checked_sub(min)?)))
+    else {
+        return Ok(None);
+    };
+    if step == 0 {
+        return Ok(None);
+    }
+    let count_f = count as f64;
+    let sample_mean: f64 = hashmap.iter().map(|(v, p)| *v as f64 * p.to_float()).sum();
+    let sample_variance: f64 = hashmap
+        .iter()
+        .map(|(v, p)| (*v as f64 - sample_mean).powi(2) * p.to_float())
+        .sum();
+    let mean = count_f * sample_mean;
+    let sd = (count_f * sample_variance).sqrt();
+    let Some((low, high)) = Value::try_from(count)
+        .ok()
+        .and_then(|count| Some((count.checked_mul(min)?, count.checked_mul(max)?)))
+    else {
+        return Ok(None);
+    };
+    let Some(steps) = high.checked_sub(low).map(|width| width / step) else {
+        return Ok(None);
+    };
+    // the first and last possible sums within the kept range around the mean
+    let first_step = ((mean - NORMAL_APPROXIMATION_SIGMAS * sd - low as f64) / step as f64)
+        .ceil()
+        .max(0.0) as Value;
+    let last_step = ((mean + NORMAL_APPROXIMATION_SIGMAS * sd - low as f64) / step as f64)
+        .floor()
+        .min(steps as f64) as Value;
+    let support = match last_step.checked_sub(first_step) {
+        Some(width) if width >= 0 => width.unsigned_abs().saturating_add(1),
+        _ => return Ok(None),
+    };
+    ctx.check_outcomes(usize::try_from(support).unwrap_or(usize::MAX))?;
+    ctx.add_work(support)?;
+    let mut densities: Vec<(Value, f64)> = Vec::with_capacity(support as usize);
+    for chunk_start in (first_step..=last_step).step_by(NORMAL_APPROXIMATION_CHUNK) {
+        let chunk_end =
+            last_step.min(chunk_start.saturating_add(NORMAL_APPROXIMATION_CHUNK as Value - 1));
+        densities.extend((chunk_start..=chunk_end).map(|k| {
+            let v = low + k * step;
+            (v, (-((v as f64 - mean) / sd).powi(2) / 2.0).exp())
+        }));
+        ctx.complete_work((chunk_end - chunk_start + 1) as u64)?;
+    }
+    let total: f64 = densities.iter().map(|(_, d)| d).sum();
+    let weights: Vec<(Value, u64)> = densities
+        .into_iter()
+        .map(|(v, d)| {
+            (
+                v,
+                (d / total * NORMAL_APPROXIMATION_RESOLUTION).round() as u64,
+            )
+        })
+        .filter(|(_, w)| *w > 0)
+        .collect();
+    let total_weight: u64 = weights.iter().map(|(_, w)| w).sum();
+    Ok(Some(
+        weights
+            .into_iter()
+            .map(|(v, w)| (v, P::from_ratio(w, total_weight)))
+            .collect(),
+    ))
+}
+
+fn gcd(a: Value, b: Value) -> Value {
+    match b {
+        0 => a.abs(),
+        b => gcd(b, a % b),
+    }
+}
+
+fn absolute_hashmap<P: ProbLike>(
+    hashmap: DistributionMap<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    let mut total_hashmap = DistributionMap::<P>::new();
 
     for (value, p) in hashmap.into_iter() {
-        let target = if value < 0 { -value } else { value };
-        match total_hashmap.entry(target) {
-            std::collections::hash_map::Entry::Occupied(mut e) => {
-                *e.get_mut() += p;
+        let target = value.checked_abs().ok_or(DiceBuildError::ValueOverflow)?;
+        total_hashmap.add_prob(target, p);
+    }
+    Ok(total_hashmap)
+}
+
+fn negated_hashmap<P: ProbLike>(
+    hashmap: DistributionMap<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    hashmap
+        .into_iter()
+        .map(|(value, p)| Ok((value.checked_neg().ok_or(DiceBuildError::ValueOverflow)?, p)))
+        .collect()
+}
+
+/// the k-th highest of n samples is at most `v`, if at least n-k+1 of the samples are at most `v`.
+/// With F = P(X <= v) this probability is the binomial tail: sum over j >= n-k+1 of C(n,j) * F^j * (1-F)^(n-j)
+fn nth_highest_hashmap<P: ProbLike>(
+    hashmap: DistributionMap<P>,
+    n: usize,
+    k: usize,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    Ok(
+        nth_highest_distribution(hashmap.into_sorted_vec(), n, k, ctx)?
+            .into_iter()
+            .collect(),
+    )
+}
+
+/// how the rolls of an exploding die in a pool explode, see [`DiceBuilder::Explode`]
+#[derive(Debug, Clone, Copy)]
+struct Explosions {
+    threshold: Value,
+    max_iterations: usize,
+    penalty: Value,
+}
+
+/// the k-th highest value of a pool of `n` exploding dice, in which every roll of an explosion is a value of its own, like in `nth_highest(4,3,d6!)`
+///
+/// The k-th highest value is at most `v`, if fewer than k values of the pool are greater than `v`.
+/// The number of those values is followed through the explosions of a single die and then summed up over the `n` dice.
+fn nth_highest_pool_hashmap<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    explosions: Explosions,
+    n: usize,
+    k: usize,
+    ctx: &mut BuildContext<P>,
+) -> Result<DistributionMap<P>, DiceBuildError> {
+    // first rolls keep their value, the rolls of explosions are reduced by the penalty
+    let mut pool_values: BTreeSet<Value> = hashmap.keys().copied().collect();
+    if explosions.max_iterations > 0 {
+        for v in hashmap.keys() {
+            pool_values.insert(checked_sub(*v, explosions.penalty)?);
+        }
+    }
+    ctx.check_outcomes(pool_values.len())?;
+    let work_per_value = (hashmap.len() as u64)
+        .saturating_add((explosions.max_iterations as u64 + 1).saturating_mul(4 * k as u64))
+        .saturating_add((n as u64).saturating_mul((k * k) as u64));
+    ctx.add_work(work_per_value.saturating_mul(pool_values.len() as u64))?;
+
+    let mut m = DistributionMap::<P>::new();
+    let mut last_cumulative = P::zero();
+    for v in pool_values {
+        let die = greater_counts_of_explosions(hashmap, v, explosions, k);
+        // the number of values greater than `v` in the whole pool, counts from k on are left out
+        let mut pool = vec![P::zero(); k];
+        pool[0] = P::one();
+        for _ in 0..n {
+            let mut next = vec![P::zero(); k];
+            for (i, p) in pool.iter().enumerate() {
+                for (j, q) in die.iter().take(k - i).enumerate() {
+                    next[i + j] += p.clone() * q.clone();
+                }
             }
-            std::collections::hash_map::Entry::Vacant(_) => {
-                total_hashmap.insert(target, p);
+            pool = next;
+        }
+        let cumulative = pool.into_iter().fold(P::zero(), |a, b| a + b);
+        let p = cumulative.clone() - last_cumulative;
+        if p > P::zero() {
+            m.insert(v, p);
+        }
+        last_cumulative = cumulative;
+        ctx.complete_work(work_per_value)?;
+    }
+    Ok(m)
+}
+
+/// distribution of the number of rolls greater than `v` of a single exploding die, counts from `k` on are left out
+fn greater_counts_of_explosions<P: ProbLike>(
+    hashmap: &DistributionMap<P>,
+    v: Value,
+    explosions: Explosions,
+    k: usize,
+) -> Vec<P> {
+    let mut counts = vec![P::zero(); k];
+    // the distribution of the count for dice that explode again
+    let mut exploding = vec![P::zero(); k];
+    exploding[0] = P::one();
+    for iteration in 0..=explosions.max_iterations {
+        let penalty = match iteration {
+            0 => 0,
+            _ => explosions.penalty,
+        };
+        // probabilities of a roll indexed by whether it is greater than `v` and whether it explodes
+        let mut classes = [[P::zero(), P::zero()], [P::zero(), P::zero()]];
+        for (x, p) in hashmap.iter() {
+            let greater = x.saturating_sub(penalty) > v;
+            let explodes = *x >= explosions.threshold && iteration < explosions.max_iterations;
+            classes[greater as usize][explodes as usize] += p;
+        }
+        let mut next = vec![P::zero(); k];
+        for (greater, class) in classes.iter().enumerate() {
+            for (explodes, q) in class.iter().enumerate() {
+                let target = match explodes {
+                    0 => &mut counts,
+                    _ => &mut next,
+                };
+                for c in 0..k - greater {
+                    target[c + greater] += exploding[c].clone() * q.clone();
+                }
             }
         }
+        exploding = next;
     }
-    return total_hashmap;
+    counts
 }
 
-impl Mul for Box<DiceBuilder> {
-    type Output = Box<DiceBuilder>;
+/// like `nth_highest_hashmap`, for a `distribution` sorted by value
+pub(crate) fn nth_highest_distribution<V, P: ProbLike>(
+    distribution: Vec<(V, P)>,
+    n: usize,
+    k: usize,
+    ctx: &mut BuildContext<P>,
+) -> Result<Vec<(V, P)>, DiceBuildError> {
+    ctx.check_outcomes(distribution.len())?;
+    // about 2k + log2(n) multiplications per value, of fractions that grow to n times the size of the probabilities
+    let multiplications = 2 * k as u64 + u64::from(usize::BITS - n.leading_zeros());
+    let work_per_value = multiplications.saturating_mul(n as u64);
+    ctx.add_work(work_per_value.saturating_mul(distribution.len() as u64))?;
+
+    // the terms of the tail are indexed by i = n-j < k, the number of samples greater than `v`, with C(n,j) = C(n,i)
+    let binomials: Vec<P> = binomial_coefficients(n, k);
+
+    let mut nth_highest = Vec::with_capacity(distribution.len());
+    let mut cumulative = P::zero();
+    let mut last_nth_cumulative = P::zero();
+    for (value, p) in distribution {
+        cumulative += p;
+        let complement = P::one() - cumulative.clone();
+        let mut complement_powers: Vec<P> = vec![P::one()];
+        for i in 1..k {
+            complement_powers.push(complement_powers[i - 1].clone() * complement.clone());
+        }
+        // F^(n-i) for i from k-1 down to 0, starting at F^(n+1-k)
+        let mut cumulative_power = prob_pow(&cumulative, n + 1 - k);
+        let mut nth_cumulative = P::zero();
+        for i in (0..k).rev() {
+            nth_cumulative +=
+                binomials[i].clone() * cumulative_power.clone() * complement_powers[i].clone();
+            cumulative_power *= &cumulative;
+        }
+        nth_highest.push((value, nth_cumulative.clone() - last_nth_cumulative));
+        last_nth_cumulative = nth_cumulative;
+        ctx.complete_work(work_per_value)?;
+    }
+    Ok(nth_highest)
+}
+
+/// C(n,0), C(n,1), ..., C(n,count-1)
+fn binomial_coefficients<P: ProbLike>(n: usize, count: usize) -> Vec<P> {
+    let mut binomials: Vec<P> = vec![P::one()];
+    for i in 1..count {
+        let next = binomials[i - 1].clone() * P::from_ratio((n + 1 - i) as u64, i as u64);
+        binomials.push(next);
+    }
+    binomials
+}
+
+/// `p^exp` by binary exponentiation
+fn prob_pow<P: ProbLike>(p: &P, mut exp: usize) -> P {
+    let mut result = P::one();
+    let mut power = p.clone();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result *= &power;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            power = power.clone() * power;
+        }
+    }
+    result
+}
+
+fn sum(first: DiceBuilder, second: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::SumCompound(vec![first, second])
+}
+
+/// subtraction is represented like in the string notation, where `a-b` is parsed as the sum of `a` and `-b`
+fn difference(first: DiceBuilder, second: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::SumCompound(vec![first, -second])
+}
+
+fn product(first: DiceBuilder, second: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::ProductCompound(vec![first, second])
+}
+
+fn quotient(first: DiceBuilder, second: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::DivisionCompound(vec![first, second])
+}
+
+/// implements a binary operator for [`DiceBuilder`] and [`Box<DiceBuilder>`], with a [`Value`] allowed on either side
+macro_rules! impl_dice_builder_operator {
+    ($trait:ident, $method:ident, $combine:ident) => {
+        impl $trait for DiceBuilder {
+            type Output = DiceBuilder;
+
+            fn $method(self, rhs: DiceBuilder) -> Self::Output {
+                $combine(self, rhs)
+            }
+        }
+
+        impl $trait<Value> for DiceBuilder {
+            type Output = DiceBuilder;
+
+            fn $method(self, rhs: Value) -> Self::Output {
+                $combine(self, DiceBuilder::Constant(rhs))
+            }
+        }
+
+        impl $trait<DiceBuilder> for Value {
+            type Output = DiceBuilder;
+
+            fn $method(self, rhs: DiceBuilder) -> Self::Output {
+                $combine(DiceBuilder::Constant(self), rhs)
+            }
+        }
+
+        impl $trait for Box<DiceBuilder> {
+            type Output = Box<DiceBuilder>;
+
+            fn $method(self, rhs: Self) -> Self::Output {
+                Box::new($combine(*self, *rhs))
+            }
+        }
+
+        impl $trait<Value> for Box<DiceBuilder> {
+            type Output = Box<DiceBuilder>;
+
+            fn $method(self, rhs: Value) -> Self::Output {
+                Box::new($combine(*self, DiceBuilder::Constant(rhs)))
+            }
+        }
+
+        impl $trait<Box<DiceBuilder>> for Value {
+            type Output = Box<DiceBuilder>;
+
+            fn $method(self, rhs: Box<DiceBuilder>) -> Self::Output {
+                Box::new($combine(DiceBuilder::Constant(self), *rhs))
+            }
+        }
+    };
+}
+
+impl_dice_builder_operator!(Add, add, sum);
+impl_dice_builder_operator!(Sub, sub, difference);
+impl_dice_builder_operator!(Mul, mul, product);
+impl_dice_builder_operator!(Div, div, quotient);
+
+impl Neg for DiceBuilder {
+    type Output = DiceBuilder;
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        Box::new(DiceBuilder::ProductCompound(vec![*self, *rhs]))
+    fn neg(self) -> Self::Output {
+        DiceBuilder::Negation(Box::new(self))
     }
 }
 
-impl Add for Box<DiceBuilder> {
+impl Neg for Box<DiceBuilder> {
     type Output = Box<DiceBuilder>;
 
-    fn add(self, rhs: Self) -> Self::Output {
-        Box::new(DiceBuilder::SumCompound(vec![*self, *rhs]))
+    fn neg(self) -> Self::Output {
+        Box::new(-*self)
     }
 }
 
-pub fn merge_hashmaps(first: &mut DistributionHashMap, second: &DistributionHashMap) {
+pub fn merge_hashmaps<P: ProbLike>(first: &mut DistributionMap<P>, second: &DistributionMap<P>) {
     for (k, v) in second.iter() {
         match first.get_mut(k) {
             Some(e) => {
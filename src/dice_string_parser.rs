@@ -1,48 +1,116 @@
-// use std::{slice::Iter, vec};
+use std::{fmt::Display, ops::Range};
 
-// use regex::Regex;
-
-use super::dice_builder::{DiceBuilder, Value};
+use super::dice_builder::{DiceBuildError, DiceBuilder, ExplodeKind, Value};
 
+/// a symbol that is a complete dice formula on its own
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum AtomicInputSymbol {
+    /// a constant number like `3`
     Constant(Value),
-    FairDie { min: Value, max: Value },
+    /// a fair die like `d6`
+    FairDie {
+        /// minimum value of the die
+        min: Value,
+        /// maximum value of the die
+        max: Value,
+    },
+    /// a variable defined earlier in the input, like `X` in `X := d20; max(X, 20-X)`
+    Variable,
 }
 
+/// a binary operator between two dice formulas
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum OperatorInputSymbol {
+    /// `+`
     Add,
+    /// `-`
+    Sub,
+    /// `*`
     Mul,
+    /// `x`
     SampleSum,
+    /// `/`
     Div,
+    /// `^`
+    Pow,
 }
 
+/// a separator between the arguments of a function or after a definition
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum SeparatorInputSymbol {
+    /// `,`
     Comma,
+    /// `;`
+    Semicolon,
+    /// `:` between the weight and the formula of a component of `mix(...)`
+    Colon,
 }
 
+/// a symbol closing a bracket or function call
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ClosingInputSymbol {
+    /// `)`
     CloseBracket,
 }
 
+/// a symbol opening a bracket or function call
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum OpeningInputSymbol {
+    /// `(`
     OpenBracket,
+    /// `max(`
     Max,
+    /// `min(`
     Min,
+    /// `abs(`
     Abs,
+    /// `nth_highest(`
+    NthHighest,
+    /// `mix(`
+    Mix,
+}
+
+/// a modifier behind a dice formula that changes how it is rolled
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PostfixInputSymbol {
+    /// `r<=2` to reroll values of at most 2 once and keep the new roll, `rb<=2` to keep the better roll
+    Reroll {
+        /// maximum value that causes a reroll
+        max_value: Value,
+        /// whether the better of both rolls is kept
+        keep_best: bool,
+    },
+    /// `b2` to count every result below 2 as 2, like a brutal weapon
+    Brutal {
+        /// the smallest result
+        min_value: Value,
+    },
+    /// `!`, `!!` or `!p` to explode on the maximum, or on values of at least 5 with `!>=5`
+    Explode {
+        /// how the rolls of the explosions are added
+        kind: ExplodeKind,
+        /// minimum value that causes an explosion
+        min_value: Option<Value>,
+    },
 }
 
+/// a symbol of the input string, after function names have been recognized
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum InputSymbol {
+    /// a constant or a die
     Atomic(AtomicInputSymbol),
+    /// a binary operator
     Operator(OperatorInputSymbol),
+    /// a separator between function arguments or after a definition
     Separator(SeparatorInputSymbol),
+    /// the start of a definition like `X :=`, the span only covers the name
+    Definition,
+    /// an opening bracket or function call
     Opening(OpeningInputSymbol),
+    /// a closing bracket
     Closing(ClosingInputSymbol),
+    /// a modifier of the operand before it
+    Postfix(PostfixInputSymbol),
 }
 
 use AtomicInputSymbol::*;
@@ -50,419 +118,755 @@ use ClosingInputSymbol::*;
 use InputSymbol::*;
 use OpeningInputSymbol::*;
 use OperatorInputSymbol::*;
+use PostfixInputSymbol::*;
 use SeparatorInputSymbol::*;
 
+/// an [`InputSymbol`] together with the byte range of the input it was read from
+///
+/// symbols that are implied by the input, like the `x` in `3d6`, have an empty span.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Token {
+    symbol: InputSymbol,
+    span: Range<usize>,
+}
+
+/// errors of the internal parsing steps, the snippet is added in [`string_to_factor`]
+type ParseResult<T> = Result<T, (DiceBuildingErrorKind, Range<usize>)>;
+
 pub fn string_to_factor(input: &str) -> Result<DiceBuilder, DiceBuildingError> {
-    let symbols = string_to_input_symbols(input)?;
-    let graph_seq = input_symbols_to_graph_seq(&symbols)?;
-    let factor = graph_seq_to_factor(graph_seq);
-    Ok(factor)
+    let to_error = |(kind, span): (DiceBuildingErrorKind, Range<usize>)| DiceBuildingError {
+        kind,
+        snippet: input[span.clone()].to_owned(),
+        span,
+    };
+    let tokens = tokenize(input).map_err(to_error)?;
+    parse(&tokens, input).map_err(to_error)
 }
 
-fn string_to_input_symbols(input: &str) -> Result<Vec<InputSymbol>, DiceBuildingError> {
-    let input = string_utils::clean_string(input)?;
-    let mut symbols: Vec<InputSymbol> = vec![];
-
-    let mut char_iterator = input.chars();
-    let mut last_taken_not_processed: Option<char> = None;
-    'outer: loop {
-        let c = match last_taken_not_processed {
-            Some(a) => {
-                last_taken_not_processed = None;
-                a
+/// definitions and comma separated expressions, like `X := d20; X+5, X x d8`
+pub(crate) type JointFormula = (Vec<(String, DiceBuilder)>, Vec<DiceBuilder>);
+
+/// parses definitions followed by comma separated expressions that can all use the defined variables
+pub(crate) fn string_to_joint_formula(input: &str) -> Result<JointFormula, DiceBuildingError> {
+    let to_error = |(kind, span): (DiceBuildingErrorKind, Range<usize>)| DiceBuildingError {
+        kind,
+        snippet: input[span.clone()].to_owned(),
+        span,
+    };
+    let tokens = tokenize(input).map_err(to_error)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        input,
+    };
+    let definitions = parser.parse_definitions().map_err(to_error)?;
+    let mut expressions = vec![parser.parse_expression(0).map_err(to_error)?];
+    while let Some(token) = parser.next() {
+        match token.symbol {
+            Separator(Comma) => expressions.push(parser.parse_expression(0).map_err(to_error)?),
+            _ => {
+                return Err(to_error((
+                    DiceBuildingErrorKind::UnknownSyntaxError,
+                    token.span,
+                )))
             }
-            None => match char_iterator.next() {
-                Some(e) => e,
-                None => break 'outer,
-            },
-        };
+        }
+    }
+    Ok((definitions, expressions))
+}
 
-        match c {
-            'M' => symbols.push(Opening(Max)),
-            'm' => symbols.push(Opening(Min)),
-            'A' => symbols.push(Opening(Abs)),
-            '(' => symbols.push(Opening(OpenBracket)),
-            ')' => symbols.push(Closing(CloseBracket)),
-            ',' => symbols.push(Separator(Comma)),
-            '*' => symbols.push(Operator(Mul)),
-            'x' => symbols.push(Operator(SampleSum)),
-            '+' => symbols.push(Operator(Add)),
-            '/' => symbols.push(Operator(Div)),
-            'd' => {
-                let mut num_char_vec: Vec<char> = vec![];
-                'inner: loop {
-                    let c2 = match char_iterator.next() {
-                        Some(e) => e,
-                        None => break 'inner,
-                    };
-                    if c2.is_numeric() {
-                        num_char_vec.push(c2)
-                    } else {
-                        last_taken_not_processed = Some(c2);
-                        break;
-                    }
-                }
-                let max: String = num_char_vec.into_iter().collect();
-                let max: i64 = match max.parse() {
-                    Ok(i) => i,
-                    Err(_) => {
-                        return Err(DiceBuildingError::NonDigitSymbolAfterDiceD);
+/// functions that can be called in the input, matched case insensitively
+const FUNCTIONS: [(&str, OpeningInputSymbol); 5] = [
+    ("nth_highest(", NthHighest),
+    ("mix(", Mix),
+    ("max(", Max),
+    ("min(", Min),
+    ("abs(", Abs),
+];
+
+fn tokenize(input: &str) -> ParseResult<Vec<Token>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut variables: Vec<&str> = vec![];
+    let mut pos: usize = 0;
+    while let Some(c) = input[pos..].chars().next() {
+        let start = pos;
+        pos += c.len_utf8();
+        // names of variables are case sensitive and take precedence over dice and functions
+        if c.is_ascii_alphabetic() || c == '_' {
+            let end = start + identifier_len(&input[start..]);
+            let name = &input[start..end];
+            let after_name = end + (input[end..].len() - input[end..].trim_start().len());
+            if input[after_name..].starts_with(":=") {
+                variables.push(name);
+                push_token(&mut tokens, Definition, start..end);
+                pos = after_name + 2;
+                continue;
+            }
+            if variables.contains(&name) {
+                push_token(&mut tokens, Atomic(Variable), start..end);
+                pos = end;
+                continue;
+            }
+        }
+        match c.to_ascii_lowercase() {
+            c if c.is_whitespace() => (),
+            '0'..='9' => {
+                pos = start + digits_len(&input[start..]);
+                let n = parse_number(
+                    input,
+                    start..pos,
+                    DiceBuildingErrorKind::NonDigitNumericCharacter,
+                )?;
+                push_token(&mut tokens, Atomic(Constant(n)), start..pos);
+            }
+            'd' | 'w' if input[pos..].starts_with('[') => {
+                let end = match input[pos..].find(']') {
+                    Some(i) => pos + i + 1,
+                    None => {
+                        return Err((
+                            DiceBuildingErrorKind::InvalidDieInterval,
+                            start..input.len(),
+                        ))
                     }
                 };
-
-                symbols.push(InputSymbol::Atomic(AtomicInputSymbol::FairDie {
-                    min: 1,
-                    max,
-                }));
+                let (min, max) = parse_die_interval(&input[pos + 1..end - 1])
+                    .ok_or((DiceBuildingErrorKind::InvalidDieInterval, start..end))?;
+                pos = end;
+                push_token(&mut tokens, Atomic(FairDie { min, max }), start..pos);
             }
-            '-' => {
-                symbols.push(InputSymbol::Operator(OperatorInputSymbol::Add));
-                symbols.push(InputSymbol::Atomic(AtomicInputSymbol::Constant(-1)));
-                symbols.push(InputSymbol::Operator(OperatorInputSymbol::Mul));
+            'd' | 'w' => {
+                let digits = digits_len(&input[pos..]);
+                if digits == 0 {
+                    return Err((DiceBuildingErrorKind::NonDigitSymbolAfterDiceD, start..pos));
+                }
+                let max = parse_number(
+                    input,
+                    pos..pos + digits,
+                    DiceBuildingErrorKind::NonDigitSymbolAfterDiceD,
+                )?;
+                pos += digits;
+                push_token(&mut tokens, Atomic(FairDie { min: 1, max }), start..pos);
             }
-            n => {
-                let mut num_char_vec: Vec<char> = vec![n];
-                'inner: loop {
-                    let c2 = match char_iterator.next() {
-                        Some(e) => e,
-                        None => break 'inner,
-                    };
-                    if c2.is_numeric() {
-                        num_char_vec.push(c2)
-                    } else {
-                        last_taken_not_processed = Some(c2);
-                        break;
+            'r' if input[pos..].starts_with("<=") || input[pos..].starts_with("b<=") => {
+                let keep_best = input[pos..].starts_with('b');
+                pos += if keep_best { 3 } else { 2 };
+                let digits = signed_digits_len(&input[pos..]);
+                if digits == 0 {
+                    return Err((DiceBuildingErrorKind::InvalidReroll, start..pos));
+                }
+                let max_value = parse_number(
+                    input,
+                    pos..pos + digits,
+                    DiceBuildingErrorKind::InvalidReroll,
+                )?;
+                pos += digits;
+                let reroll = Reroll {
+                    max_value,
+                    keep_best,
+                };
+                push_token(&mut tokens, Postfix(reroll), start..pos);
+            }
+            '!' => {
+                let kind = match input[pos..].chars().next() {
+                    Some('!') => ExplodeKind::Compounding,
+                    Some('p' | 'P') => ExplodeKind::Penetrating,
+                    _ => ExplodeKind::Standard,
+                };
+                if kind != ExplodeKind::Standard {
+                    pos += 1;
+                }
+                let mut min_value = None;
+                if input[pos..].starts_with(">=") {
+                    pos += 2;
+                    let digits = signed_digits_len(&input[pos..]);
+                    if digits == 0 {
+                        return Err((DiceBuildingErrorKind::InvalidExplode, start..pos));
                     }
+                    min_value = Some(parse_number(
+                        input,
+                        pos..pos + digits,
+                        DiceBuildingErrorKind::InvalidExplode,
+                    )?);
+                    pos += digits;
                 }
-                let n: String = num_char_vec.into_iter().collect();
-                let n: i64 = match n.parse() {
-                    Ok(i) => i,
-                    Err(_) => {
-                        return Err(DiceBuildingError::NonDigitNumericCharacter);
+                push_token(
+                    &mut tokens,
+                    Postfix(Explode { kind, min_value }),
+                    start..pos,
+                );
+            }
+            'b' if digits_len(&input[pos..]) > 0 => {
+                let digits = digits_len(&input[pos..]);
+                let min_value = parse_number(
+                    input,
+                    pos..pos + digits,
+                    DiceBuildingErrorKind::InvalidBrutal,
+                )?;
+                pos += digits;
+                push_token(&mut tokens, Postfix(Brutal { min_value }), start..pos);
+            }
+            'x' => push_token(&mut tokens, Operator(SampleSum), start..pos),
+            '+' => push_token(&mut tokens, Operator(Add), start..pos),
+            '*' => push_token(&mut tokens, Operator(Mul), start..pos),
+            '/' => push_token(&mut tokens, Operator(Div), start..pos),
+            '^' => push_token(&mut tokens, Operator(Pow), start..pos),
+            '-' => push_token(&mut tokens, Operator(Sub), start..pos),
+            '(' => push_token(&mut tokens, Opening(OpenBracket), start..pos),
+            ')' => push_token(&mut tokens, Closing(CloseBracket), start..pos),
+            ',' => push_token(&mut tokens, Separator(Comma), start..pos),
+            ';' => push_token(&mut tokens, Separator(Semicolon), start..pos),
+            ':' => push_token(&mut tokens, Separator(Colon), start..pos),
+            _ => {
+                let function = FUNCTIONS.iter().find(|(name, _)| {
+                    input[start..]
+                        .get(..name.len())
+                        .is_some_and(|s| s.eq_ignore_ascii_case(name))
+                });
+                match function {
+                    Some((name, opening)) => {
+                        pos = start + name.len();
+                        push_token(&mut tokens, Opening(*opening), start..pos);
                     }
-                };
-                symbols.push(InputSymbol::Atomic(AtomicInputSymbol::Constant(n)));
+                    None => {
+                        return Err((
+                            DiceBuildingErrorKind::InvalidCharacterInInput(c),
+                            start..pos,
+                        ))
+                    }
+                }
             }
         }
     }
 
-    // purge empty add symbols, that is all add symbols that are not behind a closing, fairdie or constant
-    // example: + "-1" * "d3" => "-1" * "d3"
-    symbols = symbols
-        .iter()
-        .enumerate()
-        .filter(|(i, e)| {
-            !(**e == InputSymbol::Operator(OperatorInputSymbol::Add)
-                && (*i == 0
-                    || *i == symbols.len() - 1
-                    || !matches!(
-                        symbols[i - 1],
-                        InputSymbol::Atomic(_) | InputSymbol::Closing(_)
-                    )))
-        })
-        .map(|(_, e)| e)
-        .cloned()
-        .collect();
-
-    Ok(symbols)
+    check_brackets(&tokens)?;
+    Ok(tokens)
+}
+
+/// pushes a token, inserting an implicit `x` before it if it directly follows a value:
+///
+/// `3d6 => 3xd6`, `)( => )x(`, `)max( => )xmax(`, `3(...) => 3x(...)`, `d3(d3) => d3x(d3)`
+fn push_token(tokens: &mut Vec<Token>, symbol: InputSymbol, span: Range<usize>) {
+    if let Some(previous) = tokens.last() {
+        if matches!(
+            (previous.symbol, symbol),
+            (Atomic(_) | Closing(_), Opening(_)) | (Atomic(_), Atomic(FairDie { .. }))
+        ) {
+            tokens.push(Token {
+                symbol: Operator(SampleSum),
+                span: span.start..span.start,
+            });
+        }
+    }
+    tokens.push(Token { symbol, span });
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum GraphSeq {
-    Atomic(DiceBuilder),
-    Add(Vec<GraphSeq>),
-    Mul(Vec<GraphSeq>),
-    Div(Vec<GraphSeq>),
-    Min(Vec<GraphSeq>),
-    Max(Vec<GraphSeq>),
-    SampleSum(Vec<GraphSeq>),
-    Absolute(Box<GraphSeq>),
+/// length in bytes of the ascii letters, digits and underscores at the start of `s`
+fn identifier_len(s: &str) -> usize {
+    s.bytes()
+        .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        .count()
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum DiceBuildingError {
-    UnknownSyntaxError(Vec<InputSymbol>),
-    OneInputSymbolButNotAtomic(InputSymbol),
+/// length in bytes of the ascii digits at the start of `s`
+fn digits_len(s: &str) -> usize {
+    s.bytes().take_while(|b| b.is_ascii_digit()).count()
+}
+
+/// length of the number at the start of `s` including a leading `-`, 0 if there is none
+fn signed_digits_len(s: &str) -> usize {
+    match s.strip_prefix('-') {
+        Some(rest) if digits_len(rest) > 0 => 1 + digits_len(rest),
+        _ => digits_len(s),
+    }
+}
+
+fn parse_number(
+    input: &str,
+    span: Range<usize>,
+    kind: DiceBuildingErrorKind,
+) -> ParseResult<Value> {
+    input[span.clone()].parse().map_err(|_| (kind, span))
+}
+
+/// parses the inside of `d[min..max]`
+fn parse_die_interval(s: &str) -> Option<(Value, Value)> {
+    let (min, max) = s.split_once("..")?;
+    Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+}
+
+/// returns an error for the first closing bracket without an opening bracket or the last opening bracket that is never closed
+fn check_brackets(tokens: &[Token]) -> ParseResult<()> {
+    let mut open: Vec<&Range<usize>> = vec![];
+    for token in tokens.iter() {
+        match token.symbol {
+            Opening(_) => open.push(&token.span),
+            Closing(_) if open.pop().is_none() => {
+                return Err((DiceBuildingErrorKind::NegativeScope, token.span.clone()));
+            }
+            _ => (),
+        }
+    }
+    match open.pop() {
+        Some(span) => Err((DiceBuildingErrorKind::UnclosedBracket, span.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Error that occured while parsing a string into a [`DiceBuilder`], pointing to the offending part of the input
+///
+/// # Examples
+/// ```
+/// use dices::DiceBuilder;
+/// let err = DiceBuilder::from_string("2d6 + max(d4,,d8)").unwrap_err();
+/// assert_eq!(err.span, 13..14);
+/// assert_eq!(err.snippet, ",");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceBuildingError {
+    /// what went wrong
+    pub kind: DiceBuildingErrorKind,
+    /// byte range of the input that caused the error, empty if something is missing at that position
+    pub span: Range<usize>,
+    /// the part of the input covered by `span`
+    pub snippet: String,
+}
+
+/// The kinds of [`DiceBuildingError`]s that can occur while parsing a string into a [`DiceBuilder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiceBuildingErrorKind {
+    /// the sequence of symbols does not form a valid dice formula
+    UnknownSyntaxError,
+    /// a single symbol that is not a constant or a die, e.g. a lone `+`
+    OneInputSymbolButNotAtomic,
+    /// a `d` is not followed by the number of sides
     NonDigitSymbolAfterDiceD,
+    /// a number could not be parsed
     NonDigitNumericCharacter,
+    /// a die interval is not of the form `d[min..max]`
+    InvalidDieInterval,
     /// more closing brackets than opening brackets up to one point
     NegativeScope,
+    /// an opening bracket that is never closed
+    UnclosedBracket,
+    /// two operators directly behind each other, e.g. `d6**2`
     MultipleOperatorsBehindEachOther,
+    /// an empty formula, e.g. `()`, an empty function argument or an empty input
     EmptySubSequence,
+    /// a character that is not allowed in dice formulas
     InvalidCharacterInInput(char),
+    /// a definition like `X := d6` that is not followed by `;`
+    IncompleteDefinition,
+    /// a `,` inside of `abs(...)`
     SeperatorsInsideAbsolute,
+    /// nth_highest(n,k,...) needs constants n and k with 1 <= k <= n
+    InvalidNthHighestArguments,
+    /// a component of `mix(...)` that does not start with a non-negative integer weight and a `:`, like `2: d6`
+    InvalidMixtureComponent,
+    /// a reroll that is not of the form `r<=2` or `rb<=2`, or that does not follow a formula
+    InvalidReroll,
+    /// an explosion that is not of the form `!`, `!!` or `!p` with an optional `>=5`, or that does not follow a formula
+    InvalidExplode,
+    /// a minimum result like `b2` that does not follow a formula
+    InvalidBrutal,
+    /// the formula was parsed, but its distribution could not be calculated, only returned by `Dice::build_from_string()`
+    Build(Box<DiceBuildError>),
 }
 
-fn input_symbols_to_graph_seq(symbols: &[InputSymbol]) -> Result<GraphSeq, DiceBuildingError> {
-    match symbols.len() {
-        0 => Err(DiceBuildingError::EmptySubSequence),
-        1 => {
-            let sym = symbols[0];
-            match sym {
-                Atomic(a) => match a {
-                    Constant(i) => Ok(GraphSeq::Atomic(DiceBuilder::Constant(i))),
-                    FairDie { min, max } => Ok(GraphSeq::Atomic(DiceBuilder::FairDie { min, max })),
-                },
-                e => Err(DiceBuildingError::OneInputSymbolButNotAtomic(e)),
+impl Display for DiceBuildingErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiceBuildingErrorKind::UnknownSyntaxError => write!(f, "invalid syntax"),
+            DiceBuildingErrorKind::OneInputSymbolButNotAtomic => {
+                write!(f, "expected a number or a die")
             }
-        }
-        _ => {
-            // precedence of operators (high -> low):  x -> * -> / -> +
-            // example: 4+3*d3xd2 is  4+(3*(d3xd2))
-            // check for operators in ascending precedence to build sequence by splitting on operators:
-
-            // consists of adds in global scope:
-            if global_scope_contains_operator(symbols, Add)? {
-                return Ok(GraphSeq::Add(split_and_assemble(symbols, Operator(Add))?));
+            DiceBuildingErrorKind::NonDigitSymbolAfterDiceD => {
+                write!(f, "expected the number of sides after 'd'")
             }
-
-            if global_scope_contains_operator(symbols, Div)? {
-                return Ok(GraphSeq::Div(split_and_assemble(symbols, Operator(Div))?));
+            DiceBuildingErrorKind::NonDigitNumericCharacter => write!(f, "invalid number"),
+            DiceBuildingErrorKind::InvalidDieInterval => {
+                write!(f, "expected a die interval like 'd[0..9]'")
             }
-
-            if global_scope_contains_operator(symbols, Mul)? {
-                return Ok(GraphSeq::Mul(split_and_assemble(symbols, Operator(Mul))?));
+            DiceBuildingErrorKind::NegativeScope => {
+                write!(f, "closing bracket without an opening bracket")
             }
-
-            if global_scope_contains_operator(symbols, SampleSum)? {
-                return Ok(GraphSeq::SampleSum(split_and_assemble(
-                    symbols,
-                    Operator(SampleSum),
-                )?));
+            DiceBuildingErrorKind::UnclosedBracket => write!(f, "bracket is never closed"),
+            DiceBuildingErrorKind::MultipleOperatorsBehindEachOther => {
+                write!(f, "multiple operators behind each other")
             }
-
-            let first = *symbols.first().unwrap();
-            let last = *symbols.last().unwrap();
-            match (first, last) {
-                (Opening(o), Closing(_)) => {
-                    let symbols_no_first_and_last = &symbols[1..(symbols.len() - 1)];
-                    match o {
-                        OpenBracket => Ok(input_symbols_to_graph_seq(symbols_no_first_and_last)?),
-                        Max => Ok(GraphSeq::Max(split_and_assemble(
-                            symbols_no_first_and_last,
-                            Separator(Comma),
-                        )?)),
-                        Min => Ok(GraphSeq::Min(split_and_assemble(
-                            symbols_no_first_and_last,
-                            Separator(Comma),
-                        )?)),
-                        Abs => {
-                            let has_commas_inside = symbols_no_first_and_last
-                                .iter()
-                                .any(|e| *e == Separator(Comma));
-                            if has_commas_inside {
-                                return Err(DiceBuildingError::SeperatorsInsideAbsolute);
-                            } else {
-                                Ok(GraphSeq::Absolute(Box::new(input_symbols_to_graph_seq(
-                                    symbols_no_first_and_last,
-                                )?)))
-                            }
-                        }
-                    }
-                }
-                _ => Err(DiceBuildingError::UnknownSyntaxError(symbols.to_vec())),
+            DiceBuildingErrorKind::EmptySubSequence => write!(f, "empty expression"),
+            DiceBuildingErrorKind::InvalidCharacterInInput(c) => {
+                write!(f, "invalid character '{c}'")
+            }
+            DiceBuildingErrorKind::IncompleteDefinition => {
+                write!(f, "expected ';' after the definition")
+            }
+            DiceBuildingErrorKind::SeperatorsInsideAbsolute => {
+                write!(f, "abs(...) takes exactly one argument")
+            }
+            DiceBuildingErrorKind::InvalidNthHighestArguments => write!(
+                f,
+                "nth_highest(n,k,...) needs constants n and k with 1 <= k <= n"
+            ),
+            DiceBuildingErrorKind::InvalidMixtureComponent => {
+                write!(f, "mix(...) components need a weight like '2: d6'")
             }
+            DiceBuildingErrorKind::InvalidReroll => {
+                write!(f, "expected a reroll like 'd20r<=2' or 'd20rb<=2'")
+            }
+            DiceBuildingErrorKind::InvalidExplode => {
+                write!(
+                    f,
+                    "expected an explosion like 'd6!', 'd6!!', 'd6!p' or 'd6!>=5'"
+                )
+            }
+            DiceBuildingErrorKind::InvalidBrutal => {
+                write!(f, "expected a minimum result behind a formula like 'd10b2'")
+            }
+            DiceBuildingErrorKind::Build(err) => write!(f, "{err}"),
         }
     }
 }
 
-// fn determineTypeOfGraphSeqBySequentialScan(){
-fn global_scope_contains_operator(
-    symbols: &[InputSymbol],
-    operator: OperatorInputSymbol,
-) -> Result<bool, DiceBuildingError> {
-    let mut scope_depth: usize = 0;
-    for symbol in symbols.iter() {
-        if scope_depth == 0 {
-            if let InputSymbol::Operator(a) = *symbol {
-                if a == operator {
-                    return Ok(true);
-                }
+impl Display for DiceBuildingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}: '{}'",
+            self.kind, self.span.start, self.span.end, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for DiceBuildingError {}
+
+/// parses the tokens of an input with `input_len` bytes into a [`DiceBuilder`]
+fn parse(tokens: &[Token], input: &str) -> ParseResult<DiceBuilder> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        input,
+    };
+    let dice_builder = parser.parse_definitions_and_expression()?;
+    match parser.next() {
+        None => Ok(dice_builder),
+        Some(token) => Err((DiceBuildingErrorKind::UnknownSyntaxError, token.span)),
+    }
+}
+
+/// A Pratt parser over the tokens of an input.
+///
+/// Chains of the same operator like `d6+d8+4` become a single compound with all operands.
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+    input: &'t str,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(token)
+    }
+
+    /// the token before the one at `index`
+    fn before(&self, index: usize) -> Option<&'t Token> {
+        self.tokens.get(index.checked_sub(1)?)
+    }
+
+    /// parses definitions like `X := d20;` followed by the expression they are used in
+    fn parse_definitions_and_expression(&mut self) -> ParseResult<DiceBuilder> {
+        let definitions = self.parse_definitions()?;
+        let mut body = self.parse_expression(0)?;
+        for (name, value) in definitions.into_iter().rev() {
+            body = DiceBuilder::Let {
+                name,
+                value: Box::new(value),
+                body: Box::new(body),
+            };
+        }
+        Ok(body)
+    }
+
+    /// parses definitions like `X := d20;` as long as there are some
+    fn parse_definitions(&mut self) -> ParseResult<Vec<(String, DiceBuilder)>> {
+        let mut definitions: Vec<(String, DiceBuilder)> = vec![];
+        while let Some(Token {
+            symbol: Definition,
+            span,
+        }) = self.peek()
+        {
+            self.pos += 1;
+            let value = self.parse_expression(0)?;
+            match self.next() {
+                Some(Token {
+                    symbol: Separator(Semicolon),
+                    ..
+                }) => (),
+                _ => return Err((DiceBuildingErrorKind::IncompleteDefinition, span.clone())),
             }
+            definitions.push((self.input[span.clone()].to_owned(), value));
         }
-        match symbol {
-            InputSymbol::Opening(_) => {
-                scope_depth += 1;
+        Ok(definitions)
+    }
+
+    /// parses operands joined by operators that bind at least as strong as `min_binding_power`
+    fn parse_expression(&mut self, min_binding_power: u8) -> ParseResult<DiceBuilder> {
+        let mut lhs = self.parse_operand(min_binding_power)?;
+        while let Some(token) = self.peek() {
+            let operator = match token.symbol {
+                Operator(operator) => operator,
+                Closing(_) | Separator(_) => break,
+                Atomic(_) | Opening(_) | Definition => {
+                    return Err((
+                        DiceBuildingErrorKind::UnknownSyntaxError,
+                        token.span.clone(),
+                    ))
+                }
+                Postfix(postfix) => return Err((postfix_error(postfix), token.span.clone())),
+            };
+            let binding_power = binding_power(operator);
+            if binding_power < min_binding_power {
+                break;
             }
-            InputSymbol::Closing(_) => {
-                if scope_depth == 0 {
-                    return Err(DiceBuildingError::NegativeScope);
+            let mut operands = vec![lhs];
+            // `+` and `-` share a binding power and form a single sum
+            while let Some(Operator(next)) = self.peek().map(|t| t.symbol) {
+                if self::binding_power(next) != binding_power {
+                    break;
                 }
-                scope_depth -= 1;
+                self.pos += 1;
+                let operand = self.parse_expression(binding_power + 1)?;
+                operands.push(match next {
+                    Sub => negated(operand),
+                    _ => operand,
+                });
             }
-            _ => (),
+            lhs = compound(operator, operands);
         }
+        Ok(lhs)
     }
 
-    Ok(false)
-}
+    /// parses a constant, a die, a bracket or a function call, together with the modifiers behind it
+    ///
+    /// `min_binding_power` is the one of the expression the operand starts, see `parse_unmodified_operand()`
+    fn parse_operand(&mut self, min_binding_power: u8) -> ParseResult<DiceBuilder> {
+        let mut operand = self.parse_unmodified_operand(min_binding_power)?;
+        // modifiers bind stronger than all operators, so `2d20r<=2` rerolls each d20
+        while let Some(Token {
+            symbol: Postfix(postfix),
+            ..
+        }) = self.peek()
+        {
+            self.pos += 1;
+            operand = match *postfix {
+                Reroll {
+                    max_value,
+                    keep_best,
+                } => DiceBuilder::Reroll {
+                    dice_builder: Box::new(operand),
+                    max_value,
+                    keep_best,
+                },
+                // every single roll of the operand has the minimum, also in pools like `4d6b2`
+                Brutal { min_value } => {
+                    DiceBuilder::MaxCompound(vec![operand, DiceBuilder::Constant(min_value)])
+                }
+                Explode { kind, min_value } => DiceBuilder::Explode {
+                    dice_builder: Box::new(operand),
+                    min_value,
+                    max_iterations: DiceBuilder::DEFAULT_MAX_EXPLOSIONS,
+                    kind,
+                },
+            };
+        }
+        Ok(operand)
+    }
 
-fn split_and_assemble(
-    symbols: &[InputSymbol],
-    splitter: InputSymbol,
-) -> Result<Vec<GraphSeq>, DiceBuildingError> {
-    let mut segments: Vec<GraphSeq> = vec![];
-    for segment in symbols
-        .split_bracket_aware(splitter)?
-        .iter()
-        .map(|segment| input_symbols_to_graph_seq(segment))
-    {
-        segments.push(segment?);
-    }
-    Ok(segments)
-}
+    /// parses a constant, a die, a bracket or a function call
+    ///
+    /// a leading `-` or `+` takes the operators binding at least as strong as `min_binding_power` into its operand, but never sums
+    fn parse_unmodified_operand(&mut self, min_binding_power: u8) -> ParseResult<DiceBuilder> {
+        let index = self.pos;
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(self.missing_operand_error()),
+        };
+        match token.symbol {
+            Atomic(Constant(i)) => Ok(DiceBuilder::Constant(i)),
+            Atomic(FairDie { min, max }) => Ok(DiceBuilder::FairDie { min, max }),
+            Atomic(Variable) => Ok(DiceBuilder::Variable(self.input[token.span].to_owned())),
+            Definition => Err((DiceBuildingErrorKind::UnknownSyntaxError, token.span)),
+            Postfix(postfix) => Err((postfix_error(postfix), token.span)),
+            Opening(opening) => self.parse_call(opening, token.span),
+            // unary minus binds everything but sums: -2d6/2 is -(2d6/2), but 2*-d6/2 is (2*-d6)/2
+            Operator(Sub) => Ok(negated(
+                self.parse_expression(min_binding_power.max(binding_power(Div)))?,
+            )),
+            Operator(Add) => self.parse_expression(min_binding_power.max(binding_power(Div))),
+            Operator(_) => match self.before(index) {
+                Some(Token {
+                    symbol: Operator(_),
+                    ..
+                }) => Err((
+                    DiceBuildingErrorKind::MultipleOperatorsBehindEachOther,
+                    token.span,
+                )),
+                _ => Err((
+                    DiceBuildingErrorKind::OneInputSymbolButNotAtomic,
+                    token.span,
+                )),
+            },
+            Closing(_) | Separator(_) => match self.before(index) {
+                Some(Token {
+                    symbol: Opening(_),
+                    span,
+                }) => Err((
+                    DiceBuildingErrorKind::EmptySubSequence,
+                    span.start..token.span.end,
+                )),
+                _ => Err((DiceBuildingErrorKind::EmptySubSequence, token.span)),
+            },
+        }
+    }
 
-trait BracketAwareSplittable {
-    fn split_bracket_aware(
-        &self,
-        splitter: InputSymbol,
-    ) -> Result<Vec<&[InputSymbol]>, DiceBuildingError>;
-}
+    /// the input ended where an operand was expected, brackets are balanced so this can only happen behind an operator
+    fn missing_operand_error(&self) -> (DiceBuildingErrorKind, Range<usize>) {
+        match self.tokens.last() {
+            None => (DiceBuildingErrorKind::EmptySubSequence, 0..self.input.len()),
+            Some(token) => (
+                DiceBuildingErrorKind::MultipleOperatorsBehindEachOther,
+                token.span.clone(),
+            ),
+        }
+    }
 
-impl BracketAwareSplittable for &[InputSymbol] {
-    fn split_bracket_aware(
-        &self,
-        splitter: InputSymbol,
-    ) -> Result<Vec<&[InputSymbol]>, DiceBuildingError> {
-        let mut index_chunks: Vec<(Option<usize>, Option<usize>)> = vec![(None, None)];
-        let mut scope_depth: usize = 0;
-        for (i, e) in self.iter().enumerate() {
-            if *e == splitter && scope_depth == 0 {
-                index_chunks.push((None, None));
-            } else {
-                let last = index_chunks.last_mut().unwrap();
-                match last {
-                    (None, None) => {
-                        *last = (Some(i), Some(i));
-                    }
-                    (Some(_), Some(_)) => {
-                        *last = (last.0, Some(i));
-                    }
-                    _ => panic!("should not happen"),
+    /// parses the comma separated arguments up to the closing bracket of a bracket or function call opened at `opening_span`
+    fn parse_call(
+        &mut self,
+        opening: OpeningInputSymbol,
+        opening_span: Range<usize>,
+    ) -> ParseResult<DiceBuilder> {
+        if opening == Mix {
+            return self.parse_mixture();
+        }
+        let mut arguments = vec![self.parse_definitions_and_expression()?];
+        let mut separator_spans: Vec<Range<usize>> = vec![];
+        let closing_span = loop {
+            // brackets are balanced after tokenization, so there is always a closing bracket left
+            let token = self.next().expect("brackets are balanced");
+            match token.symbol {
+                Closing(_) => break token.span,
+                Separator(Comma) => {
+                    separator_spans.push(token.span);
+                    arguments.push(self.parse_definitions_and_expression()?);
+                }
+                _ => return Err((DiceBuildingErrorKind::UnknownSyntaxError, token.span)),
+            }
+        };
+        let span = opening_span.start..closing_span.end;
+        match opening {
+            OpenBracket => match separator_spans.into_iter().next() {
+                Some(separator_span) => {
+                    Err((DiceBuildingErrorKind::UnknownSyntaxError, separator_span))
+                }
+                None => Ok(arguments.pop().unwrap()),
+            },
+            Abs => match separator_spans.into_iter().next() {
+                Some(separator_span) => Err((
+                    DiceBuildingErrorKind::SeperatorsInsideAbsolute,
+                    separator_span,
+                )),
+                None => Ok(DiceBuilder::Absolute(Box::new(arguments.pop().unwrap()))),
+            },
+            Max => Ok(DiceBuilder::MaxCompound(arguments)),
+            Min => Ok(DiceBuilder::MinCompound(arguments)),
+            NthHighest => {
+                if arguments.len() != 3 {
+                    return Err((DiceBuildingErrorKind::InvalidNthHighestArguments, span));
                 }
-                match *e {
-                    InputSymbol::Opening(_) => scope_depth += 1,
-                    InputSymbol::Closing(_) => {
-                        if scope_depth == 0 {
-                            return Err(DiceBuildingError::NegativeScope);
-                        }
-                        scope_depth -= 1
+                let dice_builder = Box::new(arguments.pop().unwrap());
+                match (&arguments[0], &arguments[1]) {
+                    (DiceBuilder::Constant(n), DiceBuilder::Constant(k)) if 1 <= *k && k <= n => {
+                        Ok(DiceBuilder::NthHighest {
+                            n: *n as usize,
+                            k: *k as usize,
+                            dice_builder,
+                        })
                     }
-                    _ => (),
+                    _ => Err((DiceBuildingErrorKind::InvalidNthHighestArguments, span)),
                 }
             }
+            Mix => unreachable!("mixtures are parsed by parse_mixture"),
         }
-        for e in index_chunks.iter() {
-            if let (None, None) = e {
-                return Err(DiceBuildingError::MultipleOperatorsBehindEachOther);
+    }
+
+    /// parses the comma separated `weight: formula` components up to the closing bracket of `mix(`
+    fn parse_mixture(&mut self) -> ParseResult<DiceBuilder> {
+        let mut components: Vec<(u32, DiceBuilder)> = vec![];
+        loop {
+            // brackets are balanced after tokenization, so there is always a closing bracket left
+            let token = self.next().expect("brackets are balanced");
+            let weight = match (token.symbol, self.peek().map(|t| t.symbol)) {
+                (Atomic(Constant(w)), Some(Separator(Colon))) => u32::try_from(w).ok(),
+                _ => None,
+            };
+            let Some(weight) = weight else {
+                return Err((DiceBuildingErrorKind::InvalidMixtureComponent, token.span));
+            };
+            self.pos += 1;
+            components.push((weight, self.parse_definitions_and_expression()?));
+            let token = self.next().expect("brackets are balanced");
+            match token.symbol {
+                Closing(_) => return Ok(DiceBuilder::Mixture(components)),
+                Separator(Comma) => (),
+                _ => return Err((DiceBuildingErrorKind::UnknownSyntaxError, token.span)),
             }
         }
-        let res = index_chunks
-            .iter()
-            .map(|(s, e)| &self[s.unwrap()..=e.unwrap()])
-            .collect();
-        Ok(res)
     }
 }
 
-fn graph_seq_to_factor(graph_seq: GraphSeq) -> DiceBuilder {
-    match graph_seq {
-        GraphSeq::Atomic(f) => f,
-
-        GraphSeq::Add(vec) => DiceBuilder::SumCompound(
-            vec.into_iter()
-                .map(graph_seq_to_factor)
-                .collect::<Vec<DiceBuilder>>(),
-        ),
-        GraphSeq::Mul(vec) => DiceBuilder::ProductCompound(
-            vec.into_iter()
-                .map(graph_seq_to_factor)
-                .collect::<Vec<DiceBuilder>>(),
-        ),
-        GraphSeq::Min(vec) => DiceBuilder::MinCompound(
-            vec.into_iter()
-                .map(graph_seq_to_factor)
-                .collect::<Vec<DiceBuilder>>(),
-        ),
-        GraphSeq::Max(vec) => DiceBuilder::MaxCompound(
-            vec.into_iter()
-                .map(graph_seq_to_factor)
-                .collect::<Vec<DiceBuilder>>(),
-        ),
-        GraphSeq::SampleSum(vec) => DiceBuilder::SampleSumCompound(
-            vec.into_iter()
-                .map(graph_seq_to_factor)
-                .collect::<Vec<DiceBuilder>>(),
-        ),
-        GraphSeq::Div(vec) => DiceBuilder::DivisionCompound(
-            vec.into_iter()
-                .map(graph_seq_to_factor)
-                .collect::<Vec<DiceBuilder>>(),
-        ),
-        GraphSeq::Absolute(box graphseq) => {
-            DiceBuilder::Absolute(Box::new(graph_seq_to_factor(graphseq)))
-        }
+/// precedence of operators (high -> low):  ^ -> x -> * -> / -> +,-
+///
+/// example: 4+3*d3xd2^2 is  4+(3*(d3x(d2^2)))
+fn binding_power(operator: OperatorInputSymbol) -> u8 {
+    match operator {
+        Add | Sub => 1,
+        Div => 2,
+        Mul => 3,
+        SampleSum => 4,
+        Pow => 5,
     }
 }
 
-mod string_utils {
-    use regex::Regex;
-
-    use super::DiceBuildingError;
-    const PERMITTED_CHARACTERS: &str = "minaxbs(,)dw0123456789+-*/";
-    pub fn clean_string(s: &str) -> Result<String, DiceBuildingError> {
-        let mut new_s = String::new();
-        for ch in s.to_lowercase().chars() {
-            if PERMITTED_CHARACTERS
-                .chars()
-                .into_iter()
-                .any(|ch2| ch2 == ch)
-            {
-                new_s.push(ch);
-            } else if !ch.is_whitespace() {
-                return Err(DiceBuildingError::InvalidCharacterInInput(ch));
-            }
-        }
-        let s = &mut new_s;
-        s.retain(|c| PERMITTED_CHARACTERS.chars().into_iter().any(|c2| c == c2));
-        *s = s.replace("max(", "M"); // maximum
-        *s = s.replace("abs(", "A"); // absolute
-        *s = s.replace("min(", "m"); // minimum
-        *s = s.replace('w', "d");
-
-        // 3d6 => 3xd6
-        add_token_in_string(s, "", r"\d", "d", "", "x");
-
-        // )( => )x(
-        add_token_in_string(s, r"\)", "", r"\(", "x", "");
-
-        // )M => )xM
-        add_token_in_string(s, r"\)", "", "M", "x", "");
-
-        // )m => )xm
-        add_token_in_string(s, r"\)", "", "m", "x", "");
-
-        // 3(...) => 3x(...),   d3(d3) => d3x(d3)
-        add_token_in_string(s, r"", r"(\d|d)", r"\(", "", "x");
-        Ok(new_s)
-    }
-
-    fn add_token_in_string(
-        string: &mut String,
-        before: &str,
-        search_token: &str,
-        after: &str,
-        put_before_search_token: &str,
-        put_after_search_token: &str,
-    ) {
-        let re = Regex::new(&format!("{}({}){}", before, search_token, after)).unwrap();
-        *string = re
-            .replace_all(string, &format!("{}□$1■{}", before, after))
-            .to_string();
-        *string = string
-            .replace('□', put_before_search_token)
-            .replace('■', put_after_search_token)
-            .replace('\\', "");
+/// the error of a modifier that does not follow a formula
+fn postfix_error(postfix: PostfixInputSymbol) -> DiceBuildingErrorKind {
+    match postfix {
+        Reroll { .. } => DiceBuildingErrorKind::InvalidReroll,
+        Explode { .. } => DiceBuildingErrorKind::InvalidExplode,
+        Brutal { .. } => DiceBuildingErrorKind::InvalidBrutal,
+    }
+}
+
+/// negated constants are folded into a negative constant, so `d6-2` contains the constant -2
+fn negated(dice_builder: DiceBuilder) -> DiceBuilder {
+    match dice_builder {
+        DiceBuilder::Constant(i) if i != Value::MIN => DiceBuilder::Constant(-i),
+        dice_builder => DiceBuilder::Negation(Box::new(dice_builder)),
+    }
+}
+
+fn compound(operator: OperatorInputSymbol, operands: Vec<DiceBuilder>) -> DiceBuilder {
+    match operator {
+        Add | Sub => DiceBuilder::SumCompound(operands),
+        Div => DiceBuilder::DivisionCompound(operands),
+        Mul => DiceBuilder::ProductCompound(operands),
+        SampleSum => DiceBuilder::SampleSumCompound(operands),
+        Pow => DiceBuilder::PowerCompound(operands),
     }
 }
 
@@ -472,17 +876,123 @@ mod test {
 
     use super::*;
 
+    fn string_to_input_symbols(input: &str) -> Vec<InputSymbol> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.symbol)
+            .collect()
+    }
+
     #[test]
-    fn clean_string_test() {
-        let input = r#" max(3w6)(3+4)+d3(d3)-3()  min(3,4)       "#.to_owned();
+    fn tokenize_implicit_sample_sum_test() {
+        let real = string_to_input_symbols(" MAX(3w6)(3+4)+d3(d3)  min(3,4)  ");
+        let expected: Vec<InputSymbol> = vec![
+            Opening(Max),
+            Atomic(Constant(3)),
+            Operator(SampleSum),
+            Atomic(FairDie { min: 1, max: 6 }),
+            Closing(CloseBracket),
+            Operator(SampleSum),
+            Opening(OpenBracket),
+            Atomic(Constant(3)),
+            Operator(Add),
+            Atomic(Constant(4)),
+            Closing(CloseBracket),
+            Operator(Add),
+            Atomic(FairDie { min: 1, max: 3 }),
+            Operator(SampleSum),
+            Opening(OpenBracket),
+            Atomic(FairDie { min: 1, max: 3 }),
+            Closing(CloseBracket),
+            Operator(SampleSum),
+            Opening(Min),
+            Atomic(Constant(3)),
+            Separator(Comma),
+            Atomic(Constant(4)),
+            Closing(CloseBracket),
+        ];
+        assert_eq!(real, expected);
+    }
 
-        let input = string_utils::clean_string(&input).unwrap();
-        dbg!(&input);
-        assert_eq!("M3xd6)x(3+4)+d3x(d3)-3x()xm3,4)", input);
+    #[test]
+    fn tokenize_spans_test() {
+        let tokens = tokenize("3d6 + max(d20,4)").unwrap();
+        let spans: Vec<Range<usize>> = tokens.into_iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                0..1,
+                1..1,
+                1..3,
+                4..5,
+                6..10,
+                10..13,
+                13..14,
+                14..15,
+                15..16
+            ]
+        );
     }
+
+    #[test]
+    fn error_spans_test() {
+        let cases: Vec<(&str, DiceBuildingErrorKind, Range<usize>)> = vec![
+            (
+                "2d6 + max(d4,,d8)",
+                DiceBuildingErrorKind::EmptySubSequence,
+                13..14,
+            ),
+            (
+                "d6 ** 2",
+                DiceBuildingErrorKind::MultipleOperatorsBehindEachOther,
+                4..5,
+            ),
+            (
+                "2d6 + 4?",
+                DiceBuildingErrorKind::InvalidCharacterInInput('?'),
+                7..8,
+            ),
+            (
+                "3 + d",
+                DiceBuildingErrorKind::NonDigitSymbolAfterDiceD,
+                4..5,
+            ),
+            ("d6)+2", DiceBuildingErrorKind::NegativeScope, 2..3),
+            ("max(d6,(2", DiceBuildingErrorKind::UnclosedBracket, 7..8),
+            ("2 + ()", DiceBuildingErrorKind::EmptySubSequence, 4..6),
+            (
+                "abs(d6, 2)",
+                DiceBuildingErrorKind::SeperatorsInsideAbsolute,
+                6..7,
+            ),
+            (
+                "1 + nth_highest(2,3,d6)",
+                DiceBuildingErrorKind::InvalidNthHighestArguments,
+                4..23,
+            ),
+            (
+                "mix(1: d6, d8)",
+                DiceBuildingErrorKind::InvalidMixtureComponent,
+                11..13,
+            ),
+            ("d20r<=", DiceBuildingErrorKind::InvalidReroll, 3..6),
+            ("2 + r<=2", DiceBuildingErrorKind::InvalidReroll, 4..8),
+            ("d6!>=", DiceBuildingErrorKind::InvalidExplode, 2..5),
+            ("(!!)", DiceBuildingErrorKind::InvalidExplode, 1..3),
+            ("3 * b2", DiceBuildingErrorKind::InvalidBrutal, 4..6),
+            ("", DiceBuildingErrorKind::EmptySubSequence, 0..0),
+        ];
+        for (input, kind, span) in cases {
+            let err = string_to_factor(input).unwrap_err();
+            assert_eq!((&err.kind, &err.span), (&kind, &span), "input: {input}");
+            assert_eq!(err.snippet, input[span].to_owned());
+        }
+    }
+
     #[test]
     fn string_to_input_symbols_1() {
-        let real: Vec<InputSymbol> = string_to_input_symbols("max(13,2)").unwrap();
+        let real: Vec<InputSymbol> = string_to_input_symbols("max(13,2)");
         let expected: Vec<InputSymbol> = vec![
             Opening(Max),
             Atomic(Constant(13)),
@@ -495,34 +1005,32 @@ mod test {
 
     #[test]
     fn string_to_input_symbols_2() {
-        let real: Vec<InputSymbol> = string_to_input_symbols("4 d32 - 3").unwrap();
+        let real: Vec<InputSymbol> = string_to_input_symbols("4 d32 - 3");
         let expected: Vec<InputSymbol> = vec![
             Atomic(Constant(4)),
             Operator(SampleSum),
             Atomic(FairDie { min: 1, max: 32 }),
-            Operator(Add),
-            Atomic(Constant(-1)),
-            Operator(Mul),
+            Operator(Sub),
             Atomic(Constant(3)),
         ];
         assert_eq!(real, expected);
     }
 
-    mod graph_building {
+    mod parsing {
         use super::*;
         use crate::{
             dice_builder::DiceBuilder,
-            dice_string_parser::{input_symbols_to_graph_seq, string_to_input_symbols, GraphSeq},
+            dice_string_parser::{parse, tokenize},
         };
 
         #[test]
-        /// see if graph in constructed correctly
-        fn input_symbols_to_graph_seq_test() {
+        /// see if tree in constructed correctly
+        fn parse_test() {
             let input = "max(1,2,3)";
 
-            let symbols = string_to_input_symbols(input).unwrap();
+            let tokens = tokenize(input).unwrap();
             assert_eq!(
-                symbols,
+                string_to_input_symbols(input),
                 vec![
                     Opening(Max),
                     Atomic(Constant(1)),
@@ -533,32 +1041,7 @@ mod test {
                     Closing(CloseBracket)
                 ]
             );
-            let graph = input_symbols_to_graph_seq(&symbols).unwrap();
-            let expected_graph = GraphSeq::Max(vec![
-                GraphSeq::Atomic(DiceBuilder::Constant(1)),
-                GraphSeq::Atomic(DiceBuilder::Constant(2)),
-                GraphSeq::Atomic(DiceBuilder::Constant(3)),
-            ]);
-            assert_eq!(graph, expected_graph);
-        }
-    }
-
-    mod input_to_factor {
-        use crate::dice_builder::AggrValue;
-        use crate::dice_string_parser::DiceBuildingError;
-        use crate::{
-            dice_builder::DiceBuilder,
-            dice_string_parser::{graph_seq_to_factor, string_to_factor, GraphSeq},
-        };
-
-        #[test]
-        fn graph_seq_to_factor_test() {
-            let graph = GraphSeq::Max(vec![
-                GraphSeq::Atomic(DiceBuilder::Constant(1)),
-                GraphSeq::Atomic(DiceBuilder::Constant(2)),
-                GraphSeq::Atomic(DiceBuilder::Constant(3)),
-            ]);
-            let factor = graph_seq_to_factor(graph);
+            let factor = parse(&tokens, input).unwrap();
             let expected_factor = DiceBuilder::MaxCompound(vec![
                 DiceBuilder::Constant(1),
                 DiceBuilder::Constant(2),
@@ -567,6 +1050,76 @@ mod test {
             assert_eq!(factor, expected_factor);
         }
 
+        #[test]
+        /// chains of the same operator are one compound, brackets are kept as nested compounds
+        fn parse_precedence_test() {
+            let c = DiceBuilder::Constant;
+            let input = "4*6/2*3+(1+2)+d4x3";
+            let tokens = tokenize(input).unwrap();
+            let factor = parse(&tokens, input).unwrap();
+            let expected_factor = DiceBuilder::SumCompound(vec![
+                DiceBuilder::DivisionCompound(vec![
+                    DiceBuilder::ProductCompound(vec![c(4), c(6)]),
+                    DiceBuilder::ProductCompound(vec![c(2), c(3)]),
+                ]),
+                DiceBuilder::SumCompound(vec![c(1), c(2)]),
+                DiceBuilder::SampleSumCompound(vec![DiceBuilder::FairDie { min: 1, max: 4 }, c(3)]),
+            ]);
+            assert_eq!(factor, expected_factor);
+        }
+
+        #[test]
+        /// a unary minus behind an operator only takes operators binding stronger than that one into its operand
+        fn parse_unary_minus_test() {
+            let c = DiceBuilder::Constant;
+            let d6 = || DiceBuilder::FairDie { min: 1, max: 6 };
+            let neg = |d: DiceBuilder| DiceBuilder::Negation(Box::new(d));
+            let cases: Vec<(&str, DiceBuilder)> = vec![
+                (
+                    "-2d6/2",
+                    neg(DiceBuilder::DivisionCompound(vec![
+                        DiceBuilder::SampleSumCompound(vec![c(2), d6()]),
+                        c(2),
+                    ])),
+                ),
+                (
+                    "2*-d6/2",
+                    DiceBuilder::DivisionCompound(vec![
+                        DiceBuilder::ProductCompound(vec![c(2), neg(d6())]),
+                        c(2),
+                    ]),
+                ),
+                (
+                    "3x-d6*2",
+                    DiceBuilder::ProductCompound(vec![
+                        DiceBuilder::SampleSumCompound(vec![c(3), neg(d6())]),
+                        c(2),
+                    ]),
+                ),
+                (
+                    "2^-d6x3",
+                    DiceBuilder::SampleSumCompound(vec![
+                        DiceBuilder::PowerCompound(vec![c(2), neg(d6())]),
+                        c(3),
+                    ]),
+                ),
+                (
+                    "2^-d6^2",
+                    DiceBuilder::PowerCompound(vec![c(2), neg(d6()), c(2)]),
+                ),
+            ];
+            for (input, expected) in cases {
+                let tokens = tokenize(input).unwrap();
+                assert_eq!(parse(&tokens, input).unwrap(), expected, "input: {input}");
+            }
+        }
+    }
+
+    mod input_to_factor {
+        use crate::dice_builder::Prob;
+        use crate::dice_string_parser::DiceBuildingErrorKind;
+        use crate::{dice_builder::DiceBuilder, dice_string_parser::string_to_factor};
+
         #[test]
         fn string_to_factor_test() {
             let factor = string_to_factor("max(1,2,3)  ").unwrap();
@@ -577,10 +1130,21 @@ mod test {
             ]);
             assert_eq!(factor, expected_factor);
 
-            let factor_failed = string_to_factor("max(1:,2,3)  ");
+            // `:` only separates weights inside of mix(...)
+            let factor_failed = string_to_factor("max(1:,2,3)  ").unwrap_err();
+            assert_eq!(
+                factor_failed.kind,
+                DiceBuildingErrorKind::UnknownSyntaxError
+            );
+            let factor_failed = string_to_factor("max(1;,2,3)  ").unwrap_err();
+            assert_eq!(
+                factor_failed.kind,
+                DiceBuildingErrorKind::UnknownSyntaxError
+            );
+            let factor_failed = string_to_factor("max(1?,2,3)  ").unwrap_err();
             assert_eq!(
-                factor_failed,
-                Err(DiceBuildingError::InvalidCharacterInInput(':'))
+                factor_failed.kind,
+                DiceBuildingErrorKind::InvalidCharacterInInput('?')
             );
         }
 
@@ -621,7 +1185,7 @@ mod test {
         fn test_factor_stats() {
             let factor = DiceBuilder::from_string("2w6").unwrap();
             let stats = factor.build();
-            assert_eq!(stats.mean, AggrValue::new(7u64, 1u64));
+            assert_eq!(stats.mean, Prob::new(7u64, 1u64));
         }
     }
 }
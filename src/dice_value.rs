@@ -0,0 +1,405 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+    hash::Hash,
+};
+
+use fraction::{BigInt, Zero};
+
+use crate::{
+    build_options::BuildContext,
+    dice_builder::{
+        fair_die_sides, for_each_side, nth_highest_distribution, DiceBuildError, DiceBuilder, Value,
+    },
+    prob_like::ProbLike,
+};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The integer type of the values of a [`Dice`](crate::Dice), [`i64`] by default.
+///
+/// Implemented for [`i32`], which keeps serialized distributions small, and for [`i128`] and [`BigInt`], for formulas with huge values like `d100^10*d100^10`.
+/// The trait is sealed, it cannot be implemented outside of this crate. See `DiceBuilder::build_values_as()`.
+pub trait DiceValue: sealed::Sealed + Clone + Ord + Hash + Debug + Display + Send + Sync {
+    /// true if every [`i64`] fits into the type and some values of the type do not fit into an [`i64`]
+    const WIDER_THAN_I64: bool;
+
+    /// `value` as this type, `None` if it does not fit
+    fn from_value(value: Value) -> Option<Self>;
+    /// `self` as an [`i64`], `None` if it does not fit
+    fn to_value(&self) -> Option<Value>;
+    /// `self` as a probability, used for aggregates like the mean
+    fn to_prob<P: ProbLike>(&self) -> P;
+    /// `self + other`, `None` if the result does not fit
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    /// `self * other`, `None` if the result does not fit
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    /// `-self`, `None` if the result does not fit
+    fn checked_neg(&self) -> Option<Self>;
+    /// `self^exponent` for a non-negative exponent, `None` if the result does not fit
+    fn checked_pow(&self, exponent: &Self) -> Option<Self>;
+    /// `self / divisor` rounded like [`DiceBuilder::DivisionCompound`], `None` if the divisor is 0 or the result does not fit
+    fn checked_rounded_div(&self, divisor: &Self) -> Option<Self>;
+    /// true if `self` is smaller than 0
+    fn is_negative(&self) -> bool;
+}
+
+macro_rules! impl_dice_value {
+    ($type:ident, $wider:expr) => {
+        impl sealed::Sealed for $type {}
+
+        impl DiceValue for $type {
+            const WIDER_THAN_I64: bool = $wider;
+
+            fn from_value(value: Value) -> Option<Self> {
+                $type::try_from(value).ok()
+            }
+
+            fn to_value(&self) -> Option<Value> {
+                Value::try_from(*self).ok()
+            }
+
+            fn to_prob<P: ProbLike>(&self) -> P {
+                match self.to_value() {
+                    Some(value) => P::from_value(value),
+                    None => BigInt::from(*self).to_prob(),
+                }
+            }
+
+            fn checked_add(&self, other: &Self) -> Option<Self> {
+                $type::checked_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &Self) -> Option<Self> {
+                $type::checked_mul(*self, *other)
+            }
+
+            fn checked_neg(&self) -> Option<Self> {
+                $type::checked_neg(*self)
+            }
+
+            fn checked_pow(&self, exponent: &Self) -> Option<Self> {
+                match *self {
+                    // 0, 1 and -1 stay small for any exponent, only the parity of the exponent matters
+                    0 | 1 if *exponent != 0 => Some(*self),
+                    -1 if *exponent % 2 != 0 => Some(-1),
+                    -1..=1 => Some(1),
+                    base => $type::checked_pow(base, u32::try_from(*exponent).ok()?),
+                }
+            }
+
+            fn checked_rounded_div(&self, divisor: &Self) -> Option<Self> {
+                match divisor {
+                    0 => None,
+                    -1 => $type::checked_neg(*self),
+                    divisor => Some(rounded_div::$type(*self, *divisor)),
+                }
+            }
+
+            fn is_negative(&self) -> bool {
+                *self < 0
+            }
+        }
+    };
+}
+
+impl_dice_value!(i32, false);
+impl_dice_value!(i64, false);
+impl_dice_value!(i128, true);
+
+/// [`BigInt`] values with more bits than this count as overflowing, so that formulas like `2^d1000000` fail instead of exhausting the memory
+const MAX_BIG_INT_BITS: u64 = 1 << 16;
+
+impl sealed::Sealed for BigInt {}
+
+impl DiceValue for BigInt {
+    const WIDER_THAN_I64: bool = true;
+
+    fn from_value(value: Value) -> Option<Self> {
+        Some(BigInt::from(value))
+    }
+
+    fn to_value(&self) -> Option<Value> {
+        Value::try_from(self).ok()
+    }
+
+    fn to_prob<P: ProbLike>(&self) -> P {
+        let (_, digits) = self.to_u32_digits();
+        let base = P::from_value(1 << 32);
+        let magnitude = digits.iter().rev().fold(P::zero(), |acc, digit| {
+            acc * base.clone() + P::from_value(Value::from(*digit))
+        });
+        match self.is_negative() {
+            true => P::zero() - magnitude,
+            false => magnitude,
+        }
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        within_bits(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        within_bits(self * other)
+    }
+
+    fn checked_neg(&self) -> Option<Self> {
+        Some(-self)
+    }
+
+    fn checked_pow(&self, exponent: &Self) -> Option<Self> {
+        if self.is_zero() || self.magnitude() == &1u32.into() {
+            // 0, 1 and -1 stay small for any exponent, only the parity of the exponent matters
+            let even = (exponent % 2u32).is_zero();
+            return match exponent.is_zero() {
+                true => Some(BigInt::from(1)),
+                false if self.is_negative() && even => Some(BigInt::from(1)),
+                false => Some(self.clone()),
+            };
+        }
+        let exponent = u32::try_from(exponent).ok()?;
+        if self.bits().saturating_mul(u64::from(exponent)) > MAX_BIG_INT_BITS {
+            return None;
+        }
+        Some(self.pow(exponent))
+    }
+
+    fn checked_rounded_div(&self, divisor: &Self) -> Option<Self> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let quotient = self / divisor;
+        let remainder = self % divisor;
+        // rounds halves away from zero, like `rounded_div`
+        match remainder.magnitude() * 2u32 >= *divisor.magnitude() {
+            true if self.is_negative() == divisor.is_negative() => Some(quotient + 1),
+            true => Some(quotient - 1),
+            false => Some(quotient),
+        }
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < BigInt::zero()
+    }
+}
+
+fn within_bits(value: BigInt) -> Option<BigInt> {
+    match value.bits() > MAX_BIG_INT_BITS {
+        true => None,
+        false => Some(value),
+    }
+}
+
+/// an operation that combines two values of type `V`, `None` if the result does not fit
+type ValueOperation<V> = fn(&V, &V) -> Option<V>;
+
+/// the distribution of `dice_builder` with values of type `V`, calculated without the optimizations that rely on [`i64`] values, like dense convolutions
+///
+/// `bindings` holds the values of the variables bound by the enclosing [`DiceBuilder::Let`]s, innermost last.
+pub(crate) fn value_distribution<'t, V: DiceValue, P: ProbLike>(
+    dice_builder: &'t DiceBuilder,
+    bindings: &mut Vec<(&'t str, V)>,
+    ctx: &mut BuildContext<'_, '_, P>,
+) -> Result<BTreeMap<V, P>, DiceBuildError> {
+    let map = match dice_builder {
+        DiceBuilder::Constant(v) => single(V::from_value(*v).ok_or(DiceBuildError::ValueOverflow)?),
+        DiceBuilder::FairDie { min, max } => {
+            let prob = P::from_ratio(1, fair_die_sides(*min, *max)?);
+            let mut m = BTreeMap::new();
+            for_each_side(*min, *max, ctx, |v| {
+                m.insert(
+                    V::from_value(v).ok_or(DiceBuildError::ValueOverflow)?,
+                    prob.clone(),
+                );
+                Ok(())
+            })?;
+            m
+        }
+        DiceBuilder::SumCompound(vec)
+        | DiceBuilder::ProductCompound(vec)
+        | DiceBuilder::DivisionCompound(vec)
+        | DiceBuilder::MaxCompound(vec)
+        | DiceBuilder::MinCompound(vec) => {
+            let operation: ValueOperation<V> = match dice_builder {
+                DiceBuilder::SumCompound(_) => V::checked_add,
+                DiceBuilder::ProductCompound(_) => V::checked_mul,
+                DiceBuilder::DivisionCompound(_) => V::checked_rounded_div,
+                DiceBuilder::MaxCompound(_) => |a, b| Some(a.max(b).clone()),
+                DiceBuilder::MinCompound(_) => |a, b| Some(a.min(b).clone()),
+                _ => panic!("unreachable by match"),
+            };
+            let (first, rest) = vec.split_first().ok_or(DiceBuildError::EmptyCompound)?;
+            let mut convoluted = value_distribution(first, bindings, ctx)?;
+            for d in rest {
+                let map = value_distribution(d, bindings, ctx)?;
+                if matches!(dice_builder, DiceBuilder::DivisionCompound(_))
+                    && map.keys().any(|v| v.to_value() == Some(0))
+                {
+                    return Err(DiceBuildError::DivisionByZero);
+                }
+                convoluted = convolute(&convoluted, &map, operation, ctx)?;
+            }
+            convoluted
+        }
+        DiceBuilder::SampleSumCompound(vec) => {
+            let (first, rest) = vec.split_first().ok_or(DiceBuildError::EmptyCompound)?;
+            let mut convoluted = value_distribution(first, bindings, ctx)?;
+            for d in rest {
+                let map = value_distribution(d, bindings, ctx)?;
+                convoluted = sample_sum(&convoluted, &map, ctx)?;
+            }
+            convoluted
+        }
+        DiceBuilder::PowerCompound(vec) => {
+            let mut maps = vec
+                .iter()
+                .map(|d| value_distribution(d, bindings, ctx))
+                .collect::<Result<Vec<BTreeMap<V, P>>, DiceBuildError>>()?;
+            let mut exponent = maps.pop().ok_or(DiceBuildError::EmptyCompound)?;
+            while let Some(base) = maps.pop() {
+                if let Some(negative) = exponent.keys().next().filter(|e| e.is_negative()) {
+                    let negative = negative.to_value().ok_or(DiceBuildError::ValueOverflow)?;
+                    return Err(DiceBuildError::NegativeExponent(negative));
+                }
+                exponent = convolute(&base, &exponent, V::checked_pow, ctx)?;
+            }
+            exponent
+        }
+        DiceBuilder::Absolute(d) => map_values(value_distribution(d, bindings, ctx)?, |v| match v
+            .is_negative()
+        {
+            true => v.checked_neg(),
+            false => Some(v.clone()),
+        })?,
+        DiceBuilder::Negation(d) => {
+            map_values(value_distribution(d, bindings, ctx)?, V::checked_neg)?
+        }
+        DiceBuilder::Let { name, value, body } => {
+            let value_map = value_distribution(value, bindings, ctx)?;
+            let mut m = BTreeMap::new();
+            for (v, p) in value_map.into_iter() {
+                bindings.push((name, v));
+                let body_map = value_distribution(body, bindings, ctx);
+                bindings.pop();
+                for (w, q) in body_map? {
+                    add_prob(&mut m, w, q * p.clone());
+                }
+            }
+            m
+        }
+        DiceBuilder::Variable(name) => match bindings.iter().rev().find(|(n, _)| n == name) {
+            Some((_, v)) => single(v.clone()),
+            None => return Err(DiceBuildError::UnboundVariable(name.clone())),
+        },
+        DiceBuilder::NthHighest { n, k, dice_builder } => {
+            if *k < 1 || k > n {
+                return Err(DiceBuildError::InvalidNthHighest { n: *n, k: *k });
+            }
+            let distribution = value_distribution(dice_builder, bindings, ctx)?;
+            nth_highest_distribution(distribution.into_iter().collect(), *n, *k, ctx)?
+                .into_iter()
+                .collect()
+        }
+        DiceBuilder::Mixture(components) => {
+            if components.is_empty() {
+                return Err(DiceBuildError::EmptyCompound);
+            }
+            let total_weight: u64 = components.iter().map(|(w, _)| u64::from(*w)).sum();
+            if total_weight == 0 {
+                return Err(DiceBuildError::ZeroMixtureWeight);
+            }
+            let mut m = BTreeMap::new();
+            for (weight, d) in components.iter().filter(|(w, _)| *w > 0) {
+                let weight = P::from_ratio(u64::from(*weight), total_weight);
+                for (v, p) in value_distribution(d, bindings, ctx)? {
+                    add_prob(&mut m, v, p * weight.clone());
+                }
+            }
+            m
+        }
+        DiceBuilder::Explode { .. } => {
+            return Err(DiceBuildError::NotImplemented(
+                "exploding dice with a custom value type",
+            ))
+        }
+        DiceBuilder::Reroll { .. } => {
+            return Err(DiceBuildError::NotImplemented(
+                "rerolls with a custom value type",
+            ))
+        }
+    };
+    ctx.count_node();
+    ctx.check_outcomes(map.len())?;
+    Ok(map)
+}
+
+fn single<V: DiceValue, P: ProbLike>(value: V) -> BTreeMap<V, P> {
+    BTreeMap::from([(value, P::one())])
+}
+
+fn add_prob<V: DiceValue, P: ProbLike>(map: &mut BTreeMap<V, P>, value: V, p: P) {
+    match map.get_mut(&value) {
+        Some(q) => *q += p,
+        None => {
+            map.insert(value, p);
+        }
+    }
+}
+
+fn map_values<V: DiceValue, P: ProbLike>(
+    map: BTreeMap<V, P>,
+    f: impl Fn(&V) -> Option<V>,
+) -> Result<BTreeMap<V, P>, DiceBuildError> {
+    let mut m = BTreeMap::new();
+    for (v, p) in map {
+        add_prob(&mut m, f(&v).ok_or(DiceBuildError::ValueOverflow)?, p);
+    }
+    Ok(m)
+}
+
+fn convolute<V: DiceValue, P: ProbLike>(
+    first: &BTreeMap<V, P>,
+    second: &BTreeMap<V, P>,
+    operation: ValueOperation<V>,
+    ctx: &mut BuildContext<'_, '_, P>,
+) -> Result<BTreeMap<V, P>, DiceBuildError> {
+    ctx.add_work((first.len() * second.len()) as u64)?;
+    let mut m = BTreeMap::new();
+    for (v1, p1) in first.iter() {
+        ctx.complete_work(second.len() as u64)?;
+        for (v2, p2) in second.iter() {
+            let v = operation(v1, v2).ok_or(DiceBuildError::ValueOverflow)?;
+            add_prob(&mut m, v, p1.clone() * p2.clone());
+        }
+    }
+    ctx.check_outcomes(m.len())?;
+    Ok(m)
+}
+
+/// the distribution of summing up a number of samples of `samples` given by `counts`, negative counts subtract the samples
+fn sample_sum<V: DiceValue, P: ProbLike>(
+    counts: &BTreeMap<V, P>,
+    samples: &BTreeMap<V, P>,
+    ctx: &mut BuildContext<'_, '_, P>,
+) -> Result<BTreeMap<V, P>, DiceBuildError> {
+    let zero = V::from_value(0).ok_or(DiceBuildError::ValueOverflow)?;
+    let negated = map_values(samples.clone(), V::checked_neg)?;
+    let mut m = BTreeMap::new();
+    for (count, count_p) in counts.iter() {
+        let count_value = count.to_value().ok_or(DiceBuildError::ValueOverflow)?;
+        let sample = match count_value < 0 {
+            true => &negated,
+            false => samples,
+        };
+        let mut sum = single(zero.clone());
+        for _ in 0..count_value.unsigned_abs() {
+            sum = convolute(&sum, sample, V::checked_add, ctx)?;
+        }
+        for (v, p) in sum {
+            add_prob(&mut m, v, p * count_p.clone());
+        }
+    }
+    Ok(m)
+}
@@ -0,0 +1,78 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// The map from values to probabilities that the intermediate distributions of a build are calculated in.
+///
+/// By default it is a [`HashMap`], which inserts in constant time, so only the result is sorted once.
+/// The `btree_distributions` feature switches to a [`BTreeMap`], which keeps the values sorted so builds iterate in a deterministic order, and lets benchmarks compare both backends.
+#[cfg(not(feature = "btree_distributions"))]
+pub type DistributionMap<P = Prob> = HashMap<Value, P>;
+/// The map from values to probabilities that the intermediate distributions of a build are calculated in.
+#[cfg(feature = "btree_distributions")]
+pub type DistributionMap<P = Prob> = BTreeMap<Value, P>;
+
+/// the operations on a [`DistributionMap`] that differ between its backends
+pub(crate) trait DistributionBackend<P>:
+    Default + Clone + IntoIterator<Item = (Value, P)> + FromIterator<(Value, P)>
+{
+    /// adds `p` to the probability of `value`
+    fn add_prob(&mut self, value: Value, p: P);
+    /// the smallest value, `None` if the map is empty
+    fn min_value(&self) -> Option<Value>;
+    /// the largest value, `None` if the map is empty
+    fn max_value(&self) -> Option<Value>;
+    /// all values with their probabilities in ascending order
+    fn into_sorted_vec(self) -> Vec<(Value, P)>;
+}
+
+impl<P: ProbLike> DistributionBackend<P> for BTreeMap<Value, P> {
+    fn add_prob(&mut self, value: Value, p: P) {
+        match self.entry(value) {
+            std::collections::btree_map::Entry::Occupied(mut e) => *e.get_mut() += p,
+            std::collections::btree_map::Entry::Vacant(e) => {
+                e.insert(p);
+            }
+        }
+    }
+
+    fn min_value(&self) -> Option<Value> {
+        self.keys().next().copied()
+    }
+
+    fn max_value(&self) -> Option<Value> {
+        self.keys().next_back().copied()
+    }
+
+    fn into_sorted_vec(self) -> Vec<(Value, P)> {
+        self.into_iter().collect()
+    }
+}
+
+impl<P: ProbLike> DistributionBackend<P> for HashMap<Value, P> {
+    fn add_prob(&mut self, value: Value, p: P) {
+        match self.entry(value) {
+            std::collections::hash_map::Entry::Occupied(mut e) => *e.get_mut() += p,
+            std::collections::hash_map::Entry::Vacant(e) => {
+                e.insert(p);
+            }
+        }
+    }
+
+    fn min_value(&self) -> Option<Value> {
+        self.keys().min().copied()
+    }
+
+    fn max_value(&self) -> Option<Value> {
+        self.keys().max().copied()
+    }
+
+    fn into_sorted_vec(self) -> Vec<(Value, P)> {
+        let mut vec: Vec<(Value, P)> = self.into_iter().collect();
+        vec.sort_by_key(|(v, _)| *v);
+        vec
+    }
+}
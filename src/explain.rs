@@ -0,0 +1,79 @@
+use std::fmt::Display;
+
+use crate::{
+    dice::Dice,
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// A step by step breakdown of a probability query, returned by [`Dice::explain_prob_gte()`].
+///
+/// Its `Display` writes the whole derivation in one line, like
+/// `P(2xd6 >= 11) = P(11) + P(12) = 1/18 + 1/36 = 1/12 ≈ 8.33%`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbExplanation<P = Prob> {
+    /// the formula of the queried dice
+    pub formula: String,
+    /// the condition on the rolled value, like `>= 11`
+    pub condition: String,
+    /// every value that satisfies the condition with its probability, in ascending order
+    pub outcomes: Vec<(Value, P)>,
+    /// the sum of the probabilities of `outcomes`
+    pub total: P,
+    /// `total` in percent
+    pub percent: f64,
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// explains where the probability of rolling at least `value` comes from, for example for teaching tools
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// let explanation = d.explain_prob_gte(11);
+    /// assert_eq!(explanation.outcomes.len(), 2);
+    /// assert_eq!(explanation.total, d.prob_gte(11));
+    /// assert_eq!(
+    ///     explanation.to_string(),
+    ///     "P(2xd6 >= 11) = P(11) + P(12) = 1/18 + 1/36 = 1/12 ≈ 8.33%"
+    /// );
+    /// ```
+    pub fn explain_prob_gte(&self, value: Value) -> ProbExplanation<P> {
+        let outcomes: Vec<(Value, P)> = self
+            .distribution
+            .iter()
+            .filter(|(v, _)| *v >= value)
+            .cloned()
+            .collect();
+        let mut total = P::zero();
+        for (_, p) in outcomes.iter() {
+            total += p;
+        }
+        ProbExplanation {
+            formula: self.builder_string.clone(),
+            condition: format!(">= {value}"),
+            percent: total.to_float() * 100.0,
+            outcomes,
+            total,
+        }
+    }
+}
+
+impl<P: Display> Display for ProbExplanation<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P({} {})", self.formula, self.condition)?;
+        // a single outcome would only repeat its probability
+        if self.outcomes.len() > 1 {
+            let terms: Vec<String> = self
+                .outcomes
+                .iter()
+                .map(|(v, _)| format!("P({v})"))
+                .collect();
+            write!(f, " = {}", terms.join(" + "))?;
+            let probs: Vec<String> = self.outcomes.iter().map(|(_, p)| p.to_string()).collect();
+            write!(f, " = {}", probs.join(" + "))?;
+        }
+        write!(f, " = {} ≈ {:.2}%", self.total, self.percent)
+    }
+}
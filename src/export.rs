@@ -0,0 +1,667 @@
+use std::{fmt::Display, io::Write};
+
+#[cfg(feature = "json")]
+use crate::dice_builder::Value;
+use crate::{
+    dice::Dice,
+    dice_builder::{DiceBuilder, ExplodeKind},
+    prob_like::ProbLike,
+};
+
+/// Options for [`Dice::to_csv()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// character between the columns, `','` for CSV and `'\t'` for TSV
+    pub delimiter: char,
+    /// write a first line with the column names
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            header: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// tab separated values with a header line
+    pub fn tsv() -> CsvOptions {
+        CsvOptions {
+            delimiter: '\t',
+            ..Default::default()
+        }
+    }
+}
+
+impl<P: ProbLike + Display> Dice<P> {
+    /// writes one line per value of the distribution with the columns `value`, `prob` (exact), `prob_f64` and `cumulative_prob_f64`
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{CsvOptions, Dice};
+    /// let d = Dice::build_from_string("d4").unwrap();
+    /// let mut csv: Vec<u8> = vec![];
+    /// d.to_csv(&mut csv, &CsvOptions::default()).unwrap();
+    /// let csv = String::from_utf8(csv).unwrap();
+    /// assert_eq!(csv.lines().nth(1), Some("1,1/4,0.25,0.25"));
+    /// ```
+    pub fn to_csv<W: Write>(&self, mut writer: W, options: &CsvOptions) -> std::io::Result<()> {
+        let d = options.delimiter;
+        if options.header {
+            writeln!(writer, "value{d}prob{d}prob_f64{d}cumulative_prob_f64")?;
+        }
+        for ((v, p), (_, c)) in self.distribution.iter().zip(self.cdf_iter()) {
+            writeln!(writer, "{v}{d}{p}{d}{}{d}{}", p.to_float(), c.to_float())?;
+        }
+        Ok(())
+    }
+}
+
+/// the schema of [`Dice::to_json()`]
+#[cfg(feature = "json")]
+#[derive(serde::Serialize)]
+struct JsonDistribution {
+    values: Vec<Value>,
+    probs: Vec<f64>,
+    cum_probs: Vec<f64>,
+    stats: JsonStats,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Serialize)]
+struct JsonStats {
+    min: Value,
+    max: Value,
+    median: Value,
+    mode: Vec<Value>,
+    mean: f64,
+    variance: f64,
+    sd: f64,
+}
+
+#[cfg(feature = "json")]
+impl<P: ProbLike> Dice<P> {
+    /// the distribution and its statistics as JSON with the schema `{"values": [...], "probs": [...], "cum_probs": [...], "stats": {...}}`
+    ///
+    /// `stats` contains `min`, `max`, `median`, `mode` (ascending), `mean`, `variance` and `sd`.
+    /// All floats are rounded to `precision` decimal places, if given.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d3").unwrap();
+    /// assert_eq!(
+    ///     d.to_json(Some(3)),
+    ///     r#"{"values":[1,2,3],"probs":[0.333,0.333,0.333],"cum_probs":[0.333,0.667,1.0],"stats":{"min":1,"max":3,"median":2,"mode":[1,2,3],"mean":2.0,"variance":0.667,"sd":0.816}}"#
+    /// );
+    /// ```
+    pub fn to_json(&self, precision: Option<u32>) -> String {
+        let round = |x: f64| match precision {
+            Some(digits) => {
+                let factor = 10f64.powi(digits as i32);
+                (x * factor).round() / factor
+            }
+            None => x,
+        };
+        let floats = |distribution: &[(Value, P)]| -> Vec<f64> {
+            distribution
+                .iter()
+                .map(|(_, p)| round(p.to_float()))
+                .collect()
+        };
+        let variance = self.variance.to_float();
+        let mut mode = self.mode.clone();
+        mode.sort_unstable();
+        let json = JsonDistribution {
+            values: self.distribution.iter().map(|(v, _)| *v).collect(),
+            probs: floats(&self.distribution),
+            cum_probs: self.cdf_iter().map(|(_, c)| round(c.to_float())).collect(),
+            stats: JsonStats {
+                min: self.min,
+                max: self.max,
+                median: self.median,
+                mode,
+                mean: round(self.mean.to_float()),
+                variance: round(variance),
+                sd: round(variance.sqrt()),
+            },
+        };
+        serde_json::to_string(&json).expect("a distribution of numbers can always be serialized")
+    }
+}
+
+/// a compact summary with the formula, min, max, mean, standard deviation, mode and the 5 most likely values
+///
+/// # Examples
+/// ```
+/// use dices::Dice;
+/// let d = Dice::build_from_string("2d6").unwrap();
+/// assert_eq!(
+///     d.to_string(),
+///     "2xd6: min 2, max 12, mean 7.00, sd 2.42, mode 7\nmost likely: 7 (16.67%), 6 (13.89%), 8 (13.89%), 5 (11.11%), 9 (11.11%)"
+/// );
+/// ```
+impl<P: ProbLike> Display for Dice<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.builder_string.is_empty() {
+            write!(f, "{}: ", self.builder_string)?;
+        }
+        let mut mode = self.mode.clone();
+        mode.sort_unstable();
+        let mode: Vec<String> = mode.iter().map(|v| v.to_string()).collect();
+        write!(
+            f,
+            "min {}, max {}, mean {:.2}, sd {:.2}, mode {}",
+            self.min,
+            self.max,
+            self.mean.to_float(),
+            self.variance.to_float().sqrt(),
+            mode.join(", ")
+        )?;
+        let most_likely: Vec<String> = self
+            .most_likely(5)
+            .iter()
+            .map(|(v, p)| format!("{v} ({:.2}%)", p.to_float() * 100.0))
+            .collect();
+        write!(f, "\nmost likely: {}", most_likely.join(", "))
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// the distribution as a markdown table with the probability of each value and the probability to roll at most and at least that value
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d2").unwrap();
+    /// assert_eq!(
+    ///     d.to_markdown_table(),
+    ///     "| value | probability | at most | at least |\n|------:|------------:|--------:|---------:|\n| 1 | 50.00% | 50.00% | 100.00% |\n| 2 | 50.00% | 100.00% | 50.00% |\n"
+    /// );
+    /// ```
+    pub fn to_markdown_table(&self) -> String {
+        let mut table = String::from(
+            "| value | probability | at most | at least |\n|------:|------------:|--------:|---------:|\n",
+        );
+        let mut at_least = 1.0;
+        for ((v, p), (_, c)) in self.distribution.iter().zip(self.cdf_iter()) {
+            let p = p.to_float();
+            table.push_str(&format!(
+                "| {v} | {:.2}% | {:.2}% | {:.2}% |\n",
+                p * 100.0,
+                c.to_float() * 100.0,
+                at_least * 100.0
+            ));
+            at_least -= p;
+        }
+        table
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// the distribution in the layout of the AnyDice table view, so results of both tools can be diffed
+    ///
+    /// A header with the name, mean, standard deviation, min and max is followed by one `value: percent` line per value.
+    /// The name is the builder string, or `output 1` like in AnyDice if there is none.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d4").unwrap();
+    /// assert_eq!(
+    ///     d.to_anydice_text(),
+    ///     "d4\nmean: 2.50\nsd: 1.12\nmin: 1\nmax: 4\n\n1: 25.00\n2: 25.00\n3: 25.00\n4: 25.00\n"
+    /// );
+    /// ```
+    pub fn to_anydice_text(&self) -> String {
+        let name = match self.builder_string.is_empty() {
+            true => "output 1",
+            false => &self.builder_string,
+        };
+        let mut text = format!(
+            "{name}\nmean: {:.2}\nsd: {:.2}\nmin: {}\nmax: {}\n\n",
+            self.mean.to_float(),
+            self.variance.to_float().sqrt(),
+            self.min,
+            self.max
+        );
+        for (v, p) in self.distribution.iter() {
+            text.push_str(&format!("{v}: {:.2}\n", p.to_float() * 100.0));
+        }
+        text
+    }
+}
+
+impl<P: ProbLike + Display> Dice<P> {
+    /// the distribution as a LaTeX `tabular` with the exact probability of each value as a fraction and in percent
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let table = Dice::build_from_string("d2").unwrap().to_latex_table();
+    /// assert!(table.contains(r"1 & $\frac{1}{2}$ & 50.00\% \\"));
+    /// ```
+    pub fn to_latex_table(&self) -> String {
+        let mut table = String::from(
+            "\\begin{tabular}{rrr}\n\\hline\nvalue & probability & percent \\\\\n\\hline\n",
+        );
+        for (v, p) in self.distribution.iter() {
+            let exact = p.to_string();
+            let exact = match exact.split_once('/') {
+                Some((numer, denom)) => format!("\\frac{{{numer}}}{{{denom}}}"),
+                None => exact,
+            };
+            table.push_str(&format!(
+                "{v} & ${exact}$ & {:.2}\\% \\\\\n",
+                p.to_float() * 100.0
+            ));
+        }
+        table.push_str("\\hline\n\\end{tabular}\n");
+        table
+    }
+}
+
+impl DiceBuilder {
+    /// the formula of [`self`] in LaTeX math notation, with `\frac` for divisions and `\max`/`\min` for the functions
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let builder = DiceBuilder::from_string("max(3d6, 2*d8)/2").unwrap();
+    /// assert_eq!(builder.to_latex(), r"\frac{\max(3\mathrm{d}6, 2 \cdot \mathrm{d}8)}{2}");
+    /// ```
+    pub fn to_latex(&self) -> String {
+        match self {
+            DiceBuilder::Constant(i) => i.to_string(),
+            DiceBuilder::FairDie { min, max } => match *min == 1 && *max >= 0 {
+                true => format!("\\mathrm{{d}}{max}"),
+                false => format!("\\mathrm{{d}}[{min}..{max}]"),
+            },
+            DiceBuilder::SumCompound(v) => {
+                let mut s = String::new();
+                for (i, f) in v.iter().enumerate() {
+                    match f {
+                        DiceBuilder::Negation(d) => {
+                            s.push_str(" - ");
+                            s.push_str(&d.latex_operand(1));
+                        }
+                        DiceBuilder::Constant(c) if *c < 0 && i > 0 => {
+                            s.push_str(&format!(" - {}", c.unsigned_abs()));
+                        }
+                        _ => {
+                            if i > 0 {
+                                s.push_str(" + ");
+                            }
+                            s.push_str(&f.latex_operand(1));
+                        }
+                    }
+                }
+                s
+            }
+            DiceBuilder::ProductCompound(v) => latex_join(v, 3, " \\cdot "),
+            DiceBuilder::DivisionCompound(v) => {
+                let mut iter = v.iter();
+                let first = iter.next().map(|f| f.to_latex()).unwrap_or_default();
+                iter.fold(first, |numer, f| {
+                    format!("\\frac{{{numer}}}{{{}}}", f.to_latex())
+                })
+            }
+            DiceBuilder::SampleSumCompound(v) => match v.as_slice() {
+                [count, DiceBuilder::FairDie { min: 1, max }]
+                    if *max >= 0 && count.precedence() == 6 =>
+                {
+                    format!("{}\\mathrm{{d}}{max}", count.to_latex())
+                }
+                _ => latex_join(v, 4, " \\mathbin{\\mathrm{x}} "),
+            },
+            DiceBuilder::PowerCompound(v) => {
+                let mut iter = v.iter().rev();
+                let last = iter.next().map(|f| f.to_latex()).unwrap_or_default();
+                iter.fold(last, |exponent, base| {
+                    format!("{}^{{{exponent}}}", base.latex_operand(5))
+                })
+            }
+            DiceBuilder::MaxCompound(v) => format!("\\max({})", latex_join(v, 0, ", ")),
+            DiceBuilder::MinCompound(v) => format!("\\min({})", latex_join(v, 0, ", ")),
+            DiceBuilder::Absolute(d) => format!("\\left|{}\\right|", d.to_latex()),
+            DiceBuilder::Let { name, value, body } => format!(
+                "{} := {};\\ {}",
+                latex_variable(name),
+                value.latex_operand(0),
+                body.to_latex()
+            ),
+            DiceBuilder::Variable(name) => latex_variable(name),
+            DiceBuilder::Negation(d) => format!("-{}", d.latex_operand(1)),
+            DiceBuilder::NthHighest { n, k, dice_builder } => format!(
+                "\\operatorname{{nth\\_highest}}({n}, {k}, {})",
+                dice_builder.to_latex()
+            ),
+            DiceBuilder::Mixture(components) => format!(
+                "\\operatorname{{mix}}({})",
+                components
+                    .iter()
+                    .map(|(weight, d)| format!("{weight}: {}", d.to_latex()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            DiceBuilder::Reroll {
+                dice_builder,
+                max_value,
+                keep_best,
+            } => format!(
+                "{}\\operatorname{{{}}}_{{\\le {max_value}}}",
+                dice_builder.latex_operand(5),
+                match keep_best {
+                    true => "rb",
+                    false => "r",
+                }
+            ),
+            DiceBuilder::Explode {
+                dice_builder,
+                min_value,
+                max_iterations,
+                kind,
+            } => format!(
+                "\\operatorname{{explode{}}}({}, {}, {max_iterations})",
+                match kind {
+                    ExplodeKind::Standard => "",
+                    ExplodeKind::Compounding => "\\_compounding",
+                    ExplodeKind::Penetrating => "\\_penetrating",
+                },
+                dice_builder.to_latex(),
+                match min_value {
+                    Some(i) => i.to_string(),
+                    None => "\\mathrm{None}".to_string(),
+                }
+            ),
+        }
+    }
+
+    /// the LaTeX of [`self`] as an operand of an operator with `precedence`, in brackets if the operator would otherwise bind parts of it
+    fn latex_operand(&self, precedence: u8) -> String {
+        // a fraction is visually grouped, except as the base of a power
+        let grouped = matches!(self, DiceBuilder::DivisionCompound(_)) && precedence < 5;
+        match self.precedence() <= precedence && !grouped {
+            true => format!("\\left({}\\right)", self.to_latex()),
+            false => self.to_latex(),
+        }
+    }
+}
+
+fn latex_join(v: &[DiceBuilder], precedence: u8, separator: &str) -> String {
+    v.iter()
+        .map(|f| f.latex_operand(precedence))
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// single letter variables in italics like any math variable, longer names in italics as one word
+fn latex_variable(name: &str) -> String {
+    match name.chars().count() {
+        1 => name.to_owned(),
+        _ => format!("\\mathit{{{}}}", name.replace('_', "\\_")),
+    }
+}
+
+/// Options for [`Dice::to_histogram()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistogramOptions {
+    /// number of characters of the longest bar
+    pub width: usize,
+    /// maximum number of bars, values are grouped into ranges of equal size if the distribution has more values
+    pub max_rows: usize,
+    /// show the cumulative probability P(X <= value) instead of the probability of each value
+    pub cumulative: bool,
+    /// draw the bars with `#` instead of unicode block characters
+    pub ascii_only: bool,
+}
+
+impl Default for HistogramOptions {
+    fn default() -> Self {
+        HistogramOptions {
+            width: 50,
+            max_rows: 40,
+            cumulative: false,
+            ascii_only: false,
+        }
+    }
+}
+
+/// partial blocks for eighths of a character, the full block is the last
+const BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+impl<P: ProbLike> Dice<P> {
+    /// a bar chart of the pmf with unicode block characters, at most `width` characters wide, for CLI tools and chat code blocks
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d2+d2").unwrap();
+    /// assert_eq!(
+    ///     d.to_ascii_histogram(10),
+    ///     "2 | █████       25.00%\n3 | ██████████  50.00%\n4 | █████       25.00%\n"
+    /// );
+    /// ```
+    pub fn to_ascii_histogram(&self, width: usize) -> String {
+        self.to_histogram(&HistogramOptions {
+            width,
+            ..Default::default()
+        })
+    }
+
+    /// a bar chart of the pmf or cdf, one line per value or range of values
+    pub fn to_histogram(&self, options: &HistogramOptions) -> String {
+        let mut rows: Vec<(String, f64)> = vec![];
+        let max_rows = options.max_rows.max(1);
+        if self.distribution.len() <= max_rows {
+            for ((v, p), (_, c)) in self.distribution.iter().zip(self.cdf_iter()) {
+                let p = match options.cumulative {
+                    true => c.to_float(),
+                    false => p.to_float(),
+                };
+                rows.push((v.to_string(), p));
+            }
+        } else {
+            let mut cumulative = 0.0;
+            for bucket in self.bucketed_into(max_rows) {
+                let p = bucket.prob.to_float();
+                cumulative += p;
+                let p = match options.cumulative {
+                    true => cumulative,
+                    false => p,
+                };
+                rows.push((format!("{}..{}", bucket.min, bucket.max), p));
+            }
+        }
+        let label_width = rows
+            .iter()
+            .map(|(l, _)| l.chars().count())
+            .max()
+            .unwrap_or(0);
+        let largest = rows.iter().map(|(_, p)| *p).fold(0.0, f64::max);
+        let mut histogram = String::new();
+        for (label, p) in rows.iter() {
+            let eighths = match largest > 0.0 {
+                true => (p / largest * (options.width * 8) as f64).round() as usize,
+                false => 0,
+            };
+            let bar = bar(eighths, options.ascii_only);
+            let padding = options.width.saturating_sub(bar.chars().count());
+            histogram.push_str(&format!(
+                "{label:>label_width$} | {bar}{} {:6.2}%\n",
+                " ".repeat(padding),
+                p * 100.0
+            ));
+        }
+        histogram
+    }
+}
+
+/// a bar of `eighths` eighths of a character
+fn bar(eighths: usize, ascii_only: bool) -> String {
+    if ascii_only {
+        return "#".repeat((eighths + 4) / 8);
+    }
+    let mut bar: String = std::iter::repeat_n(BLOCKS[7], eighths / 8).collect();
+    if !eighths.is_multiple_of(8) {
+        bar.push(BLOCKS[eighths % 8 - 1]);
+    }
+    bar
+}
+
+/// Options for [`Dice::to_svg()`].
+#[cfg(feature = "svg")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartOptions {
+    /// width of the image in pixels
+    pub width: u32,
+    /// height of the image in pixels
+    pub height: u32,
+    /// draw the cdf as a line on top of the bars of the pmf, with its own axis on the right
+    pub show_cdf: bool,
+    /// title above the chart, the builder string of the [`Dice`] if `None`
+    pub title: Option<String>,
+}
+
+#[cfg(feature = "svg")]
+impl Default for ChartOptions {
+    fn default() -> Self {
+        ChartOptions {
+            width: 640,
+            height: 360,
+            show_cdf: true,
+            title: None,
+        }
+    }
+}
+
+#[cfg(feature = "svg")]
+impl<P: ProbLike> Dice<P> {
+    /// a self-contained SVG image of the distribution, with bars for the pmf, a line for the cdf and marks for the mean and the median
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{ChartOptions, Dice};
+    /// let svg = Dice::build_from_string("2d6").unwrap().to_svg(&ChartOptions::default());
+    /// assert!(svg.starts_with("<svg"));
+    /// assert_eq!(svg.matches(r#"class="bar""#).count(), 11);
+    /// ```
+    pub fn to_svg(&self, options: &ChartOptions) -> String {
+        const LEFT: f64 = 60.0;
+        const RIGHT: f64 = 60.0;
+        const TOP: f64 = 40.0;
+        const BOTTOM: f64 = 40.0;
+        let width = options.width as f64;
+        let height = options.height as f64;
+        let plot_width = (width - LEFT - RIGHT).max(1.0);
+        let plot_height = (height - TOP - BOTTOM).max(1.0);
+        let bottom = TOP + plot_height;
+        let slot = plot_width / ((self.max - self.min) as f64 + 1.0);
+        let x = |v: f64| LEFT + (v - self.min as f64 + 0.5) * slot;
+        let largest = self
+            .distribution
+            .iter()
+            .map(|(_, p)| p.to_float())
+            .fold(0.0, f64::max);
+        let y_pmf = |p: f64| bottom - p / largest * plot_height;
+        let y_cdf = |p: f64| bottom - p * plot_height;
+
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {width} {height}" font-family="sans-serif" font-size="12">"#,
+            options.width, options.height
+        );
+        svg.push('\n');
+        let title = options.title.as_deref().unwrap_or(&self.builder_string);
+        svg.push_str(&format!(
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{}</text>"#,
+            width / 2.0,
+            TOP / 2.0 + 6.0,
+            escape_xml(title)
+        ));
+        svg.push('\n');
+        for (v, p) in self.distribution.iter() {
+            let top = y_pmf(p.to_float());
+            svg.push_str(&format!(
+                r##"<rect class="bar" x="{:.2}" y="{top:.2}" width="{:.2}" height="{:.2}" fill="#4e79a7"><title>{v}: {:.2}%</title></rect>"##,
+                x(*v as f64) - slot * 0.45,
+                slot * 0.9,
+                bottom - top,
+                p.to_float() * 100.0
+            ));
+            svg.push('\n');
+        }
+        if options.show_cdf {
+            let points: Vec<String> = self
+                .cdf_iter()
+                .map(|(v, c)| format!("{:.2},{:.2}", x(v as f64), y_cdf(c.to_float())))
+                .collect();
+            svg.push_str(&format!(
+                r##"<polyline class="cdf" points="{}" fill="none" stroke="#e15759" stroke-width="2"/>"##,
+                points.join(" ")
+            ));
+            svg.push('\n');
+            svg.push_str(&format!(
+                r#"<text x="{:.2}" y="{TOP}" text-anchor="start">100%</text>"#,
+                LEFT + plot_width + 6.0
+            ));
+            svg.push('\n');
+        }
+        let mean = self.mean.to_float();
+        for (class, value, label, dash) in [
+            ("mean", mean, format!("mean {mean:.2}"), "6,3"),
+            (
+                "median",
+                self.median as f64,
+                format!("median {}", self.median),
+                "2,3",
+            ),
+        ] {
+            let line_x = x(value);
+            svg.push_str(&format!(
+                r##"<line class="{class}" x1="{line_x:.2}" y1="{TOP}" x2="{line_x:.2}" y2="{bottom}" stroke="#333" stroke-dasharray="{dash}"/>"##
+            ));
+            svg.push('\n');
+            let label_y = match class {
+                "mean" => TOP + 12.0,
+                _ => TOP + 26.0,
+            };
+            svg.push_str(&format!(
+                r#"<text x="{:.2}" y="{label_y}">{label}</text>"#,
+                line_x + 4.0
+            ));
+            svg.push('\n');
+        }
+        svg.push_str(&format!(
+            r##"<line x1="{LEFT}" y1="{bottom}" x2="{:.2}" y2="{bottom}" stroke="#333"/>"##,
+            LEFT + plot_width
+        ));
+        svg.push('\n');
+        for v in [self.min, self.max] {
+            svg.push_str(&format!(
+                r#"<text x="{:.2}" y="{}" text-anchor="middle">{v}</text>"#,
+                x(v as f64),
+                bottom + 16.0
+            ));
+            svg.push('\n');
+        }
+        svg.push_str(&format!(
+            r#"<text x="{}" y="{TOP}" text-anchor="end">{:.2}%</text>"#,
+            LEFT - 6.0,
+            largest * 100.0
+        ));
+        svg.push_str("\n</svg>\n");
+        svg
+    }
+}
+
+/// `text` with the characters that have a meaning in XML replaced by entities
+#[cfg(feature = "svg")]
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
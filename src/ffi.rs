@@ -0,0 +1,377 @@
+//! A C API for embedding the engine in native hosts like C/C++ programs or game engines, enabled with the `ffi` feature.
+//!
+//! All types are `#[repr(C)]` or opaque, so a header can be generated with cbindgen.
+//! Functions that can fail return a [`DicesStatus`], the message of the last error on the calling thread is available from [`dices_last_error_message()`].
+//! Formulas are calculated with the limits of [`dices_default_build_options()`], so untrusted input cannot hang the host,
+//! other limits can be passed to [`dices_build_from_string_with_options()`].
+//!
+//! ```c
+//! DicesDice *dice = NULL;
+//! if (dices_build_from_string("2d6+3", &dice) != 0) {
+//!     fprintf(stderr, "%s\n", dices_last_error_message());
+//!     return 1;
+//! }
+//! size_t len = dices_pmf_len(dice);
+//! DicesPmfEntry *pmf = malloc(len * sizeof(DicesPmfEntry));
+//! dices_pmf_copy(dice, pmf, len);
+//! int64_t roll = dices_roll(dice);
+//! free(pmf);
+//! dices_free(dice);
+//! ```
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    time::Duration,
+};
+
+use crate::{dice::Dice, dice_builder::DiceBuilder, BuildOptions, ToFloat};
+
+/// An opaque handle to a calculated [`Dice`], created with [`dices_build_from_string()`] and released with [`dices_free()`].
+pub struct DicesDice {
+    dice: Dice,
+}
+
+/// The result of a fallible function of the C API.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DicesStatus {
+    /// the function succeeded
+    Ok = 0,
+    /// a pointer argument was null
+    NullPointer = 1,
+    /// the input string is not valid UTF-8
+    InvalidUtf8 = 2,
+    /// the input string is not a valid dice formula
+    ParseError = 3,
+    /// the formula could not be calculated, for example because of a zero-sided die
+    BuildError = 4,
+    /// an index is not smaller than the length of the distribution
+    OutOfBounds = 5,
+    /// the calculation panicked, which is a bug in the engine
+    Panic = 6,
+}
+
+/// Limits for the calculation in [`dices_build_from_string_with_options()`], a limit of 0 means unlimited.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DicesBuildOptions {
+    /// maximum number of distinct values of any intermediate distribution, see [`BuildOptions::max_outcomes`]
+    pub max_outcomes: usize,
+    /// maximum number of elementary operations, see [`BuildOptions::max_total_work`]
+    pub max_total_work: u64,
+    /// maximum time the calculation may take in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl DicesBuildOptions {
+    fn to_build_options(self) -> BuildOptions {
+        let limit = |limit: u64| (limit != 0).then_some(limit);
+        BuildOptions {
+            max_outcomes: (self.max_outcomes != 0).then_some(self.max_outcomes),
+            max_total_work: limit(self.max_total_work),
+            timeout: limit(self.timeout_ms).map(Duration::from_millis),
+            ..Default::default()
+        }
+    }
+}
+
+/// A value of the probability mass function with its probability as a double.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DicesPmfEntry {
+    /// the value
+    pub value: i64,
+    /// the probability of the value
+    pub prob: f64,
+}
+
+/// The parameters of a distribution, with the probabilistic ones as doubles.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DicesStats {
+    /// minimum value
+    pub min: i64,
+    /// maximum value
+    pub max: i64,
+    /// median
+    pub median: i64,
+    /// mean
+    pub mean: f64,
+    /// variance
+    pub variance: f64,
+    /// standard deviation
+    pub sd: f64,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn fail(status: DicesStatus, message: impl ToString) -> DicesStatus {
+    // interior nul bytes cannot occur in a C string, so they are dropped
+    let message = message.to_string().replace('\0', "");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).unwrap_or_default());
+    status
+}
+
+/// the message of the last error on the calling thread, or an empty string
+///
+/// The pointer stays valid until the next failing call on the same thread and must not be freed.
+#[no_mangle]
+pub extern "C" fn dices_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// the limits used by [`dices_build_from_string()`]: 1 000 000 outcomes, 10^10 elementary operations and 10 seconds
+#[no_mangle]
+pub extern "C" fn dices_default_build_options() -> DicesBuildOptions {
+    DicesBuildOptions {
+        max_outcomes: 1_000_000,
+        max_total_work: 10_000_000_000,
+        timeout_ms: 10_000,
+    }
+}
+
+/// parses and calculates the dice formula `input` within the limits of [`dices_default_build_options()`] and writes a handle to the result into `out_dice`
+///
+/// # Safety
+/// `input` must be a nul-terminated string and `out_dice` must point to writable memory for a pointer.
+/// On success the handle written to `out_dice` must be released with [`dices_free()`].
+#[no_mangle]
+pub unsafe extern "C" fn dices_build_from_string(
+    input: *const c_char,
+    out_dice: *mut *mut DicesDice,
+) -> DicesStatus {
+    dices_build_from_string_with_options(input, ptr::null(), out_dice)
+}
+
+/// like [`dices_build_from_string()`], but aborts with [`DicesStatus::BuildError`] as soon as one of the limits in `options` is exceeded
+///
+/// # Safety
+/// `input` must be a nul-terminated string, `options` must be null or point to a [`DicesBuildOptions`] and `out_dice` must point to writable memory for a pointer.
+/// If `options` is null, the limits of [`dices_default_build_options()`] are used.
+/// On success the handle written to `out_dice` must be released with [`dices_free()`].
+#[no_mangle]
+pub unsafe extern "C" fn dices_build_from_string_with_options(
+    input: *const c_char,
+    options: *const DicesBuildOptions,
+    out_dice: *mut *mut DicesDice,
+) -> DicesStatus {
+    if input.is_null() || out_dice.is_null() {
+        return fail(
+            DicesStatus::NullPointer,
+            "input and out_dice must not be null",
+        );
+    }
+    let input = match CStr::from_ptr(input).to_str() {
+        Ok(input) => input,
+        Err(err) => return fail(DicesStatus::InvalidUtf8, err),
+    };
+    let options = options
+        .as_ref()
+        .copied()
+        .unwrap_or_else(|| dices_default_build_options())
+        .to_build_options();
+    // a panic must not unwind into the calling C code
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let builder = match DiceBuilder::from_string(input) {
+            Ok(builder) => builder,
+            Err(err) => return Err(fail(DicesStatus::ParseError, err)),
+        };
+        builder
+            .build_with_options(&options)
+            .map_err(|err| fail(DicesStatus::BuildError, err))
+    }));
+    match result {
+        Ok(Ok(dice)) => {
+            *out_dice = Box::into_raw(Box::new(DicesDice { dice }));
+            DicesStatus::Ok
+        }
+        Ok(Err(status)) => status,
+        Err(payload) => fail(DicesStatus::Panic, panic_message(payload.as_ref())),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => format!("the calculation panicked: {message}"),
+        (_, Some(message)) => format!("the calculation panicked: {message}"),
+        _ => "the calculation panicked".to_string(),
+    }
+}
+
+/// releases a handle created by [`dices_build_from_string()`], null is ignored
+///
+/// # Safety
+/// `dice` must be null or a handle that was not released before.
+#[no_mangle]
+pub unsafe extern "C" fn dices_free(dice: *mut DicesDice) {
+    if !dice.is_null() {
+        drop(Box::from_raw(dice));
+    }
+}
+
+/// number of values of the probability mass function, 0 for null
+///
+/// # Safety
+/// `dice` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn dices_pmf_len(dice: *const DicesDice) -> usize {
+    match dice.as_ref() {
+        Some(dice) => dice.dice.distribution.len(),
+        None => 0,
+    }
+}
+
+/// writes the `index`th value of the probability mass function in ascending order into `out_entry`
+///
+/// # Safety
+/// `dice` must be null or a valid handle and `out_entry` must be null or point to writable memory for a [`DicesPmfEntry`].
+#[no_mangle]
+pub unsafe extern "C" fn dices_pmf_entry(
+    dice: *const DicesDice,
+    index: usize,
+    out_entry: *mut DicesPmfEntry,
+) -> DicesStatus {
+    let Some(dice) = dice.as_ref() else {
+        return fail(DicesStatus::NullPointer, "dice must not be null");
+    };
+    if out_entry.is_null() {
+        return fail(DicesStatus::NullPointer, "out_entry must not be null");
+    }
+    match dice.dice.distribution.get(index) {
+        Some((value, prob)) => {
+            *out_entry = DicesPmfEntry {
+                value: *value,
+                prob: prob.to_float(),
+            };
+            DicesStatus::Ok
+        }
+        None => fail(
+            DicesStatus::OutOfBounds,
+            format!("index {index} out of bounds"),
+        ),
+    }
+}
+
+/// copies up to `capacity` values of the probability mass function in ascending order into `out_entries`, returns the number of copied entries
+///
+/// # Safety
+/// `dice` must be null or a valid handle and `out_entries` must be null or point to writable memory for `capacity` [`DicesPmfEntry`]s.
+#[no_mangle]
+pub unsafe extern "C" fn dices_pmf_copy(
+    dice: *const DicesDice,
+    out_entries: *mut DicesPmfEntry,
+    capacity: usize,
+) -> usize {
+    let Some(dice) = dice.as_ref() else {
+        return 0;
+    };
+    if out_entries.is_null() {
+        return 0;
+    }
+    let n = capacity.min(dice.dice.distribution.len());
+    for (i, (value, prob)) in dice.dice.distribution.iter().take(n).enumerate() {
+        ptr::write(
+            out_entries.add(i),
+            DicesPmfEntry {
+                value: *value,
+                prob: prob.to_float(),
+            },
+        );
+    }
+    n
+}
+
+/// writes the parameters of the distribution into `out_stats`
+///
+/// # Safety
+/// `dice` must be null or a valid handle and `out_stats` must be null or point to writable memory for a [`DicesStats`].
+#[no_mangle]
+pub unsafe extern "C" fn dices_stats(
+    dice: *const DicesDice,
+    out_stats: *mut DicesStats,
+) -> DicesStatus {
+    let (Some(dice), false) = (dice.as_ref(), out_stats.is_null()) else {
+        return fail(
+            DicesStatus::NullPointer,
+            "dice and out_stats must not be null",
+        );
+    };
+    let dice = &dice.dice;
+    *out_stats = DicesStats {
+        min: dice.min,
+        max: dice.max,
+        median: dice.median,
+        mean: dice.mean.to_float(),
+        variance: dice.variance.to_float(),
+        sd: dice.variance.to_float().sqrt(),
+    };
+    DicesStatus::Ok
+}
+
+/// probability that a roll is less than or equal to `value`, NaN for null
+///
+/// # Safety
+/// `dice` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn dices_prob_lte(dice: *const DicesDice, value: i64) -> f64 {
+    match dice.as_ref() {
+        Some(dice) => dice.dice.prob_lte(value).to_float(),
+        None => f64::NAN,
+    }
+}
+
+/// probability that a roll is greater than or equal to `value`, NaN for null
+///
+/// # Safety
+/// `dice` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn dices_prob_gte(dice: *const DicesDice, value: i64) -> f64 {
+    match dice.as_ref() {
+        Some(dice) => dice.dice.prob_gte(value).to_float(),
+        None => f64::NAN,
+    }
+}
+
+/// rolls the dice once with the thread-local random number generator, 0 for null
+///
+/// # Safety
+/// `dice` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn dices_roll(dice: *const DicesDice) -> i64 {
+    match dice.as_ref() {
+        Some(dice) => dice.dice.roll(),
+        None => 0,
+    }
+}
+
+/// writes `n` rolls into `out_rolls`, using a random number generator seeded with `seed` so the rolls are reproducible
+///
+/// # Safety
+/// `dice` must be null or a valid handle and `out_rolls` must be null or point to writable memory for `n` values.
+#[no_mangle]
+pub unsafe extern "C" fn dices_roll_many_seeded(
+    dice: *const DicesDice,
+    seed: u64,
+    out_rolls: *mut i64,
+    n: usize,
+) -> DicesStatus {
+    let (Some(dice), false) = (dice.as_ref(), out_rolls.is_null()) else {
+        return fail(
+            DicesStatus::NullPointer,
+            "dice and out_rolls must not be null",
+        );
+    };
+    for (i, roll) in dice.dice.roller(seed).roll_many(n).enumerate() {
+        ptr::write(out_rolls.add(i), roll);
+    }
+    DicesStatus::Ok
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::{dice::Dice, dice_builder::Value, prob_like::ProbLike};
+
+/// The result of [`Dice::chi_square_test()`], Pearson's chi-square goodness of fit test of observed rolls against the exact distribution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChiSquareReport {
+    /// number of observed rolls
+    pub sample_size: usize,
+    /// the chi-square statistic, the sum of (observed - expected)² / expected over all values of the distribution.
+    /// Infinite if a roll was observed that the distribution can never produce.
+    pub statistic: f64,
+    /// number of values of the distribution minus 1
+    pub degrees_of_freedom: usize,
+    /// probability of a statistic at least this large if the rolls really follow the distribution
+    pub p_value: f64,
+}
+
+impl ChiSquareReport {
+    /// true if the observed rolls are unlikely to follow the distribution at the significance level `alpha`, e.g. 0.05
+    pub fn rejects_at(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// tests if `observed` rolls, for example of a physical die or another random number generator, match the distribution of `self`
+    ///
+    /// The test is only reliable if every value of the distribution is expected to be observed at least about 5 times.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// let fair = d6.chi_square_test(&[1, 2, 3, 4, 5, 6, 1, 2, 3, 4, 5, 6]);
+    /// assert_eq!(fair.statistic, 0.0);
+    /// assert!(!fair.rejects_at(0.05));
+    /// let loaded = d6.chi_square_test(&[6; 30]);
+    /// assert!(loaded.rejects_at(0.05));
+    /// ```
+    pub fn chi_square_test(&self, observed: &[Value]) -> ChiSquareReport {
+        let mut counts: HashMap<Value, usize> = HashMap::new();
+        for v in observed.iter() {
+            *counts.entry(*v).or_insert(0) += 1;
+        }
+        let sample_size = observed.len();
+        let n = sample_size as f64;
+        let mut statistic = 0.0;
+        let mut values_with_prob = 0;
+        for (v, p) in self.distribution.iter() {
+            let p = p.to_float();
+            let count = counts.remove(v).unwrap_or(0) as f64;
+            if p > 0.0 {
+                values_with_prob += 1;
+                let expected = n * p;
+                statistic += (count - expected).powi(2) / expected;
+            } else if count > 0.0 {
+                statistic = f64::INFINITY;
+            }
+        }
+        // any remaining count belongs to a value outside of the distribution
+        if !counts.is_empty() {
+            statistic = f64::INFINITY;
+        }
+        let degrees_of_freedom = values_with_prob.max(1) - 1;
+        let p_value = match (sample_size, degrees_of_freedom) {
+            (0, _) => 1.0,
+            _ if statistic.is_infinite() => 0.0,
+            (_, 0) => 1.0,
+            (_, df) => chi_square_survival(statistic, df),
+        };
+        ChiSquareReport {
+            sample_size,
+            statistic,
+            degrees_of_freedom,
+            p_value,
+        }
+    }
+}
+
+/// P(X >= x) for X following a chi-square distribution with `df` degrees of freedom
+fn chi_square_survival(x: f64, df: usize) -> f64 {
+    regularized_upper_gamma(df as f64 / 2.0, x / 2.0)
+}
+
+/// the regularized upper incomplete gamma function Q(a, x), evaluated by its series for small x and its continued fraction otherwise
+fn regularized_upper_gamma(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const MAX_ITERATIONS: usize = 1000;
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (1.0 - sum * log_prefactor.exp()).clamp(0.0, 1.0)
+    } else {
+        // modified Lentz's method
+        let tiny = f64::MIN_POSITIVE / EPSILON;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut fraction = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            fraction *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        (fraction * log_prefactor.exp()).clamp(0.0, 1.0)
+    }
+}
+
+/// natural logarithm of the gamma function for positive arguments, using the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
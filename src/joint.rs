@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use crate::{
+    build_options::{BuildContext, BuildOptions},
+    dice::Dice,
+    dice_builder::{DiceBuildError, DiceBuilder, Prob, Value},
+    dice_string_parser::string_to_joint_formula,
+    prob_like::ProbLike,
+};
+
+/// The exact joint distribution of several dice formulas that can share rolls.
+///
+/// While a [`Dice`] only knows the distribution of one value, a [`JointDice`] knows how the values of its components are correlated,
+/// for example the attack roll and the damage of an attack that crits on a 20.
+///
+/// # Examples
+/// the highest and the lowest of the same two rolls:
+/// ```
+/// use dices::JointDice;
+/// use fraction::BigFraction;
+/// let joint = JointDice::build_from_string("A := d6; B := d6; max(A,B), min(A,B)").unwrap();
+/// assert_eq!(joint.prob_of(|v| v[0] < v[1]), BigFraction::from(0));
+/// let difference = joint.map(|v| v[0] - v[1]);
+/// assert_eq!(difference.prob(0), BigFraction::new(1u64, 6u64));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointDice<P = Prob> {
+    /// for each component a formula of its own distribution, including the definitions of the shared rolls it uses
+    pub builder_strings: Vec<String>,
+    /// probabilities of all tuples of values with one value per component, sorted by the tuples
+    pub distribution: Vec<(Vec<Value>, P)>,
+}
+
+impl JointDice {
+    /// parses definitions of shared rolls followed by comma separated formulas that can use them, like `"X := d20; X+5, X x d8"`
+    pub fn build_from_string(input: &str) -> Result<JointDice, DiceBuildError> {
+        let (shared, components) = string_to_joint_formula(input)?;
+        JointDice::from_builders(&shared, &components, &BuildOptions::default())
+    }
+}
+
+impl<P: ProbLike> JointDice<P> {
+    /// calculates the joint distribution of `components`, in which the variables named in `shared` refer to the same roll
+    ///
+    /// every shared roll can use the shared rolls defined before it.
+    pub fn from_builders(
+        shared: &[(String, DiceBuilder)],
+        components: &[DiceBuilder],
+        options: &BuildOptions,
+    ) -> Result<JointDice<P>, DiceBuildError> {
+        if components.is_empty() {
+            return Err(DiceBuildError::EmptyCompound);
+        }
+        let mut ctx = BuildContext::new(options);
+        let hashmap = joint_hashmap(shared, components, &mut ctx)?;
+        let mut distribution: Vec<(Vec<Value>, P)> = hashmap.into_iter().collect();
+        distribution.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let builder_strings = components
+            .iter()
+            .map(|component| {
+                shared
+                    .iter()
+                    .rev()
+                    .fold(component.clone(), |body, (name, value)| DiceBuilder::Let {
+                        name: name.clone(),
+                        value: Box::new(value.clone()),
+                        body: Box::new(body),
+                    })
+                    .to_string()
+            })
+            .collect();
+        Ok(JointDice {
+            builder_strings,
+            distribution,
+        })
+    }
+
+    /// number of components
+    pub fn dimensions(&self) -> usize {
+        self.builder_strings.len()
+    }
+
+    /// the distribution of the component at `index`
+    ///
+    /// panics if `index` is not smaller than the number of components.
+    pub fn marginal(&self, index: usize) -> Dice<P> {
+        assert!(index < self.dimensions(), "no component at index {index}");
+        let builder_string = self.builder_strings[index].clone();
+        self.map_with_builder_string(|values| values[index], builder_string)
+    }
+
+    /// the joint distribution of the components at `indices`, in this order
+    ///
+    /// panics if an index is not smaller than the number of components.
+    pub fn project(&self, indices: &[usize]) -> JointDice<P> {
+        for index in indices.iter() {
+            assert!(*index < self.dimensions(), "no component at index {index}");
+        }
+        let mut hashmap: HashMap<Vec<Value>, P> = HashMap::new();
+        for (values, p) in self.distribution.iter() {
+            let projected: Vec<Value> = indices.iter().map(|i| values[*i]).collect();
+            add_prob(&mut hashmap, projected, p.clone());
+        }
+        let mut distribution: Vec<(Vec<Value>, P)> = hashmap.into_iter().collect();
+        distribution.sort_by(|(a, _), (b, _)| a.cmp(b));
+        JointDice {
+            builder_strings: indices
+                .iter()
+                .map(|i| self.builder_strings[*i].clone())
+                .collect(),
+            distribution,
+        }
+    }
+
+    /// the joint distribution conditional on `predicate` holding for the tuple of values, renormalized to a total probability of 1
+    ///
+    /// returns `None` if the predicate holds for no tuple of the distribution.
+    pub fn given(&self, predicate: impl Fn(&[Value]) -> bool) -> Option<JointDice<P>> {
+        let total = self.prob_of(&predicate);
+        if total <= P::zero() {
+            return None;
+        }
+        let distribution = self
+            .distribution
+            .iter()
+            .filter(|(values, _)| predicate(values))
+            .map(|(values, p)| (values.clone(), p.clone() / total.clone()))
+            .collect();
+        Some(JointDice {
+            builder_strings: self.builder_strings.clone(),
+            distribution,
+        })
+    }
+
+    /// the probability that `predicate` holds for the tuple of values
+    pub fn prob_of(&self, predicate: impl Fn(&[Value]) -> bool) -> P {
+        let mut total = P::zero();
+        for (values, p) in self.distribution.iter() {
+            if predicate(values) {
+                total += p;
+            }
+        }
+        total
+    }
+
+    /// the distribution of `f` applied to the tuple of values
+    pub fn map(&self, f: impl Fn(&[Value]) -> Value) -> Dice<P> {
+        self.map_with_builder_string(f, String::new())
+    }
+
+    /// the covariance of the components at `i` and `j`
+    ///
+    /// panics if an index is not smaller than the number of components.
+    pub fn covariance(&self, i: usize, j: usize) -> P {
+        let mean_i = self.marginal(i).mean;
+        let mean_j = self.marginal(j).mean;
+        let mut covariance = P::zero();
+        for (values, p) in self.distribution.iter() {
+            let deviation_i = P::from_value(values[i]) - mean_i.clone();
+            let deviation_j = P::from_value(values[j]) - mean_j.clone();
+            covariance += deviation_i * deviation_j * p.clone();
+        }
+        covariance
+    }
+
+    fn map_with_builder_string(
+        &self,
+        f: impl Fn(&[Value]) -> Value,
+        builder_string: String,
+    ) -> Dice<P> {
+        let mut hashmap: HashMap<Value, P> = HashMap::new();
+        for (values, p) in self.distribution.iter() {
+            add_prob(&mut hashmap, f(values), p.clone());
+        }
+        let mut distribution: Vec<(Value, P)> = hashmap.into_iter().collect();
+        distribution.sort_by_key(|(v, _)| *v);
+        Dice::from_sorted_distribution(distribution, builder_string)
+            .expect("a joint distribution has at least one tuple of values")
+    }
+}
+
+fn add_prob<K: std::hash::Hash + Eq, P: ProbLike>(hashmap: &mut HashMap<K, P>, key: K, p: P) {
+    match hashmap.entry(key) {
+        std::collections::hash_map::Entry::Occupied(mut e) => {
+            *e.get_mut() += p;
+        }
+        std::collections::hash_map::Entry::Vacant(e) => {
+            e.insert(p);
+        }
+    }
+}
+
+/// binds every value of the first shared roll in turn and recurses, the components are independent once all shared rolls are bound
+fn joint_hashmap<'t, P: ProbLike>(
+    shared: &'t [(String, DiceBuilder)],
+    components: &'t [DiceBuilder],
+    ctx: &mut BuildContext<'_, 't, P>,
+) -> Result<HashMap<Vec<Value>, P>, DiceBuildError> {
+    let mut m: HashMap<Vec<Value>, P> = HashMap::new();
+    match shared.split_first() {
+        Some(((name, value), rest)) => {
+            for (v, p) in value.distribution_hashmap(ctx)?.into_iter() {
+                ctx.bind(name, v);
+                let given_v = joint_hashmap(rest, components, ctx);
+                ctx.unbind();
+                for (values, q) in given_v?.into_iter() {
+                    add_prob(&mut m, values, q * p.clone());
+                }
+            }
+        }
+        None => {
+            m.insert(vec![], P::one());
+            for component in components.iter() {
+                let h = component.distribution_hashmap(ctx)?;
+                ctx.add_work((m.len() * h.len()) as u64)?;
+                let mut next: HashMap<Vec<Value>, P> = HashMap::new();
+                for (values, p) in m.iter() {
+                    for (v, q) in h.iter() {
+                        let mut extended = values.clone();
+                        extended.push(*v);
+                        next.insert(extended, p.clone() * q.clone());
+                    }
+                }
+                ctx.check_outcomes(next.len())?;
+                m = next;
+            }
+        }
+    }
+    Ok(m)
+}
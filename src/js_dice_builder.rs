@@ -0,0 +1,177 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    dice::JsDice,
+    dice_builder::{DiceBuilder, ExplodeKind, Value},
+    js_types::JsDiceError,
+};
+
+/// Builds the tree of a [`DiceBuilder`] from JavaScript, as an alternative to parsing a formula string.
+///
+/// Every combinator returns a new builder and leaves its operands untouched, so they can be reused:
+/// ```js
+/// const attack = JsDiceBuilder.fairDie(1, 20).add(JsDiceBuilder.constant(5));
+/// const dice = attack.build();
+/// ```
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct JsDiceBuilder {
+    builder: DiceBuilder,
+}
+
+#[wasm_bindgen]
+impl JsDiceBuilder {
+    /// parses a formula like `"2d6+3"`, throws a [`JsDiceError`] with the span of the invalid input if it cannot be parsed
+    #[wasm_bindgen(js_name = fromString)]
+    pub fn from_string(input: &str) -> Result<JsDiceBuilder, JsValue> {
+        match DiceBuilder::from_string(input) {
+            Ok(builder) => Ok(JsDiceBuilder { builder }),
+            Err(err) => Err(JsDiceError::from_parse_error(&err).to_js_value()),
+        }
+    }
+
+    /// a constant value
+    pub fn constant(value: Value) -> JsDiceBuilder {
+        DiceBuilder::Constant(value).into()
+    }
+
+    /// a fair die rolling every value in `[min, max]` with the same probability
+    #[wasm_bindgen(js_name = fairDie)]
+    pub fn fair_die(min: Value, max: Value) -> JsDiceBuilder {
+        DiceBuilder::FairDie { min, max }.into()
+    }
+
+    /// a variable bound by an enclosing `letIn`
+    pub fn variable(name: &str) -> JsDiceBuilder {
+        DiceBuilder::Variable(name.to_string()).into()
+    }
+
+    /// binds `name` to a single roll of `value` inside of `body`, like `name := value; body`
+    #[wasm_bindgen(js_name = letIn)]
+    pub fn let_in(name: &str, value: &JsDiceBuilder, body: &JsDiceBuilder) -> JsDiceBuilder {
+        DiceBuilder::Let {
+            name: name.to_string(),
+            value: Box::new(value.builder.clone()),
+            body: Box::new(body.builder.clone()),
+        }
+        .into()
+    }
+
+    /// `self + other`
+    pub fn add(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::SumCompound)
+    }
+
+    /// `self - other`
+    pub fn sub(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        let negated = DiceBuilder::Negation(Box::new(other.builder.clone()));
+        DiceBuilder::SumCompound(vec![self.builder.clone(), negated]).into()
+    }
+
+    /// `self * other`
+    pub fn mul(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::ProductCompound)
+    }
+
+    /// `self / other`, rounded like the `/` of formula strings
+    pub fn div(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::DivisionCompound)
+    }
+
+    /// `self ^ other`
+    pub fn pow(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::PowerCompound)
+    }
+
+    /// rolls `other` as many times as `self` rolls and sums up the rolls, like `self x other`
+    #[wasm_bindgen(js_name = sampleSum)]
+    pub fn sample_sum(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::SampleSumCompound)
+    }
+
+    /// `max(self, other)`
+    pub fn max(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::MaxCompound)
+    }
+
+    /// `min(self, other)`
+    pub fn min(&self, other: &JsDiceBuilder) -> JsDiceBuilder {
+        self.compound(other, DiceBuilder::MinCompound)
+    }
+
+    /// `abs(self)`
+    pub fn abs(&self) -> JsDiceBuilder {
+        DiceBuilder::Absolute(Box::new(self.builder.clone())).into()
+    }
+
+    /// `-self`
+    pub fn neg(&self) -> JsDiceBuilder {
+        DiceBuilder::Negation(Box::new(self.builder.clone())).into()
+    }
+
+    /// the `k`th highest of `n` rolls of `self`, like `nth_highest(n, k, self)`
+    #[wasm_bindgen(js_name = nthHighest)]
+    pub fn nth_highest(&self, n: usize, k: usize) -> JsDiceBuilder {
+        DiceBuilder::NthHighest {
+            n,
+            k,
+            dice_builder: Box::new(self.builder.clone()),
+        }
+        .into()
+    }
+
+    /// rolls `self` again on values of at least `min_value` (the maximum if not given) and adds the rolls, at most `max_iterations` times
+    pub fn explode(&self, min_value: Option<Value>, max_iterations: usize) -> JsDiceBuilder {
+        DiceBuilder::Explode {
+            dice_builder: Box::new(self.builder.clone()),
+            min_value,
+            max_iterations,
+            kind: ExplodeKind::Standard,
+        }
+        .into()
+    }
+
+    /// rerolls `self` once on values of at most `max_value`, keeping the better of both rolls if `keep_best` is true and the new roll otherwise
+    pub fn reroll(&self, max_value: Value, keep_best: bool) -> JsDiceBuilder {
+        DiceBuilder::Reroll {
+            dice_builder: Box::new(self.builder.clone()),
+            max_value,
+            keep_best,
+        }
+        .into()
+    }
+
+    /// the formula string of the builder, which `fromString` parses back into the same distribution
+    ///
+    /// The string syntax has no limit for the number of explosions, so builders from `explode` with another
+    /// `max_iterations` than the default are written as `explode(...)`, which `fromString` cannot parse.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_formula(&self) -> String {
+        self.builder.to_string()
+    }
+
+    /// calculates the distribution, throws a [`JsDiceError`] if the tree is degenerate
+    pub fn build(&self) -> Result<JsDice, JsValue> {
+        match self.builder.clone().try_build() {
+            Ok(dice) => Ok(JsDice { dice }),
+            Err(err) => Err(JsDiceError::from_build_error(&err).to_js_value()),
+        }
+    }
+}
+
+impl JsDiceBuilder {
+    /// combines `self` and `other` into a two-element compound of the given `variant`
+    fn compound(
+        &self,
+        other: &JsDiceBuilder,
+        variant: fn(Vec<DiceBuilder>) -> DiceBuilder,
+    ) -> JsDiceBuilder {
+        variant(vec![self.builder.clone(), other.builder.clone()]).into()
+    }
+}
+
+impl From<DiceBuilder> for JsDiceBuilder {
+    fn from(builder: DiceBuilder) -> Self {
+        JsDiceBuilder { builder }
+    }
+}
@@ -0,0 +1,227 @@
+//! The serde structs that the wasm methods return to JavaScript, or throw as errors.
+//!
+//! Their fields are part of the JavaScript API, so they only change in breaking releases.
+//! With the `typescript` feature, matching TypeScript interfaces are added to the generated `.d.ts` file
+//! and the methods of [`JsDice`](crate::dice::JsDice) declare them as their return types instead of `any`.
+
+use std::fmt::Display;
+
+use fraction::{BigFraction, ToPrimitive};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    dice::{Dice, ProbAll},
+    dice_builder::{DiceBuildError, Prob, Value},
+    dice_string_parser::DiceBuildingError,
+    transform::Bucket,
+};
+
+#[cfg(feature = "typescript")]
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+/** an exact probability as a reduced fraction like "1/6", with its rounded value */
+export interface JsFraction {
+    string: string;
+    float: number;
+}
+
+/** values with their probabilities in ascending order of value */
+export interface JsDistribution {
+    values: [number, JsFraction][];
+}
+
+/** a range of values with their summed probability */
+export interface JsBucket {
+    min: number;
+    max: number;
+    prob: JsFraction;
+}
+
+/** the parameters of a distribution */
+export interface JsStats {
+    min: number;
+    max: number;
+    median: number;
+    mode: number[];
+    mean: JsFraction;
+    variance: JsFraction;
+}
+
+/** the probabilities of all comparisons of a roll with one value */
+export interface JsProbAll {
+    lt: JsFraction;
+    lte: JsFraction;
+    eq: JsFraction;
+    gte: JsFraction;
+    gt: JsFraction;
+}
+
+/** the error thrown when a formula cannot be parsed or built */
+export interface JsDiceError {
+    kind: string;
+    message: string;
+    span_start?: number;
+    span_end?: number;
+    snippet?: string;
+}
+"#;
+
+/// an exact probability as a string like `"1/6"` and its rounded value
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsFraction {
+    pub string: String,
+    pub float: f32,
+}
+
+impl Display for JsFraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.string, self.float,)
+    }
+}
+
+impl JsFraction {
+    pub fn from_big_fraction(big_fraction: &BigFraction) -> JsFraction {
+        JsFraction {
+            string: big_fraction.to_string(),
+            float: big_fraction.to_f32().unwrap(),
+        }
+    }
+}
+
+/// values with their probabilities in ascending order of value
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsDistribution {
+    pub values: Vec<(Value, JsFraction)>,
+}
+
+impl JsDistribution {
+    pub fn from_distribution(dist: &[(Value, Prob)]) -> JsDistribution {
+        JsDistribution {
+            values: dist
+                .iter()
+                .map(|e| (e.0, JsFraction::from_big_fraction(&e.1)))
+                .collect(),
+        }
+    }
+}
+
+/// a [`Bucket`] of `JsDice::bucketed`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsBucket {
+    pub min: Value,
+    pub max: Value,
+    pub prob: JsFraction,
+}
+
+impl From<&Bucket> for JsBucket {
+    fn from(bucket: &Bucket) -> Self {
+        JsBucket {
+            min: bucket.min,
+            max: bucket.max,
+            prob: JsFraction::from_big_fraction(&bucket.prob),
+        }
+    }
+}
+
+/// the parameters of a [`Dice`] that are calculated when building it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsStats {
+    pub min: Value,
+    pub max: Value,
+    pub median: Value,
+    pub mode: Vec<Value>,
+    pub mean: JsFraction,
+    pub variance: JsFraction,
+}
+
+impl From<&Dice> for JsStats {
+    fn from(dice: &Dice) -> Self {
+        JsStats {
+            min: dice.min,
+            max: dice.max,
+            median: dice.median,
+            mode: dice.mode.clone(),
+            mean: JsFraction::from_big_fraction(&dice.mean),
+            variance: JsFraction::from_big_fraction(&dice.variance),
+        }
+    }
+}
+
+/// the [`ProbAll`] of `JsDice::prob_all`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsProbAll {
+    pub lt: JsFraction,
+    pub lte: JsFraction,
+    pub eq: JsFraction,
+    pub gte: JsFraction,
+    pub gt: JsFraction,
+}
+
+impl From<&ProbAll> for JsProbAll {
+    fn from(all: &ProbAll) -> Self {
+        JsProbAll {
+            lt: JsFraction::from_big_fraction(&all.lt),
+            lte: JsFraction::from_big_fraction(&all.lte),
+            eq: JsFraction::from_big_fraction(&all.eq),
+            gte: JsFraction::from_big_fraction(&all.gte),
+            gt: JsFraction::from_big_fraction(&all.gt),
+        }
+    }
+}
+
+/// The error object thrown to JavaScript when a formula cannot be parsed or built.
+///
+/// `span_start` and `span_end` are byte offsets into the parsed formula, they are only set for parse errors.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsDiceError {
+    /// the name of the error variant, like `"UnclosedBracket"` or `"ZeroSidedDie"`, stable for translating messages
+    pub kind: String,
+    /// an english description of the error
+    pub message: String,
+    /// start of the invalid part of the input
+    pub span_start: Option<usize>,
+    /// end of the invalid part of the input, exclusive
+    pub span_end: Option<usize>,
+    /// the invalid part of the input
+    pub snippet: Option<String>,
+}
+
+impl JsDiceError {
+    pub(crate) fn from_parse_error(err: &DiceBuildingError) -> JsDiceError {
+        JsDiceError {
+            kind: variant_name(&err.kind),
+            message: err.kind.to_string(),
+            span_start: Some(err.span.start),
+            span_end: Some(err.span.end),
+            snippet: Some(err.snippet.clone()),
+        }
+    }
+
+    pub(crate) fn from_build_error(err: &DiceBuildError) -> JsDiceError {
+        match err {
+            DiceBuildError::Parse(err) => JsDiceError::from_parse_error(err),
+            _ => JsDiceError {
+                kind: variant_name(err),
+                message: err.to_string(),
+                span_start: None,
+                span_end: None,
+                snippet: None,
+            },
+        }
+    }
+
+    pub(crate) fn to_js_value(&self) -> JsValue {
+        serde_wasm_bindgen::to_value(self).unwrap()
+    }
+}
+
+/// the name of an enum variant without its fields, taken from its `Debug` output
+fn variant_name(value: &impl std::fmt::Debug) -> String {
+    let debug = format!("{value:?}");
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
@@ -16,16 +16,16 @@
 //! mean: 7,
 //! median: 7,
 //! distribution: vec![(2, 1/36), (3, 1/18), (4, 1/12), (5, 1/9), (5, 1/9), (6, 5/36), (7, 1/6), ...]
-//! cumulative_distribution: vec![(2, 1/36), (3, 1/12), (4, 1/6), ...]
+//! cumulative_distribution(): [(2, 1/36), (3, 1/12), (4, 1/6), ...] (calculated on first access)
 //! ```
 //! A DiceBuildingError could be returned, if the `input` string could not be parsed into a proper syntax tree for the [`DiceBuilder`].
 //! ---
-//! To roll a [`Dice`] call the `roll()` function, for rolling multiple times call the `roll_multiple()` function:
+//! To roll a [`Dice`] call the `roll()` function, for rolling multiple times call the `roll_many()` function:
 //! ```
 //! use dices::Dice;
 //! let dice = Dice::build_from_string("2d6").unwrap();
 //! let num = dice.roll();
-//! let nums = dice.roll_many(10);
+//! let nums: Vec<i64> = dice.roll_many(10).collect();
 //! // num will be some i64 between 2 and 12, sampled according to the dices distribution
 //! // nums could be vec![7,3,9,11,7,8,5,6,3,6]
 //! ```
@@ -38,6 +38,10 @@
 //! ```txt
 //! "3d6", "3w6" or "3xw6"
 //! ```
+//! a ten-sided die numbered from 0 to 9 (both bounds are included):
+//! ```txt
+//! "d[0..9]"
+//! ```
 //! one six-sided die multiplied by 3:
 //! ```txt
 //! "3*d6" or "d6*3"
@@ -57,7 +61,44 @@
 //! multiplying 3 20-sided-dice
 //! ```txt
 //! "d20*d20*d20"
+//! ```
+//! the square of a 6-sided die, `^` binds stronger than all other operators
+//! ```txt
+//! "d6^2"
 //! ```   
+//! the middle die of 3 20-sided dice (the 2nd highest of 3 samples)
+//! ```txt
+//! "nth_highest(3,2,d20)"
+//! ```
+//! rolling a 20-sided die once and using the roll twice (variable names are case sensitive)
+//! ```txt
+//! "X := d20; max(X, 20-X)"
+//! ```
+//! a monster that uses one attack half of the time and another one otherwise
+//! ```txt
+//! "mix(1: 2d6, 1: d12)"
+//! ```
+//! rerolling a 20-sided die once on a 1 or 2 and keeping the new roll, or keeping the better of both rolls with `rb`
+//! ```txt
+//! "d20r<=2"
+//! "d20rb<=2"
+//! ```
+//! exploding 6-sided dice that are rolled again and added on a 6, with compounding `!!` and penetrating `!p` variants that subtract 1 from every explosion, and a 10-sided die that explodes on 9 or 10 (at most 10 explosions each)
+//! ```txt
+//! "d6!"
+//! "d6!!"
+//! "d6!p"
+//! "d10!>=9"
+//! ```
+//! the highest of 4 exploding 6-sided dice, where every explosion is a die of the pool on its own, unlike with compounding `!!` where all rolls of a die count as one value
+//! ```txt
+//! "nth_highest(4,1,d6!)"
+//! "nth_highest(4,1,d6!!)"
+//! ```
+//! a brutal weapon that deals 2d10 damage, where every die that rolls a 1 counts as a 2 (the same as `2xmax(d10,2)`)
+//! ```txt
+//! "2d10b2"
+//! ```
 //!
 //! # Calculating Probabilities
 //!
@@ -69,20 +110,84 @@
 //! The drawback is that it is less efficient than using floats.
 //!
 //! While `"d100*d100"` takes about 100ms for me, something like "d10xd100" took 9.000 ms to finish calculating the probability distribution.
-//! There is room for optimization.
+//! Big sums of dice with contiguous values are now calculated by multiplying big integers instead (Kronecker substitution), which brings "d10xd100" down to about 100ms.
 //!
 //!
 
-#![feature(box_patterns)]
 #![warn(missing_docs)]
+mod attack;
+mod build_options;
+mod cache;
+mod compact;
+mod comparison;
+mod convolution;
+pub mod convolve;
+mod cost;
+mod counts;
+mod dense_distribution;
 mod dice;
 mod dice_builder;
 mod dice_string_parser;
+mod dice_value;
+mod distribution_map;
+mod explain;
+mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod goodness_of_fit;
+mod joint;
+#[cfg(feature = "wasm")]
+mod js_dice_builder;
+#[cfg(feature = "wasm")]
+mod js_types;
+mod markov;
+mod outcome;
+pub mod presets;
+mod prob_like;
+#[cfg(feature = "python")]
+mod python;
+mod roll_report;
+mod roller;
+#[cfg(feature = "serde")]
+mod serialization;
+mod session;
+mod shared;
+mod simplify;
+mod simulation;
+mod statistics;
+mod transform;
+mod validation;
+mod visit;
 mod wasm_safe;
 
-pub use dice::Dice;
+pub use build_options::{BuildOptions, BuildProgress, BuildReport, CancellationToken};
+pub use cache::{DiceCache, LruDiceCache};
+pub use compact::CompactDistribution;
+pub use comparison::Contest;
+pub use cost::CostEstimate;
+pub use counts::{RollsUntil, TurnsToReach};
+pub use dice::{Dice, DiceF64, ProbAll, ToFloat};
 
-pub use dice_builder::DiceBuilder;
+pub use dice_builder::{DiceBuildError, DiceBuilder, ExplodeKind};
+pub use dice_string_parser::{DiceBuildingError, DiceBuildingErrorKind};
+pub use dice_value::DiceValue;
+pub use explain::ProbExplanation;
+#[cfg(feature = "svg")]
+pub use export::ChartOptions;
+pub use export::{CsvOptions, HistogramOptions};
+pub use goodness_of_fit::ChiSquareReport;
+pub use joint::JointDice;
+pub use outcome::OutcomeDice;
+pub use prob_like::ProbLike;
+pub use roll_report::{RollReport, RollTrace};
+pub use roller::DiceRoller;
+pub use session::RollSession;
+pub use shared::SharedDice;
+pub use simulation::EmpiricalDice;
+pub use statistics::{DiceSummary, QuantileMethod};
+pub use transform::{Bucket, DiceOperation};
+pub use validation::ValidationReport;
+pub use visit::Visitor;
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -92,18 +197,24 @@ use wasm_bindgen::prelude::*;
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 /// just a test function for wasm pack
 pub fn greet() -> String {
-    format!("Hello, from dices instantly")
+    "Hello, from dices instantly".to_string()
 }
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
+    use std::{str::FromStr, time::Duration};
 
-    use fraction::{ToPrimitive, Zero};
+    use fraction::{BigInt, One, ToPrimitive, Zero};
+    use rand::{rngs::StdRng, SeedableRng};
 
     use crate::{
-        dice_builder::{DiceBuilder, DistributionHashMap, Prob, Value},
-        Dice,
+        dice_builder::{DiceBuilder, Prob, Value},
+        distribution_map::DistributionMap,
+        presets::{blades_in_the_dark, dnd5e, savage_worlds, shadowrun},
+        BuildOptions, CancellationToken, CompactDistribution, Contest, CsvOptions, Dice,
+        DiceBuildError, DiceBuildingError, DiceBuildingErrorKind, DiceCache, DiceOperation,
+        ExplodeKind, HistogramOptions, JointDice, LruDiceCache, ProbAll, QuantileMethod,
+        RollSession, SharedDice, Visitor,
     };
 
     #[test]
@@ -137,6 +248,16 @@ mod tests {
         assert_eq!(d1.distribution, d2.distribution);
     }
 
+    #[test]
+    fn adding_distributions_with_gaps() {
+        let dense = Dice::build_from_string("d2+d2").unwrap();
+        assert_eq!(dense.distribution, unif(vec![2, 3, 3, 4]));
+        let sparse = Dice::build_from_string("2*d2+d2").unwrap();
+        assert_eq!(sparse.distribution, unif(vec![3, 4, 5, 6]));
+        let sample_sum = Dice::build_from_string("d2x(2*d2)").unwrap();
+        assert_eq!(sample_sum.distribution, unif(vec![2, 2, 4, 4, 4, 6, 6, 8]));
+    }
+
     #[test]
     fn adding_20_dice() {
         let mut f = Box::new(DiceBuilder::Constant(0));
@@ -192,7 +313,7 @@ mod tests {
     }
 
     fn unif(v: Vec<Value>) -> Vec<(Value, Prob)> {
-        let mut hashmap = DistributionHashMap::new();
+        let mut hashmap = DistributionMap::new();
         let l = v.len();
         let prob = Prob::new(1u64, l as u64);
         v.iter().for_each(|e| {
@@ -204,19 +325,19 @@ mod tests {
         });
         let mut distribution_vec = hashmap.into_iter().collect::<Vec<(Value, Prob)>>();
         distribution_vec.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-        return distribution_vec;
+        distribution_vec
     }
     #[test]
     fn calculating_accumulated_distribution_test() {
-        let dices = vec!["1w6+1", "3w8-3", "max(1,2,3)"];
+        let dices = ["1w6+1", "3w8-3", "max(1,2,3)"];
 
         let last_elements_of_acc_distr: Vec<Prob> = dices
             .iter()
             .map(|e| {
-                DiceBuilder::from_string(&e)
+                DiceBuilder::from_string(e)
                     .unwrap()
                     .build()
-                    .cumulative_distribution
+                    .cumulative_distribution()
                     .last()
                     .unwrap()
                     .1
@@ -253,7 +374,28 @@ mod tests {
 
         assert_eq!(d.prob_lt(-3), Prob::zero());
         assert_eq!(d.prob_lt(-3), Prob::zero());
+
+        assert_eq!(d.prob(13), Prob::zero());
+        assert_eq!(d.prob(2), Prob::new(1u64, 36u64));
+        assert_eq!(d.prob_lte(12), Prob::one());
+        assert_eq!(d.prob_lt(12), Prob::new(35u64, 36u64));
+        assert_eq!(d.prob_lt(13), Prob::one());
+        assert_eq!(d.prob_lte(1), Prob::zero());
+        assert_eq!(d.prob_gt(11), Prob::new(1u64, 36u64));
+        assert_eq!(d.prob_gte(2), Prob::one());
     }
+    #[test]
+    fn prob_range_tests() {
+        let d = Dice::build_from_string("2d6").unwrap();
+        assert_eq!(d.prob_range(10..=15), Prob::new(1u64, 6u64));
+        assert_eq!(d.prob_range(7..10), Prob::new(15u64, 36u64));
+        assert_eq!(d.prob_range(..=7), d.prob_lte(7));
+        assert_eq!(d.prob_range(8..), d.prob_gte(8));
+        assert_eq!(d.prob_range(7..7), Prob::zero());
+        assert_eq!(d.prob_range(-5..=1), Prob::zero());
+        assert_eq!(d.prob_range(..), Prob::one());
+    }
+
     #[test]
     fn quantile_tests() {
         let d = Dice::build_from_string("2d6").unwrap();
@@ -262,5 +404,2909 @@ mod tests {
         assert_eq!(d.quantile(0.5), 7);
         assert_eq!(d.quantile(Prob::from_str("1/2").unwrap()), 7);
         assert_eq!(d.quantile(Prob::from_str("-1/8").unwrap()), 2);
+        assert_eq!(d.quantile(1.5), 12);
+        assert_eq!(d.quantile(1.0 / 36.0), 2);
+        assert_eq!(d.quantile(0.03), 3);
+    }
+
+    #[test]
+    fn try_build_errors() {
+        assert_eq!(
+            Dice::try_build_from_string("d[1..-1]"),
+            Err(DiceBuildError::InvalidFairDie { min: 1, max: -1 })
+        );
+        assert_eq!(
+            DiceBuilder::SumCompound(vec![]).try_build(),
+            Err(DiceBuildError::EmptyCompound)
+        );
+        assert!(matches!(
+            Dice::try_build_from_string("max(1,,2)"),
+            Err(DiceBuildError::Parse(_))
+        ));
+        assert!(Dice::try_build_from_string("2d6").is_ok());
+        assert_eq!(
+            DiceBuilder::from_string("d6/0").unwrap().try_build(),
+            Err(DiceBuildError::DivisionByZero)
+        );
+        assert_eq!(
+            Dice::try_build_from_string("d6/d[-1..1]"),
+            Err(DiceBuildError::DivisionByZero)
+        );
+        let widest = DiceBuilder::FairDie {
+            min: -i64::MAX,
+            max: i64::MAX,
+        };
+        assert_eq!(
+            Dice::try_build_from_string("d[-9223372036854775807..9223372036854775807]"),
+            Err(DiceBuildError::ValueOverflow)
+        );
+        assert_eq!(
+            widest
+                .clone()
+                .build_values_as::<Prob, i128>(&BuildOptions::default())
+                .err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+        let divided_by_zero = DiceBuilder::from_string("d6/d[0..0]").unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(
+            divided_by_zero.try_roll_once(&mut rng),
+            Err(DiceBuildError::DivisionByZero)
+        );
+        assert_eq!(
+            divided_by_zero.roll_detailed(&mut rng).err(),
+            Some(DiceBuildError::DivisionByZero)
+        );
+        assert_eq!(
+            divided_by_zero
+                .build_values_as::<Prob, i32>(&BuildOptions::default())
+                .err(),
+            Some(DiceBuildError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn build_progress_tests() {
+        let builder = || DiceBuilder::from_string("20d100").unwrap();
+        let mut reported_work = vec![];
+        let dice = builder()
+            .build_with_progress(&BuildOptions::default(), |progress| {
+                reported_work.push(progress.work_done);
+                true
+            })
+            .unwrap();
+        assert_eq!(dice.distribution, builder().build().distribution);
+        assert!(reported_work.len() >= 4);
+        assert!(reported_work.windows(2).all(|w| w[1] - w[0] >= 100_000));
+        let mut calls = 0;
+        let cancelled = builder().build_with_progress(&BuildOptions::default(), |_| {
+            calls += 1;
+            false
+        });
+        assert_eq!(cancelled, Err(DiceBuildError::Cancelled));
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_convolution_tests() {
+        // the probabilities 1/375 and 1/1000 have no shared denominator, so the product is convoluted pair by pair over threads
+        let builder = || DiceBuilder::from_string("mix(1:d300,1:d500)*mix(1:d300,1:d500)").unwrap();
+        let mut reported_work = vec![];
+        let dice = builder()
+            .build_with_progress(&BuildOptions::default(), |progress| {
+                reported_work.push(progress.work_done);
+                true
+            })
+            .unwrap();
+        let sequential = builder()
+            .build_values_as::<Prob, Value>(&BuildOptions::default())
+            .unwrap();
+        assert_eq!(dice.distribution, sequential.distribution);
+        assert!(!reported_work.is_empty());
+        assert!(reported_work[0] < 500 * 500);
+        let cancelled = builder().build_with_progress(&BuildOptions::default(), |_| false);
+        assert_eq!(cancelled, Err(DiceBuildError::Cancelled));
+
+        let approx = DiceBuilder::from_string("d100*d100")
+            .unwrap()
+            .build_approx();
+        let exact = Dice::build_from_string("d100*d100").unwrap();
+        assert_eq!(approx.distribution.len(), exact.distribution.len());
+        assert!((approx.mean - exact.mean.to_f64().unwrap()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cancellation_token_tests() {
+        let token = CancellationToken::new();
+        let options = BuildOptions {
+            cancellation: Some(token.clone()),
+            ..Default::default()
+        };
+        let build = std::thread::spawn(move || {
+            DiceBuilder::from_string("d100xd100xd100")
+                .unwrap()
+                .build_with_options(&options)
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        token.cancel();
+        assert_eq!(build.join().unwrap(), Err(DiceBuildError::Cancelled));
+        assert_ne!(token, CancellationToken::new());
+        assert!(DiceBuilder::from_string("2d6")
+            .unwrap()
+            .build_with_options(&BuildOptions {
+                cancellation: Some(CancellationToken::new()),
+                ..Default::default()
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn cost_estimate_and_build_report_tests() {
+        let estimate = |s: &str| DiceBuilder::from_string(s).unwrap().estimate_cost();
+        assert_eq!(estimate("d6").support_size, 6);
+        assert_eq!(estimate("d6").operations, 0);
+        assert_eq!(estimate("3d6").support_size, 16);
+        assert_eq!(estimate("3d6").operations, 6 * 6 + 11 * 6);
+        assert_eq!(estimate("d6*d6").support_size, 36);
+        assert_eq!(estimate("max(d6,d6)").support_size, 6);
+        assert_eq!(estimate("X:=d6;X*X").support_size, 1);
+        assert!(estimate("3d1000").operations > 1000 * estimate("3d10").operations);
+        // the sum of many rolls of a bound variable is estimated without looping over the rolls
+        let repeated_variable = estimate("X:=d6; 1000000000000xX");
+        assert_eq!(repeated_variable.support_size, 1);
+        assert!(repeated_variable.operations >= 999_999_999_999);
+        assert!(estimate("1000000000000xd6").operations > 1000000000000);
+
+        let builder = DiceBuilder::from_string("max(3d6,3d6)+d4").unwrap();
+        let report = builder.build().build_report;
+        assert_eq!(report.max_intermediate_support, 19);
+        // the second 3d6 is memoized
+        assert_eq!(report.nodes, 6);
+        assert_eq!(
+            Dice::build_from_string("d6")
+                .unwrap()
+                .shifted(1)
+                .build_report,
+            Default::default()
+        );
+    }
+
+    #[test]
+    fn build_options_limits() {
+        let builder = || DiceBuilder::from_string("d100xd100xd100").unwrap();
+        let options = BuildOptions {
+            max_total_work: Some(100_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            builder().build_with_options(&options),
+            Err(DiceBuildError::TooMuchWork { limit: 100_000 })
+        );
+        let options = BuildOptions {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            builder().build_with_options(&options),
+            Err(DiceBuildError::Timeout { .. })
+        ));
+        // the k-th highest of many samples multiplies fractions that grow with the number of samples
+        let nth_highest = || DiceBuilder::from_string("nth_highest(200000,1,d6)").unwrap();
+        let options = BuildOptions {
+            max_total_work: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            nth_highest().build_with_options(&options),
+            Err(DiceBuildError::TooMuchWork { limit: 1000 })
+        );
+        assert_eq!(
+            nth_highest().build_values_as::<Prob, Value>(&options).err(),
+            Some(DiceBuildError::TooMuchWork { limit: 1000 })
+        );
+        let options = BuildOptions {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            DiceBuilder::from_string("nth_highest(30000,1,d6)")
+                .unwrap()
+                .build_with_options(&options),
+            Err(DiceBuildError::Timeout { .. })
+        ));
+
+        // the sides of a huge die are checked against the limits before they are allocated
+        let huge_die = || DiceBuilder::from_string("d100000000").unwrap();
+        // the product overflows an i64, so the die is calculated again with i128 values
+        let wide_huge_die =
+            || DiceBuilder::from_string("9223372036854775807*2+d100000000").unwrap();
+        let options = BuildOptions {
+            max_outcomes: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            huge_die().build_with_options(&options),
+            Err(DiceBuildError::TooManyOutcomes { limit: 1000 })
+        );
+        assert_eq!(
+            wide_huge_die()
+                .build_values_as::<Prob, i128>(&options)
+                .err(),
+            Some(DiceBuildError::TooManyOutcomes { limit: 1000 })
+        );
+        let options = BuildOptions {
+            max_total_work: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            huge_die().build_with_options(&options),
+            Err(DiceBuildError::TooMuchWork { limit: 1000 })
+        );
+        let options = BuildOptions {
+            timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            huge_die().build_with_options(&options),
+            Err(DiceBuildError::Timeout { .. })
+        ));
+        assert!(matches!(
+            wide_huge_die().build_values_as::<Prob, i128>(&options),
+            Err(DiceBuildError::Timeout { .. })
+        ));
+
+        let options = BuildOptions {
+            max_outcomes: Some(11),
+            max_total_work: Some(100),
+            timeout: Some(Duration::from_secs(10)),
+            cancellation: Some(CancellationToken::new()),
+            simplify: false,
+            normal_approximation_min_count: None,
+        };
+        let dice = DiceBuilder::from_string("2d6")
+            .unwrap()
+            .build_with_options(&options)
+            .unwrap();
+        assert_eq!(dice.max, 12);
+        let options = BuildOptions {
+            max_outcomes: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(
+            DiceBuilder::from_string("2d6")
+                .unwrap()
+                .build_with_options(&options),
+            Err(DiceBuildError::TooManyOutcomes { limit: 10 })
+        );
+    }
+
+    #[test]
+    fn build_approx_matches_exact() {
+        for input in [
+            "2d6+4",
+            "d3xd6",
+            "nth_highest(3,2,d8)",
+            "max(d6,d6)-min(d4,d4)",
+        ] {
+            let exact = Dice::build_from_string(input).unwrap();
+            let approx = DiceBuilder::from_string(input).unwrap().build_approx();
+            assert_eq!(exact.min, approx.min);
+            assert_eq!(exact.max, approx.max);
+            assert_eq!(exact.median, approx.median);
+            assert!((exact.mean.to_f64().unwrap() - approx.mean).abs() < 1e-9);
+            assert!((exact.variance.to_f64().unwrap() - approx.variance).abs() < 1e-9);
+            for ((v1, p1), (v2, p2)) in exact.distribution.iter().zip(approx.distribution.iter()) {
+                assert_eq!(v1, v2);
+                assert!((p1.to_f64().unwrap() - p2).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn identical_subtrees_are_calculated_once() {
+        // 4d6 needs 6 operations for the sides of d6 and 6*6 + 11*6 + 16*6 = 198 for the sums, max of two 4d6 needs 21*21 = 441
+        let options = BuildOptions {
+            max_total_work: Some(6 + 198 + 441),
+            ..Default::default()
+        };
+        let dice = DiceBuilder::from_string("max(4d6,4d6)")
+            .unwrap()
+            .build_with_options(&options)
+            .unwrap();
+        let expected = DiceBuilder::MaxCompound(vec![
+            DiceBuilder::from_string("4d6").unwrap(),
+            DiceBuilder::from_string("d6+d6+d6+d6").unwrap(),
+        ])
+        .build();
+        assert_eq!(dice.distribution, expected.distribution);
+    }
+
+    #[test]
+    fn validation_tests() {
+        let mut dice = Dice::build_from_string("nth_highest(4,2,d6)+d4").unwrap();
+        assert_eq!(dice.total_probability(), Prob::one());
+        assert!(dice.validate().is_valid());
+
+        // the cdf was calculated by the first validation and does not follow the changed distribution
+        dice.distribution.swap(0, 1);
+        let report = dice.validate();
+        assert!(report.sums_to_one);
+        assert!(!report.values_sorted_and_unique);
+        assert!(!report.cdf_matches_pmf);
+        assert!(report.cdf_monotone);
+        assert!(!report.is_valid());
+
+        let mut negative = Dice::build_from_string("d4").unwrap();
+        negative.distribution[1].1 = Prob::new_neg(1u64, 4u64);
+        negative.distribution[2].1 = Prob::new(3u64, 4u64);
+        let report = negative.validate();
+        assert!(report.sums_to_one);
+        assert!(!report.probabilities_non_negative);
+        assert!(report.cdf_matches_pmf);
+        assert!(!report.cdf_monotone);
+    }
+
+    #[test]
+    fn entropy_tests() {
+        assert_eq!(Dice::build_from_string("7").unwrap().entropy(), 0.0);
+        let coin = Dice::build_from_string("d2").unwrap();
+        assert!((coin.entropy() - 1.0).abs() < 1e-12);
+        // 2d6 is less uniform than d11 over the same number of values
+        let two_d6 = Dice::build_from_string("2d6").unwrap();
+        let d11 = Dice::build_from_string("d11").unwrap();
+        assert!(two_d6.entropy() < d11.entropy());
+    }
+
+    #[test]
+    fn summary_tests() {
+        let summary = Dice::build_from_string("d6").unwrap().summary();
+        assert_eq!(summary.q1, 2);
+        assert_eq!(summary.median, 3);
+        assert_eq!(summary.q3, 5);
+        assert_eq!(summary.mean_f64, 3.5);
+        assert!((summary.sd_f64 - (35.0f64 / 12.0).sqrt()).abs() < 1e-12);
+        assert_eq!(summary.mode, vec![6, 5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn expectation_tests() {
+        let d = Dice::build_from_string("d6").unwrap();
+        assert_eq!(d.expectation_of(|x| x), d.mean);
+        assert_eq!(d.expectation_of(|x| x * x), Prob::new(91u64, 6u64));
+        assert_eq!(d.expectation_of(|_| 1), Prob::one());
+        let sqrt = d.expectation_of_f64(|x| (x as f64).sqrt());
+        let expected = (1..=6).map(|x| (x as f64).sqrt()).sum::<f64>() / 6.0;
+        assert!((sqrt - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn given_tests() {
+        let d = Dice::build_from_string("2d6").unwrap();
+        let even = d.given(|x| x % 2 == 0).unwrap();
+        assert_eq!(even.prob(2), Prob::new(1u64, 18u64));
+        assert_eq!(even.prob(3), Prob::zero());
+        assert_eq!(even.mean, Prob::new(7u64, 1u64));
+        assert!(even.validate().is_valid());
+        assert!(d.given(|x| x > 12).is_none());
+        assert_eq!(d.given(|_| true).unwrap().distribution, d.distribution);
+    }
+
+    #[test]
+    fn shifted_scaled_clamped_tests() {
+        let d = Dice::build_from_string("3d4").unwrap();
+        for (transformed, rebuilt) in [
+            (d.shifted(5), "3d4+5"),
+            (d.shifted(-5), "3d4-5"),
+            (d.scaled(3), "3d4*3"),
+            (d.scaled(-2), "3d4*(-2)"),
+            (d.scaled(0), "0"),
+            (d.clamped(4, 9), "min(max(3d4,4),9)"),
+        ] {
+            let expected = Dice::build_from_string(rebuilt).unwrap();
+            assert_eq!(transformed.distribution, expected.distribution);
+            assert_eq!(
+                transformed.cumulative_distribution(),
+                expected.cumulative_distribution()
+            );
+            assert_eq!(transformed.mean, expected.mean);
+            assert_eq!(transformed.variance, expected.variance);
+            assert_eq!(transformed.median, expected.median);
+            assert_eq!(
+                (transformed.min, transformed.max),
+                (expected.min, expected.max)
+            );
+            let rebuilt = Dice::build_from_string(&transformed.builder_string).unwrap();
+            assert_eq!(rebuilt.distribution, expected.distribution);
+        }
+        let given = d.given(|x| x > 5).unwrap();
+        assert_eq!(given.shifted(1).builder_string, "");
+
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let negative = d6.scaled(-1);
+        for overflowing in [d6.try_shifted(i64::MAX), negative.try_shifted(i64::MIN)] {
+            assert_eq!(overflowing.err(), Some(DiceBuildError::ValueOverflow));
+        }
+        for overflowing in [d6.try_scaled(i64::MAX), d6.try_scaled(i64::MIN)] {
+            assert_eq!(overflowing.err(), Some(DiceBuildError::ValueOverflow));
+        }
+        let lowest = d6.try_shifted(i64::MIN + 1).unwrap();
+        assert_eq!((lowest.min, lowest.max), (i64::MIN + 2, i64::MIN + 7));
+        assert_eq!(lowest.builder_string, "d6-9223372036854775807",);
+        assert_eq!(d6.try_shifted(i64::MAX - 6).unwrap().max, i64::MAX);
+        assert_eq!(
+            d6.try_scaled(-(i64::MAX / 6)).unwrap().min,
+            -(i64::MAX / 6) * 6
+        );
+        assert!(std::panic::catch_unwind(|| d6.shifted(i64::MAX)).is_err());
+    }
+
+    #[test]
+    fn dice_operator_tests() {
+        let a = Dice::build_from_string("2d4").unwrap();
+        let b = Dice::build_from_string("max(d6,3)").unwrap();
+        for (combined, rebuilt) in [
+            (&a + &b, "2d4+max(d6,3)"),
+            (&a - &b, "2d4-max(d6,3)"),
+            (&a * &b, "2d4*max(d6,3)"),
+            (&(&a * &b) + &a, "2d4*max(d6,3)+2d4"),
+        ] {
+            let expected = Dice::build_from_string(rebuilt).unwrap();
+            assert_eq!(combined.distribution, expected.distribution);
+            assert_eq!(combined.mean, expected.mean);
+            assert_eq!(combined.variance, expected.variance);
+            assert_eq!(combined.mode, expected.mode);
+            let rebuilt = Dice::build_from_string(&combined.builder_string).unwrap();
+            assert_eq!(rebuilt.distribution, expected.distribution);
+        }
+    }
+
+    #[test]
+    fn dice_builder_operator_tests() {
+        let d6 = || DiceBuilder::FairDie { min: 1, max: 6 };
+        let d4 = || DiceBuilder::FairDie { min: 1, max: 4 };
+        for (builder, expected) in [
+            (d6() + 3, "d6+3"),
+            (3 + d6(), "3+d6"),
+            (d6() - d4(), "d6-d4"),
+            (10 - d6(), "10-d6"),
+            (3 * d6(), "3*d6"),
+            (d6() * d4() / 2, "d6*d4/2"),
+            (-d6(), "-d6"),
+            (*(Box::new(d6()) - 1), "d6-1"),
+            (*(2 * -Box::new(d4())), "-2*d4"),
+        ] {
+            let expected = Dice::build_from_string(expected).unwrap();
+            assert_eq!(builder.build().distribution, expected.distribution);
+        }
+    }
+
+    #[test]
+    fn dice_builder_clone_reuses_subtrees() {
+        let attack = DiceBuilder::FairDie { min: 1, max: 8 } + 3;
+        let two_attacks = attack.clone() + attack;
+        let expected = Dice::build_from_string("d8+3+d8+3").unwrap();
+        assert_eq!(two_attacks.build().distribution, expected.distribution);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn dice_builder_serde_roundtrip() {
+        let builder = DiceBuilder::from_string("max(2d6,nth_highest(3,2,d20))-abs(d4-3)").unwrap();
+        let json = serde_json::to_string(&builder).unwrap();
+        let deserialized: DiceBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(builder, deserialized);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn dice_serde_roundtrip() {
+        let dice = Dice::build_from_string("X := d6; X + max(X, d4) / 3").unwrap();
+        let json = serde_json::to_string(&dice).unwrap();
+        let deserialized: Dice = serde_json::from_str(&json).unwrap();
+        assert_eq!(dice, deserialized);
+
+        let approx: crate::DiceF64 = serde_json::from_str(&json).unwrap();
+        assert_eq!(approx.distribution.len(), dice.distribution.len());
+        assert_eq!(approx.builder_string, dice.builder_string);
+        let approx_json = serde_json::to_string(&approx).unwrap();
+        assert!(!approx_json.contains("\"prob\""));
+        assert!(serde_json::from_str::<Dice>(&approx_json).is_err());
+        let approx_roundtrip: crate::DiceF64 = serde_json::from_str(&approx_json).unwrap();
+        assert_eq!(approx_roundtrip, approx);
+
+        let duplicate = r#"{"builder_string":"","build_time":0,"distribution":[{"value":1,"prob":"1/2","prob_f64":0.5},{"value":1,"prob":"1/2","prob_f64":0.5}]}"#;
+        assert!(serde_json::from_str::<Dice>(duplicate).is_err());
+        let unsorted = r#"{"builder_string":"","build_time":0,"distribution":[{"value":2,"prob":"1/2","prob_f64":0.5},{"value":1,"prob":"1/2","prob_f64":0.5}]}"#;
+        let unsorted: Dice = serde_json::from_str(unsorted).unwrap();
+        assert_eq!(unsorted.distribution, unif(vec![1, 2]));
+    }
+
+    #[test]
+    fn from_str_tests() {
+        let dice: Dice = "2d6+3".parse().unwrap();
+        assert_eq!(
+            dice.distribution,
+            Dice::build_from_string("2d6+3").unwrap().distribution
+        );
+        let dice = Dice::try_from("max(d4,d4)").unwrap();
+        assert_eq!(dice.max, 4);
+        let builder: DiceBuilder = "d6*2".parse().unwrap();
+        assert_eq!(builder, DiceBuilder::try_from("d6*2").unwrap());
+        assert!(matches!(
+            "2d6?".parse::<Dice>(),
+            Err(DiceBuildError::Parse(DiceBuildingError {
+                kind: DiceBuildingErrorKind::InvalidCharacterInInput('?'),
+                ..
+            }))
+        ));
+        let err: Box<dyn std::error::Error> = Box::new("d6**2".parse::<DiceBuilder>().unwrap_err());
+        assert_eq!(
+            err.to_string(),
+            "multiple operators behind each other at 3..4: '*'"
+        );
+        let err = "d6)".parse::<Dice>().unwrap_err();
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn subtraction_round_trip_tests() {
+        for input in [
+            "d6-2",
+            "-d6+3",
+            "-3",
+            "2xd6-d4*3-1",
+            "(d6+2)*3",
+            "-(d6+2)",
+            "d20-(d4-1)",
+            "2*(-3)",
+            "max(d6,-2)-min(-d4,3)",
+            "(1+2)+3",
+            "-(-d6)",
+            "-2xd6/2",
+        ] {
+            let builder = DiceBuilder::from_string(input).unwrap();
+            assert_eq!(builder.reconstruct_string(), input);
+        }
+        let builder = DiceBuilder::from_string("d6-2").unwrap();
+        assert_eq!(
+            builder,
+            DiceBuilder::SumCompound(vec![
+                DiceBuilder::FairDie { min: 1, max: 6 },
+                DiceBuilder::Constant(-2)
+            ])
+        );
+        // the minus binds everything but sums, like the former rewrite of `-` to `+-1*` did
+        let negated = Dice::build_from_string("-2d6/4").unwrap();
+        let rewritten = DiceBuilder::DivisionCompound(vec![
+            DiceBuilder::ProductCompound(vec![
+                DiceBuilder::Constant(-1),
+                DiceBuilder::from_string("2d6").unwrap(),
+            ]),
+            DiceBuilder::Constant(4),
+        ]);
+        assert_eq!(negated.distribution, rewritten.build().distribution);
+        let programmatic =
+            DiceBuilder::FairDie { min: 1, max: 8 } - (DiceBuilder::FairDie { min: 1, max: 4 } + 1);
+        assert_eq!(programmatic.reconstruct_string(), "d8-(d4+1)");
+    }
+
+    #[test]
+    fn power_tests() {
+        let square = Dice::build_from_string("d6^2").unwrap();
+        assert_eq!(square.distribution, unif(vec![1, 4, 9, 16, 25, 36]));
+        let powers_of_two = Dice::build_from_string("2^d4").unwrap();
+        assert_eq!(powers_of_two.distribution, unif(vec![2, 4, 8, 16]));
+        let both = Dice::build_from_string("d2^d2").unwrap();
+        assert_eq!(both.distribution, unif(vec![1, 1, 2, 4]));
+        // right associative and binding stronger than all other operators
+        assert_eq!(Dice::build_from_string("2^3^2").unwrap().max, 512);
+        assert_eq!(Dice::build_from_string("-d6^2").unwrap().min, -36);
+        assert_eq!(Dice::build_from_string("2d6^2").unwrap().max, 72);
+        assert_eq!(
+            Dice::try_build_from_string("2^(d4-2)"),
+            Err(DiceBuildError::NegativeExponent(-1))
+        );
+        // exponents beyond u32 only have a result for the bases -1, 0 and 1
+        let mut rng = StdRng::seed_from_u64(4);
+        for (input, expected) in [
+            ("(-1)^4294967296", 1),
+            ("(-1)^4294967297", -1),
+            ("(d3-2)^(4294967296+d2-1)", 1),
+        ] {
+            let builder = DiceBuilder::from_string(input).unwrap();
+            let dice = builder.clone().build();
+            assert_eq!(dice.max, expected);
+            assert!(dice.prob(builder.try_roll_once(&mut rng).unwrap()) > Prob::zero());
+            let report = builder.roll_detailed(&mut rng).unwrap();
+            assert!(dice.prob(report.total) > Prob::zero());
+            let values = builder
+                .build_values_as::<Prob, i128>(&BuildOptions::default())
+                .unwrap();
+            assert_eq!(values.max, i128::from(expected));
+        }
+        assert_eq!(
+            Dice::try_build_from_string("0^4294967296")
+                .unwrap()
+                .distribution,
+            unif(vec![0])
+        );
+        assert_eq!(
+            Dice::try_build_from_string("2^4294967296"),
+            Err(DiceBuildError::ValueOverflow)
+        );
+        for input in ["(2^3)^2", "d6^2*2", "(-d6)^2", "2^d4^2"] {
+            assert_eq!(DiceBuilder::from_string(input).unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn die_interval_tests() {
+        let d = Dice::build_from_string("d[3..8]").unwrap();
+        assert_eq!(d.distribution, unif(vec![3, 4, 5, 6, 7, 8]));
+        let d = Dice::build_from_string("2d[-1..1]").unwrap();
+        assert_eq!(d.distribution, unif(vec![-2, -1, -1, 0, 0, 0, 1, 1, 2]));
+        assert_eq!(
+            DiceBuilder::from_string("d[1..6]").unwrap(),
+            DiceBuilder::from_string("d6").unwrap()
+        );
+        for (min, max) in [(0, 9), (1, 6), (-3, 3), (1, -2), (5, 5), (-10, -4)] {
+            let builder = DiceBuilder::FairDie { min, max };
+            let reconstructed = DiceBuilder::from_string(&builder.to_string()).unwrap();
+            assert_eq!(reconstructed, builder);
+        }
+    }
+
+    #[test]
+    fn degenerate_dice_tests() {
+        let d1 = Dice::build_from_string("d1").unwrap();
+        assert_eq!(d1.distribution, unif(vec![1]));
+        let zero_dice = Dice::build_from_string("0d6").unwrap();
+        assert_eq!(zero_dice.distribution, unif(vec![0]));
+        assert_eq!(
+            Dice::try_build_from_string("d0"),
+            Err(DiceBuildError::ZeroSidedDie)
+        );
+        assert_eq!(
+            Dice::try_build_from_string("3d[5..4]"),
+            Err(DiceBuildError::ZeroSidedDie)
+        );
+        assert_eq!(
+            Dice::try_build_from_string("d[5..3]"),
+            Err(DiceBuildError::InvalidFairDie { min: 5, max: 3 })
+        );
+        let err = Dice::build_from_string("2 * d[5..3]").unwrap_err();
+        assert_eq!(
+            err.kind,
+            DiceBuildingErrorKind::Build(Box::new(DiceBuildError::InvalidFairDie {
+                min: 5,
+                max: 3
+            }))
+        );
+        assert_eq!(err.snippet, "2 * d[5..3]");
+        // the number of sides of the widest die does not fit into 64 bits
+        let widest = DiceBuilder::FairDie {
+            min: i64::MIN,
+            max: i64::MAX,
+        };
+        assert_eq!(
+            widest.clone().try_build(),
+            Err(DiceBuildError::ValueOverflow)
+        );
+        assert_eq!(
+            widest
+                .clone()
+                .build_values_as::<Prob, i128>(&BuildOptions::default())
+                .err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+        assert_eq!(
+            widest.try_roll_once(&mut StdRng::seed_from_u64(0)),
+            Err(DiceBuildError::ValueOverflow)
+        );
+        // negative counts subtract the samples
+        let negative_count = DiceBuilder::SampleSumCompound(vec![
+            DiceBuilder::Constant(-2),
+            DiceBuilder::Constant(3),
+        ]);
+        assert_eq!(negative_count.build().distribution, unif(vec![-6]));
+        let count_around_zero = Dice::build_from_string("(d3-2)xd2").unwrap();
+        assert_eq!(
+            count_around_zero.distribution,
+            unif(vec![-1, -2, 0, 0, 1, 2])
+        );
+    }
+
+    #[test]
+    fn shared_roll_tests() {
+        // X+X doubles one roll, d6+d6 adds two independent rolls
+        let doubled = Dice::build_from_string("X := d6; X + X").unwrap();
+        assert_eq!(doubled.distribution, unif(vec![2, 4, 6, 8, 10, 12]));
+        let folded = Dice::build_from_string("X := d20; max(X, 20 - X)").unwrap();
+        assert_eq!((folded.min, folded.max), (10, 20));
+        assert_eq!(folded.prob(10), Prob::new(1u64, 20u64));
+        assert_eq!(folded.prob(15), Prob::new(2u64, 20u64));
+        // later definitions can use earlier ones, variables are case sensitive
+        let chained = Dice::build_from_string("A := d4; B := A x d2; B - A").unwrap();
+        let expected = Dice::build_from_string("d4 x (d2 - 1)").unwrap();
+        assert_eq!(chained.distribution, expected.distribution);
+        // definitions inside brackets and function arguments
+        let nested = Dice::build_from_string("max(Y := d6; Y - Y, (Z := 2; Z * d4))").unwrap();
+        assert_eq!(nested.min, 2);
+        for input in ["X:=d20;max(X,20-X)", "(X:=d6;X*X)+3", "A:=(B:=d4;B+B);A^2"] {
+            assert_eq!(DiceBuilder::from_string(input).unwrap().to_string(), input);
+        }
+        assert_eq!(
+            DiceBuilder::Variable("X".to_owned()).try_build(),
+            Err(DiceBuildError::UnboundVariable("X".to_owned()))
+        );
+        let err = DiceBuilder::from_string("X := d6").unwrap_err();
+        assert_eq!(err.kind, DiceBuildingErrorKind::IncompleteDefinition);
+        assert_eq!(err.span, 0..1);
+    }
+
+    #[test]
+    fn joint_dice_tests() {
+        let joint = JointDice::build_from_string("X := d6; X, X + d2").unwrap();
+        assert_eq!(joint.dimensions(), 2);
+        assert_eq!(joint.distribution.len(), 12);
+        assert_eq!(joint.builder_strings, vec!["X:=d6;X", "X:=d6;X+d2"]);
+        let first = joint.marginal(0);
+        assert_eq!(
+            first.distribution,
+            Dice::build_from_string("d6").unwrap().distribution
+        );
+        let second = joint.marginal(1);
+        assert_eq!(
+            second.distribution,
+            Dice::build_from_string("d6+d2").unwrap().distribution
+        );
+        let rebuilt = Dice::build_from_string(&second.builder_string).unwrap();
+        assert_eq!(rebuilt.distribution, second.distribution);
+        // the components share the roll of X
+        assert_eq!(joint.covariance(0, 1), first.variance);
+        let given_six = joint.given(|v| v[0] == 6).unwrap();
+        assert_eq!(given_six.marginal(1).distribution, unif(vec![7, 8]));
+        assert!(joint.given(|v| v[0] > v[1]).is_none());
+        assert_eq!(joint.project(&[1]).distribution.len(), 7);
+        assert_eq!(joint.map(|v| v[1] - v[0]).distribution, unif(vec![1, 2]));
+
+        let independent = JointDice::build_from_string("d4, d4").unwrap();
+        assert_eq!(independent.prob_of(|v| v[0] == v[1]), Prob::new(1u64, 4u64));
+        assert_eq!(independent.covariance(0, 1), Prob::zero());
+        let shared_with_builders = JointDice::<f64>::from_builders(
+            &[("Y".to_owned(), DiceBuilder::FairDie { min: 1, max: 4 })],
+            &[
+                DiceBuilder::Variable("Y".to_owned()),
+                -DiceBuilder::Variable("Y".to_owned()),
+            ],
+            &BuildOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            shared_with_builders.map(|v| v[0] + v[1]).distribution,
+            vec![(0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn prob_beats_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        assert_eq!(d6.prob_beats(&d6), Prob::new(15u64, 36u64));
+        assert_eq!(d6.prob_ties(&d6), Prob::new(1u64, 6u64));
+        assert_eq!(d6.prob_loses(&d6), Prob::new(15u64, 36u64));
+
+        let d8 = Dice::build_from_string("d8").unwrap();
+        let sum = d6.prob_beats(&d8) + d6.prob_ties(&d8) + d6.prob_loses(&d8);
+        assert_eq!(sum, Prob::one());
+        assert_eq!(d6.prob_beats(&d8), Prob::new(15u64, 48u64));
+        assert_eq!(d6.prob_loses(&d8), d8.prob_beats(&d6));
+
+        let constant = Dice::build_from_string("4").unwrap();
+        assert_eq!(d6.prob_beats(&constant), d6.prob_gt(4));
+    }
+
+    #[test]
+    fn contest_tests() {
+        let contest = Contest::build_from_strings("d6", "d6").unwrap();
+        assert_eq!(contest.win, Prob::new(15u64, 36u64));
+        assert_eq!(contest.tie, Prob::new(1u64, 6u64));
+        assert_eq!(contest.loss, contest.win);
+        assert_eq!(contest.expected_margin, Prob::zero());
+        assert_eq!(contest.margin.min, -5);
+        assert_eq!(contest.margin.max, 5);
+        assert_eq!(contest.margin.builder_string, "(d6)-(d6)");
+        assert_eq!(
+            contest.outcome.distribution,
+            vec![
+                (-1, Prob::new(15u64, 36u64)),
+                (0, Prob::new(1u64, 6u64)),
+                (1, Prob::new(15u64, 36u64))
+            ]
+        );
+
+        let d8 = Dice::build_from_string("d8").unwrap();
+        let favored = d8.contest(&Dice::build_from_string("d6").unwrap());
+        assert_eq!(favored.expected_margin, Prob::one());
+        assert_eq!(
+            favored.win,
+            d8.prob_beats(&Dice::build_from_string("d6").unwrap())
+        );
+        assert!(Contest::build_from_strings("d6", "d6+").is_err());
+        assert_eq!(
+            Contest::build_from_strings("d0", "d6").err(),
+            Some(DiceBuildError::ZeroSidedDie)
+        );
+        assert_eq!(
+            Contest::build_from_strings("d6", "d[3..1]").err(),
+            Some(DiceBuildError::InvalidFairDie { min: 3, max: 1 })
+        );
+        assert_eq!(
+            Contest::build_from_strings("9223372036854775807", "-1").err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+        assert_eq!(
+            Contest::build_from_strings("-9223372036854775807", "d6").err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+    }
+
+    #[test]
+    fn distance_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let max_of_two = Dice::build_from_string("max(d6,d6)").unwrap();
+        assert_eq!(d6.total_variation(&d6), Prob::zero());
+        assert_eq!(d6.earth_movers_distance(&d6), Prob::zero());
+        assert_eq!(d6.kl_divergence(&d6), 0.0);
+
+        // the mean of the maximum of two d6 is 161/36, both are supported on 1..=6
+        assert_eq!(
+            d6.earth_movers_distance(&max_of_two),
+            Prob::new(161u64, 36u64) - Prob::new(7u64, 2u64)
+        );
+        assert_eq!(
+            d6.total_variation(&max_of_two),
+            max_of_two.total_variation(&d6)
+        );
+        assert!(d6.kl_divergence(&max_of_two) > 0.0);
+
+        let d4 = Dice::build_from_string("d4").unwrap();
+        assert!((d4.kl_divergence(&d6) - (1.5f64).log2()).abs() < 1e-12);
+        assert_eq!(d6.kl_divergence(&d4), f64::INFINITY);
+        let far = Dice::build_from_string("d4+10").unwrap();
+        assert_eq!(d4.total_variation(&far), Prob::one());
+        assert_eq!(d4.earth_movers_distance(&far), Prob::from(10));
+    }
+
+    #[test]
+    fn chi_square_tests() {
+        let d4 = Dice::build_from_string("d4").unwrap();
+        let observed: Vec<Value> = [vec![1; 30], vec![2; 20], vec![3; 25], vec![4; 25]].concat();
+        let report = d4.chi_square_test(&observed);
+        assert_eq!(report.sample_size, 100);
+        assert_eq!(report.degrees_of_freedom, 3);
+        assert!((report.statistic - 2.0).abs() < 1e-12);
+        // the chi-square distribution with 3 degrees of freedom has P(X >= 2) = 0.5724067...
+        assert!((report.p_value - 0.5724067044).abs() < 1e-8);
+        assert!(!report.rejects_at(0.05));
+
+        let biased = d4.chi_square_test(&[vec![1; 60], vec![2; 40]].concat());
+        assert!((biased.statistic - 108.0).abs() < 1e-12);
+        assert!(biased.p_value < 1e-20);
+
+        let impossible = d4.chi_square_test(&[1, 2, 3, 5]);
+        assert_eq!(impossible.statistic, f64::INFINITY);
+        assert_eq!(impossible.p_value, 0.0);
+
+        let constant = Dice::build_from_string("3").unwrap();
+        assert_eq!(constant.chi_square_test(&[3, 3]).p_value, 1.0);
+        assert_eq!(d4.chi_square_test(&[]).p_value, 1.0);
+    }
+
+    #[test]
+    fn seeded_roll_tests() {
+        let d = Dice::build_from_string("3d6").unwrap();
+        let mut roller = d.roller(1);
+        let first: Vec<Value> = roller.roll_many(50).collect();
+        assert!(d.roller(1).roll_many(50).eq(first.iter().copied()));
+        assert!(d.roller(2).roll_many(50).ne(first.iter().copied()));
+        assert!(first.iter().all(|v| (3..=18).contains(v)));
+        assert!(roller.roll_many(50).ne(first.iter().copied()));
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let with_rng: Vec<Value> = (0..50).map(|_| d.roll_with_rng(&mut rng)).collect();
+        assert_eq!(with_rng, first);
+
+        let constant = Dice::build_from_string("4").unwrap();
+        assert!(constant.roller(3).roll_many(3).eq([4, 4, 4]));
+    }
+
+    #[test]
+    fn roll_iter_tests() {
+        let d = Dice::build_from_string("d4").unwrap();
+        let rolls: Vec<Value> = d.roll_iter().take(100).collect();
+        assert_eq!(rolls.len(), 100);
+        assert!(rolls.iter().all(|v| (1..=4).contains(v)));
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let from_iter: Vec<Value> = d.roll_iter_with_rng(&mut rng).take(20).collect();
+        let next_after_iter = d.roll_with_rng(&mut rng);
+        let mut same_seed = d.roll_iter_with_rng(StdRng::seed_from_u64(5));
+        assert_eq!(
+            same_seed.by_ref().take(20).collect::<Vec<Value>>(),
+            from_iter
+        );
+        assert_eq!(same_seed.next(), Some(next_after_iter));
+        assert_eq!(d.roller(5).take(20).collect::<Vec<Value>>(), from_iter);
+    }
+
+    #[test]
+    fn roll_many_tests() {
+        let d = Dice::build_from_string("d6").unwrap();
+        let rolls = d.roll_many(30);
+        assert_eq!(rolls.len(), 30);
+        assert!(rolls.into_iter().all(|v| (1..=6).contains(&v)));
+        #[allow(deprecated)]
+        let deprecated = d.roll_multiple(7);
+        assert_eq!(deprecated.len(), 7);
+
+        let mut rng = StdRng::seed_from_u64(9);
+        let seeded: Vec<Value> = d.roll_many_with_rng(10, &mut rng).collect();
+        assert!(d.roller(9).roll_many(10).eq(seeded));
+    }
+
+    #[test]
+    fn roll_detailed_tests() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let report = |s: &str, rng: &mut StdRng| {
+            DiceBuilder::from_string(s)
+                .unwrap()
+                .roll_detailed(rng)
+                .unwrap()
+        };
+        let r = report("2d6+3", &mut rng);
+        let dice = &r.trace.children[0];
+        assert_eq!(dice.formula, "2xd6");
+        assert_eq!(dice.rolls.len(), 2);
+        assert_eq!(r.total, dice.rolls.iter().sum::<Value>() + 3);
+        let (a, b) = (dice.rolls[0], dice.rolls[1]);
+        assert_eq!(r.to_string(), format!("2d6 ({a}, {b}) + 3 = {}", r.total));
+
+        let r = report("max(d20,d20)-1", &mut rng);
+        let (a, b) = (
+            r.trace.children[0].children[0].value,
+            r.trace.children[0].children[1].value,
+        );
+        assert_eq!(r.total, a.max(b) - 1);
+        assert_eq!(
+            r.to_string(),
+            format!("max(d20 ({a}), d20 ({b})) - 1 = {}", r.total)
+        );
+
+        let r = report("d4xd6", &mut rng);
+        let count = r.trace.children[0].value;
+        assert_eq!(r.trace.rolls.len(), count as usize);
+        assert!(r.to_string().starts_with(&format!("d4 ({count}) x d6 (")));
+
+        let r = report("(d6+1)*2", &mut rng);
+        assert!(r.to_string().starts_with("(d6 ("));
+        let r = report("X:=d6;X*X", &mut rng);
+        let x = r.trace.children[0].value;
+        assert_eq!(r.to_string(), format!("X := d6 ({x}); X * X = {}", x * x));
+        assert!(DiceBuilder::from_string("d[1..0]")
+            .unwrap()
+            .roll_detailed(&mut rng)
+            .is_err());
+    }
+
+    #[test]
+    fn simulate_tests() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let builder = DiceBuilder::from_string("X := d6; X + 2d4 - max(X, d6)").unwrap();
+        let exact = builder.clone().build();
+        let empirical = builder.simulate(20000, &mut rng).unwrap();
+        assert_eq!(empirical.sample_count, 20000);
+        assert_eq!(empirical.builder_string, exact.builder_string);
+        assert!(empirical.min >= exact.min && empirical.max <= exact.max);
+        let exact_mean = exact.mean.to_f64().unwrap();
+        assert!((empirical.mean - exact_mean).abs() < 4.0 * empirical.standard_error_of_mean());
+        assert!((empirical.total_probability() - 1.0).abs() < 1e-9);
+
+        let constant = DiceBuilder::from_string("nth_highest(3,2,4)^2")
+            .unwrap()
+            .simulate(10, &mut rng)
+            .unwrap();
+        assert_eq!(constant.distribution, vec![(16, 1.0)]);
+
+        let shared = DiceBuilder::from_string("X := d20; X - X")
+            .unwrap()
+            .simulate(100, &mut rng)
+            .unwrap();
+        assert_eq!(shared.distribution, vec![(0, 1.0)]);
+
+        // the explosion threshold of the operand depends on the value of X
+        let builder = DiceBuilder::from_string("X := d2; (X + d6)!").unwrap();
+        let exact = builder.clone().build();
+        let empirical = builder.simulate(20000, &mut rng).unwrap();
+        for (v, _) in empirical.distribution.iter() {
+            assert!(exact.prob(*v) > Prob::zero(), "value {v}");
+        }
+        let exact_mean = exact.mean.to_f64().unwrap();
+        assert!((empirical.mean - exact_mean).abs() < 4.0 * empirical.standard_error_of_mean());
+
+        let negative_count = DiceBuilder::from_string("-2 x 3").unwrap();
+        assert_eq!(negative_count.simulate(1, &mut rng).unwrap().min, -6);
+        assert_eq!(
+            DiceBuilder::Variable("Y".to_owned()).simulate(1, &mut rng),
+            Err(DiceBuildError::UnboundVariable("Y".to_owned()))
+        );
+        assert_eq!(
+            DiceBuilder::from_string("d6")
+                .unwrap()
+                .simulate(0, &mut rng),
+            Err(DiceBuildError::EmptyDistribution)
+        );
+    }
+
+    #[test]
+    fn roll_once_tests() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let builder = DiceBuilder::from_string("X := d6; 10*X + X").unwrap();
+        for _ in 0..20 {
+            let v = builder.roll_once(&mut rng);
+            assert_eq!(v % 11, 0);
+            assert!((11..=66).contains(&v));
+        }
+        let huge = DiceBuilder::from_string("1000d1000").unwrap();
+        assert!((1000..=1000000).contains(&huge.roll_once(&mut rng)));
+
+        let mut same_seed = StdRng::seed_from_u64(4);
+        let mut rng = StdRng::seed_from_u64(4);
+        let builder = DiceBuilder::from_string("d20 + max(d4, 2) x d6").unwrap();
+        assert_eq!(
+            builder.roll_once(&mut rng),
+            builder.roll_once(&mut same_seed)
+        );
+        assert_eq!(
+            DiceBuilder::from_string("d[1..0]")
+                .unwrap()
+                .try_roll_once(&mut rng),
+            Err(DiceBuildError::ZeroSidedDie)
+        );
+    }
+
+    #[test]
+    fn csv_tests() {
+        let d = Dice::build_from_string("d2+d2").unwrap();
+        let mut csv: Vec<u8> = vec![];
+        d.to_csv(&mut csv, &CsvOptions::default()).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "value,prob,prob_f64,cumulative_prob_f64\n2,1/4,0.25,0.25\n3,1/2,0.5,0.75\n4,1/4,0.25,1\n"
+        );
+
+        let approx = DiceBuilder::from_string("d2").unwrap().build_approx();
+        let mut tsv: Vec<u8> = vec![];
+        let options = CsvOptions {
+            header: false,
+            ..CsvOptions::tsv()
+        };
+        approx.to_csv(&mut tsv, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "1\t0.5\t0.5\t0.5\n2\t0.5\t0.5\t1\n"
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn js_dice_builder_tests() {
+        use crate::js_dice_builder::JsDiceBuilder;
+        let d6 = JsDiceBuilder::fair_die(1, 6);
+        let x = JsDiceBuilder::variable("X");
+        let builders = [
+            d6.add(&JsDiceBuilder::constant(5)),
+            d6.sub(&d6).abs().mul(&d6).div(&JsDiceBuilder::constant(2)),
+            JsDiceBuilder::constant(2)
+                .sample_sum(&d6)
+                .max(&d6.pow(&JsDiceBuilder::constant(2))),
+            d6.neg().min(&d6.nth_highest(3, 2)),
+            d6.reroll(-1, false).add(&d6.reroll(2, true)),
+            d6.explode(Some(-2), DiceBuilder::DEFAULT_MAX_EXPLOSIONS),
+            d6.explode(None, DiceBuilder::DEFAULT_MAX_EXPLOSIONS),
+            JsDiceBuilder::let_in("X", &d6, &x.sub(&x)),
+        ];
+        for builder in builders {
+            let formula = builder.to_formula();
+            let parsed = JsDiceBuilder::from_string(&formula).unwrap();
+            assert_eq!(parsed.to_formula(), formula);
+            assert_eq!(
+                parsed.build().unwrap().dice.distribution,
+                builder.build().unwrap().dice.distribution,
+                "formula: {formula}"
+            );
+        }
+        // the string syntax cannot limit the number of explosions
+        assert_eq!(d6.explode(None, 2).to_formula(), "explode(d6,None,2)");
+        assert_eq!(d6.explode(None, 2).build().unwrap().dice.max, 18);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn js_dice_tables_tests() {
+        use crate::{
+            dice::JsDice,
+            js_types::{JsBucket, JsDistribution},
+        };
+        let dice = JsDice {
+            dice: Dice::build_from_string("2d6").unwrap(),
+        };
+        assert_eq!(dice.quantiles(&[0.0, 0.5, 1.0]), vec![2, 7, 12]);
+        // the objects behind the JsValues, which can only be created on wasm32
+        let at_least = JsDistribution::from_distribution(&dice.dice.at_least_table());
+        assert_eq!(at_least.values.len(), 11);
+        assert_eq!(at_least.values[0].0, 2);
+        assert_eq!(at_least.values[0].1.string, "1");
+        assert_eq!(at_least.values[10].1.string, "1/36");
+        let most_likely = JsDistribution::from_distribution(&dice.dice.most_likely(1));
+        assert_eq!(most_likely.values[0].0, 7);
+        assert_eq!(most_likely.values[0].1.string, "1/6");
+        let buckets: Vec<JsBucket> = dice.dice.bucketed(6).iter().map(JsBucket::from).collect();
+        assert_eq!(
+            serde_json::to_value(&buckets).unwrap(),
+            serde_json::json!([
+                { "min": 2, "max": 7, "prob": { "string": "7/12", "float": 7.0f32 / 12.0 } },
+                { "min": 8, "max": 12, "prob": { "string": "5/12", "float": 5.0f32 / 12.0 } },
+            ])
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn js_distribution_floats_tests() {
+        use crate::dice::JsDice;
+        let dice = JsDice {
+            dice: Dice::build_from_string("d4-2").unwrap(),
+        };
+        assert_eq!(
+            dice.distribution_columns(),
+            Ok((vec![-1, 0, 1, 2], vec![0.25; 4]))
+        );
+        let wide = JsDice {
+            dice: Dice::build_from_string("d2*3000000000").unwrap(),
+        };
+        assert_eq!(
+            wide.distribution_columns(),
+            Err(DiceBuildError::ValueOverflow)
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn js_dice_error_tests() {
+        use crate::js_types::JsDiceError;
+        let parse_error = Dice::try_build_from_string("2d6 + max(d4,,d8)").unwrap_err();
+        assert_eq!(
+            JsDiceError::from_build_error(&parse_error),
+            JsDiceError {
+                kind: "EmptySubSequence".to_string(),
+                message: DiceBuildingErrorKind::EmptySubSequence.to_string(),
+                span_start: Some(13),
+                span_end: Some(14),
+                snippet: Some(",".to_string()),
+            }
+        );
+        let build_error = Dice::try_build_from_string("d[5..3]").unwrap_err();
+        assert_eq!(
+            JsDiceError::from_build_error(&build_error),
+            JsDiceError {
+                kind: "InvalidFairDie".to_string(),
+                message: "die with minimum 5 greater than maximum 3".to_string(),
+                span_start: None,
+                span_end: None,
+                snippet: None,
+            }
+        );
+        assert_eq!(
+            JsDiceError::from_build_error(&DiceBuildError::ZeroSidedDie).kind,
+            "ZeroSidedDie"
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn js_types_match_typescript_interfaces_tests() {
+        use crate::js_types::{JsDiceError, JsDistribution, JsProbAll, JsStats};
+        let dice = Dice::build_from_string("d2+d2").unwrap();
+        let quarter = serde_json::json!({ "string": "1/4", "float": 0.25 });
+        let half = serde_json::json!({ "string": "1/2", "float": 0.5 });
+        let three_quarters = serde_json::json!({ "string": "3/4", "float": 0.75 });
+        assert_eq!(
+            serde_json::to_value(JsStats::from(&dice)).unwrap(),
+            serde_json::json!({
+                "min": 2,
+                "max": 4,
+                "median": 3,
+                "mode": [3],
+                "mean": { "string": "3", "float": 3.0 },
+                "variance": half,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(JsProbAll::from(&dice.prob_all(3))).unwrap(),
+            serde_json::json!({
+                "lt": quarter,
+                "lte": three_quarters,
+                "eq": half,
+                "gte": three_quarters,
+                "gt": quarter,
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(JsDistribution::from_distribution(&dice.distribution)).unwrap(),
+            serde_json::json!({ "values": [[2, quarter], [3, half], [4, quarter]] })
+        );
+        let error = JsDiceError::from_build_error(&DiceBuildError::ZeroSidedDie);
+        assert_eq!(
+            serde_json::to_value(error).unwrap(),
+            serde_json::json!({
+                "kind": "ZeroSidedDie",
+                "message": "die without sides",
+                "span_start": null,
+                "span_end": null,
+                "snippet": null,
+            })
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_tests() {
+        let d = Dice::build_from_string("d2+d2").unwrap();
+        assert_eq!(
+            d.to_json(None),
+            r#"{"values":[2,3,4],"probs":[0.25,0.5,0.25],"cum_probs":[0.25,0.75,1.0],"stats":{"min":2,"max":4,"median":3,"mode":[3],"mean":3.0,"variance":0.5,"sd":0.7071067811865476}}"#
+        );
+        let parsed: serde_json::Value = serde_json::from_str(&d.to_json(Some(2))).unwrap();
+        assert_eq!(parsed["stats"]["sd"], 0.71);
+        let approx = DiceBuilder::from_string("d3").unwrap().build_approx();
+        let parsed: serde_json::Value = serde_json::from_str(&approx.to_json(Some(1))).unwrap();
+        assert_eq!(parsed["probs"], serde_json::json!([0.3, 0.3, 0.3]));
+    }
+
+    #[test]
+    fn histogram_tests() {
+        let d = Dice::build_from_string("d4").unwrap();
+        let options = HistogramOptions {
+            width: 8,
+            cumulative: true,
+            ascii_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            d.to_histogram(&options),
+            "1 | ##        25.00%\n2 | ####      50.00%\n3 | ######    75.00%\n4 | ######## 100.00%\n"
+        );
+        let uneven = Dice::build_from_string("d3").unwrap().to_ascii_histogram(1);
+        assert_eq!(uneven.lines().next(), Some("1 | █  33.33%"));
+
+        let wide = Dice::build_from_string("d20*d20").unwrap();
+        let histogram = wide.to_histogram(&HistogramOptions {
+            max_rows: 20,
+            ..Default::default()
+        });
+        assert_eq!(histogram.lines().count(), 20);
+        assert!(histogram.starts_with("   1..20 | "));
+        assert!(histogram.lines().last().unwrap().starts_with("381..400 | "));
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn ffi_tests() {
+        use crate::ffi::*;
+        use std::ffi::{CStr, CString};
+        unsafe {
+            let mut dice = std::ptr::null_mut();
+            let input = CString::new("2d6").unwrap();
+            assert_eq!(
+                dices_build_from_string(input.as_ptr(), &mut dice),
+                DicesStatus::Ok
+            );
+            assert_eq!(dices_pmf_len(dice), 11);
+            let mut pmf = vec![
+                DicesPmfEntry {
+                    value: 0,
+                    prob: 0.0
+                };
+                20
+            ];
+            assert_eq!(dices_pmf_copy(dice, pmf.as_mut_ptr(), pmf.len()), 11);
+            assert_eq!(pmf[5].value, 7);
+            assert!((pmf[5].prob - 6.0 / 36.0).abs() < 1e-12);
+            let mut entry = pmf[0];
+            assert_eq!(
+                dices_pmf_entry(dice, 11, &mut entry),
+                DicesStatus::OutOfBounds
+            );
+            let mut stats = std::mem::MaybeUninit::<DicesStats>::uninit();
+            assert_eq!(dices_stats(dice, stats.as_mut_ptr()), DicesStatus::Ok);
+            assert_eq!(stats.assume_init().mean, 7.0);
+            assert!((2..=12).contains(&dices_roll(dice)));
+            let mut rolls = [0i64; 10];
+            dices_roll_many_seeded(dice, 3, rolls.as_mut_ptr(), rolls.len());
+            let d = Dice::build_from_string("2d6").unwrap();
+            assert_eq!(
+                rolls.to_vec(),
+                d.roller(3).roll_many(10).collect::<Vec<_>>()
+            );
+            dices_free(dice);
+
+            let input = CString::new("2d6+").unwrap();
+            assert_eq!(
+                dices_build_from_string(input.as_ptr(), &mut dice),
+                DicesStatus::ParseError
+            );
+            let message = CStr::from_ptr(dices_last_error_message());
+            assert!(!message.to_str().unwrap().is_empty());
+            assert_eq!(dices_pmf_len(std::ptr::null()), 0);
+
+            // untrusted formulas are calculated with conservative limits by default
+            let input = CString::new("d9223372036854775807").unwrap();
+            assert_eq!(
+                dices_build_from_string(input.as_ptr(), &mut dice),
+                DicesStatus::BuildError
+            );
+            let input = CString::new("d20+d20").unwrap();
+            let options = DicesBuildOptions {
+                max_outcomes: 30,
+                ..dices_default_build_options()
+            };
+            assert_eq!(
+                dices_build_from_string_with_options(input.as_ptr(), &options, &mut dice),
+                DicesStatus::BuildError
+            );
+            let message = CStr::from_ptr(dices_last_error_message());
+            assert!(message.to_str().unwrap().contains("30"));
+            let unlimited = DicesBuildOptions {
+                max_outcomes: 0,
+                max_total_work: 0,
+                timeout_ms: 0,
+            };
+            assert_eq!(
+                dices_build_from_string_with_options(input.as_ptr(), &unlimited, &mut dice),
+                DicesStatus::Ok
+            );
+            assert_eq!(dices_pmf_len(dice), 39);
+            dices_free(dice);
+        }
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn svg_tests() {
+        let d = Dice::build_from_string("max(d4,d4)").unwrap();
+        let svg = d.to_svg(&crate::ChartOptions {
+            title: Some("max of <two> d4 & more".to_owned()),
+            ..Default::default()
+        });
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"640\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("class=\"bar\"").count(), 4);
+        assert_eq!(svg.matches("class=\"cdf\"").count(), 1);
+        assert!(svg.contains("max of &lt;two&gt; d4 &amp; more"));
+        assert!(svg.contains("mean 3.1"));
+        assert!(svg.contains("median 3"));
+
+        let without_cdf = d.to_svg(&crate::ChartOptions {
+            show_cdf: false,
+            ..Default::default()
+        });
+        assert!(!without_cdf.contains("polyline"));
+        assert!(without_cdf.contains(">max(d4,d4)</text>"));
+    }
+
+    #[test]
+    fn display_and_markdown_tests() {
+        let d = Dice::build_from_string("d4").unwrap();
+        assert_eq!(
+            d.to_string(),
+            "d4: min 1, max 4, mean 2.50, sd 1.12, mode 1, 2, 3, 4\nmost likely: 1 (25.00%), 2 (25.00%), 3 (25.00%), 4 (25.00%)"
+        );
+        let derived = d.given(|v| v > 2).unwrap();
+        assert!(derived.to_string().starts_with("min 3, max 4,"));
+
+        let table = Dice::build_from_string("d2+d2")
+            .unwrap()
+            .to_markdown_table();
+        let rows: Vec<&str> = table.lines().collect();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[2], "| 2 | 25.00% | 25.00% | 100.00% |");
+        assert_eq!(rows[4], "| 4 | 25.00% | 100.00% | 25.00% |");
+    }
+
+    #[test]
+    fn anydice_text_tests() {
+        let text = Dice::build_from_string("2d6").unwrap().to_anydice_text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[..6],
+            ["2xd6", "mean: 7.00", "sd: 2.42", "min: 2", "max: 12", ""]
+        );
+        assert_eq!(lines[6], "2: 2.78");
+        assert_eq!(lines[11], "7: 16.67");
+        assert_eq!(lines.len(), 17);
+        let derived = Dice::build_from_string("d6")
+            .unwrap()
+            .given(|v| v % 2 == 0)
+            .unwrap();
+        assert!(derived.to_anydice_text().starts_with("output 1\n"));
+    }
+
+    #[test]
+    fn latex_tests() {
+        let latex = |s: &str| DiceBuilder::from_string(s).unwrap().to_latex();
+        assert_eq!(latex("2d6+3"), r"2\mathrm{d}6 + 3");
+        assert_eq!(latex("d6-d4-2"), r"\mathrm{d}6 - \mathrm{d}4 - 2");
+        assert_eq!(
+            latex("(d6+1)*d[0..9]"),
+            r"\left(\mathrm{d}6 + 1\right) \cdot \mathrm{d}[0..9]"
+        );
+        assert_eq!(latex("d20/2/3"), r"\frac{\frac{\mathrm{d}20}{2}}{3}");
+        assert_eq!(latex("(d6/2)^2"), r"\left(\frac{\mathrm{d}6}{2}\right)^{2}");
+        assert_eq!(latex("2^3^d2"), r"2^{3^{\mathrm{d}2}}");
+        assert_eq!(
+            latex("d2x(d6+1)"),
+            r"\mathrm{d}2 \mathbin{\mathrm{x}} \left(\mathrm{d}6 + 1\right)"
+        );
+        assert_eq!(
+            latex("min(abs(d6-4),2)"),
+            r"\min(\left|\mathrm{d}6 - 4\right|, 2)"
+        );
+        assert_eq!(
+            latex("Roll := d20; Roll + 5"),
+            r"\mathit{Roll} := \mathrm{d}20;\ \mathit{Roll} + 5"
+        );
+        assert_eq!(latex("-d6"), r"-\mathrm{d}6");
+
+        let table = Dice::build_from_string("d3").unwrap().to_latex_table();
+        assert!(table.starts_with("\\begin{tabular}{rrr}\n"));
+        assert!(table.ends_with("\\end{tabular}\n"));
+        assert!(table.contains(r"3 & $\frac{1}{3}$ & 33.33\% \\"));
+        let constant = Dice::build_from_string("5").unwrap().to_latex_table();
+        assert!(constant.contains(r"5 & $1$ & 100.00\% \\"));
+    }
+
+    #[test]
+    fn bucketing_tests() {
+        let d = Dice::build_from_string("d20*d20").unwrap();
+        let buckets = d.bucketed_into(10);
+        assert_eq!(buckets.len(), 10);
+        assert_eq!((buckets[0].min, buckets[0].max), (1, 40));
+        assert_eq!((buckets[9].min, buckets[9].max), (361, 400));
+        let total = buckets
+            .iter()
+            .fold(Prob::zero(), |total, bucket| total + bucket.prob.clone());
+        assert_eq!(total, Prob::one());
+        assert_eq!(buckets[0].prob, d.prob_lte(40));
+
+        let sparse = Dice::build_from_string("10*d3").unwrap().bucketed(5);
+        let probs: Vec<Prob> = sparse.iter().map(|b| b.prob.clone()).collect();
+        let third = Prob::new(1u64, 3u64);
+        assert_eq!((sparse[1].min, sparse[1].max), (15, 19));
+        assert_eq!((sparse[4].min, sparse[4].max), (30, 30));
+        assert_eq!(
+            probs,
+            vec![
+                third.clone(),
+                Prob::zero(),
+                third.clone(),
+                Prob::zero(),
+                third
+            ]
+        );
+        let single = Dice::build_from_string("4").unwrap().bucketed(u64::MAX);
+        assert_eq!(
+            single,
+            vec![crate::Bucket {
+                min: 4,
+                max: 4,
+                prob: Prob::one()
+            }]
+        );
+        assert_eq!(
+            Dice::build_from_string("d6")
+                .unwrap()
+                .bucketed_into(100)
+                .len(),
+            6
+        );
+    }
+
+    #[test]
+    fn at_least_at_most_tests() {
+        let d = Dice::build_from_string("2d6").unwrap();
+        let at_least = d.at_least_table();
+        assert_eq!(at_least.len(), 11);
+        for (v, p) in at_least.iter() {
+            assert_eq!(*p, d.prob_gte(*v));
+        }
+        let at_most = d.at_most_table();
+        for (v, p) in at_most.iter() {
+            assert_eq!(*p, d.prob_lte(*v));
+        }
+        assert_eq!(d.at_least_table_f64()[0], (2, 1.0));
+        assert_eq!(d.at_most_table_f64()[10], (12, 1.0));
+
+        let approx = DiceBuilder::from_string("d20+d20").unwrap().build_approx();
+        assert_eq!(approx.at_least_table()[0].1, 1.0);
+        assert!((approx.at_least_table()[38].1 - 1.0 / 400.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn explain_prob_gte_tests() {
+        let d = Dice::build_from_string("d12").unwrap();
+        let explanation = d.explain_prob_gte(8);
+        assert_eq!(
+            explanation
+                .outcomes
+                .iter()
+                .map(|(v, _)| *v)
+                .collect::<Vec<_>>(),
+            vec![8, 9, 10, 11, 12]
+        );
+        assert_eq!(explanation.total, Prob::new(5u64, 12u64));
+        assert!((explanation.percent - 41.666).abs() < 0.01);
+        assert!(explanation.to_string().ends_with("= 5/12 ≈ 41.67%"));
+        assert_eq!(
+            d.explain_prob_gte(12).to_string(),
+            "P(d12 >= 12) = 1/12 ≈ 8.33%"
+        );
+        let none = d.explain_prob_gte(13);
+        assert!(none.outcomes.is_empty());
+        assert_eq!(none.to_string(), "P(d12 >= 13) = 0 ≈ 0.00%");
+    }
+
+    #[test]
+    fn visitor_tests() {
+        struct Depth {
+            current: usize,
+            max: usize,
+        }
+        impl Visitor for Depth {
+            fn enter(&mut self, _node: &DiceBuilder) -> bool {
+                self.current += 1;
+                self.max = self.max.max(self.current);
+                true
+            }
+            fn leave(&mut self, _node: &DiceBuilder) {
+                self.current -= 1;
+            }
+        }
+        let builder = DiceBuilder::from_string("X := d6; max(X, 3) + d4").unwrap();
+        let mut depth = Depth { current: 0, max: 0 };
+        builder.accept(&mut depth);
+        assert_eq!(depth.max, 4);
+        let mut count = 0;
+        builder.walk(&mut |_| count += 1);
+        assert_eq!(count, 7);
+        // constants are doubled, everything else is left alone
+        let doubled = builder.clone().map_nodes(&mut |node| match node {
+            DiceBuilder::Constant(c) => DiceBuilder::Constant(c * 2),
+            other => other,
+        });
+        assert_eq!(doubled.build().min, 7);
+        let unchanged: Result<DiceBuilder, ()> = builder.clone().try_rewrite(&mut Ok);
+        assert_eq!(unchanged, Ok(builder));
+    }
+
+    #[test]
+    fn simplify_tests() {
+        let cases = [
+            ("2*3+d6", "6+d6"),
+            ("d6+d4+d6+2+3", "2xd6+d4+5"),
+            ("(d4+d6)+(d8+0)", "d4+d6+d8"),
+            ("max(max(d6,3),5)*1", "max(d6,5)"),
+            ("d20/1/1", "d20"),
+            ("12/4/d3", "3/d3"),
+            ("d6^2^1", "d6^2"),
+            ("1xd6+2x3", "d6+6"),
+            ("-(-d6)", "d6"),
+            ("abs(-3)+d4", "3+d4"),
+        ];
+        for (input, expected) in cases {
+            let builder = DiceBuilder::from_string(input).unwrap();
+            let simplified = builder.clone().simplify();
+            assert_eq!(simplified.to_string(), expected, "{input}");
+            assert_eq!(
+                simplified.build().distribution,
+                builder.build().distribution,
+                "{input}"
+            );
+        }
+        // degenerate parts still fail after simplification
+        let empty = DiceBuilder::SumCompound(vec![
+            DiceBuilder::SumCompound(vec![]),
+            DiceBuilder::Constant(0),
+        ]);
+        assert_eq!(
+            empty.simplify().try_build(),
+            Err(DiceBuildError::EmptyCompound)
+        );
+        let negative = DiceBuilder::from_string("2^(0-1)").unwrap().simplify();
+        assert!(matches!(
+            negative.try_build(),
+            Err(DiceBuildError::NegativeExponent(_))
+        ));
+        let options = BuildOptions {
+            simplify: true,
+            ..Default::default()
+        };
+        let dice = DiceBuilder::from_string("d6+d6+0")
+            .unwrap()
+            .build_with_options(&options)
+            .unwrap();
+        assert_eq!(dice.builder_string, "d6+d6+0");
+        assert_eq!(
+            dice.distribution,
+            Dice::build_from_string("2d6").unwrap().distribution
+        );
+    }
+
+    #[test]
+    fn canonicalize_and_same_distribution_tests() {
+        let canonical = |s: &str| DiceBuilder::from_string(s).unwrap().canonicalize();
+        assert_eq!(canonical("d4+2*d6"), canonical("d6*2+d4"));
+        assert_eq!(canonical("max(d8,d6+d6)"), canonical("max(2d6,d8)"));
+        assert_eq!(canonical("3+d4-1"), canonical("d4+2"));
+        assert_ne!(canonical("d6/2"), canonical("2/d6"));
+        assert_eq!(
+            canonical("d6+d4").to_string(),
+            canonical("d4+d6").to_string()
+        );
+
+        let dice = |s: &str| Dice::build_from_string(s).unwrap();
+        assert!(dice("2d6").same_distribution(&dice("d6+d6")));
+        assert!(dice("d6").same_distribution(&dice("7-d6")));
+        assert!(!dice("d6").same_distribution(&dice("d[0..5]")));
+        assert!(!dice("2d6").same_distribution(&dice("d6*2")));
+        let approx = DiceBuilder::from_string("2d6").unwrap().build_approx();
+        assert!(approx.same_distribution(&approx));
+    }
+
+    #[test]
+    fn mixture_tests() {
+        let attack = Dice::build_from_string("mix(1: 2d6, 1: d12)").unwrap();
+        assert_eq!(attack.builder_string, "mix(1:2xd6,1:d12)");
+        // 1 only comes from the d12, 7 from both with 1/2 * 1/6 + 1/2 * 1/12
+        assert_eq!(attack.prob(1), Prob::new(1u64, 24u64));
+        assert_eq!(attack.prob(7), Prob::new(1u64, 8u64));
+        assert_eq!(attack.mean, Prob::new(27u64, 4u64));
+        let weighted = Dice::build_from_string("mix(3: 1, 1: 2, 0: d0)").unwrap();
+        assert_eq!(
+            weighted.distribution,
+            vec![(1, Prob::new(3u64, 4u64)), (2, Prob::new(1u64, 4u64))]
+        );
+        let reparsed = DiceBuilder::from_string(&attack.builder_string).unwrap();
+        assert_eq!(reparsed.to_string(), attack.builder_string);
+        assert_eq!(
+            DiceBuilder::try_build_from_string("mix(0: d6, 0: d8)"),
+            Err(DiceBuildError::ZeroMixtureWeight)
+        );
+        assert_eq!(
+            DiceBuilder::Mixture(vec![]).try_build(),
+            Err(DiceBuildError::EmptyCompound)
+        );
+        let err = DiceBuilder::from_string("mix(d6, d8)").unwrap_err();
+        assert_eq!(err.kind, DiceBuildingErrorKind::InvalidMixtureComponent);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let builder = DiceBuilder::from_string("mix(1: 10+d4, 1: 20)").unwrap();
+        for _ in 0..20 {
+            let report = builder.roll_detailed(&mut rng).unwrap();
+            assert!((11..=14).contains(&report.total) || report.total == 20);
+            assert!(report.to_string().starts_with("mix(1: "));
+        }
+    }
+
+    #[test]
+    fn value_count_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let sixes = d6.count_of(6, 10);
+        assert_eq!(sixes.distribution.len(), 11);
+        assert_eq!(sixes.prob(0), Prob::new(5u64.pow(10), 6u64.pow(10)));
+        assert_eq!(sixes.variance, Prob::new(50u64, 36u64));
+        // a value that can never be rolled is never counted
+        let sevens = d6.count_of(7, 10);
+        assert_eq!(sevens.distribution, vec![(0, Prob::one())]);
+        let ones = Dice::build_from_string("1").unwrap().count_of(1, 4);
+        assert_eq!(ones.distribution, vec![(4, Prob::one())]);
+        assert_eq!(d6.count_of(6, 0).distribution, vec![(0, Prob::one())]);
+
+        let two_d6 = Dice::build_from_string("2d6").unwrap();
+        let counts = two_d6.value_counts(5);
+        assert_eq!(counts.len(), 11);
+        let mut expected_total = Prob::zero();
+        for (_, count) in counts.iter() {
+            expected_total += count.mean.clone();
+        }
+        assert_eq!(expected_total, Prob::from(5));
+
+        let approx = DiceBuilder::from_string("d6").unwrap().build_approx();
+        assert!((approx.count_where(6, |v| v >= 5).mean - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rolls_until_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let until_six = d6.rolls_until(|v| v == 6, 50);
+        assert_eq!(until_six.prob_success, Prob::new(1u64, 6u64));
+        assert_eq!(until_six.distribution.len(), 50);
+        assert_eq!(until_six.distribution[0], (1, Prob::new(1u64, 6u64)));
+        let mut total = until_six.prob_beyond_cap.clone();
+        for (_, p) in until_six.distribution.iter() {
+            total += p;
+        }
+        assert_eq!(total, Prob::one());
+        assert_eq!(until_six.expected_rolls, Some(Prob::from(6)));
+
+        let never = d6.rolls_until(|v| v > 6, 5);
+        assert_eq!(never.expected_rolls, None);
+        assert_eq!(never.prob_beyond_cap, Prob::one());
+        let always = d6.rolls_until(|v| v >= 1, 5);
+        assert_eq!(always.prob_beyond_cap, Prob::zero());
+        assert_eq!(always.distribution[0], (1, Prob::one()));
+        assert!(d6.rolls_until(|v| v == 6, 0).distribution.is_empty());
+    }
+
+    #[test]
+    fn turns_to_reach_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let turns = d6.turns_to_reach(10, 10);
+        assert_eq!(turns.distribution.first().unwrap().0, 2);
+        assert_eq!(turns.distribution.last().unwrap().0, 10);
+        let mut total = Prob::zero();
+        for (_, p) in turns.distribution.iter() {
+            total += p;
+        }
+        assert_eq!(total, Prob::one());
+        // P(2 turns) = P(2d6 >= 10)
+        assert_eq!(
+            turns.distribution[0].1,
+            Dice::build_from_string("2d6").unwrap().prob_gte(10)
+        );
+
+        let capped = d6.turns_to_reach(10, 2);
+        assert_eq!(capped.distribution, vec![(2, Prob::new(1u64, 6u64))]);
+        assert_eq!(capped.prob_beyond_cap, Prob::new(5u64, 6u64));
+        assert_eq!(capped.expected_turns, None);
+
+        // misses deal no damage
+        let attack = Dice::build_from_string("mix(1: 0, 1: 5)").unwrap();
+        let turns = attack.turns_to_reach(5, 3);
+        assert_eq!(turns.prob_beyond_cap, Prob::new(1u64, 8u64));
+        assert_eq!(d6.turns_to_reach(0, 5).distribution, vec![(0, Prob::one())]);
+
+        // totals beyond the bounds of a value neither wrap around nor panic
+        let half = Prob::new(1u64, 2u64);
+        let huge: Dice = Dice::from_counts(vec![(i64::MAX - 1, 1), (i64::MAX, 1)]).unwrap();
+        let turns = huge.turns_to_reach(i64::MAX, 3);
+        assert_eq!(
+            turns.distribution,
+            vec![(1, half.clone()), (2, half.clone())]
+        );
+        let swinging: Dice = Dice::from_counts(vec![(-i64::MAX, 1), (i64::MAX, 1)]).unwrap();
+        let turns = swinging.turns_to_reach(i64::MAX, 2);
+        assert_eq!(turns.distribution, vec![(1, half.clone())]);
+        assert_eq!(turns.prob_beyond_cap, half);
+    }
+
+    #[test]
+    fn iterate_tests() {
+        let d6 = DiceBuilder::FairDie { min: 1, max: 6 };
+        let walk =
+            |steps| Dice::iterate(0, |field| DiceBuilder::Constant(field) + d6.clone(), steps);
+        assert_eq!(walk(0).unwrap().distribution, vec![(0, Prob::one())]);
+        assert_eq!(
+            walk(3).unwrap().distribution,
+            Dice::build_from_string("3d6").unwrap().distribution
+        );
+        // a coin that is flipped again only while it shows 0
+        let absorbing = Dice::iterate(
+            0,
+            |state| match state {
+                0 => DiceBuilder::FairDie { min: 0, max: 1 },
+                _ => DiceBuilder::Constant(state),
+            },
+            3,
+        )
+        .unwrap();
+        assert_eq!(absorbing.prob(0), Prob::new(1u64, 8u64));
+        assert_eq!(
+            Dice::iterate(1, |_| DiceBuilder::FairDie { min: 1, max: 0 }, 1),
+            Err(DiceBuildError::ZeroSidedDie)
+        );
+    }
+
+    #[test]
+    fn attack_tests() {
+        let damage = || DiceBuilder::from_string("d6").unwrap();
+        let crit_damage = || DiceBuilder::from_string("2d6").unwrap();
+        // only natural 20s hit against an impossible armor class, and they crit
+        let hopeless = DiceBuilder::attack(0, 40, damage(), 20, crit_damage()).build();
+        assert_eq!(hopeless.prob(0), Prob::new(19u64, 20u64));
+        assert_eq!(hopeless.prob_gte(2), Prob::new(1u64, 20u64));
+        // natural 1s miss even against a trivial armor class
+        let certain = DiceBuilder::attack(10, 5, damage(), 19, crit_damage()).build();
+        assert_eq!(certain.prob(0), Prob::new(1u64, 20u64));
+        let expected_mean = Prob::new(17u64, 20u64) * Prob::new(7u64, 2u64)
+            + Prob::new(2u64, 20u64) * Prob::from(7);
+        assert_eq!(certain.mean, expected_mean);
+        // without a crit range a natural 20 is a normal hit
+        let no_crits = DiceBuilder::attack(0, 40, damage(), 21, crit_damage());
+        assert_eq!(no_crits.to_string(), "mix(19:0,1:d6)");
+    }
+
+    #[test]
+    fn dnd5e_preset_tests() {
+        let d20 = || DiceBuilder::FairDie { min: 1, max: 20 };
+        let with_advantage = dnd5e::advantage(d20()).build();
+        assert_eq!(with_advantage.prob(20), Prob::new(39u64, 400u64));
+        let with_disadvantage = dnd5e::disadvantage(d20()).build();
+        assert_eq!(with_disadvantage.prob(1), Prob::new(39u64, 400u64));
+        let elven = dnd5e::elven_accuracy(d20()).build();
+        assert_eq!(elven.prob_lte(10), Prob::new(1u64, 8u64));
+
+        let lucky = dnd5e::halfling_luck().build();
+        assert_eq!(lucky.prob(1), Prob::new(1u64, 400u64));
+        assert_eq!(lucky.prob(20), Prob::new(21u64, 400u64));
+        assert_eq!(
+            dnd5e::reroll_once(6, 0).build().distribution,
+            Dice::build_from_string("d6").unwrap().distribution
+        );
+        let gwf = dnd5e::great_weapon_fighting(2, 6).build();
+        assert_eq!(gwf.mean, Prob::new(25u64, 3u64));
+
+        // a save that always succeeds halves every damage roll
+        let halved = dnd5e::save_for_half(30, 10, DiceBuilder::from_string("d4").unwrap()).build();
+        assert_eq!(
+            halved.distribution,
+            vec![
+                (0, Prob::new(1u64, 4u64)),
+                (1, Prob::new(1u64, 2u64)),
+                (2, Prob::new(1u64, 4u64))
+            ]
+        );
+        let failed = dnd5e::save_for_half(-30, 10, DiceBuilder::from_string("d4").unwrap());
+        assert_eq!(failed.to_string(), "mix(20:d4)");
+
+        let rogue = dnd5e::sneak_attack(5, 15, 1, 6, 3, 2);
+        assert_eq!(rogue.to_string(), "mix(9:0,10:3xd6+3,1:6xd6+3)");
+    }
+
+    #[test]
+    fn savage_worlds_preset_tests() {
+        let d6 = savage_worlds::exploding(6, 2).build();
+        assert_eq!((d6.min, d6.max), (1, 18));
+        assert_eq!(d6.prob(6), Prob::from(0));
+        assert_eq!(d6.prob(18), Prob::new(1u64, 216u64));
+        assert_eq!(d6.prob(7), Prob::new(1u64, 36u64));
+
+        // explosions on 5 or more, a 5 is never the final total
+        let d6 = DiceBuilder::Explode {
+            dice_builder: Box::new(DiceBuilder::FairDie { min: 1, max: 6 }),
+            min_value: Some(5),
+            max_iterations: 1,
+            kind: ExplodeKind::Standard,
+        }
+        .build();
+        assert_eq!(d6.prob(5), Prob::from(0));
+        assert_eq!(d6.prob(6), Prob::new(1u64, 36u64));
+        assert_eq!(d6.prob(7), Prob::new(2u64, 36u64));
+
+        let roll = savage_worlds::trait_roll(4, 0, 10);
+        assert_eq!(roll.to_string(), "max(d4!,d6!)");
+        let raises = savage_worlds::raises(&roll.build(), 4);
+        assert_eq!(raises.prob(-1), Prob::new(3u64, 8u64));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let report = savage_worlds::extra_roll(4, 1, 3)
+                .roll_detailed(&mut rng)
+                .unwrap();
+            assert!((2..=17).contains(&report.total));
+        }
+    }
+
+    #[test]
+    fn shadowrun_preset_tests() {
+        let pool = shadowrun::hits_and_ones(3, 6, 5);
+        assert_eq!(pool.dimensions(), 2);
+        assert_eq!(
+            pool.marginal(0).distribution,
+            shadowrun::hits(3).build().distribution
+        );
+        assert_eq!(pool.prob_of(|v| v[0] + v[1] > 3), Prob::from(0));
+        assert_eq!(pool.prob_of(|v| v == [0, 3]), Prob::new(1u64, 216u64));
+        // hits and 1s exclude each other on a single die, so they are negatively correlated
+        assert!(pool.covariance(0, 1) < Prob::from(0));
+
+        assert_eq!(shadowrun::glitch_prob(2), Prob::new(1u64, 36u64));
+        assert_eq!(shadowrun::critical_glitch_prob(2), Prob::new(1u64, 36u64));
+        assert_eq!(shadowrun::glitch_prob(0), Prob::from(0));
+        assert!(shadowrun::critical_glitch_prob(12) < shadowrun::glitch_prob(12));
+
+        // a d1 is always a 1 and also a hit if every result hits
+        let pool = shadowrun::hits_and_ones(2, 1, 1);
+        assert_eq!(pool.distribution, vec![(vec![2, 2], Prob::from(1))]);
+    }
+
+    #[test]
+    fn blades_in_the_dark_preset_tests() {
+        for dice in 0..=8 {
+            let roll = blades_in_the_dark::action_roll(dice);
+            let total = roll.critical.clone()
+                + roll.success.clone()
+                + roll.partial.clone()
+                + roll.failure.clone();
+            assert_eq!(total, Prob::from(1));
+        }
+        let roll = blades_in_the_dark::action_roll(0);
+        assert_eq!(roll.critical, Prob::from(0));
+        assert_eq!(roll.success, Prob::new(1u64, 36u64));
+        assert_eq!(roll.failure, Prob::new(3u64, 4u64));
+        let roll = blades_in_the_dark::action_roll(1);
+        assert_eq!(roll.critical, Prob::from(0));
+        assert_eq!(roll.partial, Prob::new(1u64, 3u64));
+        // the highest of a pool of d6 reads the same as the exact calculation of max
+        let highest = Dice::build_from_string("max(d6,d6,d6)").unwrap();
+        let roll = blades_in_the_dark::action_roll(3);
+        assert_eq!(roll.critical + roll.success, highest.prob(6));
+        assert_eq!(roll.failure, highest.prob_lte(3));
+    }
+
+    #[test]
+    fn outcome_dice_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let parity = d6.classify(|v| v % 2 == 0);
+        assert_eq!(
+            parity.outcomes,
+            vec![
+                (false, Prob::new(1u64, 2u64)),
+                (true, Prob::new(1u64, 2u64))
+            ]
+        );
+        assert_eq!(parity.prob(&true), Prob::new(1u64, 2u64));
+
+        let roll = d6.classify_by(&[("crit", &|v| v > 6), ("hit", &|v| v >= 3)], "miss");
+        assert_eq!(
+            roll.outcomes,
+            vec![
+                ("crit", Prob::from(0)),
+                ("hit", Prob::new(2u64, 3u64)),
+                ("miss", Prob::new(1u64, 3u64))
+            ]
+        );
+
+        let both = roll.product(&parity);
+        assert_eq!(both.outcomes.len(), 6);
+        assert_eq!(both.prob(&("hit", true)), Prob::new(1u64, 3u64));
+        let merged = both.map(|(label, _)| *label);
+        assert_eq!(merged.outcomes, roll.outcomes);
+        assert_eq!(both.prob(&("fumble", true)), Prob::from(0));
+    }
+
+    #[test]
+    fn reroll_tests() {
+        let keep_new = DiceBuilder::from_string("d20r<=2").unwrap();
+        assert_eq!(
+            keep_new,
+            DiceBuilder::Reroll {
+                dice_builder: Box::new(DiceBuilder::FairDie { min: 1, max: 20 }),
+                max_value: 2,
+                keep_best: false,
+            }
+        );
+        let keep_new = keep_new.build();
+        assert_eq!(keep_new.prob(2), Prob::new(2u64, 400u64));
+        assert_eq!(keep_new.prob(3), Prob::new(22u64, 400u64));
+        let keep_best = Dice::build_from_string("d20rb<=2").unwrap();
+        assert_eq!(keep_best.builder_string, "d20rb<=2");
+        assert_eq!(keep_best.prob(2), Prob::new(3u64, 400u64));
+        assert_eq!(keep_best.prob(3), Prob::new(22u64, 400u64));
+        assert!(keep_best.mean > keep_new.mean);
+        // the reroll of halfling luck matches the preset
+        assert_eq!(
+            Dice::build_from_string("d20r<=1").unwrap().distribution,
+            dnd5e::halfling_luck().build().distribution
+        );
+
+        // modifiers bind stronger than all operators
+        let builder = DiceBuilder::from_string("2d6r<=2+(d4+1)rb<=3").unwrap();
+        assert_eq!(builder.to_string(), "2xd6r<=2+(d4+1)rb<=3");
+        assert_eq!(DiceBuilder::from_string(&builder.to_string()), Ok(builder));
+        // thresholds can be negative, a minus further behind is a subtraction
+        let builder = DiceBuilder::from_string("(d6-4)r<=-1-1").unwrap();
+        assert_eq!(builder.to_string(), "(d6-4)r<=-1-1");
+        assert_eq!(DiceBuilder::from_string(&builder.to_string()), Ok(builder));
+        let builder = DiceBuilder::from_string("d6r<=1").unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..20 {
+            let report = builder.roll_detailed(&mut rng).unwrap();
+            assert_eq!(
+                report.trace.rolls.len(),
+                1 + (report.trace.rolls[0] == 1) as usize
+            );
+        }
+    }
+
+    #[test]
+    fn explode_kind_tests() {
+        let standard = Dice::build_from_string("d6!").unwrap();
+        let compounding = Dice::build_from_string("d6!!").unwrap();
+        let penetrating = Dice::build_from_string("d6!p").unwrap();
+        assert_eq!(standard.max, 66);
+        // a single die adds up all of its rolls either way
+        assert_eq!(standard.distribution, compounding.distribution);
+        assert_eq!(penetrating.max, 56);
+        assert_eq!(penetrating.prob(5), Prob::new(1u64, 6u64));
+        assert_eq!(penetrating.prob(6), Prob::new(1u64, 36u64));
+        assert_eq!(penetrating.prob(7), Prob::new(1u64, 36u64));
+        assert!(penetrating.mean < standard.mean);
+
+        let builder = DiceBuilder::from_string("2d10!>=9+d6!!+d4!p").unwrap();
+        assert_eq!(builder.to_string(), "2xd10!>=9+d6!!+d4!p");
+        assert_eq!(DiceBuilder::from_string(&builder.to_string()), Ok(builder));
+        let builder = DiceBuilder::from_string("d[-3..3]!>=-2").unwrap();
+        assert_eq!(builder.to_string(), "d[-3..3]!>=-2");
+        assert_eq!(DiceBuilder::from_string(&builder.to_string()), Ok(builder));
+        let capped = DiceBuilder::Explode {
+            dice_builder: Box::new(DiceBuilder::FairDie { min: 1, max: 6 }),
+            min_value: None,
+            max_iterations: 2,
+            kind: ExplodeKind::Penetrating,
+        };
+        assert_eq!(capped.to_string(), "explode(d6,None,2,Penetrating)");
+        assert_eq!(capped.build().max, 16);
+
+        // in keep-highest pools only the rolls of a compounding die count as a single value
+        let pool = |kind: ExplodeKind| DiceBuilder::NthHighest {
+            n: 3,
+            k: 2,
+            dice_builder: Box::new(DiceBuilder::Explode {
+                dice_builder: Box::new(DiceBuilder::FairDie { min: 1, max: 3 }),
+                min_value: None,
+                max_iterations: 2,
+                kind,
+            }),
+        };
+        let fractions = |pairs: &[(i64, u64, u64)]| -> Vec<(i64, Prob)> {
+            pairs
+                .iter()
+                .map(|(v, numer, denom)| (*v, Prob::new(*numer, *denom)))
+                .collect()
+        };
+        assert_eq!(
+            pool(ExplodeKind::Standard).build().distribution,
+            fractions(&[(1, 5, 27), (2, 11, 27), (3, 11, 27)])
+        );
+        assert_eq!(
+            pool(ExplodeKind::Penetrating).build().distribution,
+            fractions(&[(1, 2, 9), (2, 14, 27), (3, 7, 27)])
+        );
+        assert_eq!(
+            pool(ExplodeKind::Compounding).build().distribution,
+            fractions(&[
+                (1, 7, 27),
+                (2, 13, 27),
+                (4, 97, 729),
+                (5, 67, 729),
+                (7, 367, 19683),
+                (8, 229, 19683),
+                (9, 79, 19683)
+            ])
+        );
+        let standard = Dice::build_from_string("nth_highest(4,3,d6!)").unwrap();
+        let compounding = Dice::build_from_string("nth_highest(4,3,d6!!)").unwrap();
+        assert_eq!(standard.max, 6);
+        assert!(compounding.max > 6);
+        assert_ne!(standard.distribution, compounding.distribution);
+        // rolling the pool agrees with the calculation
+        let simulated = DiceBuilder::from_string("nth_highest(4,3,d6!)")
+            .unwrap()
+            .simulate(20000, &mut StdRng::seed_from_u64(5))
+            .unwrap();
+        assert_eq!(simulated.max, 6);
+        assert!((simulated.mean - standard.mean.to_f64().unwrap()).abs() < 0.05);
+        let report = DiceBuilder::from_string("nth_highest(2,1,d2!)")
+            .unwrap()
+            .roll_detailed(&mut StdRng::seed_from_u64(3))
+            .unwrap();
+        assert!(report.trace.rolls.len() >= 2);
+        assert!(report.trace.rolls.iter().all(|r| (1..=2).contains(r)));
+        assert_eq!(report.total, *report.trace.rolls.iter().max().unwrap());
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let compounding = DiceBuilder::from_string("d2!!").unwrap();
+        let penetrating = DiceBuilder::from_string("d2!p").unwrap();
+        for _ in 0..20 {
+            let report = compounding.roll_detailed(&mut rng).unwrap();
+            assert_eq!(report.trace.rolls, vec![report.total]);
+            let report = penetrating.roll_detailed(&mut rng).unwrap();
+            let rolls = &report.trace.rolls;
+            assert_eq!(
+                report.total,
+                rolls.iter().sum::<i64>() - (rolls.len() as i64 - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn brutal_tests() {
+        let brutal = DiceBuilder::from_string("2d10b2").unwrap();
+        assert_eq!(brutal.to_string(), "2xmax(d10,2)");
+        let brutal = brutal.build();
+        assert_eq!(brutal.min, 4);
+        assert_eq!(brutal.prob(4), Prob::new(4u64, 100u64));
+        // the minimum applies to every die, not to the sum
+        let clamped_sum = Dice::build_from_string("max(2d10,2)").unwrap();
+        assert_ne!(brutal.distribution, clamped_sum.distribution);
+        // inside pools and together with other modifiers
+        let highest = DiceBuilder::from_string("nth_highest(2,1,d6b3!)").unwrap();
+        assert_eq!(highest.to_string(), "nth_highest(2,1,max(d6,3)!)");
+        assert_eq!(highest.build().min, 3);
+        // a 1 is rerolled once, and a second 1 counts as a 2
+        let rerolled = DiceBuilder::from_string("d4r<=1b2").unwrap().build();
+        assert_eq!(rerolled.prob(2), Prob::new(6u64, 16u64));
+    }
+
+    #[test]
+    fn normal_approximation_tests() {
+        let options = BuildOptions {
+            normal_approximation_min_count: Some(100),
+            ..Default::default()
+        };
+        let build = |s: &str| {
+            DiceBuilder::from_string(s)
+                .unwrap()
+                .build_as::<f64>(&options)
+                .unwrap()
+        };
+        let dice = build("1000d6");
+        assert!(dice.build_report.approximate);
+        // values with a negligible probability far from the mean are left out
+        assert_eq!(dice.min + dice.max, 7000);
+        assert!(dice.min > 1000 && dice.max < 6000);
+        assert!((dice.mean - 3500.0).abs() < 1e-6);
+        assert!((dice.variance / (1000.0 * 35.0 / 12.0) - 1.0).abs() < 1e-3);
+        // only even sums are possible
+        let even = build("200x(2*d2)");
+        assert!(even.distribution.iter().all(|(v, _)| v % 2 == 0));
+        assert!((even.mean - 600.0).abs() < 1e-6);
+        // smaller sums and sums of constants stay exact
+        let exact = build("99d6");
+        assert!(!exact.build_report.approximate);
+        assert_eq!(exact.max, 594);
+        assert_eq!(build("1000x3").distribution, vec![(3000, 1.0)]);
+        // the approximation is opt-in
+        let exact = DiceBuilder::from_string("1000d6").unwrap().build_approx();
+        assert!(!exact.build_report.approximate);
+        assert_eq!(exact.max, 6000);
+        // the kept sums are checked against the limits before they are calculated
+        let limited = |limits: BuildOptions| {
+            DiceBuilder::from_string("1000d1000000")
+                .unwrap()
+                .build_as::<f64>(&BuildOptions {
+                    normal_approximation_min_count: Some(100),
+                    ..limits
+                })
+        };
+        assert_eq!(
+            limited(BuildOptions {
+                max_outcomes: Some(1_000_000),
+                ..Default::default()
+            })
+            .err(),
+            Some(DiceBuildError::TooManyOutcomes { limit: 1_000_000 })
+        );
+        assert_eq!(
+            limited(BuildOptions {
+                max_total_work: Some(10_000_000),
+                ..Default::default()
+            })
+            .err(),
+            Some(DiceBuildError::TooMuchWork { limit: 10_000_000 })
+        );
+    }
+
+    #[test]
+    fn kronecker_sum_convolution_tests() {
+        use crate::convolution::kronecker_sum_convolute;
+        let first: Vec<Prob> = (0..20u64).map(|i| Prob::new(i * i % 7, i + 3)).collect();
+        let second: Vec<Prob> = (0..9u64)
+            .map(|i| Prob::new(1u64, 2u64.pow(i as u32)))
+            .collect();
+        let mut naive = vec![Prob::zero(); first.len() + second.len() - 1];
+        for (i, p1) in first.iter().enumerate() {
+            for (j, p2) in second.iter().enumerate() {
+                naive[i + j] += p1.clone() * p2.clone();
+            }
+        }
+        assert_eq!(kronecker_sum_convolute(&first, &second), Some(naive));
+        let negative = vec![Prob::new(1u64, 2u64), Prob::new_neg(1u64, 2u64)];
+        assert_eq!(kronecker_sum_convolute(&negative, &second), None);
+
+        let dice = Dice::build_from_string("d10xd100").unwrap();
+        assert_eq!(dice.mean, Prob::new(1111u64, 4u64));
+        let denom = fraction::BigUint::from(10u32) * fraction::BigUint::from(100u32).pow(10);
+        assert_eq!(dice.prob(1000), Prob::new(1u32, denom));
+        let total: Prob = dice.distribution.iter().map(|(_, p)| p.clone()).sum();
+        assert_eq!(total, Prob::one());
+    }
+
+    #[test]
+    fn shared_denominator_convolution_tests() {
+        use crate::convolution::shared_denominator_convolute;
+        let naive = |first: &[(Value, Prob)], second: &[(Value, Prob)]| {
+            let mut m: std::collections::BTreeMap<Value, Prob> = Default::default();
+            for (v1, p1) in first {
+                for (v2, p2) in second {
+                    *m.entry(v1 * v2).or_insert_with(Prob::zero) += p1.clone() * p2.clone();
+                }
+            }
+            m.into_iter().collect::<Vec<_>>()
+        };
+        let first = Dice::build_from_string("3d6").unwrap().distribution;
+        let second = Dice::build_from_string("d4-2").unwrap().distribution;
+        let fast = shared_denominator_convolute(
+            first.iter().map(|(v, p)| (v, p)),
+            second.iter().map(|(v, p)| (v, p)),
+            |a, b| a.checked_mul(b),
+        );
+        assert_eq!(fast, Some(naive(&first, &second)));
+        // 1/4 and 1/6 share no denominator, 1/2 and -1/2 are no probabilities
+        for other in [
+            vec![(1, Prob::new(1u64, 4u64)), (2, Prob::new(1u64, 6u64))],
+            vec![(1, Prob::new(1u64, 2u64)), (2, Prob::new_neg(1u64, 2u64))],
+        ] {
+            let fast = shared_denominator_convolute(
+                first.iter().map(|(v, p)| (v, p)),
+                other.iter().map(|(v, p)| (v, p)),
+                |a, b| a.checked_mul(b),
+            );
+            assert_eq!(fast, None);
+        }
+        let overflow = shared_denominator_convolute(
+            first.iter().map(|(v, p)| (v, p)),
+            second.iter().map(|(v, p)| (v, p)),
+            |_, _| None,
+        );
+        assert_eq!(overflow, None);
+
+        let dice = Dice::build_from_string("d20*d20").unwrap();
+        assert_eq!(dice.prob(12), Prob::new(6u64, 400u64));
+        assert_eq!(
+            Dice::build_from_string("d6*mix(1: d4, 1: d6)")
+                .unwrap()
+                .mean,
+            Prob::new(21u64, 2u64)
+        );
+    }
+
+    #[test]
+    fn convolve_tests() {
+        use crate::convolve::{convolve_sum, convolve_with, sample_sum};
+        let build = |s: &str| Dice::build_from_string(s).unwrap().distribution;
+        let (d6, d10) = (build("d6"), build("d10*2"));
+        assert_eq!(convolve_sum(&d6, &d10).unwrap(), build("d6+d10*2"));
+        let product = convolve_with(&d6, &d10, |a, b| {
+            DiceOperation::Multiply.checked_apply(a, b)
+        });
+        assert_eq!(product.unwrap(), build("d6*(d10*2)"));
+        assert_eq!(sample_sum(&d10, 3).unwrap(), build("3x(d10*2)"));
+        assert_eq!(sample_sum(&d6, 0).unwrap(), vec![(0, Prob::one())]);
+        // unsorted distributions with duplicate values are merged first
+        let unsorted = vec![
+            (2, Prob::new(1u64, 4u64)),
+            (1, Prob::new(1u64, 2u64)),
+            (2, Prob::new(1u64, 4u64)),
+        ];
+        assert_eq!(convolve_sum(&unsorted, &d6).unwrap(), build("d2+d6"));
+        let f64_sum = convolve_sum(&[(1, 0.5), (2, 0.5)], &[(0, 0.25), (10, 0.75)]).unwrap();
+        assert_eq!(
+            f64_sum,
+            vec![(1, 0.125), (2, 0.125), (11, 0.375), (12, 0.375)]
+        );
+
+        assert_eq!(
+            convolve_sum(&d6, &[]),
+            Err(DiceBuildError::EmptyDistribution)
+        );
+        let huge = vec![(Value::MAX, Prob::one())];
+        assert_eq!(convolve_sum(&d6, &huge), Err(DiceBuildError::ValueOverflow));
+        assert_eq!(
+            convolve_with(&d6, &huge, |a, b| a.checked_mul(b)),
+            Err(DiceBuildError::ValueOverflow)
+        );
+        assert_eq!(sample_sum(&huge, 2), Err(DiceBuildError::ValueOverflow));
+    }
+
+    #[test]
+    fn from_pmf_and_counts_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let weights: Vec<(Value, Prob)> = (1..=6).rev().map(|v| (v, Prob::from(2))).collect();
+        let dice = Dice::from_pmf(weights).unwrap();
+        assert_eq!(dice.distribution, d6.distribution);
+        assert_eq!(dice.mean, d6.mean);
+        assert_eq!(dice.builder_string, "");
+        let counts = Dice::from_counts((1..=6).map(|v| (v, 7)).collect()).unwrap();
+        assert_eq!(counts.distribution, d6.distribution);
+
+        let with_zero = Dice::from_pmf(vec![(0, Prob::zero()), (5, Prob::one())]).unwrap();
+        assert_eq!((with_zero.min, with_zero.distribution.len()), (5, 1));
+        let floats: Dice<f64> = Dice::from_counts(vec![(1, 1), (2, 3)]).unwrap();
+        assert_eq!(floats.prob(2), 0.75);
+
+        assert_eq!(
+            Dice::<Prob>::from_pmf(vec![]),
+            Err(DiceBuildError::EmptyDistribution)
+        );
+        assert_eq!(
+            Dice::<Prob>::from_counts(vec![]),
+            Err(DiceBuildError::EmptyDistribution)
+        );
+        assert_eq!(
+            Dice::from_pmf(vec![(1, Prob::one()), (2, Prob::new_neg(1u64, 2u64))]),
+            Err(DiceBuildError::NegativeProbability(2))
+        );
+        assert_eq!(
+            Dice::<f64>::from_pmf(vec![(3, f64::NAN)]),
+            Err(DiceBuildError::NegativeProbability(3))
+        );
+        assert_eq!(
+            Dice::<Prob>::from_counts(vec![(1, 0)]),
+            Err(DiceBuildError::ZeroTotalProbability)
+        );
+        assert_eq!(
+            Dice::<Prob>::from_counts(vec![(1, u64::MAX), (2, 1)]),
+            Err(DiceBuildError::ValueOverflow)
+        );
+    }
+
+    #[test]
+    fn fit_from_samples_tests() {
+        let samples: Vec<Value> = (0..600).map(|i| i % 6 + 1).collect();
+        let fitted: Dice = Dice::fit_from_samples(&samples);
+        let d6 = Dice::build_from_string("d6").unwrap();
+        assert_eq!(fitted.distribution, d6.distribution);
+        assert!(fitted.same_distribution(&d6));
+        let loaded: Dice = Dice::fit_from_samples(&[1, 1, 1, 4]);
+        assert_eq!(loaded.prob(1), Prob::new(3u64, 4u64));
+        assert_eq!(loaded.mean, Prob::new(7u64, 4u64));
+        assert!(loaded.prob_beats(&d6) < Prob::new(1u64, 2u64));
+        let floats: Dice<f64> = Dice::fit_from_samples(&[2, 4]);
+        assert_eq!(floats.mean, 3.0);
+        assert!(std::panic::catch_unwind(|| Dice::<Prob>::fit_from_samples(&[])).is_err());
+    }
+
+    #[test]
+    fn combined_with_tests() {
+        let build = |s: &str| Dice::build_from_string(s).unwrap();
+        let base = build("3d6+d8");
+        for (op, formula) in [
+            (DiceOperation::Add, "3d6+d8+d4"),
+            (DiceOperation::Subtract, "3d6+d8-d4"),
+            (DiceOperation::Multiply, "(3d6+d8)*d4"),
+            (DiceOperation::Max, "max(3d6+d8,d4)"),
+            (DiceOperation::Min, "min(3d6+d8,d4)"),
+        ] {
+            let combined = base.combined_with(op, &build("d4"));
+            assert_eq!(combined.distribution, build(formula).distribution);
+            assert_eq!(combined.mean, build(formula).mean);
+        }
+        let min = base.combined_with(DiceOperation::Min, &build("d4"));
+        assert_eq!(min.builder_string, "min(3xd6+d8,d4)");
+        let plus = base.plus_formula("2").unwrap();
+        assert_eq!(plus.distribution, base.shifted(2).distribution);
+        assert_eq!(plus.builder_string, "(3xd6+d8)+(2)");
+        assert!(matches!(
+            base.plus_formula("d4+"),
+            Err(DiceBuildError::Parse(_))
+        ));
+        assert_eq!(base.plus_formula("d0"), Err(DiceBuildError::ZeroSidedDie));
+    }
+
+    #[test]
+    fn lru_dice_cache_tests() {
+        let cache = LruDiceCache::new(2);
+        let build = |s: &str| Dice::build_from_string_cached(s, &cache).unwrap();
+        let first = build("d6+d4");
+        assert_eq!(
+            first.distribution,
+            Dice::build_from_string("d6+d4").unwrap().distribution
+        );
+        build("d8");
+        // a hit makes d4+d6 the most recently used formula, so d8 is evicted
+        let hit = build("d4+d6");
+        assert_eq!(hit.builder_string, "d4+d6");
+        assert_eq!(hit.distribution, first.distribution);
+        build("2d6");
+        assert_eq!(cache.len(), 2);
+        assert!(cache
+            .get(&DiceBuilder::from_string("d8").unwrap().to_string())
+            .is_none());
+        let key = DiceBuilder::from_string("d6+d4")
+            .unwrap()
+            .canonicalize()
+            .to_string();
+        assert!(cache.get(&key).is_some());
+        assert_eq!(
+            Dice::build_from_string_cached("d0", &cache),
+            Err(DiceBuildError::ZeroSidedDie)
+        );
+        assert_eq!(cache.len(), 2);
+        let disabled = LruDiceCache::new(0);
+        Dice::build_from_string_cached("d6", &disabled).unwrap();
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn shared_dice_tests() {
+        let dice = Dice::build_from_string("3d6").unwrap();
+        let shared = dice.clone().into_shared();
+        let handle = shared.clone();
+        assert!(shared.ptr_eq(&handle));
+        assert!(!shared.ptr_eq(&SharedDice::new(dice.clone())));
+        let mean = std::thread::spawn(move || handle.mean.clone())
+            .join()
+            .unwrap();
+        assert_eq!(mean, Prob::new(21u64, 2u64));
+        assert_eq!(shared.prob(10), dice.prob(10));
+        assert_eq!(shared.into_inner(), dice);
+    }
+
+    #[test]
+    fn distribution_backend_tests() {
+        use crate::distribution_map::DistributionBackend;
+        use std::collections::{BTreeMap, HashMap};
+        let entries = [(3, 1.0), (-2, 2.0), (7, 3.0), (3, 4.0)];
+        let mut tree: BTreeMap<Value, f64> = BTreeMap::new();
+        let mut hash: HashMap<Value, f64> = HashMap::new();
+        for (v, p) in entries {
+            tree.add_prob(v, p);
+            hash.add_prob(v, p);
+        }
+        assert_eq!((tree.min_value(), tree.max_value()), (Some(-2), Some(7)));
+        assert_eq!((hash.min_value(), hash.max_value()), (Some(-2), Some(7)));
+        let sorted = vec![(-2, 2.0), (3, 5.0), (7, 3.0)];
+        assert_eq!(tree.into_sorted_vec(), sorted);
+        assert_eq!(hash.into_sorted_vec(), sorted);
+        assert_eq!(DistributionMap::<f64>::new().min_value(), None);
+    }
+
+    #[test]
+    fn value_overflow_tests() {
+        let overflow = Some(DiceBuildError::ValueOverflow);
+        assert_eq!(Dice::try_build_from_string("d20^d20^d20").err(), overflow);
+        assert_eq!(
+            Dice::try_build_from_string("3000000000*3000000000*d2").err(),
+            overflow
+        );
+        assert_eq!(
+            Dice::try_build_from_string("9223372036854775807+d2").err(),
+            overflow
+        );
+        assert_eq!(
+            Dice::try_build_from_string("9223372036854775807+max(d2,d4)*2").err(),
+            overflow
+        );
+        let min = || Box::new(DiceBuilder::Constant(Value::MIN));
+        for builder in [
+            DiceBuilder::Absolute(min()),
+            DiceBuilder::Negation(min()),
+            DiceBuilder::DivisionCompound(vec![*min(), DiceBuilder::Constant(-1)]),
+            DiceBuilder::SampleSumCompound(vec![DiceBuilder::Constant(-1), *min()]),
+        ] {
+            assert_eq!(builder.clone().try_build().err(), overflow);
+            let mut rng = StdRng::seed_from_u64(1);
+            assert_eq!(builder.try_roll_once(&mut rng).err(), overflow);
+            assert_eq!(builder.roll_detailed(&mut rng).err(), overflow);
+        }
+        // values close to the limit are still exact
+        let dice = Dice::try_build_from_string("9223372036854775805+d2").unwrap();
+        assert_eq!(dice.max, Value::MAX);
+    }
+
+    #[test]
+    fn dice_value_tests() {
+        let options = BuildOptions::default();
+        let build = |input: &str| DiceBuilder::from_string(input).unwrap();
+        // formulas that fit into i64 give the same distribution with every value type
+        let narrow: Dice<Prob, i32> = build("X:=d4;X*X+nth_highest(3,2,d6)")
+            .build_values_as(&options)
+            .unwrap();
+        let exact = Dice::build_from_string("X:=d4;X*X+nth_highest(3,2,d6)").unwrap();
+        assert_eq!(narrow.mean, exact.mean);
+        assert_eq!(narrow.max, 22);
+        let wide: Dice<Prob, i128> = build("2d6").build_values_as(&options).unwrap();
+        assert_eq!(wide.distribution.len(), 11);
+
+        assert_eq!(
+            build("d6*1000000000")
+                .build_values_as::<Prob, i32>(&options)
+                .err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+
+        // 2^64 and 2^65 do not fit into i64
+        let formula = "d2*2^62*4";
+        assert_eq!(
+            Dice::try_build_from_string(formula).err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+        let wide: Dice<Prob, i128> = build(formula).build_values_as(&options).unwrap();
+        assert_eq!(wide.min, 1i128 << 64);
+        let big: Dice<Prob, BigInt> = build(formula).build_values_as(&options).unwrap();
+        assert_eq!(big.max, BigInt::from(1i128 << 65));
+        assert_eq!(big.mean, Prob::from(3u64) * Prob::from(1u64 << 63));
+        assert_eq!(wide.mean, big.mean);
+
+        let huge: Dice<Prob, BigInt> = build("X:=d100;X^10*X^10+nth_highest(2,1,d2)")
+            .build_values_as(&options)
+            .unwrap();
+        assert_eq!(huge.max, BigInt::from(10).pow(40) + 2);
+        assert_eq!(huge.distribution.len(), 200);
+        assert_eq!(
+            build("d20^d20^d20")
+                .build_values_as::<Prob, i128>(&options)
+                .err(),
+            Some(DiceBuildError::ValueOverflow)
+        );
+        assert!(matches!(
+            build("d6!*2^62*4").build_values_as::<Prob, BigInt>(&options),
+            Err(DiceBuildError::NotImplemented(_))
+        ));
+    }
+
+    #[test]
+    fn quantile_method_tests() {
+        use QuantileMethod::*;
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let medians: Vec<f64> = [Lower, Upper, Midpoint, Interpolated]
+            .into_iter()
+            .map(|method| d6.median_with(method))
+            .collect();
+        assert_eq!(medians, vec![3.0, 4.0, 3.5, 3.0]);
+        assert_eq!(d6.median_with(QuantileMethod::default()), d6.median as f64);
+        // values in between two cdf steps have a single quantile except for interpolation
+        assert_eq!(d6.quantile_with(0.6, Upper), 4.0);
+        assert_eq!(d6.quantile_with(0.6, Midpoint), 4.0);
+        assert!((d6.quantile_with(0.6, Interpolated) - 3.6).abs() < 1e-9);
+        for method in [Lower, Upper, Midpoint, Interpolated] {
+            assert_eq!(d6.quantile_with(0.0, method), 1.0);
+            assert_eq!(d6.quantile_with(1.0, method), 6.0);
+            assert_eq!(d6.quantile_with(1.5, method), 6.0);
+        }
+        assert_eq!(d6.quantile_with(Prob::new(1u64, 3u64), Midpoint), 2.5);
+        let approx = DiceBuilder::from_string("2d6").unwrap().build_approx();
+        assert_eq!(approx.median_with(Midpoint), 7.0);
+    }
+
+    #[test]
+    fn mode_with_prob_tests() {
+        let d = Dice::build_from_string("d4+d6").unwrap();
+        let sixth = Prob::new(1u64, 6u64);
+        assert_eq!(
+            d.mode_with_prob(),
+            vec![(5, sixth.clone()), (6, sixth.clone()), (7, sixth.clone())]
+        );
+        assert_eq!(d.prob_of_mode(), sixth);
+        let approx = DiceBuilder::from_string("3d6").unwrap().build_approx();
+        assert_eq!(approx.mode_with_prob().len(), 2);
+        assert!((approx.prob_of_mode() - 27.0 / 216.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn most_likely_tests() {
+        let d = Dice::build_from_string("d4+d6").unwrap();
+        let values: Vec<Value> = d.most_likely(5).into_iter().map(|(v, _)| v).collect();
+        assert_eq!(values, vec![5, 6, 7, 4, 8]);
+        assert_eq!(d.most_likely(0), vec![]);
+        assert_eq!(d.most_likely(100).len(), d.distribution.len());
+        assert_eq!(d.most_likely(1), vec![(5, d.prob_of_mode())]);
+    }
+
+    #[test]
+    fn lazy_cdf_tests() {
+        let dice = Dice::build_from_string("3d6").unwrap();
+        let untouched = dice.clone();
+        assert!(dice.cumulative_distribution.0.get().is_none());
+        assert_eq!(dice.prob(10), Prob::new(27u64, 216u64));
+        assert!(dice.cumulative_distribution.0.get().is_none());
+        assert_eq!(dice.prob_lte(10), Prob::new(1u64, 2u64));
+        assert!(dice.cumulative_distribution.0.get().is_some());
+        assert_eq!(dice, untouched);
+        let streamed: Vec<(Value, Prob)> = untouched.cdf_iter().collect();
+        assert_eq!(streamed, dice.cumulative_distribution());
+        assert!(untouched.cumulative_distribution.0.get().is_none());
+        assert_eq!(streamed.last().unwrap().1, Prob::one());
+    }
+
+    #[test]
+    fn compact_distribution_tests() {
+        for formula in ["20d6", "d6*2-7", "mix(1: d4, 2: d10*10)"] {
+            let dice = Dice::build_from_string(formula).unwrap();
+            let compact = dice.to_compact().unwrap();
+            assert_eq!(compact.len(), dice.distribution.len());
+            assert_eq!(compact.to_distribution(), dice.distribution);
+            for (v, p) in dice.distribution.iter() {
+                assert_eq!(compact.prob(*v), *p);
+            }
+            let restored = Dice::from_compact(&compact, dice.builder_string.clone()).unwrap();
+            assert_eq!(restored.distribution, dice.distribution);
+            assert_eq!(restored.variance, dice.variance);
+            assert_eq!(restored.builder_string, dice.builder_string);
+            assert!(compact.heap_size() < dice.distribution_heap_size());
+        }
+        let sparse = Dice::build_from_string("d6*2-7")
+            .unwrap()
+            .to_compact()
+            .unwrap();
+        assert_eq!(sparse.prob(-4), Prob::zero());
+        assert_eq!(sparse.prob(Value::MIN), Prob::zero());
+        assert_eq!(sparse.numerator(-5), 1u32.into());
+        let mixture = Dice::build_from_string("mix(1: d4, 2: d10*10)").unwrap();
+        assert_eq!(mixture.to_compact().unwrap().denominator(), &60u32.into());
+
+        let mut negative = Dice::build_from_string("d4").unwrap();
+        negative.distribution[1].1 = Prob::new_neg(1u64, 4u64);
+        assert_eq!(negative.to_compact(), None);
+        assert!(CompactDistribution::from_distribution(&[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn prob_all_tests() {
+        for formula in ["d4", "3d6", "d6*2-7", "mix(1: d4, 2: d10*10)"] {
+            let dice = Dice::build_from_string(formula).unwrap();
+            let approx = DiceBuilder::from_string(formula).unwrap().build_approx();
+            for value in dice.min - 2..=dice.max + 2 {
+                let expected = ProbAll {
+                    lt: dice.prob_lt(value),
+                    lte: dice.prob_lte(value),
+                    eq: dice.prob(value),
+                    gte: dice.prob_gte(value),
+                    gt: dice.prob_gt(value),
+                };
+                assert_eq!(dice.prob_all(value), expected, "{formula} at {value}");
+                let all = approx.prob_all(value);
+                assert_eq!(
+                    (all.lt, all.lte, all.eq, all.gte, all.gt),
+                    (
+                        approx.prob_lt(value),
+                        approx.prob_lte(value),
+                        approx.prob(value),
+                        approx.prob_gte(value),
+                        approx.prob_gt(value)
+                    )
+                );
+            }
+        }
+        // lte is lt plus eq, not the other way around
+        let d4 = Dice::build_from_string("d4").unwrap().prob_all(2);
+        assert_eq!(d4.lt, Prob::new(1u64, 4u64));
+        assert_eq!(d4.eq, Prob::new(1u64, 4u64));
+        assert_eq!(d4.lte, Prob::new(1u64, 2u64));
+        assert_eq!(d4.gte, Prob::new(3u64, 4u64));
+        assert_eq!(d4.gt, Prob::new(1u64, 2u64));
+    }
+
+    #[test]
+    fn quantiles_and_inverse_cdf_tests() {
+        let dice = Dice::build_from_string("mix(1: d4, 2: d10*10)").unwrap();
+        let ps: Vec<f64> = (0..=40).map(|i| i as f64 / 40.0).collect();
+        let expected: Vec<Value> = ps.iter().map(|p| dice.quantile(*p)).collect();
+        assert_eq!(dice.quantiles(&ps), expected);
+        assert_eq!(dice.percentiles(&ps), expected);
+        assert!(dice.quantiles::<f64>(&[]).is_empty());
+
+        let steps: Vec<(Prob, Value)> = dice.inverse_cdf_iter().collect();
+        assert_eq!(steps.len(), dice.distribution.len());
+        assert_eq!(steps.last().unwrap(), &(Prob::one(), 100));
+        let mut previous = Prob::zero();
+        for (c, value) in steps {
+            assert!(c > previous);
+            // every probability of the step up to its cumulative probability maps to its value
+            let mid = (previous.clone() + c.clone()) / Prob::from(2);
+            assert_eq!(dice.quantiles(&[mid, c.clone()]), vec![value, value]);
+            previous = c;
+        }
+
+        let with_zero = Dice::from_sorted_distribution(
+            vec![
+                (1, Prob::new(1u64, 2u64)),
+                (2, Prob::zero()),
+                (3, Prob::new(1u64, 2u64)),
+            ],
+            String::new(),
+        )
+        .unwrap();
+        let values: Vec<Value> = with_zero.inverse_cdf_iter().map(|(_, v)| v).collect();
+        assert_eq!(values, vec![1, 3]);
+        let floats = DiceBuilder::from_string("3d6").unwrap().build_approx();
+        assert_eq!(floats.inverse_cdf_iter().last(), Some((1.0, 18)));
+    }
+
+    #[test]
+    fn quantile_exact_tests() {
+        let dice = Dice::build_from_string("2d6").unwrap();
+        let tiny = Prob::new(1u64, 10u64.pow(19));
+        for (value, c) in dice.cumulative_distribution() {
+            assert_eq!(dice.quantile_exact(c.clone()), *value);
+            assert_eq!(dice.quantile_exact(c.clone() - tiny.clone()), *value);
+            // f64 cannot tell p apart from the cumulative probability
+            if *value < dice.max {
+                assert_eq!(dice.quantile_exact(c.clone() + tiny.clone()), value + 1);
+                assert_eq!(dice.quantile(c.clone() + tiny.clone()), *value);
+            }
+        }
+        assert_eq!(dice.quantile_exact(Prob::zero()), 2);
+        assert_eq!(dice.quantile_exact(Prob::from(2)), 12);
+        let floats = DiceBuilder::from_string("2d6").unwrap().build_approx();
+        assert_eq!(floats.quantile_exact(0.5), floats.quantile(0.5));
+    }
+
+    #[test]
+    fn roll_session_tests() {
+        let mut session = RollSession::new(Dice::build_from_string("d6").unwrap());
+        assert_eq!(
+            (
+                session.count(),
+                session.mean(),
+                session.sd(),
+                session.luck()
+            ),
+            (0, None, None, None)
+        );
+        for roll in [1, 2, 3, 4, 5, 6] {
+            assert!(session.record(roll));
+        }
+        assert!(!session.record(7));
+        assert!(!session.record(0));
+        assert_eq!(session.sum(), 21);
+        assert_eq!(session.mean(), Some(3.5));
+        let sd = session.sd().unwrap();
+        assert!((sd * sd - 35.0 / 12.0).abs() < 1e-12);
+        assert_eq!(
+            session.histogram().values().copied().collect::<Vec<u64>>(),
+            vec![1; 6]
+        );
+        // 6d6 is symmetric around 21
+        assert!((session.luck().unwrap() - 0.5).abs() < 1e-12);
+
+        session.clear();
+        session.record(1);
+        assert_eq!(session.rolls(), &[1]);
+        assert!((session.luck().unwrap() - 1.0 / 12.0).abs() < 1e-12);
+        // the cached luck is replaced by the next roll
+        assert_eq!(session.luck(), session.luck());
+        session.record(6);
+        session.record(6);
+        let three_d6 = Dice::build_from_string("3d6").unwrap();
+        let expected =
+            three_d6.prob_lt(13).to_f64().unwrap() + three_d6.prob(13).to_f64().unwrap() / 2.0;
+        assert!((session.luck().unwrap() - expected).abs() < 1e-12);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut rolled = RollSession::new(DiceBuilder::from_string("2d10").unwrap().build_approx());
+        let rolls: Vec<Value> = (0..50).map(|_| rolled.roll_with_rng(&mut rng)).collect();
+        assert_eq!(rolled.rolls(), rolls.as_slice());
+        assert_eq!(rolled.histogram().values().sum::<u64>(), 50);
+        assert!(rolled.luck().unwrap() > 0.0 && rolled.luck().unwrap() < 1.0);
+    }
+
+    #[test]
+    fn sequence_percentile_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let three_d6 = Dice::build_from_string("3d6").unwrap();
+        let half = Prob::new(1u64, 2u64);
+        assert_eq!(
+            d6.sequence_percentile(&[2, 6, 4]),
+            three_d6.prob_lt(12) + three_d6.prob(12) * half.clone()
+        );
+        assert_eq!(d6.sequence_percentile(&[]), half);
+        assert_eq!(d6.sequence_percentile(&[7]), Prob::one());
+        assert_eq!(d6.sequence_percentile(&[0, 0]), Prob::zero());
+
+        let dice = Dice::build_from_string("d10*2-d3").unwrap();
+        for count in [0, 1, 2, 5, 8] {
+            assert_eq!(
+                crate::convolution::sample_sum_sorted(&dice.distribution, count).unwrap(),
+                crate::convolve::sample_sum(&dice.distribution, count).unwrap()
+            );
+        }
+        assert_eq!(
+            crate::convolution::sample_sum_sorted(&dice.distribution, 7).unwrap(),
+            Dice::build_from_string("7x(d10*2-d3)")
+                .unwrap()
+                .distribution
+        );
+
+        let floats = DiceBuilder::from_string("d20").unwrap().build_approx();
+        assert!((floats.sequence_percentile(&[10, 11]) - 0.5).abs() < 1e-12);
+        let big: Dice = Dice::from_counts(vec![(1, 1), (i64::MAX, 1)]).unwrap();
+        assert_eq!(big.checked_sequence_percentile(&[1, 1]), None);
+    }
+
+    #[test]
+    fn value_at_percentile_u_tests() {
+        let d6 = Dice::build_from_string("d6").unwrap();
+        let sixths: Vec<Value> = (0..6)
+            .map(|i| d6.value_at_percentile_u((i as f64 + 0.5) / 6.0))
+            .collect();
+        assert_eq!(sixths, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(d6.value_at_percentile_u(0.0), 1);
+        assert_eq!(d6.value_at_percentile_u(-1.0), 1);
+        assert_eq!(d6.value_at_percentile_u(1.0), 6);
+        assert_eq!(d6.value_at_percentile_u(7.0), 6);
+
+        // the same samples replay the rolls of an rng
+        let dice = Dice::build_from_string("2d6+d4").unwrap();
+        let samples: Vec<f64> = {
+            let mut rng = StdRng::seed_from_u64(11);
+            (0..50).map(|_| rand::Rng::gen(&mut rng)).collect()
+        };
+        let mut rng = StdRng::seed_from_u64(11);
+        let rolls: Vec<Value> = (0..50).map(|_| dice.roll_with_rng(&mut rng)).collect();
+        let replayed: Vec<Value> = samples
+            .iter()
+            .map(|u| dice.value_at_percentile_u(*u))
+            .collect();
+        assert_eq!(rolls, replayed);
+    }
+
+    #[test]
+    #[should_panic]
+    fn value_at_percentile_u_nan_panics() {
+        Dice::build_from_string("d6")
+            .unwrap()
+            .value_at_percentile_u(f64::NAN);
+    }
+
+    #[test]
+    fn nth_highest_tests() {
+        let highest = Dice::build_from_string("nth_highest(2,1,d6)").unwrap();
+        let max = Dice::build_from_string("max(d6,d6)").unwrap();
+        assert_eq!(highest.distribution, max.distribution);
+
+        let lowest = Dice::build_from_string("nth_highest(2,2,d6)").unwrap();
+        let min = Dice::build_from_string("min(d6,d6)").unwrap();
+        assert_eq!(lowest.distribution, min.distribution);
+
+        let middle = Dice::build_from_string("nth_highest(3,2,d6)").unwrap();
+        assert_eq!(middle.mean, Prob::new(7u64, 2u64));
+        assert_eq!(middle.prob(1), Prob::new(16u64, 216u64));
+        assert_eq!(middle.builder_string, "nth_highest(3,2,d6)");
+
+        assert!(DiceBuilder::from_string("nth_highest(2,3,d6)").is_err());
+        assert!(DiceBuilder::from_string("nth_highest(d2,1,d6)").is_err());
+
+        // every k of 5 samples of d4, compared with the sorted samples of all 4^5 rolls
+        for k in 1..=5 {
+            let dice = Dice::build_from_string(&format!("nth_highest(5,{k},d4)")).unwrap();
+            let mut counts: std::collections::BTreeMap<Value, u64> = Default::default();
+            for roll in 0..4usize.pow(5) {
+                let mut samples: Vec<Value> = (0..5)
+                    .map(|d| (roll / 4usize.pow(d) % 4) as Value + 1)
+                    .collect();
+                samples.sort_unstable_by(|a, b| b.cmp(a));
+                *counts.entry(samples[k - 1]).or_insert(0) += 1;
+            }
+            let expected: Vec<(Value, Prob)> = counts
+                .into_iter()
+                .map(|(v, c)| (v, Prob::new(c, 1024u64)))
+                .collect();
+            assert_eq!(dice.distribution, expected);
+        }
     }
 }
@@ -0,0 +1,69 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    build_options::BuildOptions,
+    dice::Dice,
+    dice_builder::{DiceBuildError, DiceBuilder, Prob, Value},
+    wasm_safe::{elapsed_millis, WasmSafeInstant},
+};
+
+impl Dice {
+    /// the exact distribution of a state after `steps` random transitions, starting in `initial_state`
+    ///
+    /// `transition` gives the formula for the next state of every state, so the state after each step only depends on the state before it.
+    /// The formula of every state that is reached is only built once, a [`DiceBuildError`] of any of them is returned.
+    ///
+    /// # Examples
+    /// the position of a token on a board after 3 turns of moving d6 fields, where field 10 sends it back to the start:
+    /// ```
+    /// use dices::{Dice, DiceBuilder};
+    /// let d6 = DiceBuilder::FairDie { min: 1, max: 6 };
+    /// let position = Dice::iterate(0, |field| match field {
+    ///     10 => d6.clone(),
+    ///     field => DiceBuilder::Constant(field) + d6.clone(),
+    /// }, 3).unwrap();
+    /// assert_eq!(position.max, 18);
+    /// ```
+    ///
+    /// an escalating die that grows by 2 sides whenever it rolls its maximum, up to a d12:
+    /// ```
+    /// use dices::{Dice, DiceBuilder};
+    /// use fraction::BigFraction;
+    /// let sides = Dice::iterate(4, |sides| match sides {
+    ///     12 => DiceBuilder::Constant(12),
+    ///     sides => DiceBuilder::Mixture(vec![
+    ///         (sides as u32 - 1, DiceBuilder::Constant(sides)),
+    ///         (1, DiceBuilder::Constant(sides + 2)),
+    ///     ]),
+    /// }, 10).unwrap();
+    /// assert_eq!((sides.min, sides.max), (4, 12));
+    /// assert_eq!(sides.prob(4), BigFraction::new(3u64.pow(10), 4u64.pow(10)));
+    /// ```
+    pub fn iterate(
+        initial_state: Value,
+        transition: impl Fn(Value) -> DiceBuilder,
+        steps: usize,
+    ) -> Result<Dice, DiceBuildError> {
+        let start_instant = WasmSafeInstant::now();
+        let options = BuildOptions::default();
+        let mut transitions: HashMap<Value, Vec<(Value, Prob)>> = HashMap::new();
+        let mut states: BTreeMap<Value, Prob> = BTreeMap::from([(initial_state, Prob::from(1))]);
+        for _ in 0..steps {
+            let mut next: BTreeMap<Value, Prob> = BTreeMap::new();
+            for (state, p) in states.iter() {
+                if !transitions.contains_key(state) {
+                    let (distribution, _) =
+                        transition(*state).try_distribution_vec(&options, None)?;
+                    transitions.insert(*state, distribution);
+                }
+                for (v, q) in transitions[state].iter() {
+                    *next.entry(*v).or_insert_with(|| Prob::from(0)) += p.clone() * q.clone();
+                }
+            }
+            states = next;
+        }
+        let mut dice = Dice::from_sorted_distribution(states.into_iter().collect(), String::new())?;
+        dice.build_time = elapsed_millis(&start_instant);
+        Ok(dice)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    dice::Dice,
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// The exact probabilities of labeled outcomes like hit and miss, created by classifying the values of a [`Dice`] with [`Dice::classify()`] or [`Dice::classify_by()`].
+///
+/// # Examples
+/// an attack roll against AC 15 that crits on a 20:
+/// ```
+/// use dices::Dice;
+/// use fraction::BigFraction;
+/// let d20 = Dice::build_from_string("d20").unwrap();
+/// let attack = d20.classify(|v| match v {
+///     20 => "crit",
+///     v if v + 5 >= 15 => "hit",
+///     _ => "miss",
+/// });
+/// assert_eq!(attack.prob(&"hit"), BigFraction::new(10u64, 20u64));
+/// // two independent attacks
+/// let both = attack.product(&attack);
+/// assert_eq!(both.prob(&("crit", "crit")), BigFraction::new(1u64, 400u64));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutcomeDice<T, P = Prob> {
+    /// every outcome with its probability, each outcome appears once
+    pub outcomes: Vec<(T, P)>,
+}
+
+impl<T: Clone + PartialEq, P: ProbLike> OutcomeDice<T, P> {
+    /// the probability of `outcome`, 0 if it is not one of the outcomes
+    pub fn prob(&self, outcome: &T) -> P {
+        self.outcomes
+            .iter()
+            .find(|(t, _)| t == outcome)
+            .map(|(_, p)| p.clone())
+            .unwrap_or_else(P::zero)
+    }
+
+    /// the outcomes of `f` applied to every outcome, merging the probabilities of outcomes that are mapped to the same outcome
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// let outcome = d6.classify(|v| v).map(|v| *v >= 5);
+    /// assert_eq!(outcome.prob(&true), BigFraction::new(1u64, 3u64));
+    /// ```
+    pub fn map<U: Clone + PartialEq>(&self, f: impl Fn(&T) -> U) -> OutcomeDice<U, P> {
+        let mut outcomes = OutcomeDice { outcomes: vec![] };
+        for (t, p) in self.outcomes.iter() {
+            outcomes.add(f(t), p.clone());
+        }
+        outcomes
+    }
+
+    /// the joint outcomes of `self` and `other` happening independently, ordered by the outcomes of `self` first
+    pub fn product<U: Clone + PartialEq>(
+        &self,
+        other: &OutcomeDice<U, P>,
+    ) -> OutcomeDice<(T, U), P> {
+        let mut outcomes = Vec::with_capacity(self.outcomes.len() * other.outcomes.len());
+        for (t, p) in self.outcomes.iter() {
+            for (u, q) in other.outcomes.iter() {
+                outcomes.push(((t.clone(), u.clone()), p.clone() * q.clone()));
+            }
+        }
+        OutcomeDice { outcomes }
+    }
+
+    fn add(&mut self, outcome: T, p: P) {
+        match self.outcomes.iter_mut().find(|(t, _)| *t == outcome) {
+            Some((_, q)) => *q += p,
+            None => self.outcomes.push((outcome, p)),
+        }
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// the outcomes of `classify` applied to every value, in the order of the smallest value of each outcome
+    pub fn classify<T: Clone + PartialEq>(
+        &self,
+        classify: impl Fn(Value) -> T,
+    ) -> OutcomeDice<T, P> {
+        let mut outcomes = OutcomeDice { outcomes: vec![] };
+        for (v, p) in self.distribution.iter() {
+            outcomes.add(classify(*v), p.clone());
+        }
+        outcomes
+    }
+
+    /// the outcomes of the first of `labels` whose predicate holds for a value, and `otherwise` for values no predicate holds for
+    ///
+    /// All labels are part of the outcomes in their order, also those with a probability of 0, followed by `otherwise`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// let roll = d6.classify_by(&[("success", &|v| v == 6), ("partial", &|v| v >= 4)], "failure");
+    /// assert_eq!(roll.outcomes[1], ("partial", BigFraction::new(1u64, 3u64)));
+    /// assert_eq!(roll.prob(&"failure"), BigFraction::new(1u64, 2u64));
+    /// ```
+    pub fn classify_by<T: Clone + PartialEq>(
+        &self,
+        labels: &[(T, &dyn Fn(Value) -> bool)],
+        otherwise: T,
+    ) -> OutcomeDice<T, P> {
+        let mut outcomes = OutcomeDice { outcomes: vec![] };
+        for (label, _) in labels.iter() {
+            outcomes.add(label.clone(), P::zero());
+        }
+        outcomes.add(otherwise.clone(), P::zero());
+        for (v, p) in self.distribution.iter() {
+            let label = labels
+                .iter()
+                .find(|(_, predicate)| predicate(*v))
+                .map(|(label, _)| label.clone())
+                .unwrap_or_else(|| otherwise.clone());
+            outcomes.add(label, p.clone());
+        }
+        outcomes
+    }
+}
@@ -0,0 +1,8 @@
+//! Ready-made [`DiceBuilder`](crate::DiceBuilder)s for the mechanics of popular tabletop role-playing games.
+//!
+//! Presets of rolls return a [`DiceBuilder`](crate::DiceBuilder), so they can be combined with each other and with parsed formulas.
+
+pub mod blades_in_the_dark;
+pub mod dnd5e;
+pub mod savage_worlds;
+pub mod shadowrun;
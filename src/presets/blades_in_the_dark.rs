@@ -0,0 +1,65 @@
+//! Action rolls of Blades in the Dark, where only the highest of a pool of d6 counts.
+
+use crate::{dice_builder::Prob, prob_like::ProbLike};
+
+/// The probabilities of the outcomes of an action roll, created with [`action_roll()`].
+///
+/// The probabilities of the four outcomes sum up to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionRoll {
+    /// probability of at least two 6s
+    pub critical: Prob,
+    /// probability of exactly one 6
+    pub success: Prob,
+    /// probability of a highest die of 4 or 5, a success with consequences
+    pub partial: Prob,
+    /// probability of a highest die of 1 to 3
+    pub failure: Prob,
+}
+
+/// the outcomes of an action roll with a pool of `dice` d6
+///
+/// A pool of 0 dice rolls 2d6 and reads the lower one instead, which can never be critical.
+///
+/// # Examples
+/// ```
+/// use dices::presets::blades_in_the_dark;
+/// use fraction::BigFraction;
+/// let roll = blades_in_the_dark::action_roll(2);
+/// assert_eq!(roll.critical, BigFraction::new(1u64, 36u64));
+/// assert_eq!(roll.success, BigFraction::new(10u64, 36u64));
+/// assert_eq!(roll.partial, BigFraction::new(16u64, 36u64));
+/// assert_eq!(roll.failure, BigFraction::new(9u64, 36u64));
+/// ```
+pub fn action_roll(dice: u32) -> ActionRoll {
+    // probability that every die of the pool is at most `k`
+    let all_at_most = |k: u64| power(Prob::from_ratio(k, 6), dice);
+    if dice == 0 {
+        // the lower of 2d6 is at least `k` if both dice are
+        let both_at_least = |k: u64| Prob::from_ratio((7 - k).pow(2), 36);
+        return ActionRoll {
+            critical: Prob::zero(),
+            success: both_at_least(6),
+            partial: both_at_least(4) - both_at_least(6),
+            failure: Prob::one() - both_at_least(4),
+        };
+    }
+    let no_six = all_at_most(5);
+    // one of the dice is a 6 and all others are at most 5
+    let one_six =
+        Prob::from(dice) * Prob::from_ratio(1, 6) * power(Prob::from_ratio(5, 6), dice - 1);
+    ActionRoll {
+        critical: Prob::one() - no_six.clone() - one_six.clone(),
+        success: one_six,
+        partial: no_six - all_at_most(3),
+        failure: all_at_most(3),
+    }
+}
+
+fn power(base: Prob, exponent: u32) -> Prob {
+    let mut power = Prob::one();
+    for _ in 0..exponent {
+        power *= &base;
+    }
+    power
+}
@@ -0,0 +1,151 @@
+//! Mechanics of the 5th edition of Dungeons & Dragons.
+//!
+//! # Examples
+//! a rogue with +7 to hit, a d6 shortsword, +4 damage and 3d6 sneak attack against AC 15:
+//! ```
+//! use dices::presets::dnd5e;
+//! let damage = dnd5e::sneak_attack(7, 15, 1, 6, 4, 3).build();
+//! // a critical hit rolls 2d6 for the shortsword and 6d6 for sneak attack
+//! assert_eq!(damage.max, 8 * 6 + 4);
+//! ```
+
+use crate::dice_builder::{DiceBuilder, Value};
+
+/// `count` dice with `sides` sides, like `2d6`
+fn dice(count: u32, sides: u32) -> DiceBuilder {
+    DiceBuilder::SampleSumCompound(vec![
+        DiceBuilder::Constant(Value::from(count)),
+        DiceBuilder::FairDie {
+            min: 1,
+            max: Value::from(sides),
+        },
+    ])
+}
+
+/// the higher of two rolls of `roll`, like `max(d20,d20)`
+///
+/// # Examples
+/// ```
+/// use dices::{presets::dnd5e, DiceBuilder};
+/// let d20 = DiceBuilder::FairDie { min: 1, max: 20 };
+/// assert_eq!(dnd5e::advantage(d20).to_string(), "max(d20,d20)");
+/// ```
+pub fn advantage(roll: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::MaxCompound(vec![roll.clone(), roll])
+}
+
+/// the lower of two rolls of `roll`, like `min(d20,d20)`
+pub fn disadvantage(roll: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::MinCompound(vec![roll.clone(), roll])
+}
+
+/// the highest of three rolls of `roll`, which is what advantage becomes with the Elven Accuracy feat
+pub fn elven_accuracy(roll: DiceBuilder) -> DiceBuilder {
+    DiceBuilder::MaxCompound(vec![roll.clone(), roll.clone(), roll])
+}
+
+/// a die with `sides` sides whose results up to `reroll_max` are rerolled once, the second roll is kept
+///
+/// # Examples
+/// ```
+/// use dices::presets::dnd5e;
+/// use fraction::BigFraction;
+/// let d6 = dnd5e::reroll_once(6, 2).build();
+/// // a 1 has to be rolled and then rolled again
+/// assert_eq!(d6.prob(1), BigFraction::new(2u64, 36u64));
+/// assert_eq!(d6.prob(6), BigFraction::new(8u64, 36u64));
+/// ```
+pub fn reroll_once(sides: u32, reroll_max: u32) -> DiceBuilder {
+    let die = DiceBuilder::FairDie {
+        min: 1,
+        max: Value::from(sides),
+    };
+    if reroll_max == 0 || reroll_max >= sides {
+        return die;
+    }
+    // a kept first roll is uniform over the results that are not rerolled
+    let kept = DiceBuilder::FairDie {
+        min: Value::from(reroll_max) + 1,
+        max: Value::from(sides),
+    };
+    DiceBuilder::Mixture(vec![(sides - reroll_max, kept), (reroll_max, die)])
+}
+
+/// a d20 of a halfling, whose Lucky trait rerolls natural 1s once
+pub fn halfling_luck() -> DiceBuilder {
+    reroll_once(20, 1)
+}
+
+/// `count` damage dice with `sides` sides of the Great Weapon Fighting style, which rerolls 1s and 2s once
+///
+/// # Examples
+/// a greatsword:
+/// ```
+/// use dices::presets::dnd5e;
+/// let greatsword = dnd5e::great_weapon_fighting(2, 6).build();
+/// assert_eq!((greatsword.min, greatsword.max), (2, 12));
+/// ```
+pub fn great_weapon_fighting(count: u32, sides: u32) -> DiceBuilder {
+    DiceBuilder::SampleSumCompound(vec![
+        DiceBuilder::Constant(Value::from(count)),
+        reroll_once(sides, 2),
+    ])
+}
+
+/// the damage of an effect like a fireball that deals `damage` on a failed saving throw and half of it, rounded down, on a successful one
+///
+/// The saving throw succeeds if a d20 plus `save_bonus` is at least `dc`, natural 1s and 20s have no special effect.
+///
+/// # Examples
+/// a fireball against a creature with +2 on Dexterity saving throws:
+/// ```
+/// use dices::{presets::dnd5e, DiceBuilder};
+/// use fraction::BigFraction;
+/// let fireball = DiceBuilder::from_string("8d6").unwrap();
+/// let damage = dnd5e::save_for_half(2, 15, fireball).build();
+/// assert_eq!(damage.min, 4);
+/// assert_eq!(damage.max, 48);
+/// assert_eq!(damage.mean, BigFraction::new(223u64, 10u64));
+/// ```
+pub fn save_for_half(save_bonus: Value, dc: Value, damage: DiceBuilder) -> DiceBuilder {
+    let successes = (1..=20)
+        .filter(|natural: &Value| natural.saturating_add(save_bonus) >= dc)
+        .count() as u32;
+    // X - X/2 rounds down because the division rounds halves up
+    let half = DiceBuilder::Let {
+        name: "X".to_owned(),
+        value: Box::new(damage.clone()),
+        body: Box::new(DiceBuilder::SumCompound(vec![
+            DiceBuilder::Variable("X".to_owned()),
+            DiceBuilder::Negation(Box::new(DiceBuilder::DivisionCompound(vec![
+                DiceBuilder::Variable("X".to_owned()),
+                DiceBuilder::Constant(2),
+            ]))),
+        ])),
+    };
+    let components = [(20 - successes, damage), (successes, half)];
+    DiceBuilder::Mixture(components.into_iter().filter(|(w, _)| *w > 0).collect())
+}
+
+/// the damage of a weapon attack of a rogue, with `sneak_attack_dice` d6 of Sneak Attack on a hit
+///
+/// The weapon deals `weapon_count` dice with `weapon_sides` sides plus `modifier`, see [`DiceBuilder::attack()`] for the attack roll.
+/// A critical hit on a natural 20 doubles the weapon and the Sneak Attack dice, but not the modifier.
+pub fn sneak_attack(
+    to_hit: Value,
+    armor_class: Value,
+    weapon_count: u32,
+    weapon_sides: u32,
+    modifier: Value,
+    sneak_attack_dice: u32,
+) -> DiceBuilder {
+    let damage = |multiplier: u32| {
+        DiceBuilder::SumCompound(vec![
+            dice(weapon_count * multiplier, weapon_sides),
+            dice(sneak_attack_dice * multiplier, 6),
+            DiceBuilder::Constant(modifier),
+        ])
+        .simplify()
+    };
+    DiceBuilder::attack(to_hit, armor_class, damage(1), 20, damage(2))
+}
@@ -0,0 +1,87 @@
+//! Mechanics of Savage Worlds.
+//!
+//! # Examples
+//! a Wild Card with a d8 in Shooting and a +1 bonus against the standard target number 4:
+//! ```
+//! use dices::presets::savage_worlds;
+//! use fraction::BigFraction;
+//! let roll = savage_worlds::trait_roll(8, 1, 10).build();
+//! let raises = savage_worlds::raises(&roll, 4);
+//! // the roll fails if both dice show at most 2
+//! assert_eq!(raises.prob(-1), BigFraction::new(2u64 * 2, 8u64 * 6));
+//! ```
+
+use crate::{
+    dice::Dice,
+    dice_builder::{DiceBuilder, ExplodeKind, Value},
+    prob_like::ProbLike,
+};
+
+/// a die with `sides` sides that is rolled again and added whenever it shows its maximum, called acing, at most `max_explosions` times
+///
+/// # Examples
+/// ```
+/// use dices::presets::savage_worlds;
+/// use fraction::BigFraction;
+/// let d4 = savage_worlds::exploding(4, 1).build();
+/// assert_eq!(d4.prob(4), BigFraction::from(0));
+/// assert_eq!(d4.prob(6), BigFraction::new(1u64, 16u64));
+/// assert_eq!(d4.max, 8);
+/// ```
+pub fn exploding(sides: u32, max_explosions: usize) -> DiceBuilder {
+    DiceBuilder::Explode {
+        dice_builder: Box::new(DiceBuilder::FairDie {
+            min: 1,
+            max: Value::from(sides),
+        }),
+        min_value: None,
+        max_iterations: max_explosions,
+        kind: ExplodeKind::Standard,
+    }
+}
+
+/// a trait roll of a Wild Card: the higher of the exploding trait die with `trait_sides` sides and an exploding d6 wild die, plus `modifier`
+///
+/// Every die explodes at most `max_explosions` times.
+pub fn trait_roll(trait_sides: u32, modifier: Value, max_explosions: usize) -> DiceBuilder {
+    let roll = DiceBuilder::MaxCompound(vec![
+        exploding(trait_sides, max_explosions),
+        exploding(6, max_explosions),
+    ]);
+    with_modifier(roll, modifier)
+}
+
+/// a trait roll of an Extra, who rolls the exploding trait die with `trait_sides` sides without a wild die, plus `modifier`
+pub fn extra_roll(trait_sides: u32, modifier: Value, max_explosions: usize) -> DiceBuilder {
+    with_modifier(exploding(trait_sides, max_explosions), modifier)
+}
+
+/// the outcome of `roll` against `target`: -1 for a failure, 0 for a success and the number of raises, every 4 points above `target`, otherwise
+///
+/// # Examples
+/// ```
+/// use dices::presets::savage_worlds;
+/// use fraction::BigFraction;
+/// let roll = savage_worlds::extra_roll(6, 0, 10).build();
+/// let raises = savage_worlds::raises(&roll, 4);
+/// // a 4 or a 5, or a 6 that aces into a 7
+/// assert_eq!(raises.prob(0), BigFraction::new(13u64, 36u64));
+/// // a 6 that aces into 8 to 11
+/// assert_eq!(raises.prob(1), BigFraction::new(4u64, 36u64));
+/// ```
+pub fn raises<P: ProbLike>(roll: &Dice<P>, target: Value) -> Dice<P> {
+    roll.map_values(
+        |v| match v >= target {
+            true => (v - target).div_euclid(4),
+            false => -1,
+        },
+        String::new(),
+    )
+}
+
+fn with_modifier(roll: DiceBuilder, modifier: Value) -> DiceBuilder {
+    match modifier {
+        0 => roll,
+        modifier => DiceBuilder::SumCompound(vec![roll, DiceBuilder::Constant(modifier)]),
+    }
+}
@@ -0,0 +1,113 @@
+//! Dice pools of Shadowrun, where every d6 showing 5 or 6 is a hit and too many 1s are a glitch.
+//!
+//! The pools of other games like the World of Darkness work the same way with other dice, see [`hits_and_ones()`].
+//!
+//! # Examples
+//! a pool of 4 dice glitches if at least 3 of them show a 1:
+//! ```
+//! use dices::presets::shadowrun;
+//! use fraction::BigFraction;
+//! assert_eq!(shadowrun::glitch_prob(4), BigFraction::new(21u64, 1296u64));
+//! // a critical glitch has no hits at all
+//! assert_eq!(shadowrun::critical_glitch_prob(4), BigFraction::new(13u64, 1296u64));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    dice_builder::{DiceBuilder, Prob, Value},
+    joint::JointDice,
+    prob_like::ProbLike,
+};
+
+/// the number of hits in a Shadowrun pool of `dice` d6
+///
+/// # Examples
+/// ```
+/// use dices::presets::shadowrun;
+/// use fraction::BigFraction;
+/// let hits = shadowrun::hits(6);
+/// assert_eq!(hits.to_string(), "6xmix(4:0,2:1)");
+/// assert_eq!(hits.build().mean, BigFraction::from(2));
+/// ```
+pub fn hits(dice: u32) -> DiceBuilder {
+    count(dice, 6, |v| v >= 5)
+}
+
+/// the joint distribution of the number of hits and the number of 1s in a pool of `dice` dice with `sides` sides, where every die showing at least `hit_min` is a hit
+///
+/// The two components are the hits and the 1s, in this order.
+///
+/// panics if `sides` is 0.
+///
+/// # Examples
+/// a World of Darkness pool of 5 d10 that hit on 8 or more:
+/// ```
+/// use dices::presets::shadowrun;
+/// use fraction::BigFraction;
+/// let pool = shadowrun::hits_and_ones(5, 10, 8);
+/// assert_eq!(pool.builder_strings, vec!["5xmix(7:0,3:1)", "5xmix(9:0,1:1)"]);
+/// // a botch is a roll without hits but with at least one 1
+/// let botch = pool.prob_of(|v| v[0] == 0 && v[1] > 0);
+/// assert_eq!(botch, BigFraction::new(7u64.pow(5) - 6u64.pow(5), 10u64.pow(5)));
+/// ```
+pub fn hits_and_ones(dice: u32, sides: u32, hit_min: u32) -> JointDice {
+    assert!(sides > 0, "a die needs at least one side");
+    let is_hit = |v: u32| v >= hit_min;
+    // probability of every combination of hit and 1 on a single die
+    let mut single: HashMap<(Value, Value), Prob> = HashMap::new();
+    for v in 1..=sides {
+        let outcome = (Value::from(is_hit(v)), Value::from(v == 1));
+        *single.entry(outcome).or_insert_with(Prob::zero) += Prob::from_ratio(1, u64::from(sides));
+    }
+    let mut pool: HashMap<(Value, Value), Prob> = HashMap::from([((0, 0), Prob::one())]);
+    for _ in 0..dice {
+        let mut next: HashMap<(Value, Value), Prob> = HashMap::new();
+        for ((hits, ones), p) in pool.iter() {
+            for ((hit, one), q) in single.iter() {
+                *next
+                    .entry((hits + hit, ones + one))
+                    .or_insert_with(Prob::zero) += p.clone() * q.clone();
+            }
+        }
+        pool = next;
+    }
+    let mut distribution: Vec<(Vec<Value>, Prob)> = pool
+        .into_iter()
+        .map(|((hits, ones), p)| (vec![hits, ones], p))
+        .collect();
+    distribution.sort_by(|(a, _), (b, _)| a.cmp(b));
+    JointDice {
+        builder_strings: vec![
+            count(dice, sides, is_hit).to_string(),
+            count(dice, sides, |v| v == 1).to_string(),
+        ],
+        distribution,
+    }
+}
+
+/// the probability that more than half of a Shadowrun pool of `dice` d6 show a 1
+pub fn glitch_prob(dice: u32) -> Prob {
+    hits_and_ones(dice, 6, 5).prob_of(|v| is_glitch(dice, v[1]))
+}
+
+/// the probability that more than half of a Shadowrun pool of `dice` d6 show a 1 and no die is a hit
+pub fn critical_glitch_prob(dice: u32) -> Prob {
+    hits_and_ones(dice, 6, 5).prob_of(|v| v[0] == 0 && is_glitch(dice, v[1]))
+}
+
+fn is_glitch(dice: u32, ones: Value) -> bool {
+    2 * ones > Value::from(dice)
+}
+
+/// the number of `dice` dice with `sides` sides whose result satisfies `predicate`
+fn count(dice: u32, sides: u32, predicate: impl Fn(u32) -> bool) -> DiceBuilder {
+    let matching = (1..=sides).filter(|v| predicate(*v)).count() as u32;
+    DiceBuilder::SampleSumCompound(vec![
+        DiceBuilder::Constant(Value::from(dice)),
+        DiceBuilder::Mixture(vec![
+            (sides - matching, DiceBuilder::Constant(0)),
+            (matching, DiceBuilder::Constant(1)),
+        ]),
+    ])
+}
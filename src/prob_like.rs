@@ -0,0 +1,126 @@
+use std::{
+    fmt::Debug,
+    ops::{Add, AddAssign, Div, Mul, MulAssign, Sub},
+};
+
+use fraction::{One, Zero};
+
+use crate::{
+    convolution::{kronecker_sum_convolute, shared_denominator_convolute},
+    dice::ToFloat,
+    dice_builder::{Prob, Value},
+};
+
+/// A type that can represent probabilities while calculating distributions.
+///
+/// Implemented for the exact [`BigFraction`](fraction::BigFraction) and for [`f64`], which is a lot faster but accumulates rounding errors.
+/// The same [`DiceBuilder`](crate::DiceBuilder) can be evaluated with any of them:
+/// ```
+/// use dices::{BuildOptions, Dice, DiceBuilder};
+/// let exact: Dice = DiceBuilder::from_string("2d6").unwrap().build();
+/// let approx: Dice<f64> = DiceBuilder::from_string("2d6")
+///     .unwrap()
+///     .build_as(&BuildOptions::default())
+///     .unwrap();
+/// assert_eq!(exact.median, approx.median);
+/// ```
+pub trait ProbLike:
+    Clone
+    + Send
+    + Sync
+    + Debug
+    + PartialOrd
+    + ToFloat
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + AddAssign
+    + MulAssign
+    + for<'a> AddAssign<&'a Self>
+    + for<'a> MulAssign<&'a Self>
+{
+    /// the probability 0
+    fn zero() -> Self;
+    /// the probability 1
+    fn one() -> Self;
+    /// the probability `numer / denom`
+    fn from_ratio(numer: u64, denom: u64) -> Self;
+    /// a value of a distribution, used for aggregates like the mean
+    fn from_value(value: Value) -> Self;
+
+    /// the probabilities of the sum of two independent samples of contiguous distributions, where `first[i] * second[j]` adds to the result at `i + j`
+    ///
+    /// Big sum convolutions use this instead of multiplying every pair of probabilities, if it returns `Some`.
+    /// The default implementation has no faster way and returns `None`.
+    fn fast_sum_convolute(first: &[Self], second: &[Self]) -> Option<Vec<Self>> {
+        let _ = (first, second);
+        None
+    }
+
+    /// the distribution of `operation(a, b)` for two independent samples `a` of `first` and `b` of `second`, with the probabilities of equal results added up
+    ///
+    /// Convolutions use this instead of multiplying and adding up every pair of probabilities, if it returns `Some`.
+    /// It may return `None` if `operation` overflows, the slower convolution then reports the error.
+    /// The default implementation has no faster way and returns `None`.
+    fn fast_convolute<'a, I, J, F>(first: I, second: J, operation: F) -> Option<Vec<(Value, Self)>>
+    where
+        Self: 'a,
+        I: Iterator<Item = (&'a Value, &'a Self)>,
+        J: Iterator<Item = (&'a Value, &'a Self)> + Clone,
+        F: Fn(Value, Value) -> Option<Value>,
+    {
+        let _ = (first, second, operation);
+        None
+    }
+}
+
+impl ProbLike for Prob {
+    fn zero() -> Self {
+        <Prob as Zero>::zero()
+    }
+
+    fn one() -> Self {
+        <Prob as One>::one()
+    }
+
+    fn from_ratio(numer: u64, denom: u64) -> Self {
+        Prob::new(numer, denom)
+    }
+
+    fn from_value(value: Value) -> Self {
+        Prob::from(value)
+    }
+
+    fn fast_sum_convolute(first: &[Self], second: &[Self]) -> Option<Vec<Self>> {
+        kronecker_sum_convolute(first, second)
+    }
+
+    fn fast_convolute<'a, I, J, F>(first: I, second: J, operation: F) -> Option<Vec<(Value, Self)>>
+    where
+        Self: 'a,
+        I: Iterator<Item = (&'a Value, &'a Self)>,
+        J: Iterator<Item = (&'a Value, &'a Self)> + Clone,
+        F: Fn(Value, Value) -> Option<Value>,
+    {
+        shared_denominator_convolute(first, second, operation)
+    }
+}
+
+impl ProbLike for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn from_ratio(numer: u64, denom: u64) -> Self {
+        numer as f64 / denom as f64
+    }
+
+    fn from_value(value: Value) -> Self {
+        value as f64
+    }
+}
@@ -0,0 +1,192 @@
+//! Python bindings, enabled with the `python` feature and built into an extension module with `maturin build --features python`.
+//!
+//! ```python
+//! import dices
+//! d = dices.Dice.build_from_string("2d6+3")
+//! d.mean            # Fraction(10, 1)
+//! d.distribution    # [(5, Fraction(1, 36)), (6, Fraction(1, 18)), ...]
+//! d.roll()
+//! ```
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{
+    dice::Dice,
+    dice_builder::{DiceBuilder, Prob, Value},
+    ToFloat,
+};
+
+/// a probability distribution, mirrors [`Dice`]
+#[pyclass(name = "Dice", module = "dices", frozen)]
+pub struct PyDice {
+    dice: Dice,
+}
+
+#[pymethods]
+impl PyDice {
+    /// parses and calculates a formula like `"2d6+3"`, raises a `ValueError` if it is invalid
+    #[staticmethod]
+    fn build_from_string(input: &str) -> PyResult<PyDice> {
+        match Dice::try_build_from_string(input) {
+            Ok(dice) => Ok(PyDice { dice }),
+            Err(err) => Err(PyValueError::new_err(err.to_string())),
+        }
+    }
+
+    #[getter]
+    fn builder_string(&self) -> &str {
+        &self.dice.builder_string
+    }
+
+    #[getter]
+    fn min(&self) -> Value {
+        self.dice.min
+    }
+
+    #[getter]
+    fn max(&self) -> Value {
+        self.dice.max
+    }
+
+    #[getter]
+    fn median(&self) -> Value {
+        self.dice.median
+    }
+
+    #[getter]
+    fn mode(&self) -> Vec<Value> {
+        self.dice.mode.clone()
+    }
+
+    /// the mean as a `fractions.Fraction`
+    #[getter]
+    fn mean(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.mean)
+    }
+
+    /// the variance as a `fractions.Fraction`
+    #[getter]
+    fn variance(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.variance)
+    }
+
+    /// the standard deviation as a float
+    #[getter]
+    fn sd(&self) -> f64 {
+        self.dice.variance.to_float().sqrt()
+    }
+
+    /// the pmf as a list of `(value, Fraction)` tuples in ascending order
+    #[getter]
+    fn distribution(&self, py: Python<'_>) -> PyResult<Vec<(Value, PyObject)>> {
+        to_py_distribution(py, &self.dice.distribution)
+    }
+
+    /// the cdf as a list of `(value, Fraction)` tuples in ascending order
+    #[getter]
+    fn cumulative_distribution(&self, py: Python<'_>) -> PyResult<Vec<(Value, PyObject)>> {
+        to_py_distribution(py, self.dice.cumulative_distribution())
+    }
+
+    fn roll(&self) -> Value {
+        self.dice.roll()
+    }
+
+    /// `n` rolls, reproducible if a `seed` is given
+    #[pyo3(signature = (n, seed=None))]
+    fn roll_many(&self, n: usize, seed: Option<u64>) -> Vec<Value> {
+        match seed {
+            Some(seed) => self.dice.roller(seed).roll_many(n).collect(),
+            None => self.dice.roll_many(n).collect(),
+        }
+    }
+
+    fn prob(&self, py: Python<'_>, value: Value) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.prob(value))
+    }
+
+    fn prob_lt(&self, py: Python<'_>, value: Value) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.prob_lt(value))
+    }
+
+    fn prob_lte(&self, py: Python<'_>, value: Value) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.prob_lte(value))
+    }
+
+    fn prob_gte(&self, py: Python<'_>, value: Value) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.prob_gte(value))
+    }
+
+    fn prob_gt(&self, py: Python<'_>, value: Value) -> PyResult<PyObject> {
+        to_py_fraction(py, &self.dice.prob_gt(value))
+    }
+
+    fn quantile(&self, p: f64) -> Value {
+        self.dice.quantile(p)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Dice({:?})", self.dice.builder_string)
+    }
+
+    fn __str__(&self) -> String {
+        self.dice.to_string()
+    }
+}
+
+/// the tree of a formula, mirrors [`DiceBuilder`]
+#[pyclass(name = "DiceBuilder", module = "dices", frozen)]
+pub struct PyDiceBuilder {
+    builder: DiceBuilder,
+}
+
+#[pymethods]
+impl PyDiceBuilder {
+    /// parses a formula like `"2d6+3"`, raises a `ValueError` if it is invalid
+    #[staticmethod]
+    fn from_string(input: &str) -> PyResult<PyDiceBuilder> {
+        match DiceBuilder::from_string(input) {
+            Ok(builder) => Ok(PyDiceBuilder { builder }),
+            Err(err) => Err(PyValueError::new_err(err.to_string())),
+        }
+    }
+
+    /// calculates the distribution, raises a `ValueError` if the tree is degenerate
+    fn build(&self) -> PyResult<PyDice> {
+        match self.builder.clone().try_build() {
+            Ok(dice) => Ok(PyDice { dice }),
+            Err(err) => Err(PyValueError::new_err(err.to_string())),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("DiceBuilder({:?})", self.builder.to_string())
+    }
+
+    fn __str__(&self) -> String {
+        self.builder.to_string()
+    }
+}
+
+/// converts a probability into a `fractions.Fraction`, which parses the `"n/d"` form of its `Display`
+fn to_py_fraction(py: Python<'_>, prob: &Prob) -> PyResult<PyObject> {
+    let fraction = py.import("fractions")?.getattr("Fraction")?;
+    Ok(fraction.call1((prob.to_string(),))?.unbind())
+}
+
+fn to_py_distribution(
+    py: Python<'_>,
+    distribution: &[(Value, Prob)],
+) -> PyResult<Vec<(Value, PyObject)>> {
+    distribution
+        .iter()
+        .map(|(v, p)| Ok((*v, to_py_fraction(py, p)?)))
+        .collect()
+}
+
+#[pymodule]
+fn dices(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyDice>()?;
+    m.add_class::<PyDiceBuilder>()?;
+    Ok(())
+}
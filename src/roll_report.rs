@@ -0,0 +1,384 @@
+use std::fmt::Display;
+
+use rand::Rng;
+
+use crate::{
+    dice_builder::{
+        checked_power, try_rounded_div, DiceBuildError, DiceBuilder, ExplodeKind, Operation, Value,
+    },
+    simulation::{pick_component, SampleContext},
+};
+
+/// The result of [`DiceBuilder::roll_detailed()`]: the total of one roll together with the results of every die that was rolled for it.
+///
+/// Its `Display` writes the formula with the individual results, like `2d6 (4, 5) + 3 = 12`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollReport {
+    /// the rolled value of the whole formula
+    pub total: Value,
+    /// the trace of the root of the [`DiceBuilder`] tree
+    pub trace: RollTrace,
+}
+
+/// One node of the [`DiceBuilder`] tree evaluated during [`DiceBuilder::roll_detailed()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RollTrace {
+    /// the formula of the node, like `2xd6`
+    pub formula: String,
+    /// the formula of the node with the results of its dice, like `2d6 (4, 5)`
+    pub expression: String,
+    /// the value the node rolled
+    pub value: Value,
+    /// the individual results rolled by this node itself, like the faces of a die or each sample of a sample sum
+    pub rolls: Vec<Value>,
+    /// the traces of the sub-expressions, in the order they were rolled
+    pub children: Vec<RollTrace>,
+}
+
+impl Display for RollReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.trace.expression, self.total)
+    }
+}
+
+impl DiceBuilder {
+    /// rolls [`self`] once like `roll_once()`, but keeps the result of every die and sub-expression, for example to show them in a chat
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let builder = DiceBuilder::from_string("2d6+3").unwrap();
+    /// let report = builder.roll_detailed(&mut rand::thread_rng()).unwrap();
+    /// let faces = &report.trace.children[0].rolls;
+    /// assert_eq!(report.total, faces[0] + faces[1] + 3);
+    /// assert_eq!(
+    ///     report.to_string(),
+    ///     format!("2d6 ({}, {}) + 3 = {}", faces[0], faces[1], report.total)
+    /// );
+    /// ```
+    pub fn roll_detailed<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<RollReport, DiceBuildError> {
+        let trace = self.trace(rng, &mut SampleContext::default())?;
+        Ok(RollReport {
+            total: trace.value,
+            trace,
+        })
+    }
+
+    /// rolls every die in the tree of [`self`] once like `sample()`, recording a [`RollTrace`] for every node
+    fn trace<'t, R: Rng + ?Sized>(
+        &'t self,
+        rng: &mut R,
+        ctx: &mut SampleContext<'t>,
+    ) -> Result<RollTrace, DiceBuildError> {
+        let trace = match self {
+            DiceBuilder::Constant(v) => self.node(*v, vec![], v.to_string(), vec![]),
+            DiceBuilder::FairDie { .. } => {
+                let face = self.sample(rng, ctx)?;
+                self.node(face, vec![face], format!("{self} ({face})"), vec![])
+            }
+            DiceBuilder::Variable(name) => {
+                let value = ctx
+                    .binding(name)
+                    .ok_or_else(|| DiceBuildError::UnboundVariable(name.clone()))?;
+                self.node(value, vec![], name.clone(), vec![])
+            }
+            DiceBuilder::SumCompound(vec) => {
+                let children = trace_all(vec, rng, ctx)?;
+                let mut expression = String::new();
+                for (i, child) in children.iter().enumerate() {
+                    match (&vec[i], i) {
+                        (DiceBuilder::Constant(c), _) if *c < 0 => {
+                            let separator = if i > 0 { " - " } else { "-" };
+                            expression.push_str(separator);
+                            expression.push_str(&c.unsigned_abs().to_string());
+                        }
+                        (DiceBuilder::Negation(d), _) => {
+                            let operand = &child.children[0];
+                            let separator = if i > 0 { " - " } else { "-" };
+                            expression.push_str(separator);
+                            expression.push_str(&operand_expression(d, operand, 1));
+                        }
+                        (_, 0) => expression.push_str(&operand_expression(&vec[i], child, 1)),
+                        _ => {
+                            expression.push_str(" + ");
+                            expression.push_str(&operand_expression(&vec[i], child, 1));
+                        }
+                    }
+                }
+                let value = checked_sum(children.iter().map(|c| c.value))?;
+                self.node(value, vec![], expression, children)
+            }
+            DiceBuilder::ProductCompound(vec)
+            | DiceBuilder::DivisionCompound(vec)
+            | DiceBuilder::PowerCompound(vec) => {
+                let children = trace_all(vec, rng, ctx)?;
+                let (operator, value) = match self {
+                    DiceBuilder::ProductCompound(_) => {
+                        (" * ", fold(&children, Value::checked_mul)?)
+                    }
+                    DiceBuilder::DivisionCompound(_) => {
+                        let (first, rest) = children
+                            .split_first()
+                            .ok_or(DiceBuildError::EmptyCompound)?;
+                        let value = rest
+                            .iter()
+                            .try_fold(first.value, |acc, c| try_rounded_div(acc, c.value))?;
+                        (" / ", value)
+                    }
+                    _ => (" ^ ", power_of(&children)?),
+                };
+                let expression = children
+                    .iter()
+                    .zip(vec.iter())
+                    .map(|(c, d)| operand_expression(d, c, self.precedence()))
+                    .collect::<Vec<String>>()
+                    .join(operator);
+                self.node(value, vec![], expression, children)
+            }
+            DiceBuilder::MaxCompound(vec) | DiceBuilder::MinCompound(vec) => {
+                let children = trace_all(vec, rng, ctx)?;
+                let (name, operation): (&str, Operation) = match self {
+                    DiceBuilder::MaxCompound(_) => ("max", |a, b| Some(a.max(b))),
+                    _ => ("min", |a, b| Some(a.min(b))),
+                };
+                let value = fold(&children, operation)?;
+                let arguments = children
+                    .iter()
+                    .map(|c| c.expression.clone())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                self.node(value, vec![], format!("{name}({arguments})"), children)
+            }
+            DiceBuilder::SampleSumCompound(vec) => {
+                let (first, rest) = vec.split_first().ok_or(DiceBuildError::EmptyCompound)?;
+                let mut count_trace = first.trace(rng, ctx)?;
+                let mut count_builder = first;
+                for d in rest.iter() {
+                    let count = count_trace.value;
+                    let mut samples = vec![];
+                    for _ in 0..count.unsigned_abs() {
+                        samples.push(d.trace(rng, ctx)?);
+                    }
+                    let rolls: Vec<Value> = samples.iter().map(|s| s.value).collect();
+                    let total = checked_sum(rolls.iter().copied())?;
+                    // a negative count subtracts the samples, like in the exact calculation
+                    let value = match count < 0 {
+                        true => overflow(total.checked_neg())?,
+                        false => total,
+                    };
+                    let sampled = match (count_builder, d) {
+                        (DiceBuilder::Constant(c), DiceBuilder::FairDie { min: 1, max }) => {
+                            format!("{c}d{max}")
+                        }
+                        _ => format!(
+                            "{} x {}",
+                            operand_expression(count_builder, &count_trace, 4),
+                            d.operand_string(4)
+                        ),
+                    };
+                    let expression = format!("{sampled} ({})", join_values(&rolls));
+                    let mut children = vec![count_trace];
+                    children.extend(samples);
+                    count_trace = self.node(value, rolls, expression, children);
+                    count_builder = self;
+                }
+                count_trace
+            }
+            DiceBuilder::Absolute(d) => {
+                let child = d.trace(rng, ctx)?;
+                let expression = format!("abs({})", child.expression);
+                let value = overflow(child.value.checked_abs())?;
+                self.node(value, vec![], expression, vec![child])
+            }
+            DiceBuilder::Negation(d) => {
+                let child = d.trace(rng, ctx)?;
+                let expression = format!("-{}", operand_expression(d, &child, 1));
+                let value = overflow(child.value.checked_neg())?;
+                self.node(value, vec![], expression, vec![child])
+            }
+            DiceBuilder::Let { name, value, body } => {
+                let value_trace = value.trace(rng, ctx)?;
+                ctx.bind(name, value_trace.value);
+                let body_trace = body.trace(rng, ctx);
+                ctx.unbind();
+                let body_trace = body_trace?;
+                let expression = format!(
+                    "{name} := {}; {}",
+                    value_trace.expression, body_trace.expression
+                );
+                self.node(
+                    body_trace.value,
+                    vec![],
+                    expression,
+                    vec![value_trace, body_trace],
+                )
+            }
+            DiceBuilder::NthHighest { n, k, dice_builder } => {
+                if *k < 1 || k > n {
+                    return Err(DiceBuildError::InvalidNthHighest { n: *n, k: *k });
+                }
+                let samples = (0..*n)
+                    .map(|_| dice_builder.trace(rng, ctx))
+                    .collect::<Result<Vec<RollTrace>, DiceBuildError>>()?;
+                let rolls: Vec<Value> = match dice_builder.as_ref() {
+                    // every roll of the explosions is a value of the pool on its own
+                    DiceBuilder::Explode { kind, .. } if kind.separates_rolls_in_pools() => samples
+                        .iter()
+                        .flat_map(|s| explosion_pool_values(&s.rolls, *kind))
+                        .collect::<Result<Vec<Value>, DiceBuildError>>()?,
+                    _ => samples.iter().map(|s| s.value).collect(),
+                };
+                let mut sorted = rolls.clone();
+                sorted.sort_unstable_by(|a, b| b.cmp(a));
+                let expression = format!("{self} ({})", join_values(&rolls));
+                self.node(sorted[k - 1], rolls, expression, samples)
+            }
+            DiceBuilder::Mixture(components) => {
+                let picked = pick_component(components, rng)?;
+                let child = picked.trace(rng, ctx)?;
+                // components that were not picked are shown without rolls
+                let expression = components
+                    .iter()
+                    .map(|(weight, d)| match std::ptr::eq(d, picked) {
+                        true => format!("{weight}: {}", child.expression),
+                        false => format!("{weight}: {d}"),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                self.node(
+                    child.value,
+                    vec![],
+                    format!("mix({expression})"),
+                    vec![child],
+                )
+            }
+            DiceBuilder::Reroll {
+                dice_builder,
+                max_value,
+                keep_best,
+            } => {
+                let mut samples = vec![dice_builder.trace(rng, ctx)?];
+                if samples[0].value <= *max_value {
+                    samples.push(dice_builder.trace(rng, ctx)?);
+                }
+                let rolls: Vec<Value> = samples.iter().map(|s| s.value).collect();
+                let value = match keep_best {
+                    true => *rolls.iter().max().expect("there is at least one roll"),
+                    false => rolls[rolls.len() - 1],
+                };
+                let expression = format!("{self} ({})", join_values(&rolls));
+                self.node(value, rolls, expression, samples)
+            }
+            DiceBuilder::Explode {
+                dice_builder,
+                min_value,
+                max_iterations,
+                kind,
+            } => {
+                let threshold = ctx.explosion_threshold(dice_builder, *min_value)?;
+                let mut samples = vec![dice_builder.trace(rng, ctx)?];
+                while samples.len() <= *max_iterations
+                    && samples[samples.len() - 1].value >= threshold
+                {
+                    samples.push(dice_builder.trace(rng, ctx)?);
+                }
+                let mut rolls: Vec<Value> = samples.iter().map(|s| s.value).collect();
+                let explosions = rolls.len() as Value - 1;
+                let total = overflow(
+                    checked_sum(rolls.iter().copied())?.checked_sub(explosions * kind.penalty()),
+                )?;
+                // a compounding die shows a single result
+                if *kind == ExplodeKind::Compounding {
+                    rolls = vec![total];
+                }
+                let expression = format!("{self} ({})", join_values(&rolls));
+                self.node(total, rolls, expression, samples)
+            }
+        };
+        Ok(trace)
+    }
+
+    fn node(
+        &self,
+        value: Value,
+        rolls: Vec<Value>,
+        expression: String,
+        children: Vec<RollTrace>,
+    ) -> RollTrace {
+        RollTrace {
+            formula: self.to_string(),
+            expression,
+            value,
+            rolls,
+            children,
+        }
+    }
+}
+
+fn trace_all<'t, R: Rng + ?Sized>(
+    vec: &'t [DiceBuilder],
+    rng: &mut R,
+    ctx: &mut SampleContext<'t>,
+) -> Result<Vec<RollTrace>, DiceBuildError> {
+    vec.iter().map(|d| d.trace(rng, ctx)).collect()
+}
+
+/// combines the values of `children` from left to right with `operation`
+fn fold(children: &[RollTrace], operation: Operation) -> Result<Value, DiceBuildError> {
+    let (first, rest) = children
+        .split_first()
+        .ok_or(DiceBuildError::EmptyCompound)?;
+    rest.iter()
+        .try_fold(first.value, |acc, c| overflow(operation(acc, c.value)))
+}
+
+/// the `rolls` of an exploding die as values of a pool, the rolls of the explosions reduced by the penalty of `kind`
+fn explosion_pool_values(
+    rolls: &[Value],
+    kind: ExplodeKind,
+) -> impl Iterator<Item = Result<Value, DiceBuildError>> + '_ {
+    rolls.iter().enumerate().map(move |(i, roll)| match i {
+        0 => Ok(*roll),
+        _ => overflow(roll.checked_sub(kind.penalty())),
+    })
+}
+
+fn checked_sum(mut values: impl Iterator<Item = Value>) -> Result<Value, DiceBuildError> {
+    values.try_fold(0, |acc: Value, v| overflow(acc.checked_add(v)))
+}
+
+fn overflow(value: Option<Value>) -> Result<Value, DiceBuildError> {
+    value.ok_or(DiceBuildError::ValueOverflow)
+}
+
+/// the values of `children` as a power tower, evaluated from the right
+fn power_of(children: &[RollTrace]) -> Result<Value, DiceBuildError> {
+    let (last, rest) = children.split_last().ok_or(DiceBuildError::EmptyCompound)?;
+    let mut exponent = last.value;
+    for base in rest.iter().rev() {
+        if exponent < 0 {
+            return Err(DiceBuildError::NegativeExponent(exponent));
+        }
+        exponent = overflow(checked_power(base.value, exponent))?;
+    }
+    Ok(exponent)
+}
+
+/// the expression of `trace` as an operand of an operator with `precedence`, in brackets if the operator would otherwise bind parts of it
+fn operand_expression(builder: &DiceBuilder, trace: &RollTrace, precedence: u8) -> String {
+    match builder.precedence() <= precedence {
+        true => format!("({})", trace.expression),
+        false => trace.expression.clone(),
+    }
+}
+
+fn join_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
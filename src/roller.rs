@@ -0,0 +1,62 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    dice::Dice,
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// Rolls a [`Dice`] with its own seeded random number generator, created with [`Dice::roller()`].
+///
+/// The same seed always gives the same sequence of rolls, on native targets as well as in wasm.
+///
+/// # Examples
+/// ```
+/// use dices::Dice;
+/// let d = Dice::build_from_string("d20").unwrap();
+/// let mut roller = d.roller(7);
+/// let rolls: Vec<i64> = roller.roll_many(10).collect();
+/// assert!(d.roller(7).roll_many(10).eq(rolls));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiceRoller<'d, P = Prob> {
+    dice: &'d Dice<P>,
+    rng: StdRng,
+}
+
+impl<'d, P: ProbLike> DiceRoller<'d, P> {
+    /// the [`Dice`] that is rolled
+    pub fn dice(&self) -> &'d Dice<P> {
+        self.dice
+    }
+
+    /// rolls the next number
+    pub fn roll(&mut self) -> Value {
+        self.dice.roll_with_rng(&mut self.rng)
+    }
+
+    /// rolls the next `n` numbers, lazily as the returned iterator is consumed
+    pub fn roll_many(&mut self, n: usize) -> impl ExactSizeIterator<Item = Value> + '_ {
+        let dice: &Dice<P> = self.dice;
+        dice.roll_many_with_rng(n, &mut self.rng)
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// a [`DiceRoller`] for reproducible rolls of `self`, seeded with `seed`
+    pub fn roller(&self, seed: u64) -> DiceRoller<'_, P> {
+        DiceRoller {
+            dice: self,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+/// rolls endlessly, so use it with adapters like `take()`
+impl<P: ProbLike> Iterator for DiceRoller<'_, P> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        Some(self.roll())
+    }
+}
@@ -0,0 +1,114 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    build_options::BuildReport,
+    dice::{Dice, DiceF64},
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// the serialized form of a [`Dice`], all other distribution paramters are recalculated from the pmf when deserializing
+#[derive(Serialize, Deserialize)]
+struct SerializedDice {
+    builder_string: String,
+    build_time: u64,
+    #[serde(default)]
+    build_report: BuildReport,
+    distribution: Vec<SerializedProb>,
+}
+
+/// one value of the pmf, with its probability as an exact fraction like `"1/6"` and as a float
+#[derive(Serialize, Deserialize)]
+struct SerializedProb {
+    value: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prob: Option<String>,
+    prob_f64: f64,
+}
+
+/// serializes the pmf with exact probabilities like `"1/6"` next to their float approximations
+///
+/// # Examples
+/// ```
+/// use dices::Dice;
+/// let d = Dice::build_from_string("d2").unwrap();
+/// let json = serde_json::to_string(&d).unwrap();
+/// assert!(json.contains(r#"{"value":1,"prob":"1/2","prob_f64":0.5}"#));
+/// let cached: Dice = serde_json::from_str(&json).unwrap();
+/// assert_eq!(cached, d);
+/// ```
+impl Serialize for Dice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialized_dice(self, |p| Some(p.to_string())).serialize(serializer)
+    }
+}
+
+/// reads the exact probabilities, so the float probabilities are ignored
+impl<'de> Deserialize<'de> for Dice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedDice::deserialize(deserializer)?;
+        dice_from_serialized(serialized, |entry| {
+            let prob = entry
+                .prob
+                .as_ref()
+                .ok_or_else(|| D::Error::missing_field("prob"))?;
+            prob.parse::<Prob>()
+                .map_err(|_| D::Error::custom(format!("invalid probability '{prob}'")))
+        })
+    }
+}
+
+/// serializes the pmf with float probabilities only
+impl Serialize for DiceF64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialized_dice(self, |_| None).serialize(serializer)
+    }
+}
+
+/// reads the float probabilities, so it can also read a serialized exact [`Dice`]
+impl<'de> Deserialize<'de> for DiceF64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedDice::deserialize(deserializer)?;
+        dice_from_serialized(serialized, |entry| Ok(entry.prob_f64))
+    }
+}
+
+fn serialized_dice<P: ProbLike>(
+    dice: &Dice<P>,
+    exact: impl Fn(&P) -> Option<String>,
+) -> SerializedDice {
+    SerializedDice {
+        builder_string: dice.builder_string.clone(),
+        build_time: dice.build_time,
+        build_report: dice.build_report,
+        distribution: dice
+            .distribution
+            .iter()
+            .map(|(value, p)| SerializedProb {
+                value: *value,
+                prob: exact(p),
+                prob_f64: p.to_float(),
+            })
+            .collect(),
+    }
+}
+
+fn dice_from_serialized<P: ProbLike, E: Error>(
+    serialized: SerializedDice,
+    prob: impl Fn(&SerializedProb) -> Result<P, E>,
+) -> Result<Dice<P>, E> {
+    let mut distribution = serialized
+        .distribution
+        .iter()
+        .map(|entry| Ok((entry.value, prob(entry)?)))
+        .collect::<Result<Vec<(Value, P)>, E>>()?;
+    distribution.sort_by_key(|(v, _)| *v);
+    if distribution.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err(E::custom("duplicate value in distribution"));
+    }
+    let mut dice = Dice::from_sorted_distribution(distribution, serialized.builder_string)
+        .map_err(E::custom)?;
+    dice.build_time = serialized.build_time;
+    dice.build_report = serialized.build_report;
+    Ok(dice)
+}
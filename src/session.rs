@@ -0,0 +1,150 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use rand::Rng;
+
+use crate::{
+    dice::Dice,
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// A log of the rolls of a [`Dice`] with running statistics, for companion apps that track the rolls of a game night.
+///
+/// Rolls can be made through the session with `roll()`, or rolls of physical dice can be entered with `record()`.
+///
+/// # Examples
+/// ```
+/// use dices::{Dice, RollSession};
+/// let mut session = RollSession::new(Dice::build_from_string("d20").unwrap());
+/// for roll in [3, 5, 2, 8, 1] {
+///     assert!(session.record(roll));
+/// }
+/// assert!(!session.record(25), "a d20 cannot roll 25");
+/// assert_eq!(session.count(), 5);
+/// assert_eq!(session.mean(), Some(3.8));
+/// let luck = session.luck().unwrap();
+/// assert!(luck < 0.1, "you're rolling in the bottom 10% today");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RollSession<P = Prob> {
+    dice: Dice<P>,
+    rolls: Vec<Value>,
+    histogram: BTreeMap<Value, u64>,
+    sum: Value,
+    /// running mean and sum of squared deviations of the rolls, updated with Welford's algorithm
+    mean: f64,
+    squared_deviations: f64,
+    /// the result of `luck()` until the next roll is recorded
+    luck: OnceLock<Option<f64>>,
+}
+
+impl<P: ProbLike> RollSession<P> {
+    /// a session without rolls of `dice`
+    pub fn new(dice: Dice<P>) -> RollSession<P> {
+        RollSession {
+            dice,
+            rolls: vec![],
+            histogram: BTreeMap::new(),
+            sum: 0,
+            mean: 0.0,
+            squared_deviations: 0.0,
+            luck: OnceLock::new(),
+        }
+    }
+
+    /// the [`Dice`] that is rolled
+    pub fn dice(&self) -> &Dice<P> {
+        &self.dice
+    }
+
+    /// rolls the [`Dice`] and records the roll
+    pub fn roll(&mut self) -> Value {
+        let value = self.dice.roll();
+        self.push(value);
+        value
+    }
+
+    /// rolls the [`Dice`] like `roll()`, using `rng` as the source of randomness
+    pub fn roll_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) -> Value {
+        let value = self.dice.roll_with_rng(rng);
+        self.push(value);
+        value
+    }
+
+    /// records a roll that was made outside of the session, like the roll of a physical die
+    ///
+    /// Values that the [`Dice`] cannot roll, like mistyped ones, are not recorded and `false` is returned, so they cannot skew the statistics.
+    pub fn record(&mut self, value: Value) -> bool {
+        let possible = self
+            .dice
+            .distribution
+            .binary_search_by_key(&value, |(v, _)| *v)
+            .is_ok();
+        if possible {
+            self.push(value);
+        }
+        possible
+    }
+
+    fn push(&mut self, value: Value) {
+        self.luck = OnceLock::new();
+        self.rolls.push(value);
+        *self.histogram.entry(value).or_insert(0) += 1;
+        self.sum = self.sum.saturating_add(value);
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.rolls.len() as f64;
+        self.squared_deviations += delta * (value as f64 - self.mean);
+    }
+
+    /// removes all rolls, keeping the [`Dice`]
+    pub fn clear(&mut self) {
+        *self = RollSession::new(self.dice.clone());
+    }
+
+    /// all recorded rolls in the order they were made
+    pub fn rolls(&self) -> &[Value] {
+        &self.rolls
+    }
+
+    /// number of recorded rolls
+    pub fn count(&self) -> usize {
+        self.rolls.len()
+    }
+
+    /// the sum of all recorded rolls, saturating at the bounds of [`Value`]
+    pub fn sum(&self) -> Value {
+        self.sum
+    }
+
+    /// how often each value was rolled, in ascending order of value
+    pub fn histogram(&self) -> &BTreeMap<Value, u64> {
+        &self.histogram
+    }
+
+    /// the mean of the recorded rolls, `None` without rolls
+    pub fn mean(&self) -> Option<f64> {
+        (!self.rolls.is_empty()).then_some(self.mean)
+    }
+
+    /// the standard deviation of the recorded rolls, comparable with the square root of [`Dice::variance`], `None` without rolls
+    pub fn sd(&self) -> Option<f64> {
+        (!self.rolls.is_empty()).then(|| (self.squared_deviations / self.count() as f64).sqrt())
+    }
+
+    /// the percentile of the sum of the recorded rolls in the exact distribution of the sum of that many rolls, see `Dice::sequence_percentile()`
+    ///
+    /// A luck of 0.5 is perfectly average and 0.1 means that 90% of all sessions rolled a higher sum.
+    /// `None` without rolls or if a sum does not fit into a [`Value`].
+    ///
+    /// The distribution of the sum is calculated again after every new roll, with about 2 log2(n) convolutions for n rolls,
+    /// which get wider as the session grows. The result is kept until the next roll, so repeated calls are free.
+    pub fn luck(&self) -> Option<f64> {
+        *self.luck.get_or_init(|| {
+            if self.rolls.is_empty() {
+                return None;
+            }
+            let percentile = self.dice.checked_sequence_percentile(&self.rolls)?;
+            Some(percentile.to_float())
+        })
+    }
+}
@@ -0,0 +1,79 @@
+use std::{ops::Deref, sync::Arc};
+
+use crate::{dice::Dice, dice_builder::Prob};
+
+/// A cheaply clonable handle to a [`Dice`], so a distribution can be calculated once and shared between threads, like the request handlers of a web server.
+///
+/// Cloning only copies a pointer, all methods of [`Dice`] are available through [`Deref`].
+///
+/// # Examples
+/// ```
+/// use dices::{Dice, SharedDice};
+/// let dice: SharedDice = Dice::build_from_string("4d6").unwrap().into();
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let dice = dice.clone();
+///         std::thread::spawn(move || dice.max)
+///     })
+///     .collect();
+/// for handle in handles {
+///     assert_eq!(handle.join().unwrap(), 24);
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct SharedDice<P = Prob>(Arc<Dice<P>>);
+
+impl<P> SharedDice<P> {
+    /// a handle to `dice`
+    pub fn new(dice: Dice<P>) -> SharedDice<P> {
+        SharedDice(Arc::new(dice))
+    }
+
+    /// true if both handles point to the same [`Dice`]
+    pub fn ptr_eq(&self, other: &SharedDice<P>) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<P: Clone> SharedDice<P> {
+    /// the [`Dice`] itself, only cloned if other handles to it exist
+    pub fn into_inner(self) -> Dice<P> {
+        Arc::try_unwrap(self.0).unwrap_or_else(|dice| dice.as_ref().clone())
+    }
+}
+
+/// clones the handle, not the [`Dice`]
+impl<P> Clone for SharedDice<P> {
+    fn clone(&self) -> Self {
+        SharedDice(Arc::clone(&self.0))
+    }
+}
+
+impl<P> Deref for SharedDice<P> {
+    type Target = Dice<P>;
+
+    fn deref(&self) -> &Dice<P> {
+        &self.0
+    }
+}
+
+impl<P> From<Dice<P>> for SharedDice<P> {
+    fn from(dice: Dice<P>) -> Self {
+        SharedDice::new(dice)
+    }
+}
+
+impl<P> Dice<P> {
+    /// a [`SharedDice`] handle to `self`
+    pub fn into_shared(self) -> SharedDice<P> {
+        SharedDice::new(self)
+    }
+}
+
+/// [`Dice`] with exact and with float probabilities can be sent to and shared between threads
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Dice>();
+    assert_send_sync::<crate::dice::DiceF64>();
+    assert_send_sync::<SharedDice>();
+};
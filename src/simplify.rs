@@ -0,0 +1,255 @@
+use crate::dice_builder::{DiceBuilder, Value};
+
+impl DiceBuilder {
+    /// an equivalent [`DiceBuilder`] with fewer nodes, which is faster to build for big generated formulas
+    ///
+    /// - constants are folded, like `2*3+d6` to `6+d6`
+    /// - nested sums, products, maxima and minima are flattened, like `(d4+d6)+d8` to `d4+d6+d8`
+    /// - identical dice in a sum are merged into a sample sum, like `d6+2xd6` to `3xd6`
+    /// - identity elements like `+0`, `*1`, `/1`, `^1` and `1x` are removed
+    ///
+    /// Degenerate parts like empty compounds are kept, so `build()` fails on the simplified tree exactly if it fails on the original one.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let builder = DiceBuilder::from_string("2*3+d6").unwrap();
+    /// assert_eq!(builder.simplify().to_string(), "6+d6");
+    /// let builder = DiceBuilder::from_string("d6+(d6+0)*1+2xd6").unwrap();
+    /// assert_eq!(builder.clone().simplify().to_string(), "4xd6");
+    /// assert_eq!(
+    ///     builder.clone().simplify().build().distribution,
+    ///     builder.build().distribution
+    /// );
+    /// ```
+    pub fn simplify(self) -> DiceBuilder {
+        self.map_nodes(&mut simplify_node)
+    }
+
+    /// the simplified form of [`self`] with the operands of sums, products, maxima and minima sorted, so that formulas like `d4+2*d6` and `d6*2+d4` result in the same [`DiceBuilder`]
+    ///
+    /// Equal canonical forms imply equal distributions, but not every pair of formulas with equal distributions has the same canonical form, use [`Dice::same_distribution()`](crate::Dice::same_distribution) for that.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let a = DiceBuilder::from_string("2d6+d4").unwrap();
+    /// let b = DiceBuilder::from_string("d6+d4+d6").unwrap();
+    /// assert_ne!(a, b);
+    /// assert_eq!(a.canonicalize(), b.canonicalize());
+    /// ```
+    pub fn canonicalize(self) -> DiceBuilder {
+        self.simplify().map_nodes(&mut |node| match node {
+            DiceBuilder::SumCompound(v) => DiceBuilder::SumCompound(sorted(v)),
+            DiceBuilder::ProductCompound(v) => DiceBuilder::ProductCompound(sorted(v)),
+            DiceBuilder::MaxCompound(v) => DiceBuilder::MaxCompound(sorted(v)),
+            DiceBuilder::MinCompound(v) => DiceBuilder::MinCompound(sorted(v)),
+            other => other,
+        })
+    }
+}
+
+/// simplifies a node whose children are already simplified
+fn simplify_node(node: DiceBuilder) -> DiceBuilder {
+    let simplified = match node {
+        DiceBuilder::SumCompound(vec) => {
+            let terms = flatten(vec, DiceBuilder::SumCompound);
+            DiceBuilder::SumCompound(merge_dice(fold_constants(
+                terms,
+                Value::checked_add,
+                Some(0),
+            )))
+        }
+        DiceBuilder::ProductCompound(vec) => {
+            let factors = flatten(vec, DiceBuilder::ProductCompound);
+            DiceBuilder::ProductCompound(fold_constants(factors, Value::checked_mul, Some(1)))
+        }
+        DiceBuilder::MaxCompound(vec) => {
+            let operands = flatten(vec, DiceBuilder::MaxCompound);
+            DiceBuilder::MaxCompound(fold_constants(operands, |a, b| Some(a.max(b)), None))
+        }
+        DiceBuilder::MinCompound(vec) => {
+            let operands = flatten(vec, DiceBuilder::MinCompound);
+            DiceBuilder::MinCompound(fold_constants(operands, |a, b| Some(a.min(b)), None))
+        }
+        DiceBuilder::DivisionCompound(mut vec) => {
+            // division is evaluated from the left, so only leading constants can be folded
+            while let [DiceBuilder::Constant(a), DiceBuilder::Constant(b), ..] = vec[..] {
+                if b == 0 || a.checked_div(b).is_none() {
+                    break;
+                }
+                vec.splice(0..2, [DiceBuilder::Constant(rounded_div::i64(a, b))]);
+            }
+            remove_identities(&mut vec, 1);
+            DiceBuilder::DivisionCompound(vec)
+        }
+        DiceBuilder::PowerCompound(mut vec) => {
+            // powers are evaluated from the right, so only trailing constants can be folded
+            while let [.., DiceBuilder::Constant(base), DiceBuilder::Constant(exponent)] = vec[..] {
+                let power = u32::try_from(exponent)
+                    .ok()
+                    .and_then(|e| base.checked_pow(e));
+                match (power, exponent) {
+                    (_, 1) => vec.truncate(vec.len() - 1),
+                    (Some(power), _) => {
+                        vec.truncate(vec.len() - 2);
+                        vec.push(DiceBuilder::Constant(power));
+                    }
+                    (None, _) => break,
+                }
+            }
+            if vec.len() > 1 && vec.last() == Some(&DiceBuilder::Constant(1)) {
+                vec.pop();
+            }
+            DiceBuilder::PowerCompound(vec)
+        }
+        DiceBuilder::SampleSumCompound(mut vec) => {
+            // the sample sum of constants is their product, also for negative counts
+            while let [DiceBuilder::Constant(count), DiceBuilder::Constant(c), ..] = vec[..] {
+                let Some(product) = count.checked_mul(c) else {
+                    break;
+                };
+                vec.splice(0..2, [DiceBuilder::Constant(product)]);
+            }
+            if vec.len() > 1 && vec[0] == DiceBuilder::Constant(1) {
+                vec.remove(0);
+            }
+            DiceBuilder::SampleSumCompound(vec)
+        }
+        DiceBuilder::Negation(d) => match *d {
+            DiceBuilder::Constant(c) if c != Value::MIN => DiceBuilder::Constant(-c),
+            DiceBuilder::Negation(inner) => *inner,
+            other => DiceBuilder::Negation(Box::new(other)),
+        },
+        DiceBuilder::Absolute(d) => match *d {
+            DiceBuilder::Constant(c) if c != Value::MIN => DiceBuilder::Constant(c.abs()),
+            other => DiceBuilder::Absolute(Box::new(other)),
+        },
+        other => other,
+    };
+    // a compound of a single element has the distribution of that element
+    match simplified {
+        DiceBuilder::SumCompound(mut v)
+        | DiceBuilder::ProductCompound(mut v)
+        | DiceBuilder::DivisionCompound(mut v)
+        | DiceBuilder::MaxCompound(mut v)
+        | DiceBuilder::MinCompound(mut v)
+        | DiceBuilder::SampleSumCompound(mut v)
+        | DiceBuilder::PowerCompound(mut v)
+            if v.len() == 1 =>
+        {
+            v.remove(0)
+        }
+        other => other,
+    }
+}
+
+/// replaces every non-empty compound of the same kind as `compound` by its elements, empty ones are kept so they still fail to build
+fn flatten(
+    vec: Vec<DiceBuilder>,
+    compound: fn(Vec<DiceBuilder>) -> DiceBuilder,
+) -> Vec<DiceBuilder> {
+    let kind = std::mem::discriminant(&compound(vec![]));
+    let mut flattened = Vec::with_capacity(vec.len());
+    for d in vec {
+        if std::mem::discriminant(&d) != kind || d.children().is_empty() {
+            flattened.push(d);
+            continue;
+        }
+        match d {
+            DiceBuilder::SumCompound(v)
+            | DiceBuilder::ProductCompound(v)
+            | DiceBuilder::MaxCompound(v)
+            | DiceBuilder::MinCompound(v) => flattened.extend(v),
+            other => flattened.push(other),
+        }
+    }
+    flattened
+}
+
+/// folds all constants of a commutative compound with `operation` into one at the position of the first constant
+///
+/// The folded constant is dropped if it is the `identity` of the operation and there are other elements.
+/// Constants that would overflow are kept as they are.
+fn fold_constants(
+    vec: Vec<DiceBuilder>,
+    operation: fn(Value, Value) -> Option<Value>,
+    identity: Option<Value>,
+) -> Vec<DiceBuilder> {
+    let mut folded = Vec::with_capacity(vec.len());
+    let mut constant: Option<(usize, Value)> = None;
+    for d in vec {
+        match (d, &mut constant) {
+            (DiceBuilder::Constant(c), Some((_, acc))) => match operation(*acc, c) {
+                Some(result) => *acc = result,
+                None => folded.push(DiceBuilder::Constant(c)),
+            },
+            (DiceBuilder::Constant(c), None) => {
+                constant = Some((folded.len(), c));
+                folded.push(DiceBuilder::Constant(c));
+            }
+            (d, _) => folded.push(d),
+        }
+    }
+    if let Some((i, c)) = constant {
+        match identity == Some(c) && folded.len() > 1 {
+            true => {
+                folded.remove(i);
+            }
+            false => folded[i] = DiceBuilder::Constant(c),
+        }
+    }
+    folded
+}
+
+/// removes all constants equal to `identity` except the first element, as in `d6/1/1` where only the divisors are identities
+fn remove_identities(vec: &mut Vec<DiceBuilder>, identity: Value) {
+    let mut i = 0;
+    vec.retain(|d| {
+        i += 1;
+        i == 1 || *d != DiceBuilder::Constant(identity)
+    });
+}
+
+/// merges identical dice of a sum into one sample sum at the position of the first, like `d6+d4+2xd6` to `3xd6+d4`
+fn merge_dice(terms: Vec<DiceBuilder>) -> Vec<DiceBuilder> {
+    // every slot is a term that is not a die, or a die with the number of times it is rolled
+    let mut slots: Vec<(DiceBuilder, Option<Value>)> = Vec::with_capacity(terms.len());
+    for term in terms {
+        let (die, count) = match term {
+            DiceBuilder::FairDie { .. } => (term, 1),
+            DiceBuilder::SampleSumCompound(v) => match v.as_slice() {
+                [DiceBuilder::Constant(count), die @ DiceBuilder::FairDie { .. }]
+                    if *count >= 0 =>
+                {
+                    (die.clone(), *count)
+                }
+                _ => {
+                    slots.push((DiceBuilder::SampleSumCompound(v), None));
+                    continue;
+                }
+            },
+            other => {
+                slots.push((other, None));
+                continue;
+            }
+        };
+        match slots.iter_mut().find(|(d, c)| c.is_some() && *d == die) {
+            Some((_, Some(c))) if c.checked_add(count).is_some() => *c += count,
+            _ => slots.push((die, Some(count))),
+        }
+    }
+    slots
+        .into_iter()
+        .map(|(d, count)| match count {
+            None | Some(1) => d,
+            Some(count) => DiceBuilder::SampleSumCompound(vec![DiceBuilder::Constant(count), d]),
+        })
+        .collect()
+}
+
+/// the operands of a commutative compound ordered by their formula, which is canonical because the operands are canonicalized first
+fn sorted(mut vec: Vec<DiceBuilder>) -> Vec<DiceBuilder> {
+    vec.sort_by_cached_key(|d| d.to_string());
+    vec
+}
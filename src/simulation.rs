@@ -0,0 +1,356 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+};
+
+use rand::Rng;
+
+use crate::{
+    build_options::{BuildContext, BuildOptions},
+    dice::DiceF64,
+    dice_builder::{
+        checked_power, fair_die_sides, try_rounded_div, DiceBuildError, DiceBuilder, ExplodeKind,
+        Operation, Value,
+    },
+    prob_like::ProbLike,
+};
+
+/// An approximate distribution estimated by rolling a [`DiceBuilder`] many times, created with [`DiceBuilder::simulate()`].
+///
+/// Dereferences to the [`DiceF64`] of the observed relative frequencies, so it offers the same statistics as an exactly calculated [`Dice`](crate::Dice).
+///
+/// # Examples
+/// ```
+/// use dices::DiceBuilder;
+/// use rand::{rngs::StdRng, SeedableRng};
+/// let builder = DiceBuilder::from_string("3d6").unwrap();
+/// let empirical = builder.simulate(10000, &mut StdRng::seed_from_u64(0)).unwrap();
+/// assert_eq!(empirical.sample_count, 10000);
+/// assert!((empirical.mean - 10.5).abs() < 0.2);
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct EmpiricalDice {
+    /// number of rolls the distribution was estimated from
+    pub sample_count: usize,
+    /// the relative frequencies of the rolled values
+    pub dice: DiceF64,
+}
+
+impl EmpiricalDice {
+    /// the standard error of the estimated mean, the standard deviation of the rolls divided by the square root of the number of rolls
+    pub fn standard_error_of_mean(&self) -> f64 {
+        (self.dice.variance / self.sample_count as f64).sqrt()
+    }
+}
+
+impl Deref for EmpiricalDice {
+    type Target = DiceF64;
+
+    fn deref(&self) -> &DiceF64 {
+        &self.dice
+    }
+}
+
+impl DiceBuilder {
+    /// estimates the distribution by rolling `self` `n_samples` times, for formulas whose exact distribution is too expensive to calculate
+    ///
+    /// Every roll walks the tree of [`self`] and rolls each die in it, so it takes time proportional to `n_samples` and the number of dice rolled.
+    /// Only explosions without a minimum value, like `(30d30)!`, calculate the distribution of their operand, once before the first roll that needs its maximum.
+    pub fn simulate<R: Rng + ?Sized>(
+        &self,
+        n_samples: usize,
+        rng: &mut R,
+    ) -> Result<EmpiricalDice, DiceBuildError> {
+        if n_samples == 0 {
+            return Err(DiceBuildError::EmptyDistribution);
+        }
+        let mut counts: BTreeMap<Value, usize> = BTreeMap::new();
+        // the context keeps the explosion thresholds between the rolls
+        let mut ctx = SampleContext::default();
+        for _ in 0..n_samples {
+            let v = self.sample(rng, &mut ctx)?;
+            *counts.entry(v).or_insert(0) += 1;
+        }
+        let distribution: Vec<(Value, f64)> = counts
+            .into_iter()
+            .map(|(v, count)| (v, f64::from_ratio(count as u64, n_samples as u64)))
+            .collect();
+        let dice = DiceF64::from_sorted_distribution(distribution, self.to_string())?;
+        Ok(EmpiricalDice {
+            sample_count: n_samples,
+            dice,
+        })
+    }
+
+    /// rolls [`self`] once by rolling every die in its tree and combining the rolls, without calculating the distribution
+    ///
+    /// Much cheaper than `build()` followed by `roll()` if only a few random rolls are needed.
+    ///
+    /// panics if the [`DiceBuilder`] is degenerate, use `try_roll_once()` to get a [`DiceBuildError`] instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let builder = DiceBuilder::from_string("100d100").unwrap();
+    /// let roll = builder.roll_once(&mut rand::thread_rng());
+    /// assert!((100..=10000).contains(&roll));
+    /// ```
+    pub fn roll_once<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        match self.try_roll_once(rng) {
+            Ok(value) => value,
+            Err(err) => panic!("could not roll dice: {err:?}"),
+        }
+    }
+
+    /// rolls [`self`] once like `roll_once()`, but returns a [`DiceBuildError`] instead of panicking on degenerate trees
+    pub fn try_roll_once<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<Value, DiceBuildError> {
+        self.sample(rng, &mut SampleContext::default())
+    }
+
+    /// rolls every die in the tree of [`self`] once and combines the rolls, `ctx` holds the values of the enclosing [`DiceBuilder::Let`]s
+    pub(crate) fn sample<'t, R: Rng + ?Sized>(
+        &'t self,
+        rng: &mut R,
+        ctx: &mut SampleContext<'t>,
+    ) -> Result<Value, DiceBuildError> {
+        let value = match self {
+            DiceBuilder::Constant(v) => *v,
+            DiceBuilder::FairDie { min, max } => {
+                fair_die_sides(*min, *max)?;
+                rng.gen_range(*min..=*max)
+            }
+            DiceBuilder::SumCompound(vec) => fold_samples(vec, rng, ctx, Value::checked_add)?,
+            DiceBuilder::ProductCompound(vec) => fold_samples(vec, rng, ctx, Value::checked_mul)?,
+            DiceBuilder::DivisionCompound(vec) => {
+                let (first, rest) = vec.split_first().ok_or(DiceBuildError::EmptyCompound)?;
+                let mut value = first.sample(rng, ctx)?;
+                for d in rest.iter() {
+                    value = try_rounded_div(value, d.sample(rng, ctx)?)?;
+                }
+                value
+            }
+            DiceBuilder::MaxCompound(vec) => fold_samples(vec, rng, ctx, |a, b| Some(a.max(b)))?,
+            DiceBuilder::MinCompound(vec) => fold_samples(vec, rng, ctx, |a, b| Some(a.min(b)))?,
+            DiceBuilder::SampleSumCompound(vec) => {
+                let (first, rest) = vec.split_first().ok_or(DiceBuildError::EmptyCompound)?;
+                let mut count = first.sample(rng, ctx)?;
+                for d in rest.iter() {
+                    let mut total: Value = 0;
+                    for _ in 0..count.unsigned_abs() {
+                        total = overflow(total.checked_add(d.sample(rng, ctx)?))?;
+                    }
+                    // a negative count subtracts the samples, like in the exact calculation
+                    count = match count < 0 {
+                        true => overflow(total.checked_neg())?,
+                        false => total,
+                    };
+                }
+                count
+            }
+            DiceBuilder::PowerCompound(vec) => {
+                let samples = vec
+                    .iter()
+                    .map(|d| d.sample(rng, ctx))
+                    .collect::<Result<Vec<Value>, DiceBuildError>>()?;
+                let (last, rest) = samples.split_last().ok_or(DiceBuildError::EmptyCompound)?;
+                let mut exponent = *last;
+                for base in rest.iter().rev() {
+                    if exponent < 0 {
+                        return Err(DiceBuildError::NegativeExponent(exponent));
+                    }
+                    exponent = overflow(checked_power(*base, exponent))?;
+                }
+                exponent
+            }
+            DiceBuilder::Absolute(d) => overflow(d.sample(rng, ctx)?.checked_abs())?,
+            DiceBuilder::Let { name, value, body } => {
+                let v = value.sample(rng, ctx)?;
+                ctx.bind(name, v);
+                let body_value = body.sample(rng, ctx);
+                ctx.unbind();
+                body_value?
+            }
+            DiceBuilder::Variable(name) => ctx
+                .binding(name)
+                .ok_or_else(|| DiceBuildError::UnboundVariable(name.clone()))?,
+            DiceBuilder::Negation(d) => overflow(d.sample(rng, ctx)?.checked_neg())?,
+            DiceBuilder::NthHighest { n, k, dice_builder } => {
+                if *k < 1 || k > n {
+                    return Err(DiceBuildError::InvalidNthHighest { n: *n, k: *k });
+                }
+                let mut samples = Vec::with_capacity(*n);
+                for _ in 0..*n {
+                    match dice_builder.as_ref() {
+                        // every roll of the explosions is a value of the pool on its own
+                        DiceBuilder::Explode {
+                            dice_builder,
+                            min_value,
+                            max_iterations,
+                            kind,
+                        } if kind.separates_rolls_in_pools() => samples.extend(sample_explosion(
+                            dice_builder,
+                            *min_value,
+                            *max_iterations,
+                            *kind,
+                            rng,
+                            ctx,
+                        )?),
+                        dice_builder => samples.push(dice_builder.sample(rng, ctx)?),
+                    }
+                }
+                samples.sort_unstable_by(|a, b| b.cmp(a));
+                samples[k - 1]
+            }
+            DiceBuilder::Mixture(components) => {
+                pick_component(components, rng)?.sample(rng, ctx)?
+            }
+            DiceBuilder::Reroll {
+                dice_builder,
+                max_value,
+                keep_best,
+            } => {
+                let roll = dice_builder.sample(rng, ctx)?;
+                match roll > *max_value {
+                    true => roll,
+                    false => {
+                        let reroll = dice_builder.sample(rng, ctx)?;
+                        match keep_best {
+                            true => roll.max(reroll),
+                            false => reroll,
+                        }
+                    }
+                }
+            }
+            DiceBuilder::Explode {
+                dice_builder,
+                min_value,
+                max_iterations,
+                kind,
+            } => sample_explosion(dice_builder, *min_value, *max_iterations, *kind, rng, ctx)?
+                .into_iter()
+                .try_fold(0, |total: Value, roll| overflow(total.checked_add(roll)))?,
+        };
+        Ok(value)
+    }
+}
+
+/// the rolls of an exploding die, the rolls of the explosions reduced by the penalty of `kind`
+fn sample_explosion<'t, R: Rng + ?Sized>(
+    dice_builder: &'t DiceBuilder,
+    min_value: Option<Value>,
+    max_iterations: usize,
+    kind: ExplodeKind,
+    rng: &mut R,
+    ctx: &mut SampleContext<'t>,
+) -> Result<Vec<Value>, DiceBuildError> {
+    let threshold = ctx.explosion_threshold(dice_builder, min_value)?;
+    let mut roll = dice_builder.sample(rng, ctx)?;
+    let mut rolls = vec![roll];
+    while rolls.len() <= max_iterations && roll >= threshold {
+        roll = dice_builder.sample(rng, ctx)?;
+        rolls.push(overflow(roll.checked_sub(kind.penalty()))?);
+    }
+    Ok(rolls)
+}
+
+/// samples every element of `vec` once and combines the samples from left to right with `operation`
+fn fold_samples<'t, R: Rng + ?Sized>(
+    vec: &'t [DiceBuilder],
+    rng: &mut R,
+    ctx: &mut SampleContext<'t>,
+    operation: Operation,
+) -> Result<Value, DiceBuildError> {
+    let (first, rest) = vec.split_first().ok_or(DiceBuildError::EmptyCompound)?;
+    let mut value = first.sample(rng, ctx)?;
+    for d in rest.iter() {
+        value = overflow(operation(value, d.sample(rng, ctx)?))?;
+    }
+    Ok(value)
+}
+
+fn overflow(value: Option<Value>) -> Result<Value, DiceBuildError> {
+    value.ok_or(DiceBuildError::ValueOverflow)
+}
+
+/// The state of rolling a [`DiceBuilder`] tree, kept between the rolls of `simulate()`.
+#[derive(Default)]
+pub(crate) struct SampleContext<'t> {
+    /// values of the variables bound by the enclosing [`DiceBuilder::Let`]s, innermost last
+    bindings: Vec<(&'t str, Value)>,
+    /// explosion thresholds calculated so far, by the operand of the explosion and the bindings it was calculated with
+    thresholds: HashMap<(&'t DiceBuilder, Vec<Value>), Value>,
+}
+
+impl<'t> SampleContext<'t> {
+    pub fn bind(&mut self, name: &'t str, value: Value) {
+        self.bindings.push((name, value));
+    }
+
+    pub fn unbind(&mut self) {
+        self.bindings.pop();
+    }
+
+    /// the value of the innermost binding of `name`
+    pub fn binding(&self, name: &str) -> Option<Value> {
+        self.bindings
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    /// the smallest roll of `dice_builder` that explodes, which is its maximum if no `min_value` is given
+    ///
+    /// The maximum needs the distribution of `dice_builder`, so it is calculated only once for every operand and bindings.
+    pub fn explosion_threshold(
+        &mut self,
+        dice_builder: &'t DiceBuilder,
+        min_value: Option<Value>,
+    ) -> Result<Value, DiceBuildError> {
+        if let Some(v) = min_value {
+            return Ok(v);
+        }
+        let key = (
+            dice_builder,
+            self.bindings.iter().map(|(_, v)| *v).collect(),
+        );
+        if let Some(threshold) = self.thresholds.get(&key) {
+            return Ok(*threshold);
+        }
+        let options = BuildOptions::default();
+        let mut ctx = BuildContext::<f64>::new(&options);
+        for (name, v) in self.bindings.iter() {
+            ctx.bind(name, *v);
+        }
+        let threshold = dice_builder
+            .distribution_hashmap(&mut ctx)?
+            .keys()
+            .max()
+            .copied()
+            .ok_or(DiceBuildError::EmptyCompound)?;
+        self.thresholds.insert(key, threshold);
+        Ok(threshold)
+    }
+}
+
+/// picks one component of a [`DiceBuilder::Mixture`] with a probability proportional to its weight
+pub(crate) fn pick_component<'t, R: Rng + ?Sized>(
+    components: &'t [(u32, DiceBuilder)],
+    rng: &mut R,
+) -> Result<&'t DiceBuilder, DiceBuildError> {
+    if components.is_empty() {
+        return Err(DiceBuildError::EmptyCompound);
+    }
+    let total_weight: u64 = components.iter().map(|(w, _)| u64::from(*w)).sum();
+    if total_weight == 0 {
+        return Err(DiceBuildError::ZeroMixtureWeight);
+    }
+    let mut pick = rng.gen_range(0..total_weight);
+    for (weight, d) in components.iter() {
+        match pick.checked_sub(u64::from(*weight)) {
+            Some(rest) => pick = rest,
+            None => return Ok(d),
+        }
+    }
+    unreachable!("the pick is smaller than the total weight")
+}
@@ -0,0 +1,359 @@
+use crate::{
+    convolution::sample_sum_sorted,
+    dice::{Dice, ToFloat},
+    dice_builder::Value,
+    prob_like::ProbLike,
+};
+
+/// How `Dice::quantile_with()` picks the p-quantile of a distribution, if several values or the space between two values qualify
+///
+/// The cdf of `d6` reaches 1/2 exactly at 3, so both 3 and 4 split the distribution in halves:
+/// the lower median is 3, the upper median 4 and the midpoint 3.5, like statistics packages report it for the numbers 1 to 6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum QuantileMethod {
+    /// the smallest value q with P(X ≤ q) ≥ p, the convention of `Dice::quantile()` and [`Dice::median`]
+    #[default]
+    Lower,
+    /// the smallest value q with P(X ≤ q) > p, the largest value that is still a p-quantile if the cdf reaches p exactly
+    Upper,
+    /// the mean of the lower and the upper quantile
+    Midpoint,
+    /// the point where the cdf, linearly interpolated between the values of the distribution, reaches p
+    Interpolated,
+}
+
+/// The summary statistics of a [`Dice`], everything needed to render a box-plot, created with [`Dice::summary()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiceSummary {
+    /// mininum value of the distribution
+    pub min: Value,
+    /// first quartile, the smallest 0.25-quantile
+    pub q1: Value,
+    /// median of the distribution
+    pub median: Value,
+    /// third quartile, the smallest 0.75-quantile
+    pub q3: Value,
+    /// maximum value of the distribution
+    pub max: Value,
+    /// mean of the distribution as float
+    pub mean_f64: f64,
+    /// standard deviation of the distribution as float
+    pub sd_f64: f64,
+    /// mode or modes of the distribution
+    pub mode: Vec<Value>,
+}
+
+impl DiceSummary {
+    /// the interquartile range q3 - q1
+    pub fn iqr(&self) -> Value {
+        self.q3 - self.q1
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// summary statistics of the distribution: min, quartiles, max, mean, standard deviation and mode
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let summary = Dice::build_from_string("2d6").unwrap().summary();
+    /// assert_eq!((summary.min, summary.q1, summary.median, summary.q3, summary.max), (2, 5, 7, 9, 12));
+    /// assert_eq!(summary.iqr(), 4);
+    /// ```
+    pub fn summary(&self) -> DiceSummary {
+        DiceSummary {
+            min: self.min,
+            q1: self.quantile(0.25),
+            median: self.median,
+            q3: self.quantile(0.75),
+            max: self.max,
+            mean_f64: self.mean.to_float(),
+            sd_f64: self.variance.to_float().sqrt(),
+            mode: self.mode.clone(),
+        }
+    }
+
+    /// the smallest p-quantile for each p in `ps`, see `quantile()`
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("d100").unwrap();
+    /// assert_eq!(d.percentiles(&[0.1, 0.5, 0.9]), vec![10, 50, 90]);
+    /// ```
+    pub fn percentiles(&self, ps: &[f64]) -> Vec<Value> {
+        self.quantiles(ps)
+    }
+
+    /// the smallest p-quantile for each p in `ps`, like `quantile()` for each of them, but for probabilities of any [`ToFloat`] type
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// assert_eq!(d.quantiles(&[0.0, 0.25, 0.5, 1.0]), vec![2, 5, 7, 12]);
+    /// let thirds = [BigFraction::new(1u64, 3u64), BigFraction::new(2u64, 3u64)];
+    /// assert_eq!(d.quantiles(&thirds), vec![6, 8]);
+    /// ```
+    pub fn quantiles<T: ToFloat>(&self, ps: &[T]) -> Vec<Value> {
+        ps.iter().map(|p| self.quantile(p.to_float())).collect()
+    }
+
+    /// the steps of the quantile function, the inverse of the cumulative distribution, in ascending order
+    ///
+    /// Each step is a cumulative probability with a value, `quantile(p)` is that value for every p above the probability of the previous step up to the probability of this one.
+    /// Values with probability 0 have no step and the probability of the last step is exactly 1.
+    /// Walking the steps of two [`Dice`] side by side gives the points of a QQ plot, and a stratified sample picks one value per probability interval.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("d4").unwrap();
+    /// let steps: Vec<(BigFraction, i64)> = d.inverse_cdf_iter().collect();
+    /// assert_eq!(steps[1], (BigFraction::new(1u64, 2u64), 2));
+    /// assert_eq!(steps[3], (BigFraction::from(1), 4));
+    /// ```
+    pub fn inverse_cdf_iter(&self) -> impl Iterator<Item = (P, Value)> + '_ {
+        let last = self.distribution.len() - 1;
+        self.cumulative_distribution()
+            .iter()
+            .zip(&self.distribution)
+            .enumerate()
+            .filter(|(_, (_, (_, p)))| *p > P::zero())
+            .map(move |(i, ((value, c), _))| match i == last {
+                // exactly 1, even if rounding errors of float probabilities left the last cumulative probability slightly off
+                true => (P::one(), *value),
+                false => (c.clone(), *value),
+            })
+    }
+
+    /// the p-quantile of the distribution with the convention of `method`, see [`QuantileMethod`]
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Dice, QuantileMethod};
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// assert_eq!(d6.quantile_with(0.5, QuantileMethod::Lower), 3.0);
+    /// assert_eq!(d6.quantile_with(0.5, QuantileMethod::Upper), 4.0);
+    /// assert_eq!(d6.quantile_with(0.5, QuantileMethod::Midpoint), 3.5);
+    /// let d4 = Dice::build_from_string("d4").unwrap();
+    /// assert_eq!(d4.quantile_with(0.375, QuantileMethod::Interpolated), 1.5);
+    /// ```
+    pub fn quantile_with<T: ToFloat>(&self, p: T, method: QuantileMethod) -> f64 {
+        let p: f64 = p.to_float();
+        let cdf = self.cumulative_distribution();
+        let value_at = |index: usize| match cdf.get(index) {
+            Some((value, _)) => *value as f64,
+            // rounding errors of float probabilities can leave the last cumulative probability below p
+            None => self.max as f64,
+        };
+        match method {
+            QuantileMethod::Lower => self.quantile(p) as f64,
+            QuantileMethod::Upper => value_at(cdf.partition_point(|(_, c)| c.to_float() <= p)),
+            QuantileMethod::Midpoint => {
+                (self.quantile_with(p, QuantileMethod::Lower)
+                    + self.quantile_with(p, QuantileMethod::Upper))
+                    / 2.0
+            }
+            QuantileMethod::Interpolated => {
+                let index = cdf.partition_point(|(_, c)| c.to_float() < p);
+                let (Some((low, low_c)), Some((high, high_c))) = (
+                    index.checked_sub(1).and_then(|i| cdf.get(i)),
+                    cdf.get(index),
+                ) else {
+                    return value_at(index);
+                };
+                let (low, low_c, high_c) = (*low as f64, low_c.to_float(), high_c.to_float());
+                low + (p - low_c) / (high_c - low_c) * (*high as f64 - low)
+            }
+        }
+    }
+
+    /// the median of the distribution with the convention of `method`, [`Dice::median`] is the one of [`QuantileMethod::Lower`]
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Dice, QuantileMethod};
+    /// let d = Dice::build_from_string("2d10").unwrap();
+    /// assert_eq!(d.median_with(QuantileMethod::Lower), d.median as f64);
+    /// assert_eq!(d.median_with(QuantileMethod::Midpoint), 11.0);
+    /// ```
+    pub fn median_with(&self, method: QuantileMethod) -> f64 {
+        self.quantile_with(0.5, method)
+    }
+
+    /// the exact expected value E\[f(X)\] of `f` applied to a number X sampled from `self`
+    ///
+    /// # Examples
+    /// expected payoff of max(X-10, 0) for X = 2d6+3:
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6+3").unwrap();
+    /// let payoff = d.expectation_of(|x| (x - 10).max(0));
+    /// assert_eq!(payoff, BigFraction::new(35u64, 36u64));
+    /// ```
+    pub fn expectation_of(&self, f: impl Fn(Value) -> Value) -> P {
+        let mut expectation = P::zero();
+        for (v, p) in self.distribution.iter() {
+            expectation += p.clone() * P::from_value(f(*v));
+        }
+        expectation
+    }
+
+    /// the expected value E\[f(X)\] of a float valued `f` applied to a number X sampled from `self`
+    pub fn expectation_of_f64(&self, f: impl Fn(Value) -> f64) -> f64 {
+        self.distribution
+            .iter()
+            .map(|(v, p)| p.to_float() * f(*v))
+            .sum()
+    }
+
+    /// where the total of an observed sequence of `rolls` falls in the exact distribution of the sum of that many rolls of `self`, a probability between 0 and 1
+    ///
+    /// It is the probability of rolling a lower total plus half of the probability of rolling the same total,
+    /// so 1/2 is a perfectly average sequence and 1/10 means that 90% of all sequences roll a higher total.
+    /// The distribution of the sum is calculated with about 2 log2(n) convolutions for n rolls.
+    ///
+    /// panics if the total, or the total of as many rolls of [`Dice::max`] or [`Dice::min`], does not fit into a [`Value`].
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// // 2d6 rolls less than 4 with probability 3/36 and exactly 4 with 3/36
+    /// assert_eq!(d6.sequence_percentile(&[1, 3]), BigFraction::new(1u64, 8u64));
+    /// assert_eq!(d6.sequence_percentile(&[1, 2, 3, 4, 5, 6]), BigFraction::new(1u64, 2u64));
+    /// ```
+    pub fn sequence_percentile(&self, rolls: &[Value]) -> P {
+        self.checked_sequence_percentile(rolls)
+            .expect("the sum of the rolls does not fit into 64 bits")
+    }
+
+    /// like `sequence_percentile()`, but `None` if a sum does not fit into a [`Value`]
+    pub(crate) fn checked_sequence_percentile(&self, rolls: &[Value]) -> Option<P> {
+        let total = rolls
+            .iter()
+            .try_fold(0, |total: Value, v| total.checked_add(*v))?;
+        let sums = sample_sum_sorted(&self.distribution, rolls.len())?;
+        let mut below = P::zero();
+        let mut equal = P::zero();
+        for (v, p) in sums.iter() {
+            match v.cmp(&total) {
+                std::cmp::Ordering::Less => below += p,
+                std::cmp::Ordering::Equal => equal += p,
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Some(below + equal * P::from_ratio(1, 2))
+    }
+
+    /// the Shannon entropy of the distribution in bits
+    ///
+    /// A constant has an entropy of 0, a fair die with n sides an entropy of log2(n).
+    /// The more "swingy" a formula is, the higher its entropy.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d8 = Dice::build_from_string("d8").unwrap();
+    /// assert!((d8.entropy() - 3.0).abs() < 1e-12);
+    /// ```
+    pub fn entropy(&self) -> f64 {
+        self.distribution
+            .iter()
+            .map(|(_, p)| p.to_float())
+            .filter(|p| *p > 0.0)
+            .map(|p| -p * p.log2())
+            .sum()
+    }
+
+    /// every mode of the distribution with its probability, ascending by value
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// assert_eq!(d.mode_with_prob(), vec![(7, BigFraction::new(1u64, 6u64))]);
+    /// ```
+    pub fn mode_with_prob(&self) -> Vec<(Value, P)> {
+        let mut mode = self.mode.clone();
+        mode.sort_unstable();
+        mode.into_iter().map(|v| (v, self.prob(v))).collect()
+    }
+
+    /// the `k` most likely values with their probabilities, sorted by descending probability and equally likely values by ascending value
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// let most_likely = d.most_likely(3);
+    /// assert_eq!(most_likely[0], (7, BigFraction::new(1u64, 6u64)));
+    /// assert_eq!(most_likely[1].0, 6);
+    /// assert_eq!(most_likely[2].0, 8);
+    /// ```
+    pub fn most_likely(&self, k: usize) -> Vec<(Value, P)> {
+        let mut most_likely: Vec<&(Value, P)> = self.distribution.iter().collect();
+        // stable sort, so equally likely values stay in ascending order
+        most_likely.sort_by(|(_, p), (_, q)| q.partial_cmp(p).unwrap_or(std::cmp::Ordering::Equal));
+        most_likely.into_iter().take(k).cloned().collect()
+    }
+
+    /// the probability of the most likely value, which all modes share
+    pub fn prob_of_mode(&self) -> P {
+        match self.mode.first() {
+            Some(v) => self.prob(*v),
+            None => P::zero(),
+        }
+    }
+
+    /// P(X >= v) for every value v of the distribution, the classic table of the chance to reach a target number
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let table = Dice::build_from_string("d4").unwrap().at_least_table();
+    /// assert_eq!(table[0], (1, BigFraction::from(1)));
+    /// assert_eq!(table[3], (4, BigFraction::new(1u64, 4u64)));
+    /// ```
+    pub fn at_least_table(&self) -> Vec<(Value, P)> {
+        let mut before = P::zero();
+        let mut table = Vec::with_capacity(self.distribution.len());
+        for (i, (v, c)) in self.cdf_iter().enumerate() {
+            // exactly 1 for the smallest value, even with rounding errors of float probabilities
+            let at_least = match i {
+                0 => P::one(),
+                _ => P::one() - before,
+            };
+            table.push((v, at_least));
+            before = c;
+        }
+        table
+    }
+
+    /// P(X <= v) for every value v of the distribution, the same as the cumulative distribution
+    pub fn at_most_table(&self) -> Vec<(Value, P)> {
+        self.cdf_iter().collect()
+    }
+
+    /// `at_least_table()` with float probabilities, for display
+    pub fn at_least_table_f64(&self) -> Vec<(Value, f64)> {
+        to_float_table(self.at_least_table())
+    }
+
+    /// `at_most_table()` with float probabilities, for display
+    pub fn at_most_table_f64(&self) -> Vec<(Value, f64)> {
+        to_float_table(self.at_most_table())
+    }
+}
+
+fn to_float_table<P: ProbLike>(table: Vec<(Value, P)>) -> Vec<(Value, f64)> {
+    table.into_iter().map(|(v, p)| (v, p.to_float())).collect()
+}
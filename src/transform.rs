@@ -0,0 +1,413 @@
+use std::{
+    collections::BTreeMap,
+    ops::{Add, Mul, Sub},
+};
+
+use crate::{
+    build_options::{BuildOptions, BuildReport},
+    convolution::{convolute_sorted, sum_convolute_sorted},
+    dice::{Dice, LazyCdf},
+    dice_builder::{DiceBuildError, DiceBuilder, Prob, Value},
+    prob_like::ProbLike,
+    wasm_safe::{elapsed_millis, WasmSafeInstant},
+};
+
+/// A range of values of a distribution with their summed probability, created with [`Dice::bucketed()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket<P = Prob> {
+    /// smallest value of the range
+    pub min: Value,
+    /// largest value of the range, inclusive
+    pub max: Value,
+    /// probability that a sampled value lies in the range
+    pub prob: P,
+}
+
+/// An operation that combines two independent [`Dice`] in [`Dice::combined_with()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiceOperation {
+    /// the sum of both values, like `+` in a formula
+    Add,
+    /// the first value minus the second one, like `-` in a formula
+    Subtract,
+    /// the product of both values, like `*` in a formula
+    Multiply,
+    /// the higher of both values, like `max(a,b)` in a formula
+    Max,
+    /// the lower of both values, like `min(a,b)` in a formula
+    Min,
+}
+
+impl DiceOperation {
+    /// the combined value of `a` and `b`, `None` if it does not fit into a [`Value`]
+    pub fn checked_apply(self, a: Value, b: Value) -> Option<Value> {
+        match self {
+            DiceOperation::Add => a.checked_add(b),
+            DiceOperation::Subtract => a.checked_sub(b),
+            DiceOperation::Multiply => a.checked_mul(b),
+            DiceOperation::Max => Some(a.max(b)),
+            DiceOperation::Min => Some(a.min(b)),
+        }
+    }
+
+    /// the formula of the operation on two formulas
+    fn join(self, first: &str, second: &str) -> String {
+        match self {
+            DiceOperation::Add => format!("({first})+({second})"),
+            DiceOperation::Subtract => format!("({first})-({second})"),
+            DiceOperation::Multiply => format!("({first})*({second})"),
+            DiceOperation::Max => format!("max({first},{second})"),
+            DiceOperation::Min => format!("min({first},{second})"),
+        }
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// the distribution of `op` applied to independent samples of `self` and `other`
+    ///
+    /// panics if a combined value does not fit into a [`Value`], `try_combined_with()` returns an error instead.
+    ///
+    /// Only the two distributions are convoluted, which is a lot cheaper than building the formula of both from scratch,
+    /// for example when one modifier of a big formula changes.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Dice, DiceOperation};
+    /// let attack = Dice::build_from_string("d20+5").unwrap();
+    /// let advantage = attack.combined_with(DiceOperation::Max, &attack);
+    /// assert_eq!(advantage.builder_string, "max(d20+5,d20+5)");
+    /// assert_eq!(
+    ///     advantage.distribution,
+    ///     Dice::build_from_string("max(d20,d20)+5").unwrap().distribution
+    /// );
+    /// ```
+    pub fn combined_with(&self, op: DiceOperation, other: &Dice<P>) -> Dice<P> {
+        self.try_combined_with(op, other)
+            .expect("the combined values do not fit into 64 bits")
+    }
+
+    /// the distribution of `op` applied to independent samples of `self` and `other` like `combined_with()`, [`DiceBuildError::ValueOverflow`] if a combined value does not fit into a [`Value`]
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Dice, DiceBuildError, DiceOperation};
+    /// let max = Dice::build_from_string("9223372036854775807").unwrap();
+    /// let d6 = Dice::build_from_string("d6").unwrap();
+    /// assert_eq!(
+    ///     max.try_combined_with(DiceOperation::Add, &d6).err(),
+    ///     Some(DiceBuildError::ValueOverflow)
+    /// );
+    /// assert_eq!(max.try_combined_with(DiceOperation::Subtract, &d6).unwrap().min, i64::MAX - 6);
+    /// ```
+    pub fn try_combined_with(
+        &self,
+        op: DiceOperation,
+        other: &Dice<P>,
+    ) -> Result<Dice<P>, DiceBuildError> {
+        let distribution = match op {
+            DiceOperation::Add => {
+                // only the sums of the extremes can overflow
+                for (a, b) in [(self.min, other.min), (self.max, other.max)] {
+                    op.checked_apply(a, b)
+                        .ok_or(DiceBuildError::ValueOverflow)?;
+                }
+                sum_convolute_sorted(&self.distribution, &other.distribution)
+            }
+            op => convolute_sorted(&self.distribution, &other.distribution, |a, b| {
+                op.checked_apply(a, b)
+            })
+            .ok_or(DiceBuildError::ValueOverflow)?,
+        };
+        let builder_string = derived_builder_string(&self.builder_string, |s1| {
+            derived_builder_string(&other.builder_string, |s2| op.join(s1, s2))
+        });
+        Ok(Dice::from_sorted_distribution(distribution, builder_string)
+            .expect("convoluting non-empty distributions gives a non-empty distribution"))
+    }
+
+    /// the distribution of `self` plus an independent roll of the formula `input`, see `combined_with()`
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let damage = Dice::build_from_string("2d6+3").unwrap();
+    /// let blessed = damage.plus_formula("d4").unwrap();
+    /// assert_eq!(blessed.builder_string, "(2xd6+3)+(d4)");
+    /// assert_eq!(blessed.mean, Dice::build_from_string("2d6+3+d4").unwrap().mean);
+    /// ```
+    pub fn plus_formula(&self, input: &str) -> Result<Dice<P>, DiceBuildError> {
+        let term = DiceBuilder::from_string(input)?.build_as::<P>(&BuildOptions::default())?;
+        self.try_combined_with(DiceOperation::Add, &term)
+    }
+
+    /// the distribution of `self` conditional on `predicate` holding for the sampled value, renormalized to a total probability of 1
+    ///
+    /// returns `None` if the predicate holds for no value of the distribution.
+    ///
+    /// # Examples
+    /// distribution of 2d6 given the result is at least 7:
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("2d6").unwrap();
+    /// let given = d.given(|x| x >= 7).unwrap();
+    /// assert_eq!(given.min, 7);
+    /// assert_eq!(given.prob(7), BigFraction::new(6u64, 21u64));
+    /// ```
+    pub fn given(&self, predicate: impl Fn(Value) -> bool) -> Option<Dice<P>> {
+        let filtered: Vec<(Value, P)> = self
+            .distribution
+            .iter()
+            .filter(|(v, _)| predicate(*v))
+            .cloned()
+            .collect();
+        let mut total = P::zero();
+        for (_, p) in filtered.iter() {
+            total += p;
+        }
+        if total <= P::zero() {
+            return None;
+        }
+        let distribution = filtered
+            .into_iter()
+            .map(|(v, p)| (v, p / total.clone()))
+            .collect();
+        Dice::from_sorted_distribution(distribution, String::new()).ok()
+    }
+
+    /// the distribution of `self` plus a constant `k`
+    ///
+    /// all parameters are shifted directly without recalculating them from the distribution.
+    ///
+    /// panics if a shifted value does not fit into a [`Value`], `try_shifted()` returns an error instead.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let d = Dice::build_from_string("2d6").unwrap().shifted(-2);
+    /// assert_eq!((d.min, d.median, d.max), (0, 5, 10));
+    /// assert_eq!(d.builder_string, "2xd6-2");
+    /// ```
+    pub fn shifted(&self, k: Value) -> Dice<P> {
+        self.try_shifted(k)
+            .expect("the shifted values do not fit into 64 bits")
+    }
+
+    /// the distribution of `self` plus a constant `k` like `shifted()`, [`DiceBuildError::ValueOverflow`] if a shifted value does not fit into a [`Value`]
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::{Dice, DiceBuildError};
+    /// let d = Dice::build_from_string("d6").unwrap();
+    /// assert_eq!(d.try_shifted(i64::MAX).err(), Some(DiceBuildError::ValueOverflow));
+    /// assert_eq!(d.try_shifted(-1).unwrap().max, 5);
+    /// ```
+    pub fn try_shifted(&self, k: Value) -> Result<Dice<P>, DiceBuildError> {
+        // all values lie between the minimum and the maximum
+        for extreme in [self.min, self.max] {
+            extreme
+                .checked_add(k)
+                .ok_or(DiceBuildError::ValueOverflow)?;
+        }
+        let start_instant = WasmSafeInstant::now();
+        let shift = |values: &Vec<(Value, P)>| -> Vec<(Value, P)> {
+            values.iter().map(|(v, p)| (v + k, p.clone())).collect()
+        };
+        let mut dice = Dice {
+            builder_string: derived_builder_string(&self.builder_string, |s| match k < 0 {
+                true => format!("{s}-{}", k.unsigned_abs()),
+                false => format!("{s}+{k}"),
+            }),
+            min: self.min + k,
+            max: self.max + k,
+            median: self.median + k,
+            mode: self.mode.iter().map(|v| v + k).collect(),
+            mean: self.mean.clone() + P::from_value(k),
+            variance: self.variance.clone(),
+            distribution: shift(&self.distribution),
+            cumulative_distribution: LazyCdf::default(),
+            build_time: 0,
+            build_report: BuildReport::default(),
+        };
+        dice.build_time = elapsed_millis(&start_instant);
+        Ok(dice)
+    }
+
+    /// the distribution of `self` multiplied by a constant `k`
+    ///
+    /// for positive `k` all parameters are scaled directly, otherwise they are recalculated from the distribution.
+    ///
+    /// panics if a scaled value does not fit into a [`Value`], `try_scaled()` returns an error instead.
+    pub fn scaled(&self, k: Value) -> Dice<P> {
+        self.try_scaled(k)
+            .expect("the scaled values do not fit into 64 bits")
+    }
+
+    /// the distribution of `self` multiplied by a constant `k` like `scaled()`, [`DiceBuildError::ValueOverflow`] if a scaled value does not fit into a [`Value`]
+    pub fn try_scaled(&self, k: Value) -> Result<Dice<P>, DiceBuildError> {
+        // multiplying with a constant is monotonic, so only the extremes can overflow
+        for extreme in [self.min, self.max] {
+            extreme
+                .checked_mul(k)
+                .ok_or(DiceBuildError::ValueOverflow)?;
+        }
+        let builder_string = derived_builder_string(&self.builder_string, |s| format!("({s})*{k}"));
+        if k <= 0 {
+            return Ok(self.map_values(|v| v * k, builder_string));
+        }
+        let start_instant = WasmSafeInstant::now();
+        let scale = |values: &Vec<(Value, P)>| -> Vec<(Value, P)> {
+            values.iter().map(|(v, p)| (v * k, p.clone())).collect()
+        };
+        let k_prob = P::from_value(k);
+        let mut dice = Dice {
+            builder_string,
+            min: self.min * k,
+            max: self.max * k,
+            median: self.median * k,
+            mode: self.mode.iter().map(|v| v * k).collect(),
+            mean: self.mean.clone() * k_prob.clone(),
+            variance: self.variance.clone() * k_prob.clone() * k_prob,
+            distribution: scale(&self.distribution),
+            cumulative_distribution: LazyCdf::default(),
+            build_time: 0,
+            build_report: BuildReport::default(),
+        };
+        dice.build_time = elapsed_millis(&start_instant);
+        Ok(dice)
+    }
+
+    /// the distribution of `self` where all values below `lo` become `lo` and all values above `hi` become `hi`
+    ///
+    /// panics if `lo > hi`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let d = Dice::build_from_string("d6").unwrap().clamped(2, 5);
+    /// assert_eq!(d.prob(2), BigFraction::new(1u64, 3u64));
+    /// assert_eq!(d.builder_string, "min(max(d6,2),5)");
+    /// ```
+    pub fn clamped(&self, lo: Value, hi: Value) -> Dice<P> {
+        assert!(lo <= hi, "cannot clamp to an empty interval [{lo}, {hi}]");
+        let builder_string =
+            derived_builder_string(&self.builder_string, |s| format!("min(max({s},{lo}),{hi})"));
+        self.map_values(|v| v.clamp(lo, hi), builder_string)
+    }
+
+    /// groups the values from `min` to `max` into consecutive ranges of `bucket_size` values each, with the summed probability of each range
+    ///
+    /// Ranges without any value of the distribution are included with a probability of 0, so the buckets have equal widths.
+    /// The last bucket is cut off at `max`.
+    ///
+    /// panics if `bucket_size` is 0.
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// use fraction::BigFraction;
+    /// let buckets = Dice::build_from_string("d20").unwrap().bucketed(5);
+    /// assert_eq!(buckets.len(), 4);
+    /// assert_eq!((buckets[1].min, buckets[1].max), (6, 10));
+    /// assert_eq!(buckets[1].prob, BigFraction::new(1u64, 4u64));
+    /// ```
+    pub fn bucketed(&self, bucket_size: u64) -> Vec<Bucket<P>> {
+        assert!(bucket_size > 0, "buckets must contain at least one value");
+        let mut buckets = vec![];
+        let mut distribution = self.distribution.iter().peekable();
+        let mut lo = self.min;
+        loop {
+            let hi = match i64::try_from(bucket_size - 1) {
+                Ok(size) => lo.saturating_add(size).min(self.max),
+                Err(_) => self.max,
+            };
+            let mut prob = P::zero();
+            while let Some((_, p)) = distribution.next_if(|(v, _)| *v <= hi) {
+                prob += p;
+            }
+            buckets.push(Bucket {
+                min: lo,
+                max: hi,
+                prob,
+            });
+            match hi < self.max {
+                true => lo = hi + 1,
+                false => break,
+            }
+        }
+        buckets
+    }
+
+    /// groups the values into at most `n_buckets` consecutive ranges of equal size, see `bucketed()`
+    ///
+    /// panics if `n_buckets` is 0.
+    pub fn bucketed_into(&self, n_buckets: usize) -> Vec<Bucket<P>> {
+        assert!(n_buckets > 0, "cannot group values into 0 buckets");
+        let span = self.max.abs_diff(self.min).saturating_add(1);
+        self.bucketed(span.div_ceil(n_buckets as u64))
+    }
+
+    /// applies `f` to every value of the distribution, merging the probabilities of values that are mapped to the same value
+    pub(crate) fn map_values(&self, f: impl Fn(Value) -> Value, builder_string: String) -> Dice<P> {
+        let start_instant = WasmSafeInstant::now();
+        let mut mapped: BTreeMap<Value, P> = BTreeMap::new();
+        for (v, p) in self.distribution.iter() {
+            match mapped.entry(f(*v)) {
+                std::collections::btree_map::Entry::Occupied(mut e) => {
+                    *e.get_mut() += p;
+                }
+                std::collections::btree_map::Entry::Vacant(e) => {
+                    e.insert(p.clone());
+                }
+            }
+        }
+        let mut dice = Dice::from_sorted_distribution(mapped.into_iter().collect(), builder_string)
+            .expect("mapping a non-empty distribution gives a non-empty distribution");
+        dice.build_time = elapsed_millis(&start_instant);
+        dice
+    }
+}
+
+/// adds two independent [`Dice`] by convoluting their distributions
+///
+/// # Examples
+/// ```
+/// use dices::Dice;
+/// let d6 = Dice::build_from_string("d6").unwrap();
+/// let two_d6 = &d6 + &d6;
+/// assert_eq!(two_d6.distribution, Dice::build_from_string("2d6").unwrap().distribution);
+/// ```
+impl<P: ProbLike> Add for &Dice<P> {
+    type Output = Dice<P>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.combined_with(DiceOperation::Add, rhs)
+    }
+}
+
+/// subtracts two independent [`Dice`] by convoluting their distributions
+impl<P: ProbLike> Sub for &Dice<P> {
+    type Output = Dice<P>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.combined_with(DiceOperation::Subtract, rhs)
+    }
+}
+
+/// multiplies two independent [`Dice`] by convoluting their distributions
+impl<P: ProbLike> Mul for &Dice<P> {
+    type Output = Dice<P>;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.combined_with(DiceOperation::Multiply, rhs)
+    }
+}
+
+/// the builder string of a [`Dice`] derived from a [`Dice`] with `builder_string`, empty if that has no string notation either
+fn derived_builder_string(builder_string: &str, f: impl Fn(&str) -> String) -> String {
+    match builder_string.is_empty() {
+        true => String::new(),
+        false => f(builder_string),
+    }
+}
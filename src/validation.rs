@@ -0,0 +1,91 @@
+use crate::{
+    dice::Dice,
+    dice_builder::{Prob, Value},
+    prob_like::ProbLike,
+};
+
+/// The result of [`Dice::validate()`], a structured check of the invariants every [`Dice`] should hold.
+///
+/// Useful for catching bugs when new [`DiceBuilder`](crate::DiceBuilder) variants are added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// sum of all probabilities of the pmf
+    pub total_probability: Prob,
+    /// the pmf sums to exactly 1
+    pub sums_to_one: bool,
+    /// no probability of the pmf is negative
+    pub probabilities_non_negative: bool,
+    /// the values of the pmf are strictly ascending, so sorted and unique
+    pub values_sorted_and_unique: bool,
+    /// the cdf has the same values as the pmf and its probabilities are the running sums of the pmf
+    pub cdf_matches_pmf: bool,
+    /// the probabilities of the cdf never decrease
+    pub cdf_monotone: bool,
+}
+
+impl ValidationReport {
+    /// true if all checks passed
+    pub fn is_valid(&self) -> bool {
+        self.sums_to_one
+            && self.probabilities_non_negative
+            && self.values_sorted_and_unique
+            && self.cdf_matches_pmf
+            && self.cdf_monotone
+    }
+}
+
+impl<P: ProbLike> Dice<P> {
+    /// sum of all probabilities of the distribution, which should be 1 (up to rounding errors for float probabilities)
+    pub fn total_probability(&self) -> P {
+        let mut total = P::zero();
+        for (_, p) in self.distribution.iter() {
+            total += p;
+        }
+        total
+    }
+}
+
+impl Dice {
+    /// checks that the pmf sums to exactly 1, values are sorted and unique and the cdf is monotone and consistent with the pmf
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::Dice;
+    /// let dice = Dice::build_from_string("max(2d6,d20)-3").unwrap();
+    /// assert!(dice.validate().is_valid());
+    /// ```
+    pub fn validate(&self) -> ValidationReport {
+        let total_probability = self.total_probability();
+        let sums_to_one = total_probability == Prob::one();
+        let probabilities_non_negative = self.distribution.iter().all(|(_, p)| *p >= Prob::zero());
+        let values_sorted_and_unique = strictly_ascending(&self.distribution);
+
+        let mut running_sum = Prob::zero();
+        let cdf_matches_pmf = self.distribution.len() == self.cumulative_distribution().len()
+            && self
+                .distribution
+                .iter()
+                .zip(self.cumulative_distribution().iter())
+                .all(|((v, p), (cv, cp))| {
+                    running_sum += p;
+                    v == cv && running_sum == *cp
+                });
+        let cdf_monotone = self
+            .cumulative_distribution()
+            .windows(2)
+            .all(|w| w[0].1 <= w[1].1);
+
+        ValidationReport {
+            total_probability,
+            sums_to_one,
+            probabilities_non_negative,
+            values_sorted_and_unique,
+            cdf_matches_pmf,
+            cdf_monotone,
+        }
+    }
+}
+
+fn strictly_ascending(distribution: &[(Value, Prob)]) -> bool {
+    distribution.windows(2).all(|w| w[0].0 < w[1].0)
+}
@@ -0,0 +1,196 @@
+use std::convert::Infallible;
+
+use crate::dice_builder::DiceBuilder;
+
+/// An analysis pass over a [`DiceBuilder`] tree, run with [`DiceBuilder::accept()`].
+///
+/// Both methods have empty default implementations, so a visitor only implements what it needs.
+///
+/// # Examples
+/// counting the dice in a formula:
+/// ```
+/// use dices::{DiceBuilder, Visitor};
+/// struct DieCounter(usize);
+/// impl Visitor for DieCounter {
+///     fn enter(&mut self, node: &DiceBuilder) -> bool {
+///         if let DiceBuilder::FairDie { .. } = node {
+///             self.0 += 1;
+///         }
+///         true
+///     }
+/// }
+/// let mut counter = DieCounter(0);
+/// DiceBuilder::from_string("max(d6,d8)+d4").unwrap().accept(&mut counter);
+/// assert_eq!(counter.0, 3);
+/// ```
+pub trait Visitor {
+    /// called for every node before its children, returning `false` skips the children of `node`
+    fn enter(&mut self, _node: &DiceBuilder) -> bool {
+        true
+    }
+
+    /// called for every node after its children
+    fn leave(&mut self, _node: &DiceBuilder) {}
+}
+
+impl DiceBuilder {
+    /// the direct sub-expressions of [`self`], in the order they appear in its formula
+    pub fn children(&self) -> Vec<&DiceBuilder> {
+        match self {
+            DiceBuilder::Constant(_) | DiceBuilder::FairDie { .. } | DiceBuilder::Variable(_) => {
+                vec![]
+            }
+            DiceBuilder::SumCompound(v)
+            | DiceBuilder::ProductCompound(v)
+            | DiceBuilder::DivisionCompound(v)
+            | DiceBuilder::MaxCompound(v)
+            | DiceBuilder::MinCompound(v)
+            | DiceBuilder::SampleSumCompound(v)
+            | DiceBuilder::PowerCompound(v) => v.iter().collect(),
+            DiceBuilder::Absolute(d)
+            | DiceBuilder::Negation(d)
+            | DiceBuilder::NthHighest {
+                dice_builder: d, ..
+            }
+            | DiceBuilder::Explode {
+                dice_builder: d, ..
+            }
+            | DiceBuilder::Reroll {
+                dice_builder: d, ..
+            } => vec![d],
+            DiceBuilder::Let { value, body, .. } => vec![value, body],
+            DiceBuilder::Mixture(components) => components.iter().map(|(_, d)| d).collect(),
+        }
+    }
+
+    /// runs `visitor` over the tree of [`self`], depth first
+    pub fn accept<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        if visitor.enter(self) {
+            for child in self.children() {
+                child.accept(visitor);
+            }
+        }
+        visitor.leave(self);
+    }
+
+    /// calls `f` for every node of the tree of [`self`], parents before their children
+    ///
+    /// # Examples
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let mut nodes = vec![];
+    /// DiceBuilder::from_string("d6+3").unwrap().walk(&mut |node| nodes.push(node.to_string()));
+    /// assert_eq!(nodes, vec!["d6+3", "d6", "3"]);
+    /// ```
+    pub fn walk(&self, f: &mut impl FnMut(&DiceBuilder)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+
+    /// rebuilds the tree of [`self`] bottom up: the children of every node are mapped first, then `f` is applied to the node itself
+    ///
+    /// # Examples
+    /// replacing every d6 with a d8:
+    /// ```
+    /// use dices::DiceBuilder;
+    /// let builder = DiceBuilder::from_string("2d6+max(d6,3)").unwrap();
+    /// let mapped = builder.map_nodes(&mut |node| match node {
+    ///     DiceBuilder::FairDie { min: 1, max: 6 } => DiceBuilder::FairDie { min: 1, max: 8 },
+    ///     other => other,
+    /// });
+    /// assert_eq!(mapped.to_string(), "2xd8+max(d8,3)");
+    /// ```
+    pub fn map_nodes(self, f: &mut impl FnMut(DiceBuilder) -> DiceBuilder) -> DiceBuilder {
+        let result: Result<DiceBuilder, Infallible> = self.try_rewrite(&mut |node| Ok(f(node)));
+        match result {
+            Ok(builder) => builder,
+            Err(never) => match never {},
+        }
+    }
+
+    /// rebuilds the tree of [`self`] bottom up like `map_nodes()`, but stops at the first error returned by `f`
+    ///
+    /// # Examples
+    /// rewriting `min(max(x,lo),hi)` to `max(min(x,hi),lo)`, which fails if a die has more than 20 sides:
+    /// ```
+    /// use dices::DiceBuilder;
+    /// use DiceBuilder::*;
+    /// let mut rewrite = |node: DiceBuilder| match node {
+    ///     FairDie { max, .. } if max > 20 => Err(format!("d{max} is not allowed")),
+    ///     MinCompound(outer) => match outer.as_slice() {
+    ///         [MaxCompound(inner), hi] if inner.len() == 2 => Ok(MaxCompound(vec![
+    ///             MinCompound(vec![inner[0].clone(), hi.clone()]),
+    ///             inner[1].clone(),
+    ///         ])),
+    ///         _ => Ok(MinCompound(outer)),
+    ///     },
+    ///     other => Ok(other),
+    /// };
+    /// let builder = DiceBuilder::from_string("min(max(d20,5),15)").unwrap();
+    /// let rewritten = builder.try_rewrite(&mut rewrite).unwrap();
+    /// assert_eq!(rewritten.to_string(), "max(min(d20,15),5)");
+    /// let builder = DiceBuilder::from_string("min(max(d100,5),15)").unwrap();
+    /// assert!(builder.try_rewrite(&mut rewrite).is_err());
+    /// ```
+    pub fn try_rewrite<E>(
+        self,
+        f: &mut impl FnMut(DiceBuilder) -> Result<DiceBuilder, E>,
+    ) -> Result<DiceBuilder, E> {
+        let mut rewrite_all = |v: Vec<DiceBuilder>| -> Result<Vec<DiceBuilder>, E> {
+            v.into_iter().map(|d| d.try_rewrite(f)).collect()
+        };
+        let node = match self {
+            DiceBuilder::Constant(_) | DiceBuilder::FairDie { .. } | DiceBuilder::Variable(_) => {
+                self
+            }
+            DiceBuilder::SumCompound(v) => DiceBuilder::SumCompound(rewrite_all(v)?),
+            DiceBuilder::ProductCompound(v) => DiceBuilder::ProductCompound(rewrite_all(v)?),
+            DiceBuilder::DivisionCompound(v) => DiceBuilder::DivisionCompound(rewrite_all(v)?),
+            DiceBuilder::MaxCompound(v) => DiceBuilder::MaxCompound(rewrite_all(v)?),
+            DiceBuilder::MinCompound(v) => DiceBuilder::MinCompound(rewrite_all(v)?),
+            DiceBuilder::SampleSumCompound(v) => DiceBuilder::SampleSumCompound(rewrite_all(v)?),
+            DiceBuilder::PowerCompound(v) => DiceBuilder::PowerCompound(rewrite_all(v)?),
+            DiceBuilder::Absolute(d) => DiceBuilder::Absolute(Box::new(d.try_rewrite(f)?)),
+            DiceBuilder::Negation(d) => DiceBuilder::Negation(Box::new(d.try_rewrite(f)?)),
+            DiceBuilder::NthHighest { n, k, dice_builder } => DiceBuilder::NthHighest {
+                n,
+                k,
+                dice_builder: Box::new(dice_builder.try_rewrite(f)?),
+            },
+            DiceBuilder::Explode {
+                dice_builder,
+                min_value,
+                max_iterations,
+                kind,
+            } => DiceBuilder::Explode {
+                dice_builder: Box::new(dice_builder.try_rewrite(f)?),
+                min_value,
+                max_iterations,
+                kind,
+            },
+            DiceBuilder::Reroll {
+                dice_builder,
+                max_value,
+                keep_best,
+            } => DiceBuilder::Reroll {
+                dice_builder: Box::new(dice_builder.try_rewrite(f)?),
+                max_value,
+                keep_best,
+            },
+            DiceBuilder::Let { name, value, body } => DiceBuilder::Let {
+                name,
+                value: Box::new(value.try_rewrite(f)?),
+                body: Box::new(body.try_rewrite(f)?),
+            },
+            DiceBuilder::Mixture(components) => DiceBuilder::Mixture(
+                components
+                    .into_iter()
+                    .map(|(weight, d)| Ok((weight, d.try_rewrite(f)?)))
+                    .collect::<Result<Vec<(u32, DiceBuilder)>, E>>()?,
+            ),
+        };
+        f(node)
+    }
+}
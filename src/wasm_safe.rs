@@ -1,20 +1,21 @@
-#[cfg(not(feature = "wasm"))]
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 use rand::Rng;
 
-#[cfg(not(feature = "wasm"))]
+// the clock and the random numbers of the browser are only needed on wasm32, native builds with the `wasm` feature, like its tests, use std
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 pub type WasmSafeInstant = std::time::Instant;
 
-#[cfg(not(feature = "wasm"))]
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 pub fn elapsed_millis(instant: &WasmSafeInstant) -> u64 {
     instant.elapsed().as_millis() as u64
 }
 
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 pub struct WasmSafeInstant {
     start: f64,
 }
 
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 impl WasmSafeInstant {
     pub fn now() -> WasmSafeInstant {
         WasmSafeInstant {
@@ -23,24 +24,24 @@ impl WasmSafeInstant {
     }
 }
 
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 pub fn elapsed_millis(instant: &WasmSafeInstant) -> u64 {
     let end = time_now_in_ms();
     let start = instant.start;
     (end - start) as u64
 }
 
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 fn time_now_in_ms() -> f64 {
     js_sys::Date::new_0().value_of()
 }
 
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 pub fn random_number_between_0_and_1() -> f64 {
     js_sys::Math::random()
 }
 
-#[cfg(not(feature = "wasm"))]
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 pub fn random_number_between_0_and_1() -> f64 {
     let mut rng = rand::thread_rng();
     let f: f64 = rng.gen();